@@ -0,0 +1,66 @@
+-- Базовая схема (соответствует database_schema.txt и текущему коду)
+
+CREATE TABLE IF NOT EXISTS users (
+    id SERIAL PRIMARY KEY,
+    username VARCHAR NOT NULL UNIQUE,
+    password_hash VARCHAR NOT NULL,
+    invitation_code VARCHAR NOT NULL,
+    user_uuid UUID UNIQUE,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_users_username ON users (username);
+CREATE INDEX IF NOT EXISTS idx_users_user_uuid ON users (user_uuid);
+
+CREATE TABLE IF NOT EXISTS devices (
+    device_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_uuid UUID REFERENCES users (user_uuid),
+    ip_address INET NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_devices_user_uuid ON devices (user_uuid);
+
+CREATE TABLE IF NOT EXISTS sessions (
+    session_id UUID PRIMARY KEY,
+    user_uuid UUID REFERENCES users (user_uuid),
+    device_id UUID REFERENCES devices (device_id),
+    expires_at TIMESTAMPTZ
+);
+
+CREATE TABLE IF NOT EXISTS files (
+    file_id UUID PRIMARY KEY,
+    filename VARCHAR NOT NULL,
+    user_uuid UUID NOT NULL REFERENCES users (user_uuid),
+    upload_time TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS messages (
+    message TEXT NOT NULL,
+    user_uuid UUID REFERENCES users (user_uuid),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "timestamp" TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_messages_created_at ON messages (created_at);
+
+CREATE TABLE IF NOT EXISTS profiles (
+    user_uuid UUID PRIMARY KEY REFERENCES users (user_uuid),
+    bio TEXT,
+    avatar TEXT,
+    profile_banner TEXT,
+    storage_access VARCHAR NOT NULL DEFAULT 'Private',
+    allowed_viewers UUID[] NOT NULL DEFAULT ARRAY[]::uuid[]
+);
+
+CREATE TABLE IF NOT EXISTS storages (
+    storage_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    owner_uuid UUID NOT NULL,
+    name VARCHAR NOT NULL,
+    description TEXT,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS storage_access (
+    storage_id UUID NOT NULL REFERENCES storages (storage_id),
+    user_uuid UUID NOT NULL REFERENCES users (user_uuid),
+    access_level VARCHAR NOT NULL,
+    PRIMARY KEY (storage_id, user_uuid)
+);
@@ -0,0 +1,9 @@
+-- Роли пользователей и модерация сообщений.
+-- Первого администратора назначают вручную:
+--   UPDATE users SET role = 'Admin' WHERE username = '...';
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS role VARCHAR NOT NULL DEFAULT 'User';
+
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS message_id BIGSERIAL PRIMARY KEY;
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS deleted_by UUID REFERENCES users (user_uuid);
@@ -0,0 +1,10 @@
+-- Администрирование пользователей: блокировка, последняя активность, размер файлов
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS suspended_at TIMESTAMPTZ;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS suspended_reason TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS last_login_at TIMESTAMPTZ;
+
+ALTER TABLE files ADD COLUMN IF NOT EXISTS size_bytes BIGINT NOT NULL DEFAULT 0;
+CREATE INDEX IF NOT EXISTS idx_files_user_uuid ON files (user_uuid);
+CREATE INDEX IF NOT EXISTS idx_messages_user_uuid ON messages (user_uuid);
+CREATE INDEX IF NOT EXISTS idx_sessions_user_uuid ON sessions (user_uuid);
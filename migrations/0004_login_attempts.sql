@@ -0,0 +1,22 @@
+-- Защита входа от перебора паролей
+
+CREATE TABLE IF NOT EXISTS login_attempts (
+    attempt_id BIGSERIAL PRIMARY KEY,
+    username VARCHAR NOT NULL,
+    ip_address INET NOT NULL,
+    success BOOLEAN NOT NULL,
+    attempted_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_login_attempts_username ON login_attempts (username, attempted_at);
+CREATE INDEX IF NOT EXISTS idx_login_attempts_ip_address ON login_attempts (ip_address, attempted_at);
+
+-- scope: 'account' (key = username) или 'ip' (key = IP-адрес)
+CREATE TABLE IF NOT EXISTS login_lockouts (
+    lockout_id BIGSERIAL PRIMARY KEY,
+    scope VARCHAR NOT NULL,
+    key VARCHAR NOT NULL,
+    failed_attempts INTEGER NOT NULL,
+    locked_until TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_login_lockouts_key ON login_lockouts (scope, key, locked_until);
@@ -0,0 +1,41 @@
+-- OAuth2 / OpenID Connect: клиенты, коды авторизации, согласия и токены доступа
+
+CREATE TABLE IF NOT EXISTS oauth_clients (
+    client_id VARCHAR PRIMARY KEY,
+    client_secret_hash VARCHAR,
+    name VARCHAR NOT NULL,
+    redirect_uris TEXT[] NOT NULL,
+    created_by UUID REFERENCES users (user_uuid) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS oauth_authorization_codes (
+    code_hash VARCHAR PRIMARY KEY,
+    client_id VARCHAR NOT NULL REFERENCES oauth_clients (client_id) ON DELETE CASCADE,
+    user_uuid UUID NOT NULL REFERENCES users (user_uuid) ON DELETE CASCADE,
+    redirect_uri VARCHAR NOT NULL,
+    scope VARCHAR NOT NULL,
+    nonce VARCHAR,
+    code_challenge VARCHAR NOT NULL,
+    auth_time TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ
+);
+
+CREATE TABLE IF NOT EXISTS oauth_consents (
+    user_uuid UUID NOT NULL REFERENCES users (user_uuid) ON DELETE CASCADE,
+    client_id VARCHAR NOT NULL REFERENCES oauth_clients (client_id) ON DELETE CASCADE,
+    scope VARCHAR NOT NULL,
+    granted_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_uuid, client_id)
+);
+
+CREATE TABLE IF NOT EXISTS oauth_access_tokens (
+    token_hash VARCHAR PRIMARY KEY,
+    client_id VARCHAR NOT NULL REFERENCES oauth_clients (client_id) ON DELETE CASCADE,
+    user_uuid UUID NOT NULL REFERENCES users (user_uuid) ON DELETE CASCADE,
+    scope VARCHAR NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_oauth_access_tokens_user_uuid ON oauth_access_tokens (user_uuid);
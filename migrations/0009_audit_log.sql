@@ -0,0 +1,30 @@
+-- Журнал событий безопасности. Записи только добавляются; удаляются лишь
+-- устаревшие по сроку хранения (AUDIT_LOG_RETENTION_DAYS)
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    event_id BIGSERIAL PRIMARY KEY,
+    -- Кто выполнил действие (NULL — не вошедший пользователь)
+    actor_uuid UUID,
+    action VARCHAR NOT NULL,
+    -- Чья учётная запись затронута
+    target_uuid UUID,
+    -- Прочий объект действия: имя при неудачном входе, file_id, новое значение
+    target VARCHAR,
+    device_id UUID,
+    ip_address INET,
+    user_agent VARCHAR,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_audit_log_actor_uuid ON audit_log (actor_uuid, event_id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_target_uuid ON audit_log (target_uuid, event_id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_created_at ON audit_log (created_at);
+
+CREATE OR REPLACE FUNCTION audit_log_reject_update() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS audit_log_append_only ON audit_log;
+CREATE TRIGGER audit_log_append_only BEFORE UPDATE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_reject_update();
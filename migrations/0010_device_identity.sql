@@ -0,0 +1,28 @@
+-- Устройство определяется долгоживущим токеном в cookie и отпечатком
+-- User-Agent, отдельно для каждого пользователя. IP-адреса устройства
+-- хранятся в истории, а devices.ip_address — последний известный адрес
+
+ALTER TABLE devices ADD COLUMN IF NOT EXISTS token_hash VARCHAR;
+ALTER TABLE devices ADD COLUMN IF NOT EXISTS fingerprint VARCHAR;
+ALTER TABLE devices ADD COLUMN IF NOT EXISTS user_agent VARCHAR;
+ALTER TABLE devices ADD COLUMN IF NOT EXISTS name VARCHAR;
+ALTER TABLE devices ADD COLUMN IF NOT EXISTS trusted BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE devices ADD COLUMN IF NOT EXISTS last_seen_at TIMESTAMPTZ;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_devices_user_token ON devices (user_uuid, token_hash);
+
+-- Раньше устройство находилось только по IP и могло достаться чужой сессии
+DELETE FROM sessions s USING devices d
+WHERE s.device_id = d.device_id AND s.user_uuid IS DISTINCT FROM d.user_uuid;
+
+CREATE TABLE IF NOT EXISTS device_ips (
+    device_id UUID NOT NULL REFERENCES devices (device_id) ON DELETE CASCADE,
+    ip_address INET NOT NULL,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (device_id, ip_address)
+);
+
+INSERT INTO device_ips (device_id, ip_address, first_seen_at, last_seen_at)
+SELECT device_id, ip_address, COALESCE(created_at, NOW()), COALESCE(created_at, NOW())
+FROM devices
+ON CONFLICT DO NOTHING;
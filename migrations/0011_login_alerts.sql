@@ -0,0 +1,29 @@
+-- Уведомления пользователя и предупреждения о входе с нового устройства
+
+CREATE TABLE IF NOT EXISTS notifications (
+    notification_id UUID PRIMARY KEY,
+    user_uuid UUID NOT NULL REFERENCES users (user_uuid) ON DELETE CASCADE,
+    kind VARCHAR NOT NULL,
+    message VARCHAR NOT NULL,
+    -- Куда ведёт уведомление (страница действия)
+    link VARCHAR,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    read_at TIMESTAMPTZ
+);
+CREATE INDEX IF NOT EXISTS idx_notifications_user_uuid ON notifications (user_uuid, created_at);
+
+-- Вход с незнакомого устройства или адреса. По ссылке «это был не я»
+-- (token_hash — для письма) сессии этого устройства завершаются
+CREATE TABLE IF NOT EXISTS login_alerts (
+    alert_id UUID PRIMARY KEY,
+    user_uuid UUID NOT NULL REFERENCES users (user_uuid) ON DELETE CASCADE,
+    session_id UUID NOT NULL,
+    device_id UUID NOT NULL,
+    ip_address INET,
+    user_agent VARCHAR,
+    token_hash VARCHAR NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_login_alerts_user_uuid ON login_alerts (user_uuid);
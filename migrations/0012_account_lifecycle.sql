@@ -0,0 +1,17 @@
+-- Выгрузка личных данных и удаление учётной записи с отсрочкой
+
+-- Когда учётная запись будет удалена (NULL — удаление не запрошено)
+ALTER TABLE users ADD COLUMN IF NOT EXISTS delete_after TIMESTAMPTZ;
+CREATE INDEX IF NOT EXISTS idx_users_delete_after ON users (delete_after) WHERE delete_after IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS data_exports (
+    export_id UUID PRIMARY KEY,
+    user_uuid UUID NOT NULL REFERENCES users (user_uuid) ON DELETE CASCADE,
+    -- pending, ready или failed
+    status VARCHAR NOT NULL DEFAULT 'pending',
+    size_bytes BIGINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_data_exports_user_uuid ON data_exports (user_uuid, created_at);
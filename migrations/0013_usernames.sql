@@ -0,0 +1,44 @@
+-- Смена имени пользователя, отображаемые имена и зарезервированные имена
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS display_name VARCHAR;
+-- Когда имя менялось в последний раз (для ограничения частоты смены)
+ALTER TABLE users ADD COLUMN IF NOT EXISTS username_changed_at TIMESTAMPTZ;
+CREATE INDEX IF NOT EXISTS idx_users_username_lower ON users (LOWER(username));
+
+-- История имён для модераторов
+CREATE TABLE IF NOT EXISTS username_history (
+    history_id BIGSERIAL PRIMARY KEY,
+    user_uuid UUID NOT NULL REFERENCES users (user_uuid) ON DELETE CASCADE,
+    old_username VARCHAR NOT NULL,
+    new_username VARCHAR NOT NULL,
+    -- Кто сменил имя: сам пользователь или администратор
+    changed_by UUID,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_username_history_user_uuid ON username_history (user_uuid, changed_at);
+
+-- Освобождённые имена: какое-то время их может занять только прежний владелец.
+-- Связи с users нет: запись переживает удаление учётной записи
+CREATE TABLE IF NOT EXISTS released_usernames (
+    username VARCHAR PRIMARY KEY, -- в нижнем регистре
+    released_by UUID NOT NULL,
+    released_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Имена, которые нельзя занять (список ведут администраторы)
+CREATE TABLE IF NOT EXISTS reserved_usernames (
+    username VARCHAR PRIMARY KEY, -- в нижнем регистре
+    reason VARCHAR,
+    created_by UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+INSERT INTO reserved_usernames (username, reason) VALUES
+    ('admin', 'Staff'),
+    ('administrator', 'Staff'),
+    ('moderator', 'Staff'),
+    ('root', 'Staff'),
+    ('support', 'Staff'),
+    ('system', 'System messages'),
+    ('cyb3ria', 'Service name'),
+    ('unknown user', 'Shown for deleted accounts')
+ON CONFLICT (username) DO NOTHING;
@@ -0,0 +1,10 @@
+-- Реакции на сообщения чата: один эмодзи от пользователя на сообщение — одна строка
+
+CREATE TABLE IF NOT EXISTS message_reactions (
+    message_id BIGINT NOT NULL REFERENCES messages (message_id) ON DELETE CASCADE,
+    user_uuid UUID NOT NULL REFERENCES users (user_uuid) ON DELETE CASCADE,
+    emoji VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (message_id, user_uuid, emoji)
+);
+CREATE INDEX IF NOT EXISTS idx_message_reactions_user_uuid ON message_reactions (user_uuid);
@@ -0,0 +1,6 @@
+-- Ответы на сообщения: цитата (reply_to) и ветка под корневым сообщением (thread_root).
+-- Сообщения ветки не показываются в общей ленте — только сводка у корня
+
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS reply_to BIGINT REFERENCES messages (message_id) ON DELETE SET NULL;
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS thread_root BIGINT REFERENCES messages (message_id) ON DELETE SET NULL;
+CREATE INDEX IF NOT EXISTS idx_messages_thread_root ON messages (thread_root, created_at);
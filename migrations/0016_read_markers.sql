@@ -0,0 +1,10 @@
+-- Отметки прочтения: последнее прочитанное сообщение пользователя в каждом разговоре.
+-- conversation — 'main' для общей ленты или 'thread:<message_id>' для ветки
+
+CREATE TABLE IF NOT EXISTS read_markers (
+    user_uuid UUID NOT NULL REFERENCES users (user_uuid) ON DELETE CASCADE,
+    conversation VARCHAR NOT NULL,
+    last_read_message_id BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_uuid, conversation)
+);
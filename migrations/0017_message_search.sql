@@ -0,0 +1,4 @@
+-- Полнотекстовый поиск по сообщениям. Словарь simple: в чате пишут на разных языках,
+-- а стемминг одного языка портит слова другого. Запросы должны использовать то же выражение
+
+CREATE INDEX IF NOT EXISTS idx_messages_search ON messages USING GIN (to_tsvector('simple', message));
@@ -0,0 +1,15 @@
+-- Упоминания @username в сообщениях. start_offset и end_offset — границы
+-- упоминания в тексте сообщения, в символах
+
+CREATE TABLE IF NOT EXISTS message_mentions (
+    message_id BIGINT NOT NULL REFERENCES messages (message_id) ON DELETE CASCADE,
+    start_offset INTEGER NOT NULL,
+    end_offset INTEGER NOT NULL,
+    user_uuid UUID NOT NULL REFERENCES users (user_uuid) ON DELETE CASCADE,
+    PRIMARY KEY (message_id, start_offset)
+);
+CREATE INDEX IF NOT EXISTS idx_message_mentions_user_uuid ON message_mentions (user_uuid, message_id);
+
+-- Уведомления, которые ещё не показаны в чате, приходят при следующем подключении
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS delivered_at TIMESTAMPTZ;
+UPDATE notifications SET delivered_at = created_at WHERE delivered_at IS NULL;
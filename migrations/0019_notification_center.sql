@@ -0,0 +1,11 @@
+-- Центр уведомлений: настройки по видам уведомлений. Нет строки — вид включён
+
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    user_uuid UUID NOT NULL REFERENCES users (user_uuid) ON DELETE CASCADE,
+    kind VARCHAR NOT NULL,
+    in_app BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_uuid, kind)
+);
+
+CREATE INDEX IF NOT EXISTS idx_notifications_unread ON notifications (user_uuid) WHERE read_at IS NULL;
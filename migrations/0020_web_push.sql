@@ -0,0 +1,17 @@
+-- Подписки Web Push: браузер отдаёт адрес службы доставки (endpoint) и ключи
+-- шифрования (p256dh, auth). Один endpoint принадлежит одному пользователю
+
+CREATE TABLE IF NOT EXISTS push_subscriptions (
+    subscription_id UUID PRIMARY KEY,
+    user_uuid UUID NOT NULL REFERENCES users (user_uuid) ON DELETE CASCADE,
+    endpoint VARCHAR NOT NULL UNIQUE,
+    p256dh VARCHAR NOT NULL,
+    auth VARCHAR NOT NULL,
+    user_agent VARCHAR,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_push_at TIMESTAMPTZ
+);
+CREATE INDEX IF NOT EXISTS idx_push_subscriptions_user_uuid ON push_subscriptions (user_uuid);
+
+-- Отправлять ли вид уведомлений через Web Push. NULL — как принято для вида
+ALTER TABLE notification_preferences ADD COLUMN IF NOT EXISTS push BOOLEAN;
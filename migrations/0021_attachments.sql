@@ -0,0 +1,13 @@
+-- Вложения в сообщениях чата. Файл, вложенный в сообщение, могут читать все,
+-- кто видит это сообщение, пока его не удалил модератор
+
+ALTER TABLE files ADD COLUMN IF NOT EXISTS mime_type VARCHAR;
+
+CREATE TABLE IF NOT EXISTS message_attachments (
+    message_id BIGINT NOT NULL REFERENCES messages (message_id) ON DELETE CASCADE,
+    file_id UUID NOT NULL REFERENCES files (file_id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    attached_by UUID REFERENCES users (user_uuid) ON DELETE SET NULL,
+    PRIMARY KEY (message_id, file_id)
+);
+CREATE INDEX IF NOT EXISTS idx_message_attachments_file_id ON message_attachments (file_id);
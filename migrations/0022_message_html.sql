@@ -0,0 +1,3 @@
+-- HTML сообщения, отрисованный из Markdown при сохранении. У старых сообщений
+-- его нет — они отрисовываются при чтении
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS message_html TEXT;
@@ -0,0 +1,4 @@
+-- Ограничение частоты писем для сброса пароля: с какого адреса запрошен токен
+ALTER TABLE password_reset_tokens ADD COLUMN IF NOT EXISTS requested_ip INET;
+CREATE INDEX IF NOT EXISTS idx_password_reset_tokens_requested_ip
+    ON password_reset_tokens (requested_ip, created_at);
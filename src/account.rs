@@ -0,0 +1,392 @@
+// src/account.rs
+//
+// Выгрузка личных данных в zip-архив и удаление учётной записи после
+// отсрочки. Архивы собираются в фоне и хранятся ограниченное время.
+use crate::attachments::{file_path, remove_stored_files};
+use crate::audit::{record, AuditEntry, ClientInfo};
+use crate::db::devices::{find_device_ips, find_devices_by_user_uuid};
+use crate::db::exports::{complete_export, delete_expired_exports, list_exports};
+use crate::db::files::list_files_by_user_uuid;
+use crate::db::messages::list_messages_by_user_uuid;
+use crate::db::notifications::{list_notification_preferences, list_notifications_by_user_uuid};
+use crate::db::push_subscriptions::list_push_subscriptions;
+use crate::db::reactions::list_reactions_by_user_uuid;
+use crate::db::read_markers::list_read_markers_by_user_uuid;
+use crate::db::profiles::get_profile_by_user_uuid;
+use crate::db::sessions::list_sessions_by_user_uuid;
+use crate::db::usernames::purge_released_usernames;
+use crate::db::users::{
+    delete_user, find_delete_after, find_due_deletions, find_user_by_uuid, find_user_summary,
+};
+use crate::handlers::upload::upload_dir;
+use crate::models::{AuditAction, Device, DeviceIp, Role};
+use crate::usernames::release_hold_days;
+use chrono::{DateTime, Duration, SubsecRound, Utc};
+use log::{error, info, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::error::Error as StdError;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const DEFAULT_DELETION_GRACE_DAYS: i64 = 14;
+/// Сколько дней готовый архив доступен для скачивания
+pub const EXPORT_TTL_DAYS: i64 = 7;
+const EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// Учётная запись в архиве (без хеша пароля и кода приглашения)
+#[derive(Serialize, Debug, Clone)]
+struct AccountData {
+    user_uuid: Uuid,
+    username: String,
+    display_name: Option<String>,
+    role: Role,
+    email: Option<String>,
+    registration_date: Option<DateTime<Utc>>,
+    last_activity: Option<DateTime<Utc>>,
+    suspended_at: Option<DateTime<Utc>>,
+    delete_after: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct DeviceData {
+    #[serde(flatten)]
+    device: Device,
+    ip_history: Vec<DeviceIp>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct FileData {
+    file_id: Uuid,
+    filename: String,
+    upload_time: Option<DateTime<Utc>>,
+    size_bytes: i64,
+    /// Путь внутри архива (None — файла нет на диске)
+    archive_path: Option<String>,
+}
+
+/// Один JSON-документ архива
+struct ExportDocument {
+    path: &'static str,
+    description: &'static str,
+    count: usize,
+    data: Value,
+}
+
+pub fn export_dir() -> PathBuf {
+    match std::env::var("EXPORT_DIR") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => Path::new(&upload_dir()).join("exports"),
+    }
+}
+
+pub fn export_path(export_id: &Uuid) -> PathBuf {
+    export_dir().join(format!("{}.zip", export_id))
+}
+
+pub fn deletion_grace_days() -> i64 {
+    std::env::var("ACCOUNT_DELETION_GRACE_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(DEFAULT_DELETION_GRACE_DAYS)
+}
+
+/// Имя файла без каталогов, пригодное для пути в архиве
+fn archive_file_name(filename: &str) -> String {
+    let name = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim();
+    if name.is_empty() || name == "." || name == ".." {
+        "file".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+fn document(
+    path: &'static str,
+    description: &'static str,
+    count: usize,
+    data: impl Serialize,
+) -> Result<ExportDocument, Box<dyn StdError + Send + Sync>> {
+    Ok(ExportDocument {
+        path,
+        description,
+        count,
+        data: serde_json::to_value(data)?,
+    })
+}
+
+/// Собирает из базы всё, что хранится о пользователе
+async fn collect_documents(
+    user_uuid: &Uuid,
+    files: &[FileData],
+) -> Result<(String, Vec<ExportDocument>), Box<dyn StdError + Send + Sync>> {
+    let user = find_user_by_uuid(user_uuid).await?;
+    let summary = find_user_summary(user_uuid).await?;
+    let account = AccountData {
+        user_uuid: user.user_uuid,
+        username: user.username.clone(),
+        display_name: user.display_name,
+        role: user.role,
+        email: user.email,
+        registration_date: summary.as_ref().and_then(|s| s.registration_date),
+        last_activity: summary.as_ref().and_then(|s| s.last_activity),
+        suspended_at: user.suspended_at,
+        delete_after: find_delete_after(user_uuid).await?,
+    };
+    let profile = get_profile_by_user_uuid(user_uuid).await?;
+    let sessions = list_sessions_by_user_uuid(user_uuid).await?;
+    let mut devices = Vec::new();
+    for device in find_devices_by_user_uuid(user_uuid).await? {
+        let ip_history = find_device_ips(&device.device_id).await?;
+        devices.push(DeviceData { device, ip_history });
+    }
+    let messages = list_messages_by_user_uuid(user_uuid).await?;
+    let reactions = list_reactions_by_user_uuid(user_uuid).await?;
+    let read_markers = list_read_markers_by_user_uuid(user_uuid).await?;
+    let notifications = list_notifications_by_user_uuid(user_uuid).await?;
+    let notification_preferences = list_notification_preferences(user_uuid).await?;
+    let push_subscriptions = list_push_subscriptions(user_uuid).await?;
+
+    let documents = vec![
+        document("account.json", "Account details", 1, account)?,
+        document(
+            "profile.json",
+            "Public profile",
+            usize::from(profile.is_some()),
+            profile,
+        )?,
+        document(
+            "sessions.json",
+            "Active sign-in sessions",
+            sessions.len(),
+            &sessions,
+        )?,
+        document(
+            "devices.json",
+            "Devices and the addresses they signed in from",
+            devices.len(),
+            &devices,
+        )?,
+        document(
+            "messages.json",
+            "Chat messages you sent",
+            messages.len(),
+            &messages,
+        )?,
+        document(
+            "reactions.json",
+            "Reactions you left on chat messages",
+            reactions.len(),
+            &reactions,
+        )?,
+        document(
+            "read_markers.json",
+            "Where you stopped reading each conversation",
+            read_markers.len(),
+            &read_markers,
+        )?,
+        document(
+            "notifications.json",
+            "Notifications in your notification center",
+            notifications.len(),
+            &notifications,
+        )?,
+        document(
+            "notification_preferences.json",
+            "Which notifications you receive",
+            notification_preferences.len(),
+            &notification_preferences,
+        )?,
+        document(
+            "push_subscriptions.json",
+            "Browsers that receive push notifications",
+            push_subscriptions.len(),
+            &push_subscriptions,
+        )?,
+        document("files.json", "Uploaded files", files.len(), files)?,
+    ];
+    Ok((user.username, documents))
+}
+
+/// Пишет архив на диск и возвращает его размер
+fn write_archive(
+    path: &Path,
+    manifest: &Value,
+    documents: &[ExportDocument],
+    files: &[(PathBuf, String)],
+) -> Result<i64, Box<dyn StdError + Send + Sync>> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let partial = path.with_extension("zip.part");
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(std::fs::File::create(&partial)?);
+
+    zip.start_file("manifest.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(manifest)?)?;
+    for document in documents {
+        zip.start_file(document.path, options)?;
+        zip.write_all(&serde_json::to_vec_pretty(&document.data)?)?;
+    }
+    for (source, archive_path) in files {
+        zip.start_file(archive_path.as_str(), options)?;
+        std::io::copy(&mut std::fs::File::open(source)?, &mut zip)?;
+    }
+    zip.finish()?;
+
+    std::fs::rename(&partial, path)?;
+    Ok(std::fs::metadata(path)?.len() as i64)
+}
+
+async fn write_export(
+    export_id: &Uuid,
+    user_uuid: &Uuid,
+) -> Result<i64, Box<dyn StdError + Send + Sync>> {
+    let mut files = Vec::new();
+    let mut file_sources = Vec::new();
+    for file in list_files_by_user_uuid(user_uuid).await? {
+        let source = file_path(&file.file_id);
+        let archive_path = if tokio::fs::try_exists(&source).await.unwrap_or(false) {
+            let archive_path = format!(
+                "files/{}/{}",
+                file.file_id,
+                archive_file_name(&file.filename)
+            );
+            file_sources.push((source, archive_path.clone()));
+            Some(archive_path)
+        } else {
+            warn!(
+                "File {} of export {} is missing on disk",
+                file.filename, export_id
+            );
+            None
+        };
+        files.push(FileData {
+            file_id: file.file_id,
+            filename: file.filename,
+            upload_time: file.upload_time,
+            size_bytes: file.size_bytes,
+            archive_path,
+        });
+    }
+
+    let (username, documents) = collect_documents(user_uuid, &files).await?;
+    let manifest = json!({
+        "format_version": EXPORT_FORMAT_VERSION,
+        "export_id": export_id,
+        "generated_at": Utc::now(),
+        "user_uuid": user_uuid,
+        "username": username,
+        "contents": documents
+            .iter()
+            .map(|document| json!({
+                "path": document.path,
+                "description": document.description,
+                "count": document.count,
+            }))
+            .collect::<Vec<_>>(),
+        "files_directory": "files/<file_id>/<filename>",
+    });
+
+    let path = export_path(export_id);
+    tokio::task::spawn_blocking(move || write_archive(&path, &manifest, &documents, &file_sources))
+        .await?
+}
+
+/// Собирает архив и отмечает выгрузку готовой или неудавшейся
+pub async fn build_export(export_id: Uuid, user_uuid: Uuid) {
+    let size_bytes = match write_export(&export_id, &user_uuid).await {
+        Ok(size_bytes) => {
+            info!(
+                "Data export {} for user_uuid: {} is ready ({} bytes)",
+                export_id, user_uuid, size_bytes
+            );
+            Some(size_bytes)
+        }
+        Err(e) => {
+            error!("Failed to build data export {}: {}", export_id, e);
+            let _ =
+                tokio::fs::remove_file(export_path(&export_id).with_extension("zip.part")).await;
+            None
+        }
+    };
+    if let Err(e) = complete_export(&export_id, size_bytes).await {
+        error!("Failed to update data export {}: {}", export_id, e);
+    }
+}
+
+async fn remove_export_archive(export_id: &Uuid) {
+    let path = export_path(export_id);
+    match tokio::fs::remove_file(&path).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => error!("Failed to remove export {}: {}", path.display(), e),
+    }
+}
+
+/// Удаляет учётные записи, срок удаления которых наступил. Возвращает их количество
+pub async fn delete_due_accounts() -> Result<usize, Box<dyn StdError + Send + Sync>> {
+    let mut deleted = 0;
+    for user_uuid in find_due_deletions(Utc::now()).await? {
+        let exports = list_exports(&user_uuid).await?;
+        let file_ids = match delete_user(&user_uuid).await? {
+            Some(file_ids) => file_ids,
+            None => continue,
+        };
+        for export in exports {
+            remove_export_archive(&export.export_id).await;
+        }
+        remove_stored_files(&file_ids).await;
+
+        info!("Account {} deleted at the owner's request", user_uuid);
+        let entry = AuditEntry::own(AuditAction::UserDeleted, user_uuid);
+        record(entry, &ClientInfo::default()).await;
+        deleted += 1;
+    }
+    Ok(deleted)
+}
+
+/// Удаляет просроченные архивы. Возвращает их количество
+pub async fn purge_expired_exports() -> Result<usize, Box<dyn StdError + Send + Sync>> {
+    let expired = delete_expired_exports(Utc::now()).await?;
+    for export_id in &expired {
+        remove_export_archive(export_id).await;
+    }
+    Ok(expired.len())
+}
+
+/// Срок, до которого можно передумать (с точностью, которую хранит база)
+pub fn deletion_deadline() -> DateTime<Utc> {
+    (Utc::now() + Duration::days(deletion_grace_days())).trunc_subsecs(6)
+}
+
+/// Фоновое удаление учётных записей, старых архивов и удержаний имён раз в час
+pub async fn account_task() {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+    loop {
+        interval.tick().await;
+        match delete_due_accounts().await {
+            Ok(0) => {}
+            Ok(deleted) => info!("Deleted {} accounts after the grace period", deleted),
+            Err(e) => error!("Failed to delete scheduled accounts: {}", e),
+        }
+        match purge_expired_exports().await {
+            Ok(0) => {}
+            Ok(purged) => info!("Purged {} expired data exports", purged),
+            Err(e) => error!("Failed to purge data exports: {}", e),
+        }
+        let hold_until = Utc::now() - Duration::days(release_hold_days());
+        match purge_released_usernames(hold_until).await {
+            Ok(0) => {}
+            Ok(purged) => info!("Released {} held usernames", purged),
+            Err(e) => error!("Failed to purge released usernames: {}", e),
+        }
+    }
+}
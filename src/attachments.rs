@@ -0,0 +1,152 @@
+// src/attachments.rs
+//
+// Вложения в сообщениях чата: тип файла, адреса для скачивания и миниатюры
+// картинок. Миниатюры не хранятся — их делают по запросу, а браузер кэширует ответ.
+use crate::db::files::list_all_files;
+use crate::handlers::upload::upload_dir;
+use crate::models::{Attachment, ChatPayload, File, NotificationKind};
+use crate::notifications::notify;
+use image::ImageFormat;
+use log::error;
+use std::error::Error as StdError;
+use std::ffi::OsStr;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Сколько файлов можно вложить в одно сообщение
+pub const MAX_ATTACHMENTS: usize = 10;
+/// Наибольшая сторона миниатюры в пикселях
+const THUMBNAIL_SIZE: u32 = 256;
+/// Картинки больше этого размера не уменьшаются
+const MAX_THUMBNAIL_SOURCE_BYTES: i64 = 20 * 1024 * 1024;
+/// Типы, для которых есть миниатюра; их же можно показывать прямо в браузере
+const IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// Тип файла по имени; неизвестные — application/octet-stream
+pub fn guess_mime_type(filename: &str) -> String {
+    mime_guess::from_path(filename)
+        .first_or_octet_stream()
+        .essence_str()
+        .to_string()
+}
+
+/// Тип, присланный браузером при загрузке, без параметров; иначе — по имени файла
+pub fn upload_mime_type(content_type: Option<&str>, filename: &str) -> String {
+    content_type
+        .and_then(|content_type| content_type.parse::<mime_guess::mime::Mime>().ok())
+        .map(|mime| mime.essence_str().to_string())
+        .filter(|mime| mime != "application/octet-stream")
+        .unwrap_or_else(|| guess_mime_type(filename))
+}
+
+pub fn mime_type(file: &File) -> String {
+    file.mime_type
+        .clone()
+        .unwrap_or_else(|| guess_mime_type(&file.filename))
+}
+
+/// Картинка, которую можно показать в браузере как есть
+pub fn is_image(file: &File) -> bool {
+    IMAGE_TYPES.contains(&mime_type(file).as_str())
+}
+
+pub fn has_thumbnail(file: &File) -> bool {
+    is_image(file) && file.size_bytes <= MAX_THUMBNAIL_SOURCE_BYTES
+}
+
+/// Путь к содержимому файла на диске: файлы хранятся под своим file_id
+pub fn file_path(file_id: &Uuid) -> PathBuf {
+    Path::new(&upload_dir()).join(file_id.to_string())
+}
+
+/// Описание вложения для сообщения
+pub fn attachment(file: &File) -> Attachment {
+    Attachment {
+        file_id: file.file_id,
+        filename: file.filename.clone(),
+        size_bytes: file.size_bytes,
+        mime_type: mime_type(file),
+        url: format!("/api/files/{}", file.file_id),
+        thumbnail_url: has_thumbnail(file)
+            .then(|| format!("/api/files/{}/thumbnail", file.file_id)),
+    }
+}
+
+/// Уменьшает картинку до THUMBNAIL_SIZE по большей стороне и кодирует в PNG
+pub fn make_thumbnail(bytes: &[u8]) -> Result<Vec<u8>, Box<dyn StdError + Send + Sync>> {
+    let image = image::load_from_memory(bytes)?;
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    let mut png = Cursor::new(Vec::new());
+    thumbnail.write_to(&mut png, ImageFormat::Png)?;
+    Ok(png.into_inner())
+}
+
+/// Переносит файлы, загруженные, когда на диске они лежали под присланным
+/// именем, на место по file_id. Одно имя могло быть у нескольких записей,
+/// поэтому содержимое копируется, а старые файлы удаляются в конце
+pub async fn move_legacy_uploads() -> Result<usize, Box<dyn StdError + Send + Sync>> {
+    let upload_dir = upload_dir();
+    let mut legacy_paths: Vec<PathBuf> = Vec::new();
+    for file in list_all_files().await? {
+        let target = file_path(&file.file_id);
+        // Имена с каталогами в старых записях не переносятся
+        if Path::new(&file.filename).file_name() != Some(OsStr::new(&file.filename))
+            || tokio::fs::try_exists(&target).await?
+        {
+            continue;
+        }
+        let legacy = Path::new(&upload_dir).join(&file.filename);
+        if !tokio::fs::try_exists(&legacy).await? {
+            continue;
+        }
+        tokio::fs::copy(&legacy, &target).await?;
+        if !legacy_paths.contains(&legacy) {
+            legacy_paths.push(legacy);
+        }
+    }
+    for legacy in &legacy_paths {
+        if let Err(e) = tokio::fs::remove_file(legacy).await {
+            error!("Failed to remove legacy upload {}: {}", legacy.display(), e);
+        }
+    }
+    Ok(legacy_paths.len())
+}
+
+/// Удаляет с диска содержимое файлов, записи о которых уже удалены
+pub async fn remove_stored_files(file_ids: &[Uuid]) {
+    for file_id in file_ids {
+        if let Err(e) = tokio::fs::remove_file(file_path(file_id)).await {
+            error!("Failed to remove file {}: {}", file_id, e);
+        }
+    }
+}
+
+/// Уведомляет владельцев файлов, которые другой пользователь вложил в
+/// сообщение: теперь их видят все участники разговора. Один владелец получает
+/// одно уведомление на сообщение; о своих файлах автор не уведомляется
+pub async fn notify_file_shares(sharer_uuid: Uuid, payload: &ChatPayload, files: &[File]) {
+    let mut owners: Vec<Uuid> = Vec::new();
+    for file in files {
+        if file.user_uuid != sharer_uuid && !owners.contains(&file.user_uuid) {
+            owners.push(file.user_uuid);
+        }
+    }
+    for owner in owners {
+        let filenames: Vec<&str> = files
+            .iter()
+            .filter(|file| file.user_uuid == owner)
+            .map(|file| file.filename.as_str())
+            .collect();
+        if let Err(e) = notify(
+            owner,
+            NotificationKind::FileShared,
+            format!("{} shared your file: {}", payload.author, filenames.join(", ")),
+            Some(format!("/static/chat.html#message-{}", payload.message_id)),
+        )
+        .await
+        {
+            error!("Failed to notify {} about a shared file: {}", owner, e);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+// src/audit.rs
+//
+// Журнал безопасности: входы, выходы, смена пароля, отзыв сессий и другие
+// действия с учётной записью. Ошибка записи в журнал не прерывает запрос.
+// Срок хранения — AUDIT_LOG_RETENTION_DAYS (по умолчанию 365, 0 — бессрочно).
+use crate::db::audit::{purge_audit_events, save_audit_event};
+use crate::db::sessions::find_session_by_session_id;
+use crate::devices::DEVICE_COOKIE;
+use crate::models::AuditAction;
+use chrono::{Duration, Utc};
+use log::{error, info};
+use std::net::{IpAddr, SocketAddr};
+use uuid::Uuid;
+use warp::{Filter, Rejection};
+
+const DEFAULT_RETENTION_DAYS: i64 = 365;
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+/// Откуда пришёл запрос
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    /// Сессия запроса: по ней журнал находит устройство
+    pub session_id: Option<Uuid>,
+    /// Токен устройства из cookie (см. crate::devices)
+    pub device_token: Option<String>,
+}
+
+/// Адрес, User-Agent и сессия текущего запроса
+pub fn with_client_info() -> impl Filter<Extract = (ClientInfo,), Error = Rejection> + Clone {
+    warp::addr::remote()
+        .and(warp::header::optional::<String>("user-agent"))
+        .and(warp::cookie::optional::<String>("session_id"))
+        .and(warp::cookie::optional::<String>(DEVICE_COOKIE))
+        .map(
+            |addr: Option<SocketAddr>,
+             user_agent: Option<String>,
+             session_id: Option<String>,
+             device_token: Option<String>| ClientInfo {
+                ip_address: addr.map(|addr| addr.ip()),
+                user_agent: user_agent
+                    .map(|user_agent| user_agent.chars().take(MAX_USER_AGENT_LENGTH).collect()),
+                session_id: session_id.and_then(|session_id| Uuid::parse_str(&session_id).ok()),
+                device_token,
+            },
+        )
+}
+
+/// Запись для журнала
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub action: AuditAction,
+    pub actor_uuid: Option<Uuid>,
+    pub target_uuid: Option<Uuid>,
+    pub target: Option<String>,
+    pub device_id: Option<Uuid>,
+}
+
+impl AuditEntry {
+    /// Действие пользователя над собственной учётной записью
+    pub fn own(action: AuditAction, user_uuid: Uuid) -> AuditEntry {
+        AuditEntry {
+            action,
+            actor_uuid: Some(user_uuid),
+            target_uuid: Some(user_uuid),
+            target: None,
+            device_id: None,
+        }
+    }
+
+    /// Действие `actor_uuid` над чужой учётной записью (администратор)
+    pub fn on_user(action: AuditAction, actor_uuid: Uuid, target_uuid: Uuid) -> AuditEntry {
+        AuditEntry {
+            action,
+            actor_uuid: Some(actor_uuid),
+            target_uuid: Some(target_uuid),
+            target: None,
+            device_id: None,
+        }
+    }
+
+    pub fn with_target(mut self, target: impl Into<String>) -> AuditEntry {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn with_device(mut self, device_id: Uuid) -> AuditEntry {
+        self.device_id = Some(device_id);
+        self
+    }
+}
+
+/// Записывает событие. Устройство берётся из сессии запроса, если не указано
+pub async fn record(entry: AuditEntry, client: &ClientInfo) {
+    let mut device_id = entry.device_id;
+    if device_id.is_none() {
+        if let Some(session_id) = client.session_id {
+            if let Ok(Some(session)) = find_session_by_session_id(&session_id).await {
+                device_id = Some(session.device_id);
+            }
+        }
+    }
+
+    if let Err(e) = save_audit_event(
+        entry.actor_uuid,
+        entry.action,
+        entry.target_uuid,
+        entry.target.as_deref(),
+        device_id,
+        client.ip_address,
+        client.user_agent.as_deref(),
+    )
+    .await
+    {
+        error!("Failed to write audit event {}: {}", entry.action, e);
+    }
+}
+
+pub fn retention_days() -> i64 {
+    std::env::var("AUDIT_LOG_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+}
+
+/// Удаляет записи старше срока хранения. Возвращает их количество
+pub async fn purge_expired_events() -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let days = retention_days();
+    if days <= 0 {
+        return Ok(0);
+    }
+    purge_audit_events(Utc::now() - Duration::days(days)).await
+}
+
+/// Фоновая очистка журнала раз в сутки
+pub async fn retention_task() {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(24 * 60 * 60));
+    loop {
+        interval.tick().await;
+        match purge_expired_events().await {
+            Ok(0) => {}
+            Ok(deleted) => info!("Purged {} expired audit events", deleted),
+            Err(e) => error!("Failed to purge audit log: {}", e),
+        }
+    }
+}
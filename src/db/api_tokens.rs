@@ -0,0 +1,128 @@
+// src/db/api_tokens.rs
+use crate::db::connect_to_db;
+use crate::models::ApiToken;
+use log::debug;
+use std::error::Error as StdError;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+const TOKEN_COLUMNS: &str =
+    "token_id, user_uuid, name, scopes, expires_at, last_used_at, created_at";
+
+fn token_from_row(row: &Row) -> ApiToken {
+    ApiToken {
+        token_id: row.get(0),
+        user_uuid: row.get(1),
+        name: row.get(2),
+        scopes: row.get(3),
+        expires_at: row.get(4),
+        last_used_at: row.get(5),
+        created_at: row.get(6),
+    }
+}
+
+/// Сохраняет новый токен вместе с хешем секрета
+pub async fn save_api_token(
+    token: &ApiToken,
+    token_hash: &str,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!(
+        "Saving API token {} for user_uuid: {}",
+        token.token_id, token.user_uuid
+    );
+
+    let scopes: Vec<String> = token.scopes.iter().map(|scope| scope.to_string()).collect();
+    client
+        .execute(
+            "INSERT INTO api_tokens (token_id, user_uuid, name, token_hash, scopes, expires_at, created_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            &[
+                &token.token_id,
+                &token.user_uuid,
+                &token.name,
+                &token_hash,
+                &scopes,
+                &token.expires_at,
+                &token.created_at,
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Ищет токен по хешу секрета
+pub async fn find_api_token_by_hash(
+    token_hash: &str,
+) -> Result<Option<ApiToken>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM api_tokens WHERE token_hash = $1",
+                TOKEN_COLUMNS
+            ),
+            &[&token_hash],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(token_from_row))
+}
+
+/// Токены пользователя, новые сначала
+pub async fn list_api_tokens(
+    user_uuid: &Uuid,
+) -> Result<Vec<ApiToken>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM api_tokens WHERE user_uuid = $1 ORDER BY created_at DESC",
+                TOKEN_COLUMNS
+            ),
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(rows.iter().map(token_from_row).collect())
+}
+
+/// Отзывает токен пользователя. Возвращает false, если такого токена нет
+pub async fn delete_api_token(
+    token_id: &Uuid,
+    user_uuid: &Uuid,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!(
+        "Deleting API token {} for user_uuid: {}",
+        token_id, user_uuid
+    );
+
+    let deleted = client
+        .execute(
+            "DELETE FROM api_tokens WHERE token_id = $1 AND user_uuid = $2",
+            &[&token_id, &user_uuid],
+        )
+        .await?;
+
+    Ok(deleted > 0)
+}
+
+/// Запоминает время последнего использования токена
+pub async fn touch_api_token(token_id: &Uuid) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    client
+        .execute(
+            "UPDATE api_tokens SET last_used_at = NOW() WHERE token_id = $1",
+            &[&token_id],
+        )
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+// src/db/attachments.rs
+use crate::attachments::attachment;
+use crate::db::connect_to_db;
+use crate::db::files::{file_from_row, FILE_COLUMNS};
+use crate::models::Attachment;
+use log::debug;
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use uuid::Uuid;
+
+/// Сохраняет вложения сообщения в заданном порядке
+pub async fn save_attachments(
+    message_id: i64,
+    user_uuid: &Uuid,
+    file_ids: &[Uuid],
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    if file_ids.is_empty() {
+        return Ok(());
+    }
+    let client = connect_to_db().await?;
+
+    debug!(
+        "Saving {} attachments in message {}",
+        file_ids.len(),
+        message_id
+    );
+
+    let positions: Vec<i32> = (0..file_ids.len() as i32).collect();
+    client
+        .execute(
+            "INSERT INTO message_attachments (message_id, file_id, position, attached_by) \
+             SELECT $1, file_id, position, $2 FROM UNNEST($3::UUID[], $4::INTEGER[]) AS a (file_id, position) \
+             ON CONFLICT DO NOTHING",
+            &[&message_id, &user_uuid, &file_ids, &positions],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Вложения в сообщениях, по порядку
+pub async fn attachments_for_messages(
+    message_ids: &[i64],
+) -> Result<HashMap<i64, Vec<Attachment>>, Box<dyn StdError + Send + Sync>> {
+    let mut attachments: HashMap<i64, Vec<Attachment>> = HashMap::new();
+    if message_ids.is_empty() {
+        return Ok(attachments);
+    }
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {}, ma.message_id FROM message_attachments ma JOIN files f ON f.file_id = ma.file_id \
+                 WHERE ma.message_id = ANY($1) ORDER BY ma.message_id, ma.position",
+                FILE_COLUMNS
+            ),
+            &[&message_ids],
+        )
+        .await?;
+
+    for row in rows {
+        attachments
+            .entry(row.get(6))
+            .or_default()
+            .push(attachment(&file_from_row(&row)));
+    }
+    Ok(attachments)
+}
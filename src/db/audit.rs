@@ -0,0 +1,105 @@
+// src/db/audit.rs
+use crate::db::connect_to_db;
+use crate::models::{AuditAction, AuditEvent};
+use chrono::{DateTime, Utc};
+use log::debug;
+use std::error::Error as StdError;
+use std::net::IpAddr;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+const EVENT_COLUMNS: &str =
+    "event_id, actor_uuid, action, target_uuid, target, device_id, ip_address, user_agent, created_at";
+
+fn event_from_row(row: &Row) -> AuditEvent {
+    AuditEvent {
+        event_id: row.get(0),
+        actor_uuid: row.get(1),
+        action: row.get(2),
+        target_uuid: row.get(3),
+        target: row.get(4),
+        device_id: row.get(5),
+        ip_address: row.get(6),
+        user_agent: row.get(7),
+        created_at: row.get(8),
+    }
+}
+
+/// Добавляет запись в журнал безопасности
+#[allow(clippy::too_many_arguments)]
+pub async fn save_audit_event(
+    actor_uuid: Option<Uuid>,
+    action: AuditAction,
+    target_uuid: Option<Uuid>,
+    target: Option<&str>,
+    device_id: Option<Uuid>,
+    ip_address: Option<IpAddr>,
+    user_agent: Option<&str>,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Saving audit event {} by {:?}", action, actor_uuid);
+
+    client
+        .execute(
+            "INSERT INTO audit_log (actor_uuid, action, target_uuid, target, device_id, ip_address, user_agent) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            &[
+                &actor_uuid,
+                &action.as_str(),
+                &target_uuid,
+                &target,
+                &device_id,
+                &ip_address,
+                &user_agent,
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// События, где пользователь — исполнитель или объект (None — все),
+/// от новых к старым, с идентификатором меньше `before`
+pub async fn list_audit_events(
+    user_uuid: Option<Uuid>,
+    action: Option<AuditAction>,
+    before: Option<i64>,
+    limit: i64,
+) -> Result<Vec<AuditEvent>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM audit_log \
+                 WHERE ($1::uuid IS NULL OR actor_uuid = $1 OR target_uuid = $1) \
+                 AND ($2::varchar IS NULL OR action = $2) \
+                 AND ($3::bigint IS NULL OR event_id < $3) \
+                 ORDER BY event_id DESC LIMIT $4",
+                EVENT_COLUMNS
+            ),
+            &[
+                &user_uuid,
+                &action.map(|action| action.as_str()),
+                &before,
+                &limit,
+            ],
+        )
+        .await?;
+
+    Ok(rows.iter().map(event_from_row).collect())
+}
+
+/// Удаляет записи старше `before` (срок хранения). Возвращает их количество
+pub async fn purge_audit_events(
+    before: DateTime<Utc>,
+) -> Result<u64, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let deleted = client
+        .execute("DELETE FROM audit_log WHERE created_at < $1", &[&before])
+        .await?;
+
+    Ok(deleted)
+}
@@ -1,58 +1,236 @@
-use log::{debug, error};
-use std::error::Error as StdError;
-use std::net::IpAddr; // Импортируем функцию
 use crate::db::connect_to_db; // Правильный импорт
-use crate::models::Device; // Импортируем функцию
+use crate::models::{Device, DeviceIp};
+use log::debug;
+use std::error::Error as StdError;
+use std::net::IpAddr;
+use tokio_postgres::Row;
+use uuid::Uuid;
 
-/// Сохраняет устройство в базу данных
-pub async fn save_device_to_db(device: Device) -> Result<(), Box<dyn StdError + Send + Sync>> {
-    let client = connect_to_db().await?;
+const DEVICE_COLUMNS: &str =
+    "device_id, user_uuid, ip_address, name, user_agent, trusted, created_at, last_seen_at, fingerprint";
+
+fn device_from_row(row: &Row) -> Device {
+    Device {
+        device_id: row.get(0),
+        user_uuid: row.get(1),
+        ip_address: row.get::<_, IpAddr>(2).to_string(),
+        name: row.get(3),
+        user_agent: row.get(4),
+        trusted: row.get(5),
+        created_at: row.get(6),
+        last_seen_at: row.get(7),
+        fingerprint: row.get(8),
+    }
+}
+
+/// Сохраняет новое устройство вместе с хешем его токена и первым адресом
+pub async fn save_device_to_db(
+    device: &Device,
+    token_hash: &str,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let mut client = connect_to_db().await?;
 
     debug!("Saving device to database: {:?}", device);
 
-    client
+    let ip_address: IpAddr = device.ip_address.parse()?;
+    let transaction = client.transaction().await?;
+    transaction
         .execute(
-            "INSERT INTO devices (device_id, user_uuid, ip_address) VALUES ($1, $2, $3)",
+            "INSERT INTO devices (device_id, user_uuid, ip_address, token_hash, fingerprint, user_agent, last_seen_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, NOW())",
             &[
                 &device.device_id,
                 &device.user_uuid,
-                &device.ip_address.parse::<IpAddr>().unwrap(),
+                &ip_address,
+                &token_hash,
+                &device.fingerprint,
+                &device.user_agent,
             ],
         )
         .await?;
+    transaction
+        .execute(
+            "INSERT INTO device_ips (device_id, ip_address) VALUES ($1, $2)",
+            &[&device.device_id, &ip_address],
+        )
+        .await?;
+    transaction.commit().await?;
 
     Ok(())
 }
 
-/// Ищет устройство по IP-адресу
-pub async fn find_device_by_ip_mac(
-    ip_address: &str,
+/// Ищет устройство пользователя по хешу токена из cookie
+pub async fn find_device_by_token(
+    user_uuid: &Uuid,
+    token_hash: &str,
 ) -> Result<Option<Device>, Box<dyn StdError + Send + Sync>> {
-    use std::net::IpAddr;
     let client = connect_to_db().await?;
 
-    debug!("Finding device by IP: {}", ip_address);
-
-    let ip_addr: IpAddr = ip_address.parse().map_err(|e| {
-        error!("Failed to parse IP address: {}", e);
-        e
-    })?;
+    debug!("Finding device by token for user_uuid: {}", user_uuid);
 
     let row = client
         .query_opt(
-            "SELECT device_id, user_uuid, ip_address FROM devices WHERE ip_address = $1",
-            &[&ip_addr],
+            &format!(
+                "SELECT {} FROM devices WHERE user_uuid = $1 AND token_hash = $2",
+                DEVICE_COLUMNS
+            ),
+            &[&user_uuid, &token_hash],
         )
         .await?;
 
-    if let Some(row) = row {
-        let device = Device {
-            device_id: row.get(0),
-            user_uuid: row.get(1),
-            ip_address: row.get::<_, IpAddr>(2).to_string(),
-        };
-        Ok(Some(device))
-    } else {
-        Ok(None)
-    }
+    Ok(row.as_ref().map(device_from_row))
+}
+
+/// Отмечает вход с устройства: последний адрес, User-Agent и история адресов
+pub async fn touch_device(
+    device_id: &Uuid,
+    ip_address: IpAddr,
+    user_agent: Option<&str>,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let mut client = connect_to_db().await?;
+
+    let transaction = client.transaction().await?;
+    transaction
+        .execute(
+            "UPDATE devices SET ip_address = $2, user_agent = COALESCE($3, user_agent), last_seen_at = NOW() \
+             WHERE device_id = $1",
+            &[&device_id, &ip_address, &user_agent],
+        )
+        .await?;
+    transaction
+        .execute(
+            "INSERT INTO device_ips (device_id, ip_address) VALUES ($1, $2) \
+             ON CONFLICT (device_id, ip_address) DO UPDATE SET last_seen_at = NOW()",
+            &[&device_id, &ip_address],
+        )
+        .await?;
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+/// Возвращает все устройства пользователя
+pub async fn find_devices_by_user_uuid(
+    user_uuid: &Uuid,
+) -> Result<Vec<Device>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Finding devices for user_uuid: {}", user_uuid);
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM devices WHERE user_uuid = $1 ORDER BY created_at",
+                DEVICE_COLUMNS
+            ),
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(rows.iter().map(device_from_row).collect())
+}
+
+/// Есть ли у пользователя хоть одно устройство
+pub async fn has_devices(user_uuid: &Uuid) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_one(
+            "SELECT EXISTS (SELECT 1 FROM devices WHERE user_uuid = $1)",
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(row.get(0))
+}
+
+/// Входил ли пользователь из той же сети: /24 для IPv4, /48 для IPv6
+pub async fn ip_range_seen(
+    user_uuid: &Uuid,
+    ip_address: IpAddr,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_one(
+            "SELECT EXISTS (SELECT 1 FROM device_ips di JOIN devices d ON d.device_id = di.device_id \
+             WHERE d.user_uuid = $1 \
+             AND di.ip_address <<= network(set_masklen($2::inet, CASE family($2::inet) WHEN 4 THEN 24 ELSE 48 END)))",
+            &[&user_uuid, &ip_address],
+        )
+        .await?;
+
+    Ok(row.get(0))
+}
+
+/// История адресов устройства, последние сначала
+pub async fn find_device_ips(
+    device_id: &Uuid,
+) -> Result<Vec<DeviceIp>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            "SELECT ip_address, first_seen_at, last_seen_at FROM device_ips \
+             WHERE device_id = $1 ORDER BY last_seen_at DESC",
+            &[&device_id],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| DeviceIp {
+            ip_address: row.get::<_, IpAddr>(0).to_string(),
+            first_seen_at: row.get(1),
+            last_seen_at: row.get(2),
+        })
+        .collect())
+}
+
+/// Меняет название и доверие устройства. Возвращает false, если устройства нет
+pub async fn update_device(
+    device_id: &Uuid,
+    user_uuid: &Uuid,
+    name: Option<&str>,
+    trusted: bool,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Updating device {} for user_uuid: {}", device_id, user_uuid);
+
+    let updated = client
+        .execute(
+            "UPDATE devices SET name = $3, trusted = $4 WHERE device_id = $1 AND user_uuid = $2",
+            &[&device_id, &user_uuid, &name, &trusted],
+        )
+        .await?;
+
+    Ok(updated > 0)
+}
+
+/// Удаляет устройство и завершает его сессии. Возвращает false, если устройства нет
+pub async fn delete_device(
+    device_id: &Uuid,
+    user_uuid: &Uuid,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let mut client = connect_to_db().await?;
+
+    debug!("Deleting device {} for user_uuid: {}", device_id, user_uuid);
+
+    let transaction = client.transaction().await?;
+    transaction
+        .execute(
+            "DELETE FROM sessions WHERE device_id = $1 AND user_uuid = $2",
+            &[&device_id, &user_uuid],
+        )
+        .await?;
+    let deleted = transaction
+        .execute(
+            "DELETE FROM devices WHERE device_id = $1 AND user_uuid = $2",
+            &[&device_id, &user_uuid],
+        )
+        .await?;
+    transaction.commit().await?;
+
+    Ok(deleted > 0)
 }
@@ -0,0 +1,128 @@
+// src/db/exports.rs
+use crate::db::connect_to_db;
+use crate::models::{DataExport, ExportStatus};
+use chrono::{DateTime, Utc};
+use log::debug;
+use std::error::Error as StdError;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+const EXPORT_COLUMNS: &str =
+    "export_id, user_uuid, status, size_bytes, created_at, completed_at, expires_at";
+
+fn export_from_row(row: &Row) -> DataExport {
+    DataExport {
+        export_id: row.get(0),
+        user_uuid: row.get(1),
+        status: row.get(2),
+        size_bytes: row.get(3),
+        created_at: row.get(4),
+        completed_at: row.get(5),
+        expires_at: row.get(6),
+    }
+}
+
+/// Заводит выгрузку, если у пользователя нет незавершённой.
+/// Возвращает false, если выгрузка уже готовится
+pub async fn create_export(export: &DataExport) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!(
+        "Creating data export {} for user_uuid: {}",
+        export.export_id, export.user_uuid
+    );
+
+    let inserted = client
+        .execute(
+            "INSERT INTO data_exports (export_id, user_uuid, status, created_at, expires_at) \
+             SELECT $1, $2, $3, $4, $5 \
+             WHERE NOT EXISTS (SELECT 1 FROM data_exports WHERE user_uuid = $2 AND status = 'pending')",
+            &[
+                &export.export_id,
+                &export.user_uuid,
+                &export.status.as_str(),
+                &export.created_at,
+                &export.expires_at,
+            ],
+        )
+        .await?;
+
+    Ok(inserted > 0)
+}
+
+/// Отмечает выгрузку готовой (size_bytes) или неудавшейся (None)
+pub async fn complete_export(
+    export_id: &Uuid,
+    size_bytes: Option<i64>,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let status = match size_bytes {
+        Some(_) => ExportStatus::Ready,
+        None => ExportStatus::Failed,
+    };
+    client
+        .execute(
+            "UPDATE data_exports SET status = $2, size_bytes = $3, completed_at = NOW() WHERE export_id = $1",
+            &[&export_id, &status.as_str(), &size_bytes],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Выгрузка пользователя
+pub async fn find_export(
+    export_id: &Uuid,
+    user_uuid: &Uuid,
+) -> Result<Option<DataExport>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM data_exports WHERE export_id = $1 AND user_uuid = $2",
+                EXPORT_COLUMNS
+            ),
+            &[&export_id, &user_uuid],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(export_from_row))
+}
+
+/// Выгрузки пользователя, новые сначала
+pub async fn list_exports(
+    user_uuid: &Uuid,
+) -> Result<Vec<DataExport>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM data_exports WHERE user_uuid = $1 ORDER BY created_at DESC",
+                EXPORT_COLUMNS
+            ),
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(rows.iter().map(export_from_row).collect())
+}
+
+/// Удаляет записи о просроченных выгрузках.
+/// Возвращает их идентификаторы, чтобы убрать архивы с диска
+pub async fn delete_expired_exports(
+    now: DateTime<Utc>,
+) -> Result<Vec<Uuid>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            "DELETE FROM data_exports WHERE expires_at < $1 RETURNING export_id",
+            &[&now],
+        )
+        .await?;
+
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
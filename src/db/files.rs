@@ -1,15 +1,32 @@
 // src/db/files.rs
-use crate::models::FileInfo;
+use crate::models::{File, FileInfo};
 use chrono::{DateTime, Utc}; // Добавляем импорт
 use log::debug;
 use std::error::Error as StdError;
+use tokio_postgres::Row;
 use uuid::Uuid;
 use crate::db::connect_to_db;
 
-/// Сохраняет информацию о файле в базу данных
+pub(crate) const FILE_COLUMNS: &str = "f.file_id, f.user_uuid, f.filename, f.upload_time, f.size_bytes, f.mime_type";
+
+pub(crate) fn file_from_row(row: &Row) -> File {
+    File {
+        file_id: row.get(0),
+        user_uuid: row.get(1),
+        filename: row.get(2),
+        upload_time: row.get(3),
+        size_bytes: row.get(4),
+        mime_type: row.get(5),
+    }
+}
+
+/// Сохраняет информацию о файле, уже записанном на диск под file_id
 pub async fn save_file_info(
+    file_id: &Uuid,
     filename: &str,
     user_uuid: Uuid,
+    size_bytes: i64,
+    mime_type: &str,
 ) -> Result<(), Box<dyn StdError + Send + Sync>> {
     let client = connect_to_db().await?;
 
@@ -19,11 +36,9 @@ pub async fn save_file_info(
         "Saving file info to database: filename={}, user_uuid={}",
         filename, user_uuid
     );
-    let file_uuid = Uuid::new_v4(); // Add this line
-
     client.execute(
-        "INSERT INTO files (file_id, filename, user_uuid, upload_time) VALUES ($1, $2, $3, NOW())", // Update this line
-        &[&file_uuid, &filename, &user_uuid], // Update this line
+        "INSERT INTO files (file_id, filename, user_uuid, upload_time, size_bytes, mime_type) VALUES ($1, $2, $3, NOW(), $4, $5)",
+        &[&file_id, &filename, &user_uuid, &size_bytes, &mime_type],
     )
     .await?;
 
@@ -63,3 +78,85 @@ pub async fn get_files_by_user_uuid(
 
     Ok(files)
 }
+
+/// Возвращает количество файлов пользователя и их суммарный размер в байтах
+pub async fn get_storage_usage(
+    user_uuid: Uuid,
+) -> Result<(i64, i64), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Getting storage usage for user_uuid: {}", user_uuid);
+
+    let row = client
+        .query_one(
+            "SELECT COUNT(*), COALESCE(SUM(size_bytes), 0)::BIGINT FROM files WHERE user_uuid = $1",
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok((row.get(0), row.get(1)))
+}
+
+/// Все файлы всех пользователей
+pub async fn list_all_files() -> Result<Vec<File>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(&format!("SELECT {} FROM files f", FILE_COLUMNS), &[])
+        .await?;
+
+    Ok(rows.iter().map(file_from_row).collect())
+}
+
+/// Все файлы пользователя вместе с размером
+pub async fn list_files_by_user_uuid(
+    user_uuid: &Uuid,
+) -> Result<Vec<File>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM files f WHERE f.user_uuid = $1 ORDER BY f.upload_time",
+                FILE_COLUMNS
+            ),
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(rows.iter().map(file_from_row).collect())
+}
+
+/// Файлы из списка, которые пользователь может читать и вкладывать в сообщения:
+/// свои и вложенные в сообщения, которые не удалены
+pub async fn find_readable_files(
+    user_uuid: &Uuid,
+    file_ids: &[Uuid],
+) -> Result<Vec<File>, Box<dyn StdError + Send + Sync>> {
+    if file_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM files f WHERE f.file_id = ANY($2) AND (f.user_uuid = $1 OR EXISTS ( \
+                 SELECT 1 FROM message_attachments ma JOIN messages m ON m.message_id = ma.message_id \
+                 WHERE ma.file_id = f.file_id AND m.deleted_at IS NULL))",
+                FILE_COLUMNS
+            ),
+            &[&user_uuid, &file_ids],
+        )
+        .await?;
+
+    Ok(rows.iter().map(file_from_row).collect())
+}
+
+/// Файл, если пользователь может его читать
+pub async fn find_readable_file(
+    user_uuid: &Uuid,
+    file_id: &Uuid,
+) -> Result<Option<File>, Box<dyn StdError + Send + Sync>> {
+    Ok(find_readable_files(user_uuid, &[*file_id]).await?.pop())
+}
@@ -0,0 +1,110 @@
+// src/db/login_alerts.rs
+use crate::db::connect_to_db;
+use crate::models::LoginAlert;
+use log::debug;
+use std::error::Error as StdError;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+const ALERT_COLUMNS: &str =
+    "alert_id, user_uuid, session_id, device_id, ip_address, user_agent, expires_at, revoked_at";
+
+fn alert_from_row(row: &Row) -> LoginAlert {
+    LoginAlert {
+        alert_id: row.get(0),
+        user_uuid: row.get(1),
+        session_id: row.get(2),
+        device_id: row.get(3),
+        ip_address: row.get(4),
+        user_agent: row.get(5),
+        expires_at: row.get(6),
+        revoked_at: row.get(7),
+    }
+}
+
+/// Сохраняет предупреждение вместе с хешем токена из письма
+pub async fn save_login_alert(
+    alert: &LoginAlert,
+    token_hash: &str,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!(
+        "Saving login alert {} for user_uuid: {}",
+        alert.alert_id, alert.user_uuid
+    );
+
+    client
+        .execute(
+            "INSERT INTO login_alerts (alert_id, user_uuid, session_id, device_id, ip_address, user_agent, token_hash, expires_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            &[
+                &alert.alert_id,
+                &alert.user_uuid,
+                &alert.session_id,
+                &alert.device_id,
+                &alert.ip_address,
+                &alert.user_agent,
+                &token_hash,
+                &alert.expires_at,
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Ищет предупреждение по хешу токена из письма
+pub async fn find_login_alert_by_token(
+    token_hash: &str,
+) -> Result<Option<LoginAlert>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM login_alerts WHERE token_hash = $1",
+                ALERT_COLUMNS
+            ),
+            &[&token_hash],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(alert_from_row))
+}
+
+/// Ищет предупреждение пользователя
+pub async fn find_login_alert(
+    alert_id: &Uuid,
+    user_uuid: &Uuid,
+) -> Result<Option<LoginAlert>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM login_alerts WHERE alert_id = $1 AND user_uuid = $2",
+                ALERT_COLUMNS
+            ),
+            &[&alert_id, &user_uuid],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(alert_from_row))
+}
+
+/// Отмечает предупреждение обработанным. Возвращает false, если это уже сделано
+pub async fn mark_login_alert_revoked(
+    alert_id: &Uuid,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let updated = client
+        .execute(
+            "UPDATE login_alerts SET revoked_at = NOW() WHERE alert_id = $1 AND revoked_at IS NULL",
+            &[&alert_id],
+        )
+        .await?;
+
+    Ok(updated > 0)
+}
@@ -0,0 +1,152 @@
+// src/db/login_attempts.rs
+use crate::db::connect_to_db;
+use crate::models::LoginLockout;
+use chrono::{DateTime, Utc};
+use log::debug;
+use std::error::Error as StdError;
+use std::net::IpAddr;
+use tokio_postgres::Row;
+
+fn lockout_from_row(row: &Row) -> LoginLockout {
+    LoginLockout {
+        lockout_id: row.get(0),
+        scope: row.get(1),
+        key: row.get(2),
+        failed_attempts: row.get(3),
+        locked_until: row.get(4),
+        created_at: row.get(5),
+    }
+}
+
+/// Записывает попытку входа
+pub async fn record_login_attempt(
+    username: &str,
+    ip_address: IpAddr,
+    success: bool,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!(
+        "Recording login attempt: username={}, ip={}, success={}",
+        username, ip_address, success
+    );
+
+    client
+        .execute(
+            "INSERT INTO login_attempts (username, ip_address, success) VALUES ($1, $2, $3)",
+            &[&username, &ip_address, &success],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Количество неудачных попыток для учётной записи после `since`
+/// и после последнего успешного входа
+pub async fn count_account_failures(
+    username: &str,
+    since: DateTime<Utc>,
+) -> Result<i64, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_one(
+            "SELECT COUNT(*) FROM login_attempts WHERE username = $1 AND NOT success \
+             AND attempted_at > GREATEST($2, (SELECT MAX(attempted_at) FROM login_attempts WHERE username = $1 AND success))",
+            &[&username, &since],
+        )
+        .await?;
+
+    Ok(row.get(0))
+}
+
+/// Количество неудачных попыток с IP-адреса после `since`
+pub async fn count_ip_failures(
+    ip_address: IpAddr,
+    since: DateTime<Utc>,
+) -> Result<i64, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_one(
+            "SELECT COUNT(*) FROM login_attempts WHERE ip_address = $1 AND NOT success AND attempted_at > $2",
+            &[&ip_address, &since],
+        )
+        .await?;
+
+    Ok(row.get(0))
+}
+
+/// Сохраняет блокировку входа
+pub async fn save_lockout(
+    scope: &str,
+    key: &str,
+    failed_attempts: i32,
+    locked_until: DateTime<Utc>,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Locking out {} {} until {}", scope, key, locked_until);
+
+    client
+        .execute(
+            "INSERT INTO login_lockouts (scope, key, failed_attempts, locked_until) VALUES ($1, $2, $3, $4)",
+            &[&scope, &key, &failed_attempts, &locked_until],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Возвращает время окончания самой долгой действующей блокировки
+/// для учётной записи или IP-адреса
+pub async fn find_active_lockout(
+    username: &str,
+    ip_address: IpAddr,
+) -> Result<Option<DateTime<Utc>>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_one(
+            "SELECT MAX(locked_until) FROM login_lockouts WHERE locked_until > NOW() \
+             AND ((scope = 'account' AND key = $1) OR (scope = 'ip' AND key = $2))",
+            &[&username, &ip_address.to_string()],
+        )
+        .await?;
+
+    Ok(row.get(0))
+}
+
+/// Последние блокировки (для администраторов)
+pub async fn list_lockouts(
+    active_only: bool,
+    limit: i64,
+) -> Result<Vec<LoginLockout>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            "SELECT lockout_id, scope, key, failed_attempts, locked_until, created_at FROM login_lockouts \
+             WHERE NOT $1 OR locked_until > NOW() ORDER BY created_at DESC LIMIT $2",
+            &[&active_only, &limit],
+        )
+        .await?;
+
+    Ok(rows.iter().map(lockout_from_row).collect())
+}
+
+/// Досрочно снимает блокировку. Возвращает false, если блокировки нет
+pub async fn clear_lockout(lockout_id: i64) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Clearing lockout {}", lockout_id);
+
+    let updated = client
+        .execute(
+            "UPDATE login_lockouts SET locked_until = NOW() WHERE lockout_id = $1 AND locked_until > NOW()",
+            &[&lockout_id],
+        )
+        .await?;
+
+    Ok(updated > 0)
+}
@@ -0,0 +1,92 @@
+// src/db/mentions.rs
+use crate::db::connect_to_db;
+use crate::models::Mention;
+use log::debug;
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use uuid::Uuid;
+
+/// Пользователи по именам без учёта регистра: имя в нижнем регистре → (user_uuid, имя)
+pub async fn find_users_by_usernames(
+    usernames: &[String],
+) -> Result<HashMap<String, (Uuid, String)>, Box<dyn StdError + Send + Sync>> {
+    let mut users = HashMap::new();
+    if usernames.is_empty() {
+        return Ok(users);
+    }
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            "SELECT user_uuid, username FROM users WHERE LOWER(username) = ANY($1)",
+            &[&usernames],
+        )
+        .await?;
+
+    for row in rows {
+        let username: String = row.get(1);
+        users.insert(username.to_lowercase(), (row.get(0), username));
+    }
+    Ok(users)
+}
+
+/// Сохраняет упоминания в сообщении
+pub async fn save_mentions(
+    message_id: i64,
+    mentions: &[Mention],
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    if mentions.is_empty() {
+        return Ok(());
+    }
+    let client = connect_to_db().await?;
+
+    debug!(
+        "Saving {} mentions in message {}",
+        mentions.len(),
+        message_id
+    );
+
+    let starts: Vec<i32> = mentions.iter().map(|mention| mention.start).collect();
+    let ends: Vec<i32> = mentions.iter().map(|mention| mention.end).collect();
+    let users: Vec<Uuid> = mentions.iter().map(|mention| mention.user_uuid).collect();
+    client
+        .execute(
+            "INSERT INTO message_mentions (message_id, start_offset, end_offset, user_uuid) \
+             SELECT $1, * FROM UNNEST($2::INTEGER[], $3::INTEGER[], $4::UUID[]) \
+             ON CONFLICT DO NOTHING",
+            &[&message_id, &starts, &ends, &users],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Упоминания в сообщениях, по порядку в тексте
+pub async fn mentions_for_messages(
+    message_ids: &[i64],
+) -> Result<HashMap<i64, Vec<Mention>>, Box<dyn StdError + Send + Sync>> {
+    let mut mentions: HashMap<i64, Vec<Mention>> = HashMap::new();
+    if message_ids.is_empty() {
+        return Ok(mentions);
+    }
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            "SELECT mm.message_id, mm.user_uuid, u.username, mm.start_offset, mm.end_offset \
+             FROM message_mentions mm JOIN users u ON u.user_uuid = mm.user_uuid \
+             WHERE mm.message_id = ANY($1) ORDER BY mm.message_id, mm.start_offset",
+            &[&message_ids],
+        )
+        .await?;
+
+    for row in rows {
+        mentions.entry(row.get(0)).or_default().push(Mention {
+            user_uuid: row.get(1),
+            username: row.get(2),
+            start: row.get(3),
+            end: row.get(4),
+        });
+    }
+    Ok(mentions)
+}
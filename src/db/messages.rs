@@ -1,49 +1,100 @@
-use bytes::BytesMut;
 use chrono::{DateTime, Utc};
-use futures_util::SinkExt;
-use log::{debug, error};
+use log::debug;
 use std::error::Error as StdError;
-use std::sync::Arc;
-use tokio::sync::Mutex as TokioMutex;
-use tokio_postgres::types::ToSql;
-use tokio_postgres::types::Type;
 use uuid::Uuid;
-use warp::ws::{Message, WebSocket};
 use crate::db::connect_to_db;
+use crate::markdown::render_markdown;
+use crate::models::{ChatMessage, ChatPayload, QuotedMessage};
+use crate::usernames::chat_name;
+use tokio_postgres::Row;
+
+/// Столбцы сообщения для клиентов вместе с цитатой (см. PAYLOAD_FROM)
+pub(crate) const PAYLOAD_COLUMNS: &str =
+    "m.message_id, m.user_uuid, u.username, u.display_name, m.message, m.created_at, m.thread_root, \
+     q.message_id, q.user_uuid, qu.username, qu.display_name, q.message, q.created_at, q.deleted_at, \
+     m.message_html";
+
+/// Сообщение m с автором u и цитируемым сообщением q с автором qu
+pub(crate) const PAYLOAD_FROM: &str =
+    "messages m LEFT JOIN users u ON u.user_uuid = m.user_uuid \
+     LEFT JOIN messages q ON q.message_id = m.reply_to \
+     LEFT JOIN users qu ON qu.user_uuid = q.user_uuid";
+
+/// Подпись автора. Сообщения удалённых пользователей остаются без автора
+pub(crate) fn author_name(username: Option<String>, display_name: Option<String>) -> String {
+    match username {
+        Some(username) => chat_name(&username, display_name.as_deref()),
+        None => "Unknown User".to_string(),
+    }
+}
 
+/// Сообщение из строки с PAYLOAD_COLUMNS; реакции, сводка ветки и упоминания — пустые
+pub(crate) fn payload_from_row(row: &Row) -> ChatPayload {
+    let reply_to = row.get::<_, Option<i64>>(7).map(|message_id| {
+        let deleted_at: Option<DateTime<Utc>> = row.get(13);
+        QuotedMessage {
+            message_id,
+            user_uuid: row.get(8),
+            author: author_name(row.get(9), row.get(10)),
+            text: deleted_at.is_none().then(|| row.get(11)),
+            created_at: row.get(12),
+        }
+    });
+    let text: String = row.get(4);
+    // Сообщения, сохранённые до появления Markdown, отрисовываются при чтении
+    let html = row
+        .get::<_, Option<String>>(14)
+        .unwrap_or_else(|| render_markdown(&text));
+    ChatPayload {
+        message_id: row.get(0),
+        user_uuid: row.get(1),
+        author: author_name(row.get(2), row.get(3)),
+        text,
+        html,
+        created_at: row.get(5),
+        reactions: Vec::new(),
+        reply_to,
+        thread_root: row.get(6),
+        thread: None,
+        mentions: Vec::new(),
+        attachments: Vec::new(),
+    }
+}
 
-// Обертка для DateTime<Utc>, чтобы обойти "orphan rule"
-#[derive(Debug, Copy, Clone)]
-struct Timestamp(DateTime<Utc>);
+/// Сообщение для клиентов; None — его нет или оно удалено
+pub async fn find_payload(message_id: i64) -> Result<Option<ChatPayload>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
 
-impl ToSql for Timestamp {
-    tokio_postgres::types::accepts!(TIMESTAMP, TIMESTAMPTZ);
-    tokio_postgres::types::to_sql_checked!();
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM {} WHERE m.message_id = $1 AND m.deleted_at IS NULL",
+                PAYLOAD_COLUMNS, PAYLOAD_FROM
+            ),
+            &[&message_id],
+        )
+        .await?;
 
-    fn to_sql(
-        &self,
-        _type: &Type,
-        out: &mut BytesMut,
-    ) -> Result<tokio_postgres::types::IsNull, Box<dyn StdError + Sync + Send>> {
-        let timestamp = self.0.timestamp();
-        (timestamp).to_sql(_type, out) // Убрали .map_err
-    }
+    Ok(row.as_ref().map(payload_from_row))
 }
 
 /// Экранирует строку для безопасного отображения в HTML
-fn escape_html(text: &str) -> String {
-    text.replace("&", "&amp")
-        .replace("<", "&lt")
-        .replace(">", "&gt")
-        .replace("\"", "&quot")
-        .replace("'", "&apos;")
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
 }
 
-/// Сохраняет сообщение в базу данных
+/// Сохраняет сообщение в базу данных вместе с отрисованным HTML. Возвращает его номер и время.
+/// reply_to — цитируемое сообщение, thread_root — корень ветки
 pub async fn save_message_to_db(
     message: &str,
     user_uuid: Uuid,
-) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    reply_to: Option<i64>,
+    thread_root: Option<i64>,
+) -> Result<(i64, DateTime<Utc>), Box<dyn StdError + Send + Sync>> {
     let client = connect_to_db().await?;
 
     
@@ -53,62 +104,93 @@ pub async fn save_message_to_db(
         message, user_uuid
     );
 
-    client
-        .execute(
-            "INSERT INTO messages (message, user_uuid, created_at) VALUES ($1, $2, NOW())",
-            &[&message, &user_uuid],
+    let html = render_markdown(message);
+    let row = client
+        .query_one(
+            "INSERT INTO messages (message, message_html, user_uuid, created_at, reply_to, thread_root) \
+             VALUES ($1, $2, $3, NOW(), $4, $5) RETURNING message_id, created_at",
+            &[&message, &html, &user_uuid, &reply_to, &thread_root],
         )
         .await?;
 
-    Ok(())
+    Ok((row.get(0), row.get(1)))
 }
 
- /// Отправляет историю сообщений клиенту
-pub async fn send_message_history(
-    client_ws_sender: Arc<TokioMutex<futures_util::stream::SplitSink<WebSocket, Message>>>,
-) -> Result<(), Box<dyn StdError + Send + Sync>> {
+/// Помечает сообщение удалённым модератором. Возвращает false, если сообщения нет
+pub async fn delete_message(
+    message_id: i64,
+    moderator_uuid: Uuid,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
     let client = connect_to_db().await?;
 
-    
+    debug!("Deleting message {} by moderator {}", message_id, moderator_uuid);
+
+    let updated = client
+        .execute(
+            "UPDATE messages SET deleted_at = NOW(), deleted_by = $2 WHERE message_id = $1 AND deleted_at IS NULL",
+            &[&message_id, &moderator_uuid],
+        )
+        .await?;
+
+    Ok(updated > 0)
+}
+
+/// Все сообщения пользователя, старые сначала
+pub async fn list_messages_by_user_uuid(
+    user_uuid: &Uuid,
+) -> Result<Vec<ChatMessage>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
 
     let rows = client
         .query(
-            "SELECT message, user_uuid FROM messages ORDER BY created_at DESC LIMIT 50",
-            &[],
+            "SELECT message_id, user_uuid, message, created_at, deleted_at, reply_to, thread_root \
+             FROM messages WHERE user_uuid = $1 ORDER BY created_at, message_id",
+            &[&user_uuid],
         )
         .await?;
 
-    for row in rows {
-        let message: String = row.get(0);
-        // Получаем user_uuid, обрабатывая возможность NULL значения
-        let user_uuid: Option<Uuid> = row.get(1);
-
-        let formatted_message = match user_uuid {
-            Some(uuid) => {
-                // Получаем имя пользователя по user_uuid
-                let username_result = client
-                    .query_one("SELECT username FROM users WHERE user_uuid = $1", &[&uuid])
-                    .await;
-                match username_result {
-                    Ok(row) => {
-                        let username: String = row.get(0);
-                        let escaped_username = escape_html(&username); // Экранируем имя пользователя
-                        format!("{}: {}", escaped_username, message)
-                    }
-                    Err(e) => {
-                        error!("Failed to get username from database: {}", e);
-                        format!("Unknown User: {}", message)
-                    }
-                }
-            }
-            None => format!("Unknown User: {}", message), // Обработка NULL значения
-        };
-
-        if let Err(e) = client_ws_sender.lock().await.send(Message::text(formatted_message)).await
-        {
-            error!("Failed to send message: {}", e);
-        }
-    }
+    Ok(rows
+        .iter()
+        .map(|row| ChatMessage {
+            message_id: row.get(0),
+            user_uuid: row.get(1),
+            message: row.get(2),
+            created_at: row.get(3),
+            deleted_at: row.get(4),
+            reply_to: row.get(5),
+            thread_root: row.get(6),
+        })
+        .collect())
+}
+
+/// Количество сообщений пользователя
+pub async fn count_messages_by_user_uuid(
+    user_uuid: &Uuid,
+) -> Result<i64, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_one("SELECT COUNT(*) FROM messages WHERE user_uuid = $1", &[&user_uuid])
+        .await?;
+
+    Ok(row.get(0))
+}
+
+/// Последние сообщения общей ленты (без ответов в ветках), новые сначала.
+/// Реакции и сводки веток не заполняются
+pub async fn recent_messages(limit: i64) -> Result<Vec<ChatPayload>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
 
-    Ok(())
-}
\ No newline at end of file
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM {} WHERE m.deleted_at IS NULL AND m.thread_root IS NULL \
+                 ORDER BY m.created_at DESC, m.message_id DESC LIMIT $1",
+                PAYLOAD_COLUMNS, PAYLOAD_FROM
+            ),
+            &[&limit],
+        )
+        .await?;
+
+    Ok(rows.iter().map(payload_from_row).collect())
+}
@@ -1,10 +1,26 @@
+pub mod api_tokens;
+pub mod attachments;
+pub mod audit;
 pub mod devices;
+pub mod exports;
 pub mod files;
+pub mod login_alerts;
+pub mod login_attempts;
+pub mod mentions;
 pub mod messages;
+pub mod notifications;
+pub mod oauth;
+pub mod password_resets;
 pub mod profiles;
+pub mod push_subscriptions;
+pub mod reactions;
+pub mod read_markers;
+pub mod search;
 pub mod sessions;
+pub mod threads;
+pub mod two_factor;
+pub mod usernames;
 pub mod users;
-pub use messages::send_message_history;
 
 use tokio_postgres::NoTls;
 use std::error::Error as StdError;
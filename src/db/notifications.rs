@@ -0,0 +1,305 @@
+// src/db/notifications.rs
+use crate::db::connect_to_db;
+use crate::models::{
+    Notification, NotificationKind, NotificationPreference, NotificationPreferenceUpdate,
+};
+use chrono::{DateTime, Utc};
+use log::debug;
+use std::error::Error as StdError;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+const NOTIFICATION_COLUMNS: &str =
+    "notification_id, user_uuid, kind, message, link, created_at, delivered_at, read_at";
+
+fn notification_from_row(row: &Row) -> Notification {
+    Notification {
+        notification_id: row.get(0),
+        user_uuid: row.get(1),
+        kind: row.get(2),
+        message: row.get(3),
+        link: row.get(4),
+        created_at: row.get(5),
+        delivered_at: row.get(6),
+        read_at: row.get(7),
+    }
+}
+
+/// Сохраняет уведомление
+pub async fn save_notification(
+    notification: &Notification,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!(
+        "Saving {} notification for user_uuid: {}",
+        notification.kind, notification.user_uuid
+    );
+
+    client
+        .execute(
+            "INSERT INTO notifications (notification_id, user_uuid, kind, message, link, created_at, delivered_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            &[
+                &notification.notification_id,
+                &notification.user_uuid,
+                &notification.kind.as_str(),
+                &notification.message,
+                &notification.link,
+                &notification.created_at,
+                &notification.delivered_at,
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Уведомления пользователя, новые сначала. before — только созданные раньше
+pub async fn list_notifications(
+    user_uuid: &Uuid,
+    unread_only: bool,
+    kind: Option<NotificationKind>,
+    before: Option<DateTime<Utc>>,
+    limit: i64,
+) -> Result<Vec<Notification>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM notifications WHERE user_uuid = $1 \
+                 AND ($2 = FALSE OR read_at IS NULL) \
+                 AND ($3::varchar IS NULL OR kind = $3) \
+                 AND ($4::timestamptz IS NULL OR created_at < $4) \
+                 ORDER BY created_at DESC LIMIT $5",
+                NOTIFICATION_COLUMNS
+            ),
+            &[
+                &user_uuid,
+                &unread_only,
+                &kind.map(|kind| kind.as_str()),
+                &before,
+                &limit,
+            ],
+        )
+        .await?;
+
+    Ok(rows.iter().map(notification_from_row).collect())
+}
+
+/// Все уведомления пользователя, старые сначала
+pub async fn list_notifications_by_user_uuid(
+    user_uuid: &Uuid,
+) -> Result<Vec<Notification>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM notifications WHERE user_uuid = $1 ORDER BY created_at",
+                NOTIFICATION_COLUMNS
+            ),
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(rows.iter().map(notification_from_row).collect())
+}
+
+/// Сколько уведомлений пользователь ещё не прочитал
+pub async fn count_unread_notifications(
+    user_uuid: &Uuid,
+) -> Result<i64, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_one(
+            "SELECT COUNT(*) FROM notifications WHERE user_uuid = $1 AND read_at IS NULL",
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(row.get(0))
+}
+
+/// Отмечает уведомление прочитанным. None — у пользователя нет такого уведомления
+pub async fn mark_notification_read(
+    user_uuid: &Uuid,
+    notification_id: &Uuid,
+) -> Result<Option<Notification>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_opt(
+            &format!(
+                "UPDATE notifications SET read_at = COALESCE(read_at, NOW()) \
+                 WHERE user_uuid = $1 AND notification_id = $2 RETURNING {}",
+                NOTIFICATION_COLUMNS
+            ),
+            &[&user_uuid, &notification_id],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(notification_from_row))
+}
+
+/// Отмечает прочитанными все уведомления пользователя. Возвращает их число
+pub async fn mark_all_notifications_read(
+    user_uuid: &Uuid,
+) -> Result<u64, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let updated = client
+        .execute(
+            "UPDATE notifications SET read_at = NOW() WHERE user_uuid = $1 AND read_at IS NULL",
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(updated)
+}
+
+/// Удаляет уведомление. false — у пользователя нет такого уведомления
+pub async fn delete_notification(
+    user_uuid: &Uuid,
+    notification_id: &Uuid,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let deleted = client
+        .execute(
+            "DELETE FROM notifications WHERE user_uuid = $1 AND notification_id = $2",
+            &[&user_uuid, &notification_id],
+        )
+        .await?;
+
+    Ok(deleted > 0)
+}
+
+/// Очищает центр уведомлений (read_only — только прочитанные). Возвращает число удалённых
+pub async fn clear_notifications(
+    user_uuid: &Uuid,
+    read_only: bool,
+) -> Result<u64, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Clearing notifications for user_uuid: {}", user_uuid);
+
+    let deleted = client
+        .execute(
+            "DELETE FROM notifications WHERE user_uuid = $1 AND ($2 = FALSE OR read_at IS NOT NULL)",
+            &[&user_uuid, &read_only],
+        )
+        .await?;
+
+    Ok(deleted)
+}
+
+/// Настройка из строки (kind, in_app, push); NULL в push — как принято для вида
+fn preference_from_row(row: &Row) -> NotificationPreference {
+    let kind: NotificationKind = row.get(0);
+    let push: Option<bool> = row.get(2);
+    NotificationPreference {
+        kind,
+        in_app: row.get(1),
+        push: push.unwrap_or_else(|| kind.push_by_default()),
+    }
+}
+
+fn default_preference(kind: NotificationKind) -> NotificationPreference {
+    NotificationPreference {
+        kind,
+        in_app: true,
+        push: kind.push_by_default(),
+    }
+}
+
+/// Настройки всех видов уведомлений; без сохранённой настройки вид включён
+pub async fn list_notification_preferences(
+    user_uuid: &Uuid,
+) -> Result<Vec<NotificationPreference>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            "SELECT kind, in_app, push FROM notification_preferences WHERE user_uuid = $1",
+            &[&user_uuid],
+        )
+        .await?;
+    let saved: Vec<NotificationPreference> = rows.iter().map(preference_from_row).collect();
+
+    Ok(NotificationKind::ALL
+        .iter()
+        .map(|kind| {
+            saved
+                .iter()
+                .find(|preference| preference.kind == *kind)
+                .cloned()
+                .unwrap_or_else(|| default_preference(*kind))
+        })
+        .collect())
+}
+
+/// Сохраняет настройку вида уведомлений; не указанные поля не меняются
+pub async fn save_notification_preference(
+    user_uuid: &Uuid,
+    update: &NotificationPreferenceUpdate,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    client
+        .execute(
+            "INSERT INTO notification_preferences (user_uuid, kind, in_app, push, updated_at) \
+             VALUES ($1, $2, COALESCE($3, TRUE), $4, NOW()) \
+             ON CONFLICT (user_uuid, kind) DO UPDATE SET \
+             in_app = COALESCE($3, notification_preferences.in_app), \
+             push = COALESCE($4, notification_preferences.push), updated_at = NOW()",
+            &[&user_uuid, &update.kind.as_str(), &update.in_app, &update.push],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Настройка вида уведомлений пользователя
+pub async fn find_notification_preference(
+    user_uuid: &Uuid,
+    kind: NotificationKind,
+) -> Result<NotificationPreference, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_opt(
+            "SELECT kind, in_app, push FROM notification_preferences WHERE user_uuid = $1 AND kind = $2",
+            &[&user_uuid, &kind.as_str()],
+        )
+        .await?;
+
+    Ok(row
+        .as_ref()
+        .map(preference_from_row)
+        .unwrap_or_else(|| default_preference(kind)))
+}
+
+/// Забирает уведомления, которые ещё не показаны в чате, старые сначала
+pub async fn take_undelivered_notifications(
+    user_uuid: &Uuid,
+) -> Result<Vec<Notification>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            &format!(
+                "UPDATE notifications SET delivered_at = NOW() \
+                 WHERE user_uuid = $1 AND delivered_at IS NULL RETURNING {}",
+                NOTIFICATION_COLUMNS
+            ),
+            &[&user_uuid],
+        )
+        .await?;
+
+    let mut notifications: Vec<Notification> = rows.iter().map(notification_from_row).collect();
+    notifications.sort_by_key(|notification| notification.created_at);
+    Ok(notifications)
+}
@@ -0,0 +1,264 @@
+// src/db/oauth.rs
+use crate::db::connect_to_db;
+use crate::models::{AuthorizationCode, OAuthAccessToken, OAuthClient};
+use log::debug;
+use std::error::Error as StdError;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+const CLIENT_COLUMNS: &str = "client_id, client_secret_hash, name, redirect_uris, created_at";
+
+fn client_from_row(row: &Row) -> OAuthClient {
+    let client_secret_hash: Option<String> = row.get(1);
+    OAuthClient {
+        client_id: row.get(0),
+        confidential: client_secret_hash.is_some(),
+        client_secret_hash,
+        name: row.get(2),
+        redirect_uris: row.get(3),
+        created_at: row.get(4),
+    }
+}
+
+/// Регистрирует приложение
+pub async fn save_oauth_client(
+    client: &OAuthClient,
+    created_by: &Uuid,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let db = connect_to_db().await?;
+
+    debug!("Saving OAuth client: {}", client.client_id);
+
+    db.execute(
+        "INSERT INTO oauth_clients (client_id, client_secret_hash, name, redirect_uris, created_by, created_at) \
+         VALUES ($1, $2, $3, $4, $5, $6)",
+        &[
+            &client.client_id,
+            &client.client_secret_hash,
+            &client.name,
+            &client.redirect_uris,
+            &created_by,
+            &client.created_at,
+        ],
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub async fn find_oauth_client(
+    client_id: &str,
+) -> Result<Option<OAuthClient>, Box<dyn StdError + Send + Sync>> {
+    let db = connect_to_db().await?;
+
+    let row = db
+        .query_opt(
+            &format!(
+                "SELECT {} FROM oauth_clients WHERE client_id = $1",
+                CLIENT_COLUMNS
+            ),
+            &[&client_id],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(client_from_row))
+}
+
+pub async fn list_oauth_clients() -> Result<Vec<OAuthClient>, Box<dyn StdError + Send + Sync>> {
+    let db = connect_to_db().await?;
+
+    let rows = db
+        .query(
+            &format!(
+                "SELECT {} FROM oauth_clients ORDER BY created_at",
+                CLIENT_COLUMNS
+            ),
+            &[],
+        )
+        .await?;
+
+    Ok(rows.iter().map(client_from_row).collect())
+}
+
+/// Удаляет приложение вместе с его кодами, согласиями и токенами
+pub async fn delete_oauth_client(client_id: &str) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let db = connect_to_db().await?;
+
+    debug!("Deleting OAuth client: {}", client_id);
+
+    let deleted = db
+        .execute(
+            "DELETE FROM oauth_clients WHERE client_id = $1",
+            &[&client_id],
+        )
+        .await?;
+
+    Ok(deleted > 0)
+}
+
+/// Сохраняет код авторизации (хранится только хеш)
+pub async fn save_authorization_code(
+    code_hash: &str,
+    code: &AuthorizationCode,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let db = connect_to_db().await?;
+
+    db.execute(
+        "INSERT INTO oauth_authorization_codes \
+         (code_hash, client_id, user_uuid, redirect_uri, scope, nonce, code_challenge, auth_time, expires_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        &[
+            &code_hash,
+            &code.client_id,
+            &code.user_uuid,
+            &code.redirect_uri,
+            &code.scope,
+            &code.nonce,
+            &code.code_challenge,
+            &code.auth_time,
+            &code.expires_at,
+        ],
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Погашает код авторизации. Возвращает его, если код ещё не использовался
+pub async fn consume_authorization_code(
+    code_hash: &str,
+) -> Result<Option<AuthorizationCode>, Box<dyn StdError + Send + Sync>> {
+    let db = connect_to_db().await?;
+
+    let row = db
+        .query_opt(
+            "UPDATE oauth_authorization_codes SET used_at = NOW() \
+             WHERE code_hash = $1 AND used_at IS NULL \
+             RETURNING client_id, user_uuid, redirect_uri, scope, nonce, code_challenge, auth_time, expires_at",
+            &[&code_hash],
+        )
+        .await?;
+
+    Ok(row.map(|row| AuthorizationCode {
+        client_id: row.get(0),
+        user_uuid: row.get(1),
+        redirect_uri: row.get(2),
+        scope: row.get(3),
+        nonce: row.get(4),
+        code_challenge: row.get(5),
+        auth_time: row.get(6),
+        expires_at: row.get(7),
+    }))
+}
+
+/// Владелец и приложение уже использованного кода (для отзыва токенов при повторе)
+pub async fn find_used_authorization_code(
+    code_hash: &str,
+) -> Result<Option<(Uuid, String)>, Box<dyn StdError + Send + Sync>> {
+    let db = connect_to_db().await?;
+
+    let row = db
+        .query_opt(
+            "SELECT user_uuid, client_id FROM oauth_authorization_codes \
+             WHERE code_hash = $1 AND used_at IS NOT NULL",
+            &[&code_hash],
+        )
+        .await?;
+
+    Ok(row.map(|row| (row.get(0), row.get(1))))
+}
+
+/// Области, на которые пользователь уже согласился для приложения
+pub async fn find_consent(
+    user_uuid: &Uuid,
+    client_id: &str,
+) -> Result<Option<String>, Box<dyn StdError + Send + Sync>> {
+    let db = connect_to_db().await?;
+
+    let row = db
+        .query_opt(
+            "SELECT scope FROM oauth_consents WHERE user_uuid = $1 AND client_id = $2",
+            &[&user_uuid, &client_id],
+        )
+        .await?;
+
+    Ok(row.map(|row| row.get(0)))
+}
+
+pub async fn save_consent(
+    user_uuid: &Uuid,
+    client_id: &str,
+    scope: &str,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let db = connect_to_db().await?;
+
+    debug!("Saving consent of {} for client {}", user_uuid, client_id);
+
+    db.execute(
+        "INSERT INTO oauth_consents (user_uuid, client_id, scope) VALUES ($1, $2, $3) \
+         ON CONFLICT (user_uuid, client_id) DO UPDATE SET scope = EXCLUDED.scope, granted_at = NOW()",
+        &[&user_uuid, &client_id, &scope],
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub async fn save_oauth_access_token(
+    token_hash: &str,
+    token: &OAuthAccessToken,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let db = connect_to_db().await?;
+
+    db.execute(
+        "INSERT INTO oauth_access_tokens (token_hash, client_id, user_uuid, scope, expires_at) \
+         VALUES ($1, $2, $3, $4, $5)",
+        &[
+            &token_hash,
+            &token.client_id,
+            &token.user_uuid,
+            &token.scope,
+            &token.expires_at,
+        ],
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub async fn find_oauth_access_token(
+    token_hash: &str,
+) -> Result<Option<OAuthAccessToken>, Box<dyn StdError + Send + Sync>> {
+    let db = connect_to_db().await?;
+
+    let row = db
+        .query_opt(
+            "SELECT client_id, user_uuid, scope, expires_at FROM oauth_access_tokens WHERE token_hash = $1",
+            &[&token_hash],
+        )
+        .await?;
+
+    Ok(row.map(|row| OAuthAccessToken {
+        client_id: row.get(0),
+        user_uuid: row.get(1),
+        scope: row.get(2),
+        expires_at: row.get(3),
+    }))
+}
+
+/// Отзывает токены доступа пользователя для приложения
+pub async fn delete_oauth_access_tokens(
+    user_uuid: &Uuid,
+    client_id: &str,
+) -> Result<u64, Box<dyn StdError + Send + Sync>> {
+    let db = connect_to_db().await?;
+
+    let deleted = db
+        .execute(
+            "DELETE FROM oauth_access_tokens WHERE user_uuid = $1 AND client_id = $2",
+            &[&user_uuid, &client_id],
+        )
+        .await?;
+
+    Ok(deleted)
+}
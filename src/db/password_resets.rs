@@ -0,0 +1,121 @@
+// src/db/password_resets.rs
+use crate::db::connect_to_db;
+use chrono::{DateTime, Utc};
+use log::debug;
+use std::error::Error as StdError;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+/// Сохраняет хеш токена сброса пароля и адрес, с которого его запросили
+pub async fn save_password_reset_token(
+    token_hash: &str,
+    user_uuid: &Uuid,
+    expires_at: DateTime<Utc>,
+    requested_ip: Option<IpAddr>,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Saving password reset token for user_uuid: {}", user_uuid);
+
+    client
+        .execute(
+            "INSERT INTO password_reset_tokens (token_hash, user_uuid, expires_at, requested_ip) \
+             VALUES ($1, $2, $3, $4)",
+            &[&token_hash, &user_uuid, &expires_at, &requested_ip],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Есть ли у пользователя действующий токен, выданный после since
+pub async fn has_recent_password_reset_token(
+    user_uuid: &Uuid,
+    since: DateTime<Utc>,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_one(
+            "SELECT EXISTS (SELECT 1 FROM password_reset_tokens \
+             WHERE user_uuid = $1 AND created_at > $2 AND used_at IS NULL AND expires_at > NOW())",
+            &[&user_uuid, &since],
+        )
+        .await?;
+
+    Ok(row.get(0))
+}
+
+/// Сколько токенов запрошено с адреса после since
+pub async fn count_password_resets_from_ip(
+    ip_address: IpAddr,
+    since: DateTime<Utc>,
+) -> Result<i64, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_one(
+            "SELECT COUNT(*) FROM password_reset_tokens WHERE requested_ip = $1 AND created_at > $2",
+            &[&ip_address, &since],
+        )
+        .await?;
+
+    Ok(row.get(0))
+}
+
+/// Ищет владельца действующего (неиспользованного и не истёкшего) токена
+pub async fn find_password_reset_token(
+    token_hash: &str,
+) -> Result<Option<Uuid>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_opt(
+            "SELECT user_uuid FROM password_reset_tokens \
+             WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()",
+            &[&token_hash],
+        )
+        .await?;
+
+    Ok(row.map(|row| row.get(0)))
+}
+
+/// Погашает токен сброса пароля. Возвращает владельца, если токен
+/// существует, ещё не использован и не истёк
+pub async fn consume_password_reset_token(
+    token_hash: &str,
+) -> Result<Option<Uuid>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_opt(
+            "UPDATE password_reset_tokens SET used_at = NOW() \
+             WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW() \
+             RETURNING user_uuid",
+            &[&token_hash],
+        )
+        .await?;
+
+    Ok(row.map(|row| row.get(0)))
+}
+
+/// Удаляет все неиспользованные токены пользователя
+pub async fn delete_password_reset_tokens(
+    user_uuid: &Uuid,
+) -> Result<u64, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!(
+        "Deleting password reset tokens for user_uuid: {}",
+        user_uuid
+    );
+
+    let deleted = client
+        .execute(
+            "DELETE FROM password_reset_tokens WHERE user_uuid = $1 AND used_at IS NULL",
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(deleted)
+}
@@ -28,7 +28,7 @@ pub async fn get_profile_by_user_uuid(
                 bio: row.get(1),
                 avatar: row.get(2),
                 profile_banner: row.get(3),
-                storage_access: storage_access,
+                storage_access,
                 allowed_viewers: row.get(5), // Убедитесь, что тип данных соответствует массиву UUID
             };
             Ok(Some(profile))
@@ -0,0 +1,149 @@
+// src/db/push_subscriptions.rs
+use crate::db::connect_to_db;
+use crate::models::PushSubscription;
+use log::debug;
+use std::error::Error as StdError;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+const SUBSCRIPTION_COLUMNS: &str =
+    "subscription_id, user_uuid, endpoint, p256dh, auth, user_agent, created_at, last_push_at";
+
+fn subscription_from_row(row: &Row) -> PushSubscription {
+    PushSubscription {
+        subscription_id: row.get(0),
+        user_uuid: row.get(1),
+        endpoint: row.get(2),
+        p256dh: row.get(3),
+        auth: row.get(4),
+        user_agent: row.get(5),
+        created_at: row.get(6),
+        last_push_at: row.get(7),
+    }
+}
+
+/// Сохраняет подписку. Тот же endpoint заменяет прежнюю подписку, даже чужую:
+/// браузер, где сменили пользователя, получает уведомления нового
+pub async fn save_push_subscription(
+    subscription: &PushSubscription,
+) -> Result<PushSubscription, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!(
+        "Saving push subscription {} for user_uuid: {}",
+        subscription.subscription_id, subscription.user_uuid
+    );
+
+    let row = client
+        .query_one(
+            &format!(
+                "INSERT INTO push_subscriptions (subscription_id, user_uuid, endpoint, p256dh, auth, user_agent, created_at) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7) \
+                 ON CONFLICT (endpoint) DO UPDATE SET \
+                 subscription_id = EXCLUDED.subscription_id, user_uuid = EXCLUDED.user_uuid, \
+                 p256dh = EXCLUDED.p256dh, auth = EXCLUDED.auth, user_agent = EXCLUDED.user_agent, \
+                 created_at = EXCLUDED.created_at, last_push_at = NULL \
+                 RETURNING {}",
+                SUBSCRIPTION_COLUMNS
+            ),
+            &[
+                &subscription.subscription_id,
+                &subscription.user_uuid,
+                &subscription.endpoint,
+                &subscription.p256dh,
+                &subscription.auth,
+                &subscription.user_agent,
+                &subscription.created_at,
+            ],
+        )
+        .await?;
+
+    Ok(subscription_from_row(&row))
+}
+
+/// Подписки пользователя, новые сначала
+pub async fn list_push_subscriptions(
+    user_uuid: &Uuid,
+) -> Result<Vec<PushSubscription>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM push_subscriptions WHERE user_uuid = $1 ORDER BY created_at DESC",
+                SUBSCRIPTION_COLUMNS
+            ),
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(rows.iter().map(subscription_from_row).collect())
+}
+
+/// Подписка с этим endpoint, чья бы она ни была
+pub async fn find_push_subscription_by_endpoint(
+    endpoint: &str,
+) -> Result<Option<PushSubscription>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_opt(
+            &format!(
+                "SELECT {} FROM push_subscriptions WHERE endpoint = $1",
+                SUBSCRIPTION_COLUMNS
+            ),
+            &[&endpoint],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(subscription_from_row))
+}
+
+/// Удаляет подписку пользователя. false — у пользователя нет такой подписки
+pub async fn delete_push_subscription(
+    user_uuid: &Uuid,
+    subscription_id: &Uuid,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let deleted = client
+        .execute(
+            "DELETE FROM push_subscriptions WHERE user_uuid = $1 AND subscription_id = $2",
+            &[&user_uuid, &subscription_id],
+        )
+        .await?;
+
+    Ok(deleted > 0)
+}
+
+/// Удаляет подписку, которую служба доставки больше не принимает
+pub async fn delete_push_subscription_by_endpoint(
+    endpoint: &str,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    client
+        .execute(
+            "DELETE FROM push_subscriptions WHERE endpoint = $1",
+            &[&endpoint],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Запоминает время последней успешной отправки
+pub async fn touch_push_subscription(
+    subscription_id: &Uuid,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    client
+        .execute(
+            "UPDATE push_subscriptions SET last_push_at = NOW() WHERE subscription_id = $1",
+            &[&subscription_id],
+        )
+        .await?;
+
+    Ok(())
+}
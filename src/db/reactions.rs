@@ -0,0 +1,151 @@
+// src/db/reactions.rs
+use crate::db::connect_to_db;
+use crate::models::{Reaction, ReactionCount};
+use log::debug;
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+/// Есть ли неудалённое сообщение с таким номером
+async fn message_exists(
+    client: &Client,
+    message_id: i64,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let row = client
+        .query_opt(
+            "SELECT 1 FROM messages WHERE message_id = $1 AND deleted_at IS NULL",
+            &[&message_id],
+        )
+        .await?;
+    Ok(row.is_some())
+}
+
+async fn count_reactions(
+    client: &Client,
+    message_id: i64,
+    emoji: &str,
+) -> Result<i64, Box<dyn StdError + Send + Sync>> {
+    let row = client
+        .query_one(
+            "SELECT COUNT(*) FROM message_reactions WHERE message_id = $1 AND emoji = $2",
+            &[&message_id, &emoji],
+        )
+        .await?;
+    Ok(row.get(0))
+}
+
+/// Ставит реакцию. Возвращает (добавлена ли она сейчас, сколько таких реакций);
+/// None — сообщения нет или оно удалено
+pub async fn add_reaction(
+    message_id: i64,
+    user_uuid: &Uuid,
+    emoji: &str,
+) -> Result<Option<(bool, i64)>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!(
+        "Adding reaction {} to message {} by user_uuid: {}",
+        emoji, message_id, user_uuid
+    );
+
+    if !message_exists(&client, message_id).await? {
+        return Ok(None);
+    }
+    let inserted = client
+        .execute(
+            "INSERT INTO message_reactions (message_id, user_uuid, emoji) VALUES ($1, $2, $3) \
+             ON CONFLICT DO NOTHING",
+            &[&message_id, user_uuid, &emoji],
+        )
+        .await?;
+    let count = count_reactions(&client, message_id, emoji).await?;
+
+    Ok(Some((inserted > 0, count)))
+}
+
+/// Снимает реакцию. Возвращает (была ли она, сколько таких реакций осталось);
+/// None — сообщения нет или оно удалено
+pub async fn remove_reaction(
+    message_id: i64,
+    user_uuid: &Uuid,
+    emoji: &str,
+) -> Result<Option<(bool, i64)>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!(
+        "Removing reaction {} from message {} by user_uuid: {}",
+        emoji, message_id, user_uuid
+    );
+
+    if !message_exists(&client, message_id).await? {
+        return Ok(None);
+    }
+    let deleted = client
+        .execute(
+            "DELETE FROM message_reactions WHERE message_id = $1 AND user_uuid = $2 AND emoji = $3",
+            &[&message_id, user_uuid, &emoji],
+        )
+        .await?;
+    let count = count_reactions(&client, message_id, emoji).await?;
+
+    Ok(Some((deleted > 0, count)))
+}
+
+/// Сводка реакций по сообщениям; reacted отмечает реакции viewer
+pub async fn reactions_for_messages(
+    message_ids: &[i64],
+    viewer: Option<&Uuid>,
+) -> Result<HashMap<i64, Vec<ReactionCount>>, Box<dyn StdError + Send + Sync>> {
+    let mut reactions: HashMap<i64, Vec<ReactionCount>> = HashMap::new();
+    if message_ids.is_empty() {
+        return Ok(reactions);
+    }
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            "SELECT message_id, emoji, COUNT(*), COALESCE(BOOL_OR(user_uuid = $2), FALSE) \
+             FROM message_reactions WHERE message_id = ANY($1) \
+             GROUP BY message_id, emoji ORDER BY message_id, MIN(created_at), emoji",
+            &[&message_ids, &viewer],
+        )
+        .await?;
+
+    for row in rows {
+        reactions
+            .entry(row.get(0))
+            .or_default()
+            .push(ReactionCount {
+                emoji: row.get(1),
+                count: row.get(2),
+                reacted: row.get(3),
+            });
+    }
+    Ok(reactions)
+}
+
+/// Все реакции пользователя
+pub async fn list_reactions_by_user_uuid(
+    user_uuid: &Uuid,
+) -> Result<Vec<Reaction>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            "SELECT message_id, user_uuid, emoji, created_at FROM message_reactions \
+             WHERE user_uuid = $1 ORDER BY created_at",
+            &[user_uuid],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| Reaction {
+            message_id: row.get(0),
+            user_uuid: row.get(1),
+            emoji: row.get(2),
+            created_at: row.get(3),
+        })
+        .collect())
+}
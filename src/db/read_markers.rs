@@ -0,0 +1,113 @@
+// src/db/read_markers.rs
+use crate::db::connect_to_db;
+use crate::models::{ReadMarker, UnreadCount};
+use log::debug;
+use std::error::Error as StdError;
+use uuid::Uuid;
+
+/// Общая лента
+pub const MAIN_CONVERSATION: &str = "main";
+
+/// Ключ разговора: общая лента или ветка под thread_root
+pub fn conversation_key(thread_root: Option<i64>) -> String {
+    match thread_root {
+        Some(root) => format!("thread:{}", root),
+        None => MAIN_CONVERSATION.to_string(),
+    }
+}
+
+/// Сдвигает отметку вперёд (назад она не двигается).
+/// Возвращает текущую отметку после обновления
+pub async fn mark_read(
+    user_uuid: &Uuid,
+    conversation: &str,
+    message_id: i64,
+) -> Result<i64, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!(
+        "Marking {} read up to {} for user_uuid: {}",
+        conversation, message_id, user_uuid
+    );
+
+    let row = client
+        .query_one(
+            "INSERT INTO read_markers (user_uuid, conversation, last_read_message_id, updated_at) \
+             VALUES ($1, $2, $3, NOW()) \
+             ON CONFLICT (user_uuid, conversation) DO UPDATE SET \
+             last_read_message_id = GREATEST(read_markers.last_read_message_id, EXCLUDED.last_read_message_id), \
+             updated_at = NOW() \
+             RETURNING last_read_message_id",
+            &[user_uuid, &conversation, &message_id],
+        )
+        .await?;
+
+    Ok(row.get(0))
+}
+
+/// Все отметки пользователя
+pub async fn list_read_markers_by_user_uuid(
+    user_uuid: &Uuid,
+) -> Result<Vec<ReadMarker>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            "SELECT conversation, last_read_message_id, updated_at FROM read_markers \
+             WHERE user_uuid = $1 ORDER BY conversation",
+            &[user_uuid],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| ReadMarker {
+            conversation: row.get(0),
+            last_read_message_id: row.get(1),
+            updated_at: row.get(2),
+        })
+        .collect())
+}
+
+/// Непрочитанное: общая лента и ветки, которые пользователь начал или где отвечал
+pub async fn unread_counts(
+    user_uuid: &Uuid,
+) -> Result<Vec<UnreadCount>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            "WITH conversations AS ( \
+                 SELECT $2::VARCHAR AS conversation, NULL::BIGINT AS thread_root \
+                 UNION ALL \
+                 SELECT 'thread:' || r.message_id, r.message_id FROM messages r \
+                 WHERE r.thread_root IS NULL AND r.deleted_at IS NULL \
+                 AND EXISTS (SELECT 1 FROM messages t WHERE t.thread_root = r.message_id) \
+                 AND (r.user_uuid = $1 OR EXISTS ( \
+                     SELECT 1 FROM messages t WHERE t.thread_root = r.message_id AND t.user_uuid = $1)) \
+             ) \
+             SELECT c.conversation, c.thread_root, rm.last_read_message_id, \
+                 (SELECT MAX(m.message_id) FROM messages m \
+                  WHERE m.deleted_at IS NULL AND m.thread_root IS NOT DISTINCT FROM c.thread_root), \
+                 (SELECT COUNT(*) FROM messages m \
+                  WHERE m.deleted_at IS NULL AND m.thread_root IS NOT DISTINCT FROM c.thread_root \
+                  AND m.message_id > COALESCE(rm.last_read_message_id, 0) \
+                  AND m.user_uuid IS DISTINCT FROM $1) \
+             FROM conversations c \
+             LEFT JOIN read_markers rm ON rm.user_uuid = $1 AND rm.conversation = c.conversation \
+             ORDER BY c.thread_root NULLS FIRST",
+            &[user_uuid, &MAIN_CONVERSATION],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| UnreadCount {
+            conversation: row.get(0),
+            thread_root: row.get(1),
+            last_read_message_id: row.get(2),
+            last_message_id: row.get(3),
+            unread_count: row.get(4),
+        })
+        .collect())
+}
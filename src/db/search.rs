@@ -0,0 +1,85 @@
+// src/db/search.rs
+use crate::db::connect_to_db;
+use crate::db::messages::{escape_html, payload_from_row, PAYLOAD_COLUMNS, PAYLOAD_FROM};
+use crate::models::{SearchFilters, SearchResult};
+use log::debug;
+use std::error::Error as StdError;
+
+/// Границы совпадений в ts_headline: символы из области для частного
+/// использования, чтобы после экранирования заменить их на <mark>
+const MATCH_START: char = '\u{E000}';
+const MATCH_STOP: char = '\u{E001}';
+
+fn headline_options() -> String {
+    format!(
+        "StartSel={}, StopSel={}, MaxFragments=2, MaxWords=20, MinWords=5, FragmentDelimiter=\" … \"",
+        MATCH_START, MATCH_STOP
+    )
+}
+
+/// Экранирует фрагмент и выделяет совпадения тегом <mark>
+fn highlight_html(headline: &str) -> String {
+    escape_html(headline)
+        .replace(MATCH_START, "<mark>")
+        .replace(MATCH_STOP, "</mark>")
+}
+
+/// Ищет неудалённые сообщения по запросу в синтаксисе websearch_to_tsquery,
+/// самые подходящие сначала
+pub async fn search_messages(
+    query: &str,
+    filters: &SearchFilters,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<SearchResult>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Searching messages: {:?} {:?}", query, filters);
+
+    let in_thread = filters.thread_root.map(|root| root.is_some());
+    let thread_root = filters.thread_root.flatten();
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {}, ts_rank(to_tsvector('simple', m.message), query), \
+                 ts_headline('simple', m.message, query, $10) \
+                 FROM {}, websearch_to_tsquery('simple', $1) query \
+                 WHERE m.deleted_at IS NULL AND to_tsvector('simple', m.message) @@ query \
+                 AND ($2::uuid IS NULL OR m.user_uuid = $2) \
+                 AND ($3::text IS NULL OR LOWER(u.username) = LOWER($3)) \
+                 AND ($4::bool IS NULL OR ($4 = FALSE AND m.thread_root IS NULL) OR m.thread_root = $5) \
+                 AND ($6::timestamptz IS NULL OR m.created_at >= $6) \
+                 AND ($7::timestamptz IS NULL OR m.created_at < $7) \
+                 AND ($11::bool IS NULL OR $11 = EXISTS \
+                     (SELECT 1 FROM message_attachments a WHERE a.message_id = m.message_id)) \
+                 ORDER BY 16 DESC, m.created_at DESC, m.message_id DESC LIMIT $8 OFFSET $9",
+                PAYLOAD_COLUMNS, PAYLOAD_FROM
+            ),
+            &[
+                &query,
+                &filters.author_uuid,
+                &filters.author_username,
+                &in_thread,
+                &thread_root,
+                &filters.from,
+                &filters.to,
+                &limit,
+                &offset,
+                &headline_options(),
+                &filters.has_attachment,
+            ],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let headline: String = row.get(16);
+            SearchResult {
+                message: payload_from_row(row),
+                rank: row.get(15),
+                highlight: highlight_html(&headline),
+            }
+        })
+        .collect())
+}
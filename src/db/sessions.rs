@@ -1,55 +1,21 @@
 use crate::db::connect_to_db;
 use crate::models::Session;
-use bytes::BytesMut;
 use chrono::{DateTime, Utc};
 use log::debug;
 use std::error::Error as StdError;
 use std::result::Result;
-use tokio_postgres::types::{ToSql, Type};
 use uuid::Uuid;
 
 
-// Обертка для DateTime<Utc>, чтобы обойти "orphan rule"
-#[derive(Debug, Copy, Clone)]
-struct Timestamp(DateTime<Utc>);
-
-impl ToSql for Timestamp {
-    tokio_postgres::types::accepts!(TIMESTAMP, TIMESTAMPTZ);
-    tokio_postgres::types::to_sql_checked!();
-
-    fn to_sql(
-        &self,
-        _type: &Type,
-        out: &mut BytesMut,
-    ) -> Result<tokio_postgres::types::IsNull, Box<dyn StdError + Sync + Send>> {
-        let timestamp = self.0.timestamp();
-        (timestamp).to_sql(_type, out)
-    }
-}
-
-impl<'a> tokio_postgres::types::FromSql<'a> for Timestamp {
-    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn StdError + Sync + Send>> {
-        let timestamp: i64 = tokio_postgres::types::FromSql::from_sql(ty, raw)?;
-        let datetime = DateTime::<Utc>::from_timestamp(timestamp, 0).ok_or("invalid timestamp")?;
-        Ok(Timestamp(datetime))
-    }
-
-    tokio_postgres::types::accepts!(TIMESTAMP, TIMESTAMPTZ);
-}
-
-
-
 /// Сохраняет сессию в базу данных
 pub async fn save_session_to_db(session: Session) -> Result<(), Box<dyn StdError + Send + Sync>> {
     let client = connect_to_db().await?;
 
     debug!("Saving session to database: {:?}", session);
 
-    let expires_at = session.expires_at.map(Timestamp);
-
     client.execute(
         "INSERT INTO sessions (session_id, user_uuid, device_id, expires_at) VALUES ($1, $2, $3, $4)",
-        &[&session.session_id, &session.user_uuid, &session.device_id, &expires_at],
+        &[&session.session_id, &session.user_uuid, &session.device_id, &session.expires_at],
     )
     .await?;
 
@@ -69,14 +35,13 @@ pub async fn find_session_by_session_id(
         .await?;
 
     if let Some(row) = row {
-        let expires_at: Option<Timestamp> = row.get(3); // Получаем expires_at как Option<Timestamp>
-        let expires_at_datetime: Option<DateTime<Utc>> = expires_at.map(|ts| ts.0); // Преобразуем Timestamp в DateTime<Utc>
+        let expires_at: Option<DateTime<Utc>> = row.get(3);
 
         let session = Session {
             session_id: row.get(0),
             user_uuid: row.get(1),
             device_id: row.get(2),
-            expires_at: expires_at_datetime,
+            expires_at,
         };
         Ok(Some(session))
     } else {
@@ -101,3 +66,81 @@ pub async fn delete_session_by_session_id(
 
     Ok(())
 }
+
+/// Удаляет все сессии пользователя. Возвращает количество удалённых сессий
+pub async fn delete_sessions_by_user_uuid(
+    user_uuid: &Uuid,
+) -> Result<u64, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Deleting all sessions for user_uuid: {}", user_uuid);
+
+    let deleted = client
+        .execute("DELETE FROM sessions WHERE user_uuid = $1", &[&user_uuid])
+        .await?;
+
+    Ok(deleted)
+}
+
+/// Удаляет все сессии пользователя, кроме указанной. Возвращает количество удалённых сессий
+pub async fn delete_other_sessions(
+    user_uuid: &Uuid,
+    keep_session_id: &Uuid,
+) -> Result<u64, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Deleting other sessions for user_uuid: {}", user_uuid);
+
+    let deleted = client
+        .execute(
+            "DELETE FROM sessions WHERE user_uuid = $1 AND session_id <> $2",
+            &[&user_uuid, &keep_session_id],
+        )
+        .await?;
+
+    Ok(deleted)
+}
+
+/// Удаляет сессии устройства. Возвращает количество удалённых сессий
+pub async fn delete_sessions_by_device(
+    user_uuid: &Uuid,
+    device_id: &Uuid,
+) -> Result<u64, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Deleting sessions of device {} for user_uuid: {}", device_id, user_uuid);
+
+    let deleted = client
+        .execute(
+            "DELETE FROM sessions WHERE user_uuid = $1 AND device_id = $2",
+            &[&user_uuid, &device_id],
+        )
+        .await?;
+
+    Ok(deleted)
+}
+
+/// Сессии пользователя
+pub async fn list_sessions_by_user_uuid(
+    user_uuid: &Uuid,
+) -> Result<Vec<Session>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            "SELECT session_id, user_uuid, device_id, expires_at FROM sessions \
+             WHERE user_uuid = $1 ORDER BY expires_at",
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| Session {
+            session_id: row.get(0),
+            user_uuid: row.get(1),
+            device_id: row.get(2),
+            expires_at: row.get(3),
+        })
+        .collect())
+}
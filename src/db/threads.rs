@@ -0,0 +1,105 @@
+// src/db/threads.rs
+use crate::db::connect_to_db;
+use crate::db::messages::{author_name, payload_from_row, PAYLOAD_COLUMNS, PAYLOAD_FROM};
+use crate::models::{ChatPayload, QuotedMessage, ThreadParticipant, ThreadSummary};
+use std::collections::HashMap;
+use std::error::Error as StdError;
+
+/// Ветка неудалённого сообщения: Some(None) — общая лента,
+/// Some(Some(root)) — ответ в ветке root, None — сообщения нет или оно удалено
+pub async fn find_message_thread(
+    message_id: i64,
+) -> Result<Option<Option<i64>>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_opt(
+            "SELECT thread_root FROM messages WHERE message_id = $1 AND deleted_at IS NULL",
+            &[&message_id],
+        )
+        .await?;
+
+    Ok(row.map(|row| row.get(0)))
+}
+
+/// Последние limit ответов ветки, старые сначала
+pub async fn list_thread_replies(
+    root_id: i64,
+    limit: i64,
+) -> Result<Vec<ChatPayload>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM {} WHERE m.thread_root = $1 AND m.deleted_at IS NULL \
+                 ORDER BY m.created_at DESC, m.message_id DESC LIMIT $2",
+                PAYLOAD_COLUMNS, PAYLOAD_FROM
+            ),
+            &[&root_id, &limit],
+        )
+        .await?;
+
+    let mut replies: Vec<ChatPayload> = rows.iter().map(payload_from_row).collect();
+    replies.reverse();
+    Ok(replies)
+}
+
+/// Сводки веток под сообщениями; у сообщений без ответов сводки нет
+pub async fn thread_summaries(
+    root_ids: &[i64],
+) -> Result<HashMap<i64, ThreadSummary>, Box<dyn StdError + Send + Sync>> {
+    let mut summaries = HashMap::new();
+    if root_ids.is_empty() {
+        return Ok(summaries);
+    }
+    let client = connect_to_db().await?;
+
+    // Последний ответ и число ответов в каждой ветке
+    let rows = client
+        .query(
+            "SELECT DISTINCT ON (m.thread_root) m.thread_root, \
+             COUNT(*) OVER (PARTITION BY m.thread_root), \
+             m.message_id, m.user_uuid, u.username, u.display_name, m.message, m.created_at \
+             FROM messages m LEFT JOIN users u ON u.user_uuid = m.user_uuid \
+             WHERE m.thread_root = ANY($1) AND m.deleted_at IS NULL \
+             ORDER BY m.thread_root, m.created_at DESC, m.message_id DESC",
+            &[&root_ids],
+        )
+        .await?;
+    for row in rows {
+        let summary = ThreadSummary {
+            reply_count: row.get(1),
+            last_reply: QuotedMessage {
+                message_id: row.get(2),
+                user_uuid: row.get(3),
+                author: author_name(row.get(4), row.get(5)),
+                text: Some(row.get(6)),
+                created_at: row.get(7),
+            },
+            participants: Vec::new(),
+        };
+        summaries.insert(row.get::<_, i64>(0), summary);
+    }
+
+    let rows = client
+        .query(
+            "SELECT m.thread_root, u.user_uuid, u.username, u.display_name \
+             FROM messages m JOIN users u ON u.user_uuid = m.user_uuid \
+             WHERE m.thread_root = ANY($1) AND m.deleted_at IS NULL \
+             GROUP BY m.thread_root, u.user_uuid, u.username, u.display_name \
+             ORDER BY m.thread_root, MIN(m.created_at)",
+            &[&root_ids],
+        )
+        .await?;
+    for row in rows {
+        if let Some(summary) = summaries.get_mut(&row.get::<_, i64>(0)) {
+            summary.participants.push(ThreadParticipant {
+                user_uuid: row.get(1),
+                author: author_name(row.get(2), row.get(3)),
+            });
+        }
+    }
+
+    Ok(summaries)
+}
@@ -0,0 +1,217 @@
+// src/db/two_factor.rs
+use crate::db::connect_to_db;
+use crate::models::{LoginChallenge, TotpSecret};
+use log::debug;
+use std::error::Error as StdError;
+use uuid::Uuid;
+
+/// Ищет секрет TOTP пользователя (подтверждённый или ожидающий подтверждения)
+pub async fn find_totp(
+    user_uuid: &Uuid,
+) -> Result<Option<TotpSecret>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_opt(
+            "SELECT user_uuid, secret, enabled_at, last_used_step FROM user_totp WHERE user_uuid = $1",
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(row.map(|row| TotpSecret {
+        user_uuid: row.get(0),
+        secret: row.get(1),
+        enabled_at: row.get(2),
+        last_used_step: row.get(3),
+    }))
+}
+
+/// Сохраняет новый неподтверждённый секрет, заменяя прежний неподтверждённый
+pub async fn save_pending_totp(
+    user_uuid: &Uuid,
+    secret: &str,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Saving pending TOTP secret for user_uuid: {}", user_uuid);
+
+    client
+        .execute(
+            "INSERT INTO user_totp (user_uuid, secret) VALUES ($1, $2) \
+             ON CONFLICT (user_uuid) DO UPDATE SET secret = EXCLUDED.secret, enabled_at = NULL, \
+             last_used_step = NULL, created_at = NOW() WHERE user_totp.enabled_at IS NULL",
+            &[&user_uuid, &secret],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Включает 2FA и заменяет коды восстановления
+pub async fn enable_totp(
+    user_uuid: &Uuid,
+    step: i64,
+    recovery_code_hashes: &[String],
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let mut client = connect_to_db().await?;
+
+    debug!("Enabling TOTP for user_uuid: {}", user_uuid);
+
+    let transaction = client.transaction().await?;
+    transaction
+        .execute(
+            "UPDATE user_totp SET enabled_at = NOW(), last_used_step = $2 WHERE user_uuid = $1",
+            &[&user_uuid, &step],
+        )
+        .await?;
+    transaction
+        .execute(
+            "DELETE FROM recovery_codes WHERE user_uuid = $1",
+            &[&user_uuid],
+        )
+        .await?;
+    for code_hash in recovery_code_hashes {
+        transaction
+            .execute(
+                "INSERT INTO recovery_codes (user_uuid, code_hash) VALUES ($1, $2)",
+                &[&user_uuid, &code_hash],
+            )
+            .await?;
+    }
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+/// Запоминает использованный шаг TOTP. Возвращает false, если код с этим
+/// или более поздним шагом уже использовался (защита от повтора)
+pub async fn mark_totp_step_used(
+    user_uuid: &Uuid,
+    step: i64,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let updated = client
+        .execute(
+            "UPDATE user_totp SET last_used_step = $2 WHERE user_uuid = $1 \
+             AND (last_used_step IS NULL OR last_used_step < $2)",
+            &[&user_uuid, &step],
+        )
+        .await?;
+
+    Ok(updated > 0)
+}
+
+/// Погашает код восстановления. Возвращает false, если код не найден или уже использован
+pub async fn use_recovery_code(
+    user_uuid: &Uuid,
+    code_hash: &str,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let updated = client
+        .execute(
+            "UPDATE recovery_codes SET used_at = NOW() WHERE code_id = \
+             (SELECT code_id FROM recovery_codes WHERE user_uuid = $1 AND code_hash = $2 AND used_at IS NULL LIMIT 1)",
+            &[&user_uuid, &code_hash],
+        )
+        .await?;
+
+    Ok(updated > 0)
+}
+
+/// Количество неиспользованных кодов восстановления
+pub async fn count_unused_recovery_codes(
+    user_uuid: &Uuid,
+) -> Result<i64, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_one(
+            "SELECT COUNT(*) FROM recovery_codes WHERE user_uuid = $1 AND used_at IS NULL",
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(row.get(0))
+}
+
+/// Отключает 2FA и удаляет коды восстановления
+pub async fn disable_totp(user_uuid: &Uuid) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let mut client = connect_to_db().await?;
+
+    debug!("Disabling TOTP for user_uuid: {}", user_uuid);
+
+    let transaction = client.transaction().await?;
+    transaction
+        .execute(
+            "DELETE FROM recovery_codes WHERE user_uuid = $1",
+            &[&user_uuid],
+        )
+        .await?;
+    transaction
+        .execute("DELETE FROM user_totp WHERE user_uuid = $1", &[&user_uuid])
+        .await?;
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+/// Сохраняет незавершённый вход
+pub async fn create_login_challenge(
+    challenge: &LoginChallenge,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    client
+        .execute(
+            "INSERT INTO login_challenges (challenge_id, user_uuid, expires_at, attempts) VALUES ($1, $2, $3, $4)",
+            &[
+                &challenge.challenge_id,
+                &challenge.user_uuid,
+                &challenge.expires_at,
+                &challenge.attempts,
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Ищет незавершённый вход и засчитывает ещё одну попытку ввода кода
+pub async fn take_login_challenge_attempt(
+    challenge_id: &Uuid,
+) -> Result<Option<LoginChallenge>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_opt(
+            "UPDATE login_challenges SET attempts = attempts + 1 WHERE challenge_id = $1 \
+             RETURNING challenge_id, user_uuid, expires_at, attempts",
+            &[&challenge_id],
+        )
+        .await?;
+
+    Ok(row.map(|row| LoginChallenge {
+        challenge_id: row.get(0),
+        user_uuid: row.get(1),
+        expires_at: row.get(2),
+        attempts: row.get(3),
+    }))
+}
+
+/// Удаляет незавершённый вход
+pub async fn delete_login_challenge(
+    challenge_id: &Uuid,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    client
+        .execute(
+            "DELETE FROM login_challenges WHERE challenge_id = $1",
+            &[&challenge_id],
+        )
+        .await?;
+
+    Ok(())
+}
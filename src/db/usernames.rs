@@ -0,0 +1,158 @@
+// src/db/usernames.rs
+use crate::db::connect_to_db;
+use crate::models::{ReservedUsername, UsernameChange};
+use chrono::{DateTime, Utc};
+use log::debug;
+use std::error::Error as StdError;
+use uuid::Uuid;
+
+/// Занято ли имя другим пользователем (без учёта регистра)
+pub async fn is_username_taken(
+    username: &str,
+    except: Option<&Uuid>,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_one(
+            "SELECT EXISTS (SELECT 1 FROM users WHERE LOWER(username) = LOWER($1) \
+             AND ($2::UUID IS NULL OR user_uuid <> $2))",
+            &[&username, &except],
+        )
+        .await?;
+
+    Ok(row.get(0))
+}
+
+/// Есть ли имя в списке зарезервированных
+pub async fn is_username_reserved(username: &str) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_one(
+            "SELECT EXISTS (SELECT 1 FROM reserved_usernames WHERE username = LOWER($1))",
+            &[&username],
+        )
+        .await?;
+
+    Ok(row.get(0))
+}
+
+/// Освобождено ли имя другим пользователем после since
+pub async fn is_username_held(
+    username: &str,
+    except: Option<&Uuid>,
+    since: DateTime<Utc>,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_one(
+            "SELECT EXISTS (SELECT 1 FROM released_usernames WHERE username = LOWER($1) \
+             AND released_at > $3 AND ($2::UUID IS NULL OR released_by <> $2))",
+            &[&username, &except, &since],
+        )
+        .await?;
+
+    Ok(row.get(0))
+}
+
+/// Удаляет освобождённые имена, срок удержания которых истёк
+pub async fn purge_released_usernames(
+    before: DateTime<Utc>,
+) -> Result<u64, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let deleted = client
+        .execute(
+            "DELETE FROM released_usernames WHERE released_at < $1",
+            &[&before],
+        )
+        .await?;
+
+    Ok(deleted)
+}
+
+/// Прежние имена пользователя, последние сначала
+pub async fn list_username_history(
+    user_uuid: &Uuid,
+) -> Result<Vec<UsernameChange>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            "SELECT old_username, new_username, changed_by, changed_at FROM username_history \
+             WHERE user_uuid = $1 ORDER BY changed_at DESC, history_id DESC",
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| UsernameChange {
+            old_username: row.get(0),
+            new_username: row.get(1),
+            changed_by: row.get(2),
+            changed_at: row.get(3),
+        })
+        .collect())
+}
+
+pub async fn list_reserved_usernames(
+) -> Result<Vec<ReservedUsername>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            "SELECT username, reason, created_by, created_at FROM reserved_usernames ORDER BY username",
+            &[],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| ReservedUsername {
+            username: row.get(0),
+            reason: row.get(1),
+            created_by: row.get(2),
+            created_at: row.get(3),
+        })
+        .collect())
+}
+
+/// Резервирует имя. Возвращает false, если оно уже в списке
+pub async fn add_reserved_username(
+    username: &str,
+    reason: Option<&str>,
+    created_by: &Uuid,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Reserving username {} by {}", username, created_by);
+
+    let inserted = client
+        .execute(
+            "INSERT INTO reserved_usernames (username, reason, created_by) VALUES (LOWER($1), $2, $3) \
+             ON CONFLICT (username) DO NOTHING",
+            &[&username, &reason, &created_by],
+        )
+        .await?;
+
+    Ok(inserted > 0)
+}
+
+/// Убирает имя из списка. Возвращает false, если его там не было
+pub async fn remove_reserved_username(
+    username: &str,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let deleted = client
+        .execute(
+            "DELETE FROM reserved_usernames WHERE username = LOWER($1)",
+            &[&username],
+        )
+        .await?;
+
+    Ok(deleted > 0)
+}
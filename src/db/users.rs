@@ -1,9 +1,46 @@
-use crate::models::User;
+use crate::models::{AdminUserSummary, Role, User};
+use chrono::{DateTime, Utc};
 use log::debug;
 use std::error::Error as StdError;
+use tokio_postgres::Row;
 use uuid::Uuid;
 use crate::db::connect_to_db;
 
+const USER_COLUMNS: &str = "username, password_hash, invitation_code, user_uuid, role, suspended_at, email, \
+    display_name, username_changed_at";
+
+// Последняя активность: вход или последнее сообщение, что позже
+const SUMMARY_COLUMNS: &str = "u.user_uuid, u.username, u.display_name, u.role, u.created_at, \
+    GREATEST(u.last_login_at, (SELECT MAX(m.created_at) FROM messages m WHERE m.user_uuid = u.user_uuid)), \
+    u.suspended_at, u.suspended_reason";
+
+fn user_from_row(row: &Row) -> User {
+    User {
+        username: row.get(0),
+        password_hash: row.get(1),
+        invitation_code: row.get(2),
+        user_uuid: row.get(3),
+        role: row.get(4),
+        suspended_at: row.get(5),
+        email: row.get(6),
+        display_name: row.get(7),
+        username_changed_at: row.get(8),
+    }
+}
+
+fn summary_from_row(row: &Row) -> AdminUserSummary {
+    AdminUserSummary {
+        user_uuid: row.get(0),
+        username: row.get(1),
+        display_name: row.get(2),
+        role: row.get(3),
+        registration_date: row.get(4),
+        last_activity: row.get(5),
+        suspended_at: row.get(6),
+        suspended_reason: row.get(7),
+    }
+}
+
 /// Сохраняет пользователя в базу данных
 pub async fn save_user_to_db(user: User) -> Result<(), Box<dyn StdError + Send + Sync>> {
     let client = connect_to_db().await?;  
@@ -12,8 +49,8 @@ pub async fn save_user_to_db(user: User) -> Result<(), Box<dyn StdError + Send +
     debug!("Saving user to database: {}", user.username);
 
     client.execute(
-        "INSERT INTO users (username, password_hash, invitation_code, user_uuid) VALUES ($1, $2, $3, $4)",
-        &[&user.username, &user.password_hash, &user.invitation_code, &user.user_uuid],
+        "INSERT INTO users (username, password_hash, invitation_code, user_uuid, role, email) VALUES ($1, $2, $3, $4, $5, $6)",
+        &[&user.username, &user.password_hash, &user.invitation_code, &user.user_uuid, &user.role.to_string(), &user.email],
     )
     .await?;
 
@@ -29,17 +66,28 @@ pub async fn find_user_by_username(
     debug!("Finding user in database by username: {}", username);
 
     let row = client
-        .query_one("SELECT username, password_hash, invitation_code, user_uuid FROM users WHERE username = $1", &[&username])
+        .query_one(&format!("SELECT {} FROM users WHERE username = $1", USER_COLUMNS), &[&username])
         .await?;
 
-    let user = User {
-        username: row.get(0),
-        password_hash: row.get(1),
-        invitation_code: row.get(2),
-        user_uuid: row.get(3),
-    };
+    Ok(user_from_row(&row))
+}
+
+/// Ищет пользователя по адресу почты (без учёта регистра)
+pub async fn find_user_by_email(
+    email: &str,
+) -> Result<Option<User>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Finding user in database by email");
+
+    let row = client
+        .query_opt(
+            &format!("SELECT {} FROM users WHERE LOWER(email) = LOWER($1)", USER_COLUMNS),
+            &[&email],
+        )
+        .await?;
 
-    Ok(user)
+    Ok(row.as_ref().map(user_from_row))
 }
 
 /// Ищет пользователя по UUID
@@ -49,15 +97,319 @@ pub async fn find_user_by_uuid(user_uuid: &Uuid) -> Result<User, Box<dyn StdErro
     debug!("Finding user in database by user_uuid: {}", user_uuid);
 
     let row = client
-        .query_one("SELECT username, password_hash, invitation_code, user_uuid FROM users WHERE user_uuid = $1", &[&user_uuid])
+        .query_one(&format!("SELECT {} FROM users WHERE user_uuid = $1", USER_COLUMNS), &[&user_uuid])
         .await?;
 
-    let user = User {
-        username: row.get(0),
-        password_hash: row.get(1),
-        invitation_code: row.get(2),
-        user_uuid: row.get(3),
+    Ok(user_from_row(&row))
+}
+
+/// Меняет роль пользователя. Возвращает false, если пользователь не найден
+pub async fn update_user_role(
+    user_uuid: &Uuid,
+    role: Role,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Updating role for user_uuid: {} to {}", user_uuid, role);
+
+    let updated = client
+        .execute(
+            "UPDATE users SET role = $1 WHERE user_uuid = $2",
+            &[&role.to_string(), &user_uuid],
+        )
+        .await?;
+
+    Ok(updated > 0)
+}
+
+/// Запоминает время последнего входа
+pub async fn update_last_login(user_uuid: &Uuid) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    client
+        .execute(
+            "UPDATE users SET last_login_at = NOW() WHERE user_uuid = $1",
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Список пользователей с поиском по части имени или отображаемого имени
+pub async fn list_users(
+    search: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<AdminUserSummary>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Listing users: search={:?}, limit={}, offset={}", search, limit, offset);
+
+    // Экранируем спецсимволы LIKE, чтобы искать их буквально
+    let pattern = search.map(|search| {
+        format!(
+            "%{}%",
+            search.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+        )
+    });
+
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {} FROM users u WHERE ($1::TEXT IS NULL OR u.username ILIKE $1 OR u.display_name ILIKE $1) \
+                 ORDER BY u.created_at DESC LIMIT $2 OFFSET $3",
+                SUMMARY_COLUMNS
+            ),
+            &[&pattern, &limit, &offset],
+        )
+        .await?;
+
+    Ok(rows.iter().map(summary_from_row).collect())
+}
+
+/// Сведения о пользователе для администратора
+pub async fn find_user_summary(
+    user_uuid: &Uuid,
+) -> Result<Option<AdminUserSummary>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_opt(
+            &format!("SELECT {} FROM users u WHERE u.user_uuid = $1", SUMMARY_COLUMNS),
+            &[&user_uuid],
+        )
+        .await?;
+
+    Ok(row.as_ref().map(summary_from_row))
+}
+
+/// Блокирует (reason = Some) или разблокирует (reason = None) пользователя.
+/// Возвращает false, если пользователь не найден
+pub async fn set_user_suspended(
+    user_uuid: &Uuid,
+    suspended: bool,
+    reason: Option<&str>,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Setting suspended={} for user_uuid: {}", suspended, user_uuid);
+
+    let updated = if suspended {
+        client
+            .execute(
+                "UPDATE users SET suspended_at = NOW(), suspended_reason = $2 WHERE user_uuid = $1",
+                &[&user_uuid, &reason],
+            )
+            .await?
+    } else {
+        client
+            .execute(
+                "UPDATE users SET suspended_at = NULL, suspended_reason = NULL WHERE user_uuid = $1",
+                &[&user_uuid],
+            )
+            .await?
     };
 
-    Ok(user)
+    Ok(updated > 0)
+}
+
+/// Заменяет хеш пароля. Возвращает false, если пользователь не найден
+pub async fn update_password_hash(
+    user_uuid: &Uuid,
+    password_hash: &str,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Updating password hash for user_uuid: {}", user_uuid);
+
+    let updated = client
+        .execute(
+            "UPDATE users SET password_hash = $1 WHERE user_uuid = $2",
+            &[&password_hash, &user_uuid],
+        )
+        .await?;
+
+    Ok(updated > 0)
+}
+
+/// Задаёт адрес почты для восстановления пароля. Возвращает false, если пользователь не найден
+pub async fn update_email(
+    user_uuid: &Uuid,
+    email: Option<&str>,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Updating email for user_uuid: {}", user_uuid);
+
+    let updated = client
+        .execute(
+            "UPDATE users SET email = $1 WHERE user_uuid = $2",
+            &[&email, &user_uuid],
+        )
+        .await?;
+
+    Ok(updated > 0)
+}
+
+/// Удаляет пользователя вместе с сессиями, устройствами, профилем и файлами.
+/// Сообщения остаются, но теряют автора ("Unknown User").
+/// Возвращает file_id удалённых файлов, чтобы убрать их с диска,
+/// или None, если пользователь не найден
+pub async fn delete_user(
+    user_uuid: &Uuid,
+) -> Result<Option<Vec<Uuid>>, Box<dyn StdError + Send + Sync>> {
+    let mut client = connect_to_db().await?;
+
+    debug!("Deleting user_uuid: {}", user_uuid);
+
+    let transaction = client.transaction().await?;
+
+    let exists = transaction
+        .query_opt("SELECT 1 FROM users WHERE user_uuid = $1 FOR UPDATE", &[&user_uuid])
+        .await?;
+    if exists.is_none() {
+        return Ok(None);
+    }
+
+    let file_ids: Vec<Uuid> = transaction
+        .query("SELECT file_id FROM files WHERE user_uuid = $1", &[&user_uuid])
+        .await?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+
+    for statement in [
+        // Имя какое-то время не достанется другому пользователю
+        "INSERT INTO released_usernames (username, released_by) \
+         SELECT LOWER(username), user_uuid FROM users WHERE user_uuid = $1 \
+         ON CONFLICT (username) DO UPDATE SET released_by = EXCLUDED.released_by, released_at = NOW()",
+        "DELETE FROM sessions WHERE user_uuid = $1",
+        "DELETE FROM devices WHERE user_uuid = $1",
+        "DELETE FROM files WHERE user_uuid = $1",
+        "DELETE FROM profiles WHERE user_uuid = $1",
+        "DELETE FROM storage_access WHERE user_uuid = $1",
+        "UPDATE messages SET user_uuid = NULL WHERE user_uuid = $1",
+        "UPDATE messages SET deleted_by = NULL WHERE deleted_by = $1",
+        "DELETE FROM users WHERE user_uuid = $1",
+    ] {
+        transaction.execute(statement, &[&user_uuid]).await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(Some(file_ids))
+}
+
+/// Назначает (Some) или отменяет (None) удаление учётной записи.
+/// Возвращает false, если пользователь не найден
+pub async fn set_delete_after(
+    user_uuid: &Uuid,
+    delete_after: Option<DateTime<Utc>>,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    debug!("Setting delete_after={:?} for user_uuid: {}", delete_after, user_uuid);
+
+    let updated = client
+        .execute(
+            "UPDATE users SET delete_after = $2 WHERE user_uuid = $1",
+            &[&user_uuid, &delete_after],
+        )
+        .await?;
+
+    Ok(updated > 0)
+}
+
+/// Когда будет удалена учётная запись (None — удаление не запрошено)
+pub async fn find_delete_after(
+    user_uuid: &Uuid,
+) -> Result<Option<DateTime<Utc>>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let row = client
+        .query_opt("SELECT delete_after FROM users WHERE user_uuid = $1", &[&user_uuid])
+        .await?;
+
+    Ok(row.and_then(|row| row.get(0)))
+}
+
+/// Учётные записи, срок удаления которых наступил
+pub async fn find_due_deletions(
+    now: DateTime<Utc>,
+) -> Result<Vec<Uuid>, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let rows = client
+        .query(
+            "SELECT user_uuid FROM users WHERE delete_after <= $1 ORDER BY delete_after",
+            &[&now],
+        )
+        .await?;
+
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Меняет имя пользователя, записывает прежнее в историю и придерживает его.
+/// Возвращает прежнее имя или None, если пользователь не найден
+pub async fn change_username(
+    user_uuid: &Uuid,
+    new_username: &str,
+    changed_by: &Uuid,
+) -> Result<Option<String>, Box<dyn StdError + Send + Sync>> {
+    let mut client = connect_to_db().await?;
+
+    debug!("Changing username of user_uuid: {} to {}", user_uuid, new_username);
+
+    let transaction = client.transaction().await?;
+    let old_username: String = match transaction
+        .query_opt("SELECT username FROM users WHERE user_uuid = $1 FOR UPDATE", &[&user_uuid])
+        .await?
+    {
+        Some(row) => row.get(0),
+        None => return Ok(None),
+    };
+
+    transaction
+        .execute(
+            "UPDATE users SET username = $2, username_changed_at = NOW() WHERE user_uuid = $1",
+            &[&user_uuid, &new_username],
+        )
+        .await?;
+    transaction
+        .execute(
+            "INSERT INTO username_history (user_uuid, old_username, new_username, changed_by) \
+             VALUES ($1, $2, $3, $4)",
+            &[&user_uuid, &old_username, &new_username, &changed_by],
+        )
+        .await?;
+    if old_username.to_lowercase() != new_username.to_lowercase() {
+        transaction
+            .execute(
+                "INSERT INTO released_usernames (username, released_by) VALUES (LOWER($1), $2) \
+                 ON CONFLICT (username) DO UPDATE SET released_by = EXCLUDED.released_by, released_at = NOW()",
+                &[&old_username, &user_uuid],
+            )
+            .await?;
+    }
+    transaction.commit().await?;
+
+    Ok(Some(old_username))
+}
+
+/// Задаёт или убирает отображаемое имя. Возвращает false, если пользователь не найден
+pub async fn update_display_name(
+    user_uuid: &Uuid,
+    display_name: Option<&str>,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let client = connect_to_db().await?;
+
+    let updated = client
+        .execute(
+            "UPDATE users SET display_name = $2 WHERE user_uuid = $1",
+            &[&user_uuid, &display_name],
+        )
+        .await?;
+
+    Ok(updated > 0)
 }
@@ -0,0 +1,141 @@
+// src/devices.rs
+//
+// Определение устройства при входе. Браузер получает долгоживущий токен в
+// cookie; устройство — это пара (пользователь, токен), так что за одним NAT
+// или в одном браузере у разных пользователей устройства разные. Отпечаток
+// User-Agent без номеров версий защищает от переноса cookie в другой браузер:
+// при несовпадении выдаётся новый токен и заводится новое устройство.
+use crate::audit::ClientInfo;
+use crate::db::devices::{
+    find_device_by_token, has_devices, ip_range_seen, save_device_to_db, touch_device,
+};
+use crate::models::Device;
+use log::{debug, warn};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::error::Error as StdError;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+/// Имя cookie с токеном устройства
+pub const DEVICE_COOKIE: &str = "device_token";
+const DEVICE_TOKEN_BYTES: usize = 32;
+const DEVICE_COOKIE_MAX_AGE: i64 = 365 * 24 * 60 * 60;
+
+fn sha256_hex(value: &str) -> String {
+    data_encoding::HEXLOWER.encode(&Sha256::digest(value.as_bytes()))
+}
+
+fn generate_device_token() -> String {
+    let mut bytes = [0u8; DEVICE_TOKEN_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    data_encoding::HEXLOWER.encode(&bytes)
+}
+
+fn is_well_formed(token: &str) -> bool {
+    token.len() == DEVICE_TOKEN_BYTES * 2
+        && token
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// Отпечаток User-Agent: обновление браузера меняет только номера версий
+pub fn user_agent_fingerprint(user_agent: Option<&str>) -> String {
+    let normalized: String = user_agent
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_ascii_digit() && *c != '.' && *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect();
+    sha256_hex(&normalized)
+}
+
+/// Заголовок Set-Cookie с токеном устройства
+pub fn device_cookie(token: &str) -> String {
+    // Токен нужен только при входе, в остальных запросах он не передаётся
+    format!(
+        "{}={}; HttpOnly; Secure; SameSite=Strict; Path=/api/login; Max-Age={}",
+        DEVICE_COOKIE, token, DEVICE_COOKIE_MAX_AGE
+    )
+}
+
+/// Устройство, с которого выполняется вход
+#[derive(Debug, Clone)]
+pub struct ResolvedDevice {
+    pub device: Device,
+    /// Токен, если cookie нужно (пере)выставить
+    pub new_token: Option<String>,
+    /// Вход с нового устройства или из незнакомой сети — о нём стоит предупредить.
+    /// Первый вход в учётную запись и доверенные устройства сюда не попадают
+    pub unfamiliar: bool,
+}
+
+/// Находит устройство пользователя по cookie или заводит новое
+pub async fn resolve_device(
+    user_uuid: &Uuid,
+    client: &ClientInfo,
+) -> Result<ResolvedDevice, Box<dyn StdError + Send + Sync>> {
+    let ip_address = client.ip_address.unwrap_or(IpAddr::from([0, 0, 0, 0]));
+    let fingerprint = user_agent_fingerprint(client.user_agent.as_deref());
+    let first_login = !has_devices(user_uuid).await?;
+    let known_network = ip_range_seen(user_uuid, ip_address).await?;
+
+    let presented = client
+        .device_token
+        .as_deref()
+        .filter(|token| is_well_formed(token));
+    let mut reuse_token = presented;
+    if let Some(token) = presented {
+        if let Some(device) = find_device_by_token(user_uuid, &sha256_hex(token)).await? {
+            if device.fingerprint.as_deref() == Some(fingerprint.as_str()) {
+                touch_device(&device.device_id, ip_address, client.user_agent.as_deref()).await?;
+                debug!(
+                    "Recognized device {} for user_uuid: {}",
+                    device.device_id, user_uuid
+                );
+                return Ok(ResolvedDevice {
+                    unfamiliar: !known_network && !device.trusted,
+                    device,
+                    new_token: None,
+                });
+            }
+            warn!(
+                "Device token of {} presented by a different user agent, issuing a new one",
+                device.device_id
+            );
+            reuse_token = None;
+        }
+    }
+
+    // Браузер уже знаком по входу другого пользователя — оставляем его токен
+    let token = match reuse_token {
+        Some(token) => token.to_string(),
+        None => generate_device_token(),
+    };
+    let device = Device {
+        device_id: Uuid::new_v4(),
+        user_uuid: *user_uuid,
+        ip_address: ip_address.to_string(),
+        name: None,
+        user_agent: client.user_agent.clone(),
+        trusted: false,
+        created_at: None,
+        last_seen_at: None,
+        fingerprint: Some(fingerprint),
+    };
+    save_device_to_db(&device, &sha256_hex(&token)).await?;
+    debug!(
+        "New device {} for user_uuid: {}",
+        device.device_id, user_uuid
+    );
+
+    Ok(ResolvedDevice {
+        device,
+        new_token: if reuse_token.is_some() {
+            None
+        } else {
+            Some(token)
+        },
+        unfamiliar: !first_login,
+    })
+}
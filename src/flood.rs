@@ -0,0 +1,318 @@
+// src/flood.rs
+//
+// Защита чата от флуда. Сообщения и реакции расходуют жетоны из двух вёдер:
+// своего у соединения и общего у пользователя (на все его соединения), так что
+// новые вкладки лимит не обходят. Кроме того: предел длины сообщения, запрет
+// повторять одно и то же сообщение подряд и временный медленный режим, который
+// модератор включает для ленты или ветки. Всё хранится в памяти, в Flood,
+// которым владеет routes().
+use crate::models::SlowModeStatus;
+use chrono::Utc;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Сколько сообщений подряд можно отправить из одного соединения
+const CONNECTION_BURST: f64 = 10.0;
+/// Сколько жетонов в секунду возвращается соединению
+const CONNECTION_REFILL_PER_SECOND: f64 = 1.0;
+/// То же для всех соединений пользователя вместе
+const USER_BURST: f64 = 20.0;
+const USER_REFILL_PER_SECOND: f64 = 2.0;
+/// Наибольшая длина сообщения в символах
+pub const MAX_MESSAGE_LENGTH: usize = 4000;
+/// Сколько то же сообщение в том же месте считается повтором
+const DUPLICATE_WINDOW: Duration = Duration::from_secs(30);
+/// Пределы медленного режима
+pub const MAX_SLOW_MODE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+pub const MAX_SLOW_MODE_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+/// Состояние пользователя, который столько молчит, можно забыть
+const IDLE_USER_TTL: Duration = Duration::from_secs(10 * 60);
+/// Сколько пользователей хранить, прежде чем чистить молчащих
+const MAX_TRACKED_USERS: usize = 10_000;
+
+/// Отказ в запросе клиента; retry_after — через сколько можно повторить
+#[derive(Debug, Clone, PartialEq)]
+pub struct Refusal {
+    pub message: String,
+    pub retry_after: Option<Duration>,
+}
+
+impl Refusal {
+    fn limited(message: &str, retry_after: Duration) -> Self {
+        Refusal {
+            message: message.to_string(),
+            retry_after: Some(retry_after),
+        }
+    }
+
+    /// retry_after в целых секундах, с округлением вверх
+    pub fn retry_after_seconds(&self) -> Option<u64> {
+        self.retry_after
+            .map(|retry_after| retry_after.as_millis().div_ceil(1000).max(1) as u64)
+    }
+}
+
+impl From<String> for Refusal {
+    fn from(message: String) -> Self {
+        Refusal {
+            message,
+            retry_after: None,
+        }
+    }
+}
+
+/// Ведро жетонов: вмещает capacity, пополняется со скоростью refill_per_second
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_second: f64,
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: f64, refill_per_second: f64) -> Self {
+        TokenBucket {
+            capacity,
+            refill_per_second,
+            tokens: capacity,
+            updated_at: Instant::now(),
+        }
+    }
+
+    /// Ведро одного соединения чата
+    pub fn for_connection() -> Self {
+        TokenBucket::new(CONNECTION_BURST, CONNECTION_REFILL_PER_SECOND)
+    }
+
+    /// Берёт жетон; если их нет — через сколько появится следующий
+    pub fn try_take(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        self.updated_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.refill_per_second,
+            ))
+        }
+    }
+}
+
+struct UserState {
+    bucket: TokenBucket,
+    /// Отпечаток последнего сообщения и когда оно отправлено
+    last_message: Option<(u64, Instant)>,
+    /// Последнее сообщение в ленте (None) или ветке
+    last_posts: HashMap<Option<i64>, Instant>,
+    seen_at: Instant,
+}
+
+impl UserState {
+    fn new() -> Self {
+        UserState {
+            bucket: TokenBucket::new(USER_BURST, USER_REFILL_PER_SECOND),
+            last_message: None,
+            last_posts: HashMap::new(),
+            seen_at: Instant::now(),
+        }
+    }
+}
+
+struct SlowMode {
+    interval: Duration,
+    expires_at: Instant,
+    status: SlowModeStatus,
+}
+
+/// Состояние защиты от флуда: пользователи и медленные режимы
+#[derive(Default)]
+pub struct Flood {
+    users: Mutex<HashMap<Uuid, UserState>>,
+    slow_modes: Mutex<HashMap<Option<i64>, SlowMode>>,
+}
+
+pub type FloodState = Arc<Flood>;
+
+/// Что запомнил check_and_record_message; нужно, чтобы отменить запись,
+/// если сообщение так и не сохранилось
+pub struct RecordedMessage {
+    room: Option<i64>,
+    entry: (u64, Instant),
+    previous_message: Option<(u64, Instant)>,
+    previous_post: Option<Instant>,
+}
+
+impl Flood {
+    /// Выполняет f над состоянием пользователя, заводя его при необходимости
+    fn with_user<T>(&self, user_uuid: Uuid, f: impl FnOnce(&mut UserState) -> T) -> T {
+        let mut users = self.users.lock().unwrap();
+        if !users.contains_key(&user_uuid) && users.len() >= MAX_TRACKED_USERS {
+            users.retain(|_, state| state.seen_at.elapsed() < IDLE_USER_TTL);
+        }
+        let state = users.entry(user_uuid).or_insert_with(UserState::new);
+        state.seen_at = Instant::now();
+        f(state)
+    }
+
+    /// Берёт жетон из ведра соединения и из общего ведра пользователя
+    pub fn take_token(&self, user_uuid: Uuid, connection: &mut TokenBucket) -> Result<(), Refusal> {
+        const MESSAGE: &str = "You are sending messages too fast";
+        connection
+            .try_take()
+            .map_err(|retry_after| Refusal::limited(MESSAGE, retry_after))?;
+        self.with_user(user_uuid, |state| state.bucket.try_take())
+            .map_err(|retry_after| Refusal::limited(MESSAGE, retry_after))
+    }
+
+    /// Проверки перед публикацией в ленте или ветке room: повтор предыдущего
+    /// сообщения и медленный режим (exempt — модераторы его не соблюдают).
+    /// Прошедшее проверки сообщение запоминается под той же блокировкой,
+    /// так что соединения одного пользователя не проскочат их одновременно
+    pub fn check_and_record_message(
+        &self,
+        user_uuid: Uuid,
+        room: Option<i64>,
+        text: &str,
+        attachments: &[Uuid],
+        exempt: bool,
+    ) -> Result<RecordedMessage, Refusal> {
+        let interval = if exempt { None } else { self.slow_mode_interval(room) };
+        let fingerprint = fingerprint(room, text, attachments);
+        self.with_user(user_uuid, |state| {
+            let now = Instant::now();
+            if let Some((last, sent_at)) = state.last_message {
+                if last == fingerprint {
+                    if let Some(retry_after) = remaining_in_window(sent_at, DUPLICATE_WINDOW, now) {
+                        return Err(Refusal::limited("Duplicate message", retry_after));
+                    }
+                }
+            }
+            if let (Some(interval), Some(posted_at)) = (interval, state.last_posts.get(&room)) {
+                if let Some(retry_after) = remaining_in_window(*posted_at, interval, now) {
+                    return Err(Refusal::limited("Slow mode is on", retry_after));
+                }
+            }
+            let entry = (fingerprint, now);
+            Ok(RecordedMessage {
+                room,
+                entry,
+                previous_message: state.last_message.replace(entry),
+                previous_post: state.last_posts.insert(room, now),
+            })
+        })
+    }
+
+    /// Отменяет запись check_and_record_message, если после неё не было новых
+    pub fn forget_message(&self, user_uuid: Uuid, recorded: RecordedMessage) {
+        self.with_user(user_uuid, |state| {
+            if state.last_message == Some(recorded.entry) {
+                state.last_message = recorded.previous_message;
+            }
+            if state.last_posts.get(&recorded.room) == Some(&recorded.entry.1) {
+                match recorded.previous_post {
+                    Some(posted_at) => state.last_posts.insert(recorded.room, posted_at),
+                    None => state.last_posts.remove(&recorded.room),
+                };
+            }
+        });
+    }
+
+    /// Действующий медленный режим в ленте или ветке
+    fn slow_mode_interval(&self, room: Option<i64>) -> Option<Duration> {
+        let mut slow_modes = self.slow_modes.lock().unwrap();
+        match slow_modes.get(&room) {
+            Some(slow_mode) if slow_mode.expires_at > Instant::now() => Some(slow_mode.interval),
+            Some(_) => {
+                slow_modes.remove(&room);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Включает медленный режим на duration; повторный вызов заменяет прежний
+    pub fn set_slow_mode(
+        &self,
+        room: Option<i64>,
+        interval: Duration,
+        duration: Duration,
+    ) -> SlowModeStatus {
+        let status = SlowModeStatus {
+            thread_root: room,
+            interval_seconds: interval.as_secs(),
+            expires_at: Some(Utc::now() + duration),
+        };
+        self.slow_modes.lock().unwrap().insert(
+            room,
+            SlowMode {
+                interval,
+                expires_at: Instant::now() + duration,
+                status: status.clone(),
+            },
+        );
+        status
+    }
+
+    /// Выключает медленный режим. None — он не был включён
+    pub fn clear_slow_mode(&self, room: Option<i64>) -> Option<SlowModeStatus> {
+        let removed = self.slow_modes.lock().unwrap().remove(&room)?;
+        (removed.expires_at > Instant::now()).then_some(SlowModeStatus {
+            thread_root: room,
+            interval_seconds: 0,
+            expires_at: None,
+        })
+    }
+
+    /// Все действующие медленные режимы
+    pub fn active_slow_modes(&self) -> Vec<SlowModeStatus> {
+        let now = Instant::now();
+        let mut slow_modes = self.slow_modes.lock().unwrap();
+        slow_modes.retain(|_, slow_mode| slow_mode.expires_at > now);
+        let mut active: Vec<SlowModeStatus> = slow_modes
+            .values()
+            .map(|slow_mode| slow_mode.status.clone())
+            .collect();
+        active.sort_by_key(|status| status.thread_root);
+        active
+    }
+}
+
+/// Проверяет текст до сохранения: не пустой и не длиннее MAX_MESSAGE_LENGTH
+pub fn check_length(text: &str, has_attachments: bool) -> Result<(), Refusal> {
+    if text.trim().is_empty() && !has_attachments {
+        return Err("Message is empty".to_string().into());
+    }
+    if text.chars().count() > MAX_MESSAGE_LENGTH {
+        return Err(format!("Message is longer than {} characters", MAX_MESSAGE_LENGTH).into());
+    }
+    Ok(())
+}
+
+/// Отпечаток сообщения: регистр и лишние пробелы не важны
+fn fingerprint(room: Option<i64>, text: &str, attachments: &[Uuid]) -> u64 {
+    let normalized = text
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut hasher = DefaultHasher::new();
+    room.hash(&mut hasher);
+    normalized.hash(&mut hasher);
+    attachments.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Сколько ещё длится окно window, начавшееся в started, на момент now;
+/// None — окно закончилось. Не паникует на границе и при now раньше started
+pub fn remaining_in_window(started: Instant, window: Duration, now: Instant) -> Option<Duration> {
+    let remaining = window.saturating_sub(now.saturating_duration_since(started));
+    (!remaining.is_zero()).then_some(remaining)
+}
@@ -0,0 +1,472 @@
+// src/handlers/account.rs
+use crate::account::{build_export, deletion_deadline, export_path, EXPORT_TTL_DAYS};
+use crate::audit::{record, with_client_info, AuditEntry, ClientInfo};
+use crate::db::exports::{create_export, find_export, list_exports};
+use crate::db::usernames::list_username_history;
+use crate::db::users::{
+    change_username, find_delete_after, find_user_by_uuid, set_delete_after, update_display_name,
+};
+use crate::middleware::auth::with_auth;
+use crate::models::{AuditAction, DataExport, ExportStatus, UsernameChange};
+use crate::password::verify_password_async;
+use crate::usernames::{
+    check_display_name, check_username_available, check_username_format, next_username_change,
+};
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use std::error::Error as StdError;
+use tokio_postgres::error::SqlState;
+use uuid::Uuid;
+use warp::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AccountResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ChangeUsernameRequest {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ChangeUsernameResponse {
+    pub message: String,
+    pub username: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DisplayNameRequest {
+    pub display_name: Option<String>,
+}
+
+/// Имя для входа, отображаемое имя и прежние имена
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UsernameInfo {
+    pub username: String,
+    pub display_name: Option<String>,
+    /// Раньше этого времени имя сменить нельзя (None — можно сейчас)
+    pub next_change_at: Option<DateTime<Utc>>,
+    pub history: Vec<UsernameChange>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DeletionRequest {
+    pub password: String,
+}
+
+/// Запланированное удаление (None — не запрошено)
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DeletionStatus {
+    pub delete_after: Option<DateTime<Utc>>,
+}
+
+fn account_response(message: &str, status: StatusCode) -> Response {
+    let response = AccountResponse {
+        message: message.to_string(),
+    };
+    warp::reply::with_status(warp::reply::json(&response), status).into_response()
+}
+
+fn internal_error(message: &str, e: Box<dyn StdError + Send + Sync>) -> Response {
+    error!("{}: {}", message, e);
+    account_response(message, StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Кто-то успел занять имя между проверкой и сменой
+fn is_unique_violation(e: &(dyn StdError + Send + Sync + 'static)) -> bool {
+    e.downcast_ref::<tokio_postgres::Error>()
+        .and_then(|e| e.code())
+        == Some(&SqlState::UNIQUE_VIOLATION)
+}
+
+/// Запускает сборку архива с личными данными
+pub async fn request_export_handler(
+    user_uuid: Uuid,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    debug!("Received data export request from user_uuid: {}", user_uuid);
+
+    let now = Utc::now();
+    let export = DataExport {
+        export_id: Uuid::new_v4(),
+        user_uuid,
+        status: ExportStatus::Pending,
+        size_bytes: None,
+        created_at: now,
+        completed_at: None,
+        expires_at: now + Duration::days(EXPORT_TTL_DAYS),
+    };
+    match create_export(&export).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Ok(account_response(
+                "An export is already being prepared.",
+                StatusCode::CONFLICT,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to start export.", e)),
+    }
+
+    info!(
+        "Data export {} requested by user_uuid: {}",
+        export.export_id, user_uuid
+    );
+    let entry = AuditEntry::own(AuditAction::DataExportRequested, user_uuid)
+        .with_target(export.export_id.to_string());
+    record(entry, &client).await;
+
+    tokio::spawn(build_export(export.export_id, user_uuid));
+    Ok(warp::reply::with_status(warp::reply::json(&export), StatusCode::ACCEPTED).into_response())
+}
+
+pub async fn list_exports_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
+    match list_exports(&user_uuid).await {
+        Ok(exports) => Ok(warp::reply::json(&exports).into_response()),
+        Err(e) => Ok(internal_error("Failed to list exports.", e)),
+    }
+}
+
+/// Отдаёт готовый архив
+pub async fn download_export_handler(
+    user_uuid: Uuid,
+    export_id: Uuid,
+) -> Result<Response, Rejection> {
+    let export = match find_export(&export_id, &user_uuid).await {
+        Ok(Some(export)) if export.expires_at > Utc::now() => export,
+        Ok(_) => return Ok(account_response("Export not found.", StatusCode::NOT_FOUND)),
+        Err(e) => return Ok(internal_error("Failed to find export.", e)),
+    };
+    match export.status {
+        ExportStatus::Ready => {}
+        ExportStatus::Pending => {
+            return Ok(account_response(
+                "The export is still being prepared.",
+                StatusCode::CONFLICT,
+            ))
+        }
+        ExportStatus::Failed => {
+            return Ok(account_response(
+                "The export failed. Please request a new one.",
+                StatusCode::GONE,
+            ))
+        }
+    }
+
+    let body = match tokio::fs::read(export_path(&export_id)).await {
+        Ok(body) => body,
+        Err(e) => return Ok(internal_error("Failed to read export.", e.into())),
+    };
+    let mut response = Response::new(body.into());
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, "application/zip".parse().unwrap());
+    headers.insert(
+        CONTENT_DISPOSITION,
+        format!("attachment; filename=\"cyb3ria-export-{}.zip\"", export_id)
+            .parse()
+            .unwrap(),
+    );
+    Ok(response)
+}
+
+pub async fn deletion_status_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
+    match find_delete_after(&user_uuid).await {
+        Ok(delete_after) => Ok(warp::reply::json(&DeletionStatus { delete_after }).into_response()),
+        Err(e) => Ok(internal_error("Failed to get deletion status.", e)),
+    }
+}
+
+/// Назначает удаление учётной записи после отсрочки
+pub async fn request_deletion_handler(
+    user_uuid: Uuid,
+    request: DeletionRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    debug!(
+        "Received account deletion request from user_uuid: {}",
+        user_uuid
+    );
+
+    let user = match find_user_by_uuid(&user_uuid).await {
+        Ok(user) => user,
+        Err(e) => return Ok(internal_error("Failed to find user.", e)),
+    };
+    match verify_password_async(&request.password, &user.password_hash).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Ok(account_response(
+                "Invalid password.",
+                StatusCode::UNAUTHORIZED,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to verify password.", e)),
+    }
+
+    let delete_after = deletion_deadline();
+    if let Err(e) = set_delete_after(&user_uuid, Some(delete_after)).await {
+        return Ok(internal_error("Failed to schedule deletion.", e));
+    }
+
+    info!(
+        "Account {} scheduled for deletion after {}",
+        user_uuid, delete_after
+    );
+    let entry = AuditEntry::own(AuditAction::AccountDeletionRequested, user_uuid)
+        .with_target(delete_after.to_rfc3339());
+    record(entry, &client).await;
+    let status = DeletionStatus {
+        delete_after: Some(delete_after),
+    };
+    Ok(warp::reply::json(&status).into_response())
+}
+
+pub async fn cancel_deletion_handler(
+    user_uuid: Uuid,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    match find_delete_after(&user_uuid).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return Ok(account_response(
+                "Account deletion is not scheduled.",
+                StatusCode::NOT_FOUND,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to cancel deletion.", e)),
+    }
+    if let Err(e) = set_delete_after(&user_uuid, None).await {
+        return Ok(internal_error("Failed to cancel deletion.", e));
+    }
+
+    info!("Account deletion cancelled by user_uuid: {}", user_uuid);
+    record(
+        AuditEntry::own(AuditAction::AccountDeletionCancelled, user_uuid),
+        &client,
+    )
+    .await;
+    Ok(account_response(
+        "Account deletion cancelled.",
+        StatusCode::OK,
+    ))
+}
+
+pub async fn username_info_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
+    let user = match find_user_by_uuid(&user_uuid).await {
+        Ok(user) => user,
+        Err(e) => return Ok(internal_error("Failed to find user.", e)),
+    };
+    let history = match list_username_history(&user_uuid).await {
+        Ok(history) => history,
+        Err(e) => return Ok(internal_error("Failed to get username history.", e)),
+    };
+    let info = UsernameInfo {
+        next_change_at: next_username_change(&user),
+        username: user.username,
+        display_name: user.display_name,
+        history,
+    };
+    Ok(warp::reply::json(&info).into_response())
+}
+
+/// Смена имени для входа: не чаще раза в USERNAME_CHANGE_INTERVAL_DAYS
+pub async fn change_username_handler(
+    user_uuid: Uuid,
+    request: ChangeUsernameRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    debug!(
+        "Received username change to {} from user_uuid: {}",
+        request.username, user_uuid
+    );
+
+    if let Some(message) = check_username_format(&request.username) {
+        return Ok(account_response(&message, StatusCode::BAD_REQUEST));
+    }
+    let user = match find_user_by_uuid(&user_uuid).await {
+        Ok(user) => user,
+        Err(e) => return Ok(internal_error("Failed to find user.", e)),
+    };
+    if user.username == request.username {
+        return Ok(account_response(
+            "That is already your username.",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    if let Some(next_change_at) = next_username_change(&user) {
+        return Ok(account_response(
+            &format!(
+                "You can change your username again after {}.",
+                next_change_at.format("%Y-%m-%d %H:%M UTC")
+            ),
+            StatusCode::TOO_MANY_REQUESTS,
+        ));
+    }
+    match verify_password_async(&request.password, &user.password_hash).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Ok(account_response(
+                "Invalid password.",
+                StatusCode::UNAUTHORIZED,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to verify password.", e)),
+    }
+    match check_username_available(&request.username, Some(&user_uuid)).await {
+        Ok(None) => {}
+        Ok(Some(message)) => return Ok(account_response(message, StatusCode::CONFLICT)),
+        Err(e) => return Ok(internal_error("Failed to check username.", e)),
+    }
+
+    let old_username = match change_username(&user_uuid, &request.username, &user_uuid).await {
+        Ok(Some(old_username)) => old_username,
+        Ok(None) => return Ok(account_response("User not found.", StatusCode::NOT_FOUND)),
+        Err(e) if is_unique_violation(e.as_ref()) => {
+            return Ok(account_response(
+                "Username is already taken.",
+                StatusCode::CONFLICT,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to change username.", e)),
+    };
+
+    info!(
+        "User {} changed username from {} to {}",
+        user_uuid, old_username, request.username
+    );
+    let entry = AuditEntry::own(AuditAction::UsernameChanged, user_uuid)
+        .with_target(format!("{} -> {}", old_username, request.username));
+    record(entry, &client).await;
+    let response = ChangeUsernameResponse {
+        message: "Username changed.".to_string(),
+        username: request.username,
+    };
+    Ok(warp::reply::json(&response).into_response())
+}
+
+/// Отображаемое имя в чате; пустое значение убирает его
+pub async fn update_display_name_handler(
+    user_uuid: Uuid,
+    request: DisplayNameRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    let display_name = request
+        .display_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty());
+    if let Some(display_name) = display_name {
+        match check_display_name(display_name).await {
+            Ok(None) => {}
+            Ok(Some(message)) => return Ok(account_response(&message, StatusCode::BAD_REQUEST)),
+            Err(e) => return Ok(internal_error("Failed to check display name.", e)),
+        }
+    }
+
+    match update_display_name(&user_uuid, display_name).await {
+        Ok(true) => {
+            let mut entry = AuditEntry::own(AuditAction::DisplayNameChanged, user_uuid);
+            if let Some(display_name) = display_name {
+                entry = entry.with_target(display_name);
+            }
+            record(entry, &client).await;
+            Ok(account_response("Display name updated.", StatusCode::OK))
+        }
+        Ok(false) => Ok(account_response("User not found.", StatusCode::NOT_FOUND)),
+        Err(e) => Ok(internal_error("Failed to update display name.", e)),
+    }
+}
+
+pub fn account_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let request_export = warp::path!("api" / "account" / "export")
+        .and(warp::post())
+        .and(with_auth())
+        .and(with_client_info())
+        .and_then(|user_uuid: Uuid, client: ClientInfo| async move {
+            request_export_handler(user_uuid, client).await
+        });
+
+    let list = warp::path!("api" / "account" / "export")
+        .and(warp::get())
+        .and(with_auth())
+        .and_then(|user_uuid: Uuid| async move { list_exports_handler(user_uuid).await });
+
+    let download = warp::path!("api" / "account" / "export" / Uuid)
+        .and(warp::get())
+        .and(with_auth())
+        .and_then(|export_id: Uuid, user_uuid: Uuid| async move {
+            download_export_handler(user_uuid, export_id).await
+        });
+
+    let deletion_status = warp::path!("api" / "account" / "deletion")
+        .and(warp::get())
+        .and(with_auth())
+        .and_then(|user_uuid: Uuid| async move { deletion_status_handler(user_uuid).await });
+
+    let request_deletion = warp::path!("api" / "account" / "deletion")
+        .and(warp::post())
+        .and(with_auth())
+        .and(warp::body::json())
+        .and(with_client_info())
+        .and_then(
+            |user_uuid: Uuid, request: DeletionRequest, client: ClientInfo| async move {
+                request_deletion_handler(user_uuid, request, client).await
+            },
+        );
+
+    let cancel_deletion = warp::path!("api" / "account" / "deletion")
+        .and(warp::delete())
+        .and(with_auth())
+        .and(with_client_info())
+        .and_then(|user_uuid: Uuid, client: ClientInfo| async move {
+            cancel_deletion_handler(user_uuid, client).await
+        });
+
+    let username_info = warp::path!("api" / "account" / "username")
+        .and(warp::get())
+        .and(with_auth())
+        .and_then(|user_uuid: Uuid| async move { username_info_handler(user_uuid).await });
+
+    let change_username = warp::path!("api" / "account" / "username")
+        .and(warp::put())
+        .and(with_auth())
+        .and(warp::body::json())
+        .and(with_client_info())
+        .and_then(
+            |user_uuid: Uuid, request: ChangeUsernameRequest, client: ClientInfo| async move {
+                change_username_handler(user_uuid, request, client).await
+            },
+        );
+
+    let display_name = warp::path!("api" / "account" / "display-name")
+        .and(warp::put())
+        .and(with_auth())
+        .and(warp::body::json())
+        .and(with_client_info())
+        .and_then(
+            |user_uuid: Uuid, request: DisplayNameRequest, client: ClientInfo| async move {
+                update_display_name_handler(user_uuid, request, client).await
+            },
+        );
+
+    request_export
+        .or(list)
+        .unify()
+        .or(download)
+        .unify()
+        .or(deletion_status)
+        .unify()
+        .or(request_deletion)
+        .unify()
+        .or(cancel_deletion)
+        .unify()
+        .or(username_info)
+        .unify()
+        .or(change_username)
+        .unify()
+        .or(display_name)
+        .unify()
+}
@@ -0,0 +1,616 @@
+// src/handlers/admin.rs
+use crate::attachments::remove_stored_files;
+use crate::audit::{record, with_client_info, AuditEntry, ClientInfo};
+use crate::db::devices::find_devices_by_user_uuid;
+use crate::db::files::get_storage_usage;
+use crate::db::login_attempts::{clear_lockout, list_lockouts};
+use crate::db::messages::count_messages_by_user_uuid;
+use crate::db::sessions::delete_sessions_by_user_uuid;
+use crate::db::usernames::{
+    add_reserved_username, is_username_taken, list_reserved_usernames, list_username_history,
+    remove_reserved_username,
+};
+use crate::db::users::{
+    change_username, delete_user, find_user_by_uuid, find_user_summary, list_users,
+    set_user_suspended, update_password_hash, update_user_role,
+};
+use crate::handlers::auth::password::send_reset_email;
+use crate::middleware::auth::with_permission;
+use crate::models::{AdminUserDetails, AuditAction, Permission, Role};
+use crate::password::hash_password_async;
+use crate::usernames::check_username_format;
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UpdateRoleRequest {
+    pub role: Role,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct SuspendRequest {
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UserSearchQuery {
+    pub search: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LockoutQuery {
+    pub active: Option<bool>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RenameUserRequest {
+    pub username: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ReserveUsernameRequest {
+    pub username: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ReservedUsernameQuery {
+    pub username: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AdminResponse {
+    pub message: String,
+}
+
+/// Тело запроса блокировки; причина необязательна, и пустое тело — то же, что {}
+fn parse_suspend_request(body: &[u8]) -> Result<SuspendRequest, serde_json::Error> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(SuspendRequest::default());
+    }
+    serde_json::from_slice(body)
+}
+
+fn admin_response(message: &str, status: StatusCode) -> Response {
+    let response = AdminResponse {
+        message: message.to_string(),
+    };
+    warp::reply::with_status(warp::reply::json(&response), status).into_response()
+}
+
+fn internal_error(message: &str, e: Box<dyn std::error::Error + Send + Sync>) -> Response {
+    error!("{}: {}", message, e);
+    admin_response(message, StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+pub async fn list_users_handler(query: UserSearchQuery) -> Result<Response, Rejection> {
+    debug!("Received admin user list request: {:?}", query);
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let search = query.search.as_deref().filter(|s| !s.is_empty());
+
+    match list_users(search, limit, offset).await {
+        Ok(users) => Ok(warp::reply::json(&users).into_response()),
+        Err(e) => Ok(internal_error("Failed to list users.", e)),
+    }
+}
+
+pub async fn user_details_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
+    debug!("Received admin details request for user {}", user_uuid);
+
+    let user = match find_user_summary(&user_uuid).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return Ok(admin_response("User not found.", StatusCode::NOT_FOUND)),
+        Err(e) => return Ok(internal_error("Failed to get user.", e)),
+    };
+
+    let (file_count, storage_bytes) = match get_storage_usage(user_uuid).await {
+        Ok(usage) => usage,
+        Err(e) => return Ok(internal_error("Failed to get storage usage.", e)),
+    };
+    let message_count = match count_messages_by_user_uuid(&user_uuid).await {
+        Ok(count) => count,
+        Err(e) => return Ok(internal_error("Failed to count messages.", e)),
+    };
+    let devices = match find_devices_by_user_uuid(&user_uuid).await {
+        Ok(devices) => devices,
+        Err(e) => return Ok(internal_error("Failed to get devices.", e)),
+    };
+
+    let details = AdminUserDetails {
+        user,
+        file_count,
+        storage_bytes,
+        message_count,
+        devices,
+    };
+    Ok(warp::reply::json(&details).into_response())
+}
+
+pub async fn update_role_handler(
+    admin_uuid: Uuid,
+    user_uuid: Uuid,
+    request: UpdateRoleRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    debug!(
+        "Received role update from {} for user {}: {:?}",
+        admin_uuid, user_uuid, request.role
+    );
+
+    if admin_uuid == user_uuid {
+        return Ok(admin_response(
+            "You cannot change your own role.",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    match update_user_role(&user_uuid, request.role).await {
+        Ok(true) => {
+            info!(
+                "User {} set role of {} to {}",
+                admin_uuid, user_uuid, request.role
+            );
+            let entry = AuditEntry::on_user(AuditAction::RoleChanged, admin_uuid, user_uuid)
+                .with_target(request.role.to_string());
+            record(entry, &client).await;
+            Ok(admin_response("Role updated.", StatusCode::OK))
+        }
+        Ok(false) => Ok(admin_response("User not found.", StatusCode::NOT_FOUND)),
+        Err(e) => Ok(internal_error("Failed to update role.", e)),
+    }
+}
+
+pub async fn suspend_handler(
+    admin_uuid: Uuid,
+    user_uuid: Uuid,
+    suspended: bool,
+    request: SuspendRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    if admin_uuid == user_uuid {
+        return Ok(admin_response(
+            "You cannot suspend your own account.",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    match set_user_suspended(&user_uuid, suspended, request.reason.as_deref()).await {
+        Ok(true) => {}
+        Ok(false) => return Ok(admin_response("User not found.", StatusCode::NOT_FOUND)),
+        Err(e) => return Ok(internal_error("Failed to update user.", e)),
+    }
+
+    if suspended {
+        // Блокировка немедленно завершает все сессии пользователя
+        if let Err(e) = delete_sessions_by_user_uuid(&user_uuid).await {
+            return Ok(internal_error("Failed to revoke sessions.", e));
+        }
+        info!(
+            "User {} suspended {}: {:?}",
+            admin_uuid, user_uuid, request.reason
+        );
+        let mut entry = AuditEntry::on_user(AuditAction::UserSuspended, admin_uuid, user_uuid);
+        entry.target = request.reason;
+        record(entry, &client).await;
+        let entry = AuditEntry::on_user(AuditAction::SessionsRevoked, admin_uuid, user_uuid);
+        record(entry, &client).await;
+        Ok(admin_response("User suspended.", StatusCode::OK))
+    } else {
+        info!("User {} unsuspended {}", admin_uuid, user_uuid);
+        let entry = AuditEntry::on_user(AuditAction::UserUnsuspended, admin_uuid, user_uuid);
+        record(entry, &client).await;
+        Ok(admin_response("User unsuspended.", StatusCode::OK))
+    }
+}
+
+pub async fn reset_password_handler(
+    admin_uuid: Uuid,
+    user_uuid: Uuid,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    // Администратор не видит ни старого, ни нового пароля: пароль заменяется
+    // случайным, а пользователь получает ссылку для сброса на свою почту
+    match find_user_summary(&user_uuid).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Ok(admin_response("User not found.", StatusCode::NOT_FOUND)),
+        Err(e) => return Ok(internal_error("Failed to find user.", e)),
+    }
+    let user = match find_user_by_uuid(&user_uuid).await {
+        Ok(user) => user,
+        Err(e) => return Ok(internal_error("Failed to find user.", e)),
+    };
+    let Some(email) = user.email.clone() else {
+        return Ok(admin_response(
+            "User has no email address to send a reset link to.",
+            StatusCode::CONFLICT,
+        ));
+    };
+
+    let password_hash = match hash_password_async(&Uuid::new_v4().to_string()).await {
+        Ok(hash) => hash,
+        Err(e) => return Ok(internal_error("Failed to hash password.", e)),
+    };
+    match update_password_hash(&user_uuid, &password_hash).await {
+        Ok(true) => {}
+        Ok(false) => return Ok(admin_response("User not found.", StatusCode::NOT_FOUND)),
+        Err(e) => return Ok(internal_error("Failed to reset password.", e)),
+    }
+
+    if let Err(e) = delete_sessions_by_user_uuid(&user_uuid).await {
+        return Ok(internal_error("Failed to revoke sessions.", e));
+    }
+
+    info!("User {} reset password of {}", admin_uuid, user_uuid);
+    let entry = AuditEntry::on_user(AuditAction::PasswordReset, admin_uuid, user_uuid);
+    record(entry, &client).await;
+    let entry = AuditEntry::on_user(AuditAction::SessionsRevoked, admin_uuid, user_uuid);
+    record(entry, &client).await;
+
+    if let Err(e) = send_reset_email(&user, &email, None).await {
+        return Ok(internal_error("Failed to send password reset email.", e));
+    }
+    Ok(admin_response(
+        "Password reset. A reset link was sent to the user's email.",
+        StatusCode::OK,
+    ))
+}
+
+pub async fn delete_user_handler(
+    admin_uuid: Uuid,
+    user_uuid: Uuid,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    if admin_uuid == user_uuid {
+        return Ok(admin_response(
+            "You cannot delete your own account.",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let file_ids = match delete_user(&user_uuid).await {
+        Ok(Some(file_ids)) => file_ids,
+        Ok(None) => return Ok(admin_response("User not found.", StatusCode::NOT_FOUND)),
+        Err(e) => return Ok(internal_error("Failed to delete user.", e)),
+    };
+
+    remove_stored_files(&file_ids).await;
+
+    info!("User {} deleted account {}", admin_uuid, user_uuid);
+    let entry = AuditEntry::on_user(AuditAction::UserDeleted, admin_uuid, user_uuid);
+    record(entry, &client).await;
+    Ok(admin_response("User deleted.", StatusCode::OK))
+}
+
+pub async fn username_history_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
+    match list_username_history(&user_uuid).await {
+        Ok(history) => Ok(warp::reply::json(&history).into_response()),
+        Err(e) => Ok(internal_error("Failed to get username history.", e)),
+    }
+}
+
+/// Принудительная смена имени (например, оскорбительного): без ограничения частоты
+/// и с возможностью выдать зарезервированное имя
+pub async fn rename_user_handler(
+    admin_uuid: Uuid,
+    user_uuid: Uuid,
+    request: RenameUserRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    if let Some(message) = check_username_format(&request.username) {
+        return Ok(admin_response(&message, StatusCode::BAD_REQUEST));
+    }
+    match is_username_taken(&request.username, Some(&user_uuid)).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return Ok(admin_response(
+                "Username is already taken.",
+                StatusCode::CONFLICT,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to check username.", e)),
+    }
+
+    let old_username = match change_username(&user_uuid, &request.username, &admin_uuid).await {
+        Ok(Some(old_username)) => old_username,
+        Ok(None) => return Ok(admin_response("User not found.", StatusCode::NOT_FOUND)),
+        Err(e) => return Ok(internal_error("Failed to change username.", e)),
+    };
+
+    info!(
+        "User {} renamed {} from {} to {}",
+        admin_uuid, user_uuid, old_username, request.username
+    );
+    let entry = AuditEntry::on_user(AuditAction::UsernameChanged, admin_uuid, user_uuid)
+        .with_target(format!("{} -> {}", old_username, request.username));
+    record(entry, &client).await;
+    Ok(admin_response("Username changed.", StatusCode::OK))
+}
+
+pub async fn list_reserved_usernames_handler() -> Result<Response, Rejection> {
+    match list_reserved_usernames().await {
+        Ok(names) => Ok(warp::reply::json(&names).into_response()),
+        Err(e) => Ok(internal_error("Failed to list reserved usernames.", e)),
+    }
+}
+
+pub async fn reserve_username_handler(
+    admin_uuid: Uuid,
+    request: ReserveUsernameRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    let username = request.username.trim();
+    if username.is_empty() {
+        return Ok(admin_response(
+            "Username must not be empty.",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    let reason = request
+        .reason
+        .as_deref()
+        .map(str::trim)
+        .filter(|reason| !reason.is_empty());
+
+    match add_reserved_username(username, reason, &admin_uuid).await {
+        Ok(true) => {
+            info!("User {} reserved username {}", admin_uuid, username);
+            let entry = AuditEntry::own(AuditAction::ReservedUsernameAdded, admin_uuid)
+                .with_target(username.to_lowercase());
+            record(entry, &client).await;
+            Ok(admin_response("Username reserved.", StatusCode::CREATED))
+        }
+        Ok(false) => Ok(admin_response(
+            "Username is already reserved.",
+            StatusCode::CONFLICT,
+        )),
+        Err(e) => Ok(internal_error("Failed to reserve username.", e)),
+    }
+}
+
+pub async fn unreserve_username_handler(
+    admin_uuid: Uuid,
+    query: ReservedUsernameQuery,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    match remove_reserved_username(&query.username).await {
+        Ok(true) => {
+            info!(
+                "User {} released reserved username {}",
+                admin_uuid, query.username
+            );
+            let entry = AuditEntry::own(AuditAction::ReservedUsernameRemoved, admin_uuid)
+                .with_target(query.username.to_lowercase());
+            record(entry, &client).await;
+            Ok(admin_response(
+                "Username is no longer reserved.",
+                StatusCode::OK,
+            ))
+        }
+        Ok(false) => Ok(admin_response(
+            "Reserved username not found.",
+            StatusCode::NOT_FOUND,
+        )),
+        Err(e) => Ok(internal_error("Failed to remove reserved username.", e)),
+    }
+}
+
+pub async fn list_lockouts_handler(query: LockoutQuery) -> Result<Response, Rejection> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    match list_lockouts(query.active.unwrap_or(false), limit).await {
+        Ok(lockouts) => Ok(warp::reply::json(&lockouts).into_response()),
+        Err(e) => Ok(internal_error("Failed to list lockouts.", e)),
+    }
+}
+
+pub async fn clear_lockout_handler(
+    admin_uuid: Uuid,
+    lockout_id: i64,
+) -> Result<Response, Rejection> {
+    match clear_lockout(lockout_id).await {
+        Ok(true) => {
+            info!("User {} cleared lockout {}", admin_uuid, lockout_id);
+            Ok(admin_response("Lockout cleared.", StatusCode::OK))
+        }
+        Ok(false) => Ok(admin_response(
+            "Active lockout not found.",
+            StatusCode::NOT_FOUND,
+        )),
+        Err(e) => Ok(internal_error("Failed to clear lockout.", e)),
+    }
+}
+
+pub fn admin_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let list_users = warp::path!("api" / "admin" / "users")
+        .and(warp::get())
+        .and(with_permission(Permission::ManageUsers))
+        .and(warp::query::<UserSearchQuery>())
+        .and_then(|_admin_uuid: Uuid, query: UserSearchQuery| async move {
+            list_users_handler(query).await
+        });
+
+    let user_details = warp::path!("api" / "admin" / "users" / Uuid)
+        .and(warp::get())
+        .and(with_permission(Permission::ManageUsers))
+        .and_then(|user_uuid: Uuid, _admin_uuid: Uuid| async move {
+            user_details_handler(user_uuid).await
+        });
+
+    let update_role =
+        warp::path!("api" / "admin" / "users" / Uuid / "role")
+            .and(warp::put())
+            .and(with_permission(Permission::ManageRoles))
+            .and(warp::body::json())
+            .and(with_client_info())
+            .and_then(
+                |user_uuid: Uuid,
+                 admin_uuid: Uuid,
+                 request: UpdateRoleRequest,
+                 client: ClientInfo| async move {
+                    update_role_handler(admin_uuid, user_uuid, request, client).await
+                },
+            );
+
+    let suspend = warp::path!("api" / "admin" / "users" / Uuid / "suspend")
+        .and(warp::post())
+        .and(with_permission(Permission::ManageUsers))
+        .and(warp::body::bytes())
+        .and(with_client_info())
+        .and_then(
+            |user_uuid: Uuid, admin_uuid: Uuid, body: bytes::Bytes, client: ClientInfo| async move {
+                match parse_suspend_request(&body) {
+                    Ok(request) => suspend_handler(admin_uuid, user_uuid, true, request, client).await,
+                    Err(e) => {
+                        debug!("Invalid suspend request body: {}", e);
+                        Ok(admin_response("Invalid request body.", StatusCode::BAD_REQUEST))
+                    }
+                }
+            },
+        );
+
+    let unsuspend = warp::path!("api" / "admin" / "users" / Uuid / "unsuspend")
+        .and(warp::post())
+        .and(with_permission(Permission::ManageUsers))
+        .and(with_client_info())
+        .and_then(
+            |user_uuid: Uuid, admin_uuid: Uuid, client: ClientInfo| async move {
+                suspend_handler(
+                    admin_uuid,
+                    user_uuid,
+                    false,
+                    SuspendRequest::default(),
+                    client,
+                )
+                .await
+            },
+        );
+
+    let reset_password = warp::path!("api" / "admin" / "users" / Uuid / "reset-password")
+        .and(warp::post())
+        .and(with_permission(Permission::ManageUsers))
+        .and(with_client_info())
+        .and_then(
+            |user_uuid: Uuid, admin_uuid: Uuid, client: ClientInfo| async move {
+                reset_password_handler(admin_uuid, user_uuid, client).await
+            },
+        );
+
+    let delete = warp::path!("api" / "admin" / "users" / Uuid)
+        .and(warp::delete())
+        .and(with_permission(Permission::ManageUsers))
+        .and(with_client_info())
+        .and_then(
+            |user_uuid: Uuid, admin_uuid: Uuid, client: ClientInfo| async move {
+                delete_user_handler(admin_uuid, user_uuid, client).await
+            },
+        );
+
+    let username_history = warp::path!("api" / "admin" / "users" / Uuid / "username-history")
+        .and(warp::get())
+        .and(with_permission(Permission::ManageUsers))
+        .and_then(|user_uuid: Uuid, _admin_uuid: Uuid| async move {
+            username_history_handler(user_uuid).await
+        });
+
+    let rename =
+        warp::path!("api" / "admin" / "users" / Uuid / "username")
+            .and(warp::put())
+            .and(with_permission(Permission::ManageUsers))
+            .and(warp::body::json())
+            .and(with_client_info())
+            .and_then(
+                |user_uuid: Uuid,
+                 admin_uuid: Uuid,
+                 request: RenameUserRequest,
+                 client: ClientInfo| async move {
+                    rename_user_handler(admin_uuid, user_uuid, request, client).await
+                },
+            );
+
+    let reserved = warp::path!("api" / "admin" / "reserved-usernames")
+        .and(warp::get())
+        .and(with_permission(Permission::ManageUsers))
+        .and_then(|_admin_uuid: Uuid| async move { list_reserved_usernames_handler().await });
+
+    let reserve = warp::path!("api" / "admin" / "reserved-usernames")
+        .and(warp::post())
+        .and(with_permission(Permission::ManageUsers))
+        .and(warp::body::json())
+        .and(with_client_info())
+        .and_then(
+            |admin_uuid: Uuid, request: ReserveUsernameRequest, client: ClientInfo| async move {
+                reserve_username_handler(admin_uuid, request, client).await
+            },
+        );
+
+    let unreserve = warp::path!("api" / "admin" / "reserved-usernames")
+        .and(warp::delete())
+        .and(with_permission(Permission::ManageUsers))
+        .and(warp::query::<ReservedUsernameQuery>())
+        .and(with_client_info())
+        .and_then(
+            |admin_uuid: Uuid, query: ReservedUsernameQuery, client: ClientInfo| async move {
+                unreserve_username_handler(admin_uuid, query, client).await
+            },
+        );
+
+    let lockouts = warp::path!("api" / "admin" / "lockouts")
+        .and(warp::get())
+        .and(with_permission(Permission::ManageUsers))
+        .and(warp::query::<LockoutQuery>())
+        .and_then(|_admin_uuid: Uuid, query: LockoutQuery| async move {
+            list_lockouts_handler(query).await
+        });
+
+    let clear_lockout = warp::path!("api" / "admin" / "lockouts" / i64)
+        .and(warp::delete())
+        .and(with_permission(Permission::ManageUsers))
+        .and_then(|lockout_id: i64, admin_uuid: Uuid| async move {
+            clear_lockout_handler(admin_uuid, lockout_id).await
+        });
+
+    list_users
+        .or(user_details)
+        .unify()
+        .or(update_role)
+        .unify()
+        .or(suspend)
+        .unify()
+        .or(unsuspend)
+        .unify()
+        .or(reset_password)
+        .unify()
+        .or(delete)
+        .unify()
+        .or(lockouts)
+        .unify()
+        .or(clear_lockout)
+        .unify()
+        .or(username_history)
+        .unify()
+        .or(rename)
+        .unify()
+        .or(reserved)
+        .unify()
+        .or(reserve)
+        .unify()
+        .or(unreserve)
+        .unify()
+}
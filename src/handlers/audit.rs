@@ -0,0 +1,83 @@
+// src/handlers/audit.rs
+use crate::db::audit::list_audit_events;
+use crate::middleware::auth::{with_auth, with_permission};
+use crate::models::{AuditAction, Permission};
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AuditResponse {
+    pub message: String,
+}
+
+/// Фильтр журнала. Страницы идут от новых событий к старым:
+/// следующая начинается с `before` = event_id последней записи
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct AuditQuery {
+    pub user_uuid: Option<Uuid>,
+    pub action: Option<AuditAction>,
+    pub before: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+async fn list_events(user_uuid: Option<Uuid>, query: AuditQuery) -> Result<Response, Rejection> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    match list_audit_events(user_uuid, query.action, query.before, limit).await {
+        Ok(events) => Ok(warp::reply::json(&events).into_response()),
+        Err(e) => {
+            error!("Failed to list audit events: {}", e);
+            let response = AuditResponse {
+                message: "Failed to list audit events.".to_string(),
+            };
+            Ok(warp::reply::with_status(
+                warp::reply::json(&response),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            )
+            .into_response())
+        }
+    }
+}
+
+/// События своей учётной записи: и свои действия, и действия над ней
+pub async fn own_audit_handler(user_uuid: Uuid, query: AuditQuery) -> Result<Response, Rejection> {
+    debug!("Received audit log request for user_uuid: {}", user_uuid);
+    list_events(Some(user_uuid), query).await
+}
+
+/// Журнал всех пользователей; `user_uuid` в запросе сужает выборку
+pub async fn admin_audit_handler(
+    admin_uuid: Uuid,
+    query: AuditQuery,
+) -> Result<Response, Rejection> {
+    debug!(
+        "Received admin audit log request from {}: {:?}",
+        admin_uuid, query
+    );
+    list_events(query.user_uuid, query).await
+}
+
+pub fn audit_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let own = warp::path!("api" / "audit")
+        .and(warp::get())
+        .and(with_auth())
+        .and(warp::query::<AuditQuery>())
+        .and_then(own_audit_handler);
+
+    let admin = warp::path!("api" / "admin" / "audit")
+        .and(warp::get())
+        .and(with_permission(Permission::ViewAuditLog))
+        .and(warp::query::<AuditQuery>())
+        .and_then(admin_audit_handler);
+
+    own.or(admin).unify()
+}
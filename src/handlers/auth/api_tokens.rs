@@ -0,0 +1,198 @@
+// src/handlers/auth/api_tokens.rs
+use crate::audit::{record, with_client_info, AuditEntry, ClientInfo};
+use crate::db::api_tokens::{delete_api_token, list_api_tokens, save_api_token};
+use crate::middleware::auth::{hash_api_token, with_auth};
+use crate::models::{ApiToken, AuditAction, Scope};
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, error, info};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::error::Error as StdError;
+use uuid::Uuid;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+/// Префикс, по которому токен легко узнать в конфигурации и логах
+const TOKEN_PREFIX: &str = "cyb3ria_";
+const DEFAULT_EXPIRES_IN_DAYS: i64 = 90;
+const MAX_EXPIRES_IN_DAYS: i64 = 365;
+const MAX_TOKENS_PER_USER: usize = 50;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ApiTokenResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CreateApiTokenRequest {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub expires_in_days: Option<i64>,
+}
+
+/// Ответ на создание: секрет показывается только здесь
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CreatedApiTokenResponse {
+    pub token_id: Uuid,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub expires_at: DateTime<Utc>,
+    pub token: String,
+}
+
+fn api_token_response(message: &str, status: StatusCode) -> Response {
+    let response = ApiTokenResponse {
+        message: message.to_string(),
+    };
+    warp::reply::with_status(warp::reply::json(&response), status).into_response()
+}
+
+fn internal_error(message: &str, e: Box<dyn StdError + Send + Sync>) -> Response {
+    error!("{}: {}", message, e);
+    api_token_response(message, StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{}{}", TOKEN_PREFIX, data_encoding::HEXLOWER.encode(&bytes))
+}
+
+pub async fn create_api_token_handler(
+    user_uuid: Uuid,
+    request: CreateApiTokenRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    debug!("Received API token request for user_uuid: {}", user_uuid);
+
+    let name = request.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > 64 {
+        return Ok(api_token_response(
+            "Token name must be between 1 and 64 characters",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    if request.scopes.is_empty() {
+        return Ok(api_token_response(
+            "At least one scope is required",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    let expires_in_days = request.expires_in_days.unwrap_or(DEFAULT_EXPIRES_IN_DAYS);
+    if !(1..=MAX_EXPIRES_IN_DAYS).contains(&expires_in_days) {
+        return Ok(api_token_response(
+            &format!(
+                "Token expiry must be between 1 and {} days",
+                MAX_EXPIRES_IN_DAYS
+            ),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    match list_api_tokens(&user_uuid).await {
+        Ok(tokens) if tokens.len() >= MAX_TOKENS_PER_USER => {
+            return Ok(api_token_response(
+                "Too many API tokens. Revoke unused ones first.",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+        Ok(_) => {}
+        Err(e) => return Ok(internal_error("Failed to create API token.", e)),
+    }
+
+    let mut scopes = Vec::new();
+    for scope in request.scopes {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    let now = Utc::now();
+    let api_token = ApiToken {
+        token_id: Uuid::new_v4(),
+        user_uuid,
+        name,
+        scopes,
+        expires_at: now + Duration::days(expires_in_days),
+        last_used_at: None,
+        created_at: now,
+    };
+    let token = generate_token();
+    if let Err(e) = save_api_token(&api_token, &hash_api_token(&token)).await {
+        return Ok(internal_error("Failed to create API token.", e));
+    }
+
+    info!(
+        "API token {} created for user_uuid: {}",
+        api_token.token_id, user_uuid
+    );
+    let entry = AuditEntry::own(AuditAction::ApiTokenCreated, user_uuid)
+        .with_target(api_token.token_id.to_string());
+    record(entry, &client).await;
+    let response = CreatedApiTokenResponse {
+        token_id: api_token.token_id,
+        name: api_token.name,
+        scopes: api_token.scopes,
+        expires_at: api_token.expires_at,
+        token,
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::CREATED).into_response())
+}
+
+pub async fn list_api_tokens_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
+    match list_api_tokens(&user_uuid).await {
+        Ok(tokens) => Ok(warp::reply::json(&tokens).into_response()),
+        Err(e) => Ok(internal_error("Failed to list API tokens.", e)),
+    }
+}
+
+pub async fn revoke_api_token_handler(
+    user_uuid: Uuid,
+    token_id: Uuid,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    match delete_api_token(&token_id, &user_uuid).await {
+        Ok(true) => {
+            info!("API token {} revoked by user_uuid: {}", token_id, user_uuid);
+            let entry = AuditEntry::own(AuditAction::ApiTokenRevoked, user_uuid)
+                .with_target(token_id.to_string());
+            record(entry, &client).await;
+            Ok(api_token_response("API token revoked.", StatusCode::OK))
+        }
+        Ok(false) => Ok(api_token_response(
+            "API token not found.",
+            StatusCode::NOT_FOUND,
+        )),
+        Err(e) => Ok(internal_error("Failed to revoke API token.", e)),
+    }
+}
+
+/// Управление токенами — только по сессии, сам токен здесь не принимается
+pub fn api_tokens_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let create = warp::path!("api" / "tokens")
+        .and(warp::post())
+        .and(with_auth())
+        .and(warp::body::json())
+        .and(with_client_info())
+        .and_then(
+            |user_uuid: Uuid, request: CreateApiTokenRequest, client: ClientInfo| async move {
+                create_api_token_handler(user_uuid, request, client).await
+            },
+        );
+
+    let list = warp::path!("api" / "tokens")
+        .and(warp::get())
+        .and(with_auth())
+        .and_then(|user_uuid: Uuid| async move { list_api_tokens_handler(user_uuid).await });
+
+    let revoke = warp::path!("api" / "tokens" / Uuid)
+        .and(warp::delete())
+        .and(with_auth())
+        .and(with_client_info())
+        .and_then(
+            |token_id: Uuid, user_uuid: Uuid, client: ClientInfo| async move {
+                revoke_api_token_handler(user_uuid, token_id, client).await
+            },
+        );
+
+    create.or(list).unify().or(revoke).unify()
+}
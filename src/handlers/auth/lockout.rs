@@ -0,0 +1,138 @@
+// src/handlers/auth/lockout.rs
+//
+// Защита входа от перебора: учёт неудачных попыток по учётной записи и IP,
+// экспоненциально растущая блокировка.
+use crate::db::login_attempts::{
+    count_account_failures, count_ip_failures, find_active_lockout, record_login_attempt,
+    save_lockout,
+};
+use chrono::{DateTime, Duration, Utc};
+use log::info;
+use std::error::Error as StdError;
+use std::net::IpAddr;
+
+#[derive(Debug, Clone)]
+pub struct LockoutPolicy {
+    pub max_account_failures: i64, // После стольких неудач учётная запись блокируется
+    pub max_ip_failures: i64,      // То же для IP-адреса
+    pub window: Duration,          // Окно, в котором считаются неудачи
+    pub base_lockout: Duration,    // Первая блокировка, дальше удваивается
+    pub max_lockout: Duration,
+}
+
+impl Default for LockoutPolicy {
+    fn default() -> Self {
+        LockoutPolicy {
+            max_account_failures: 5,
+            max_ip_failures: 20,
+            window: Duration::hours(1),
+            base_lockout: Duration::minutes(1),
+            max_lockout: Duration::days(1),
+        }
+    }
+}
+
+fn env_i64(name: &str) -> Option<i64> {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+}
+
+impl LockoutPolicy {
+    /// Настройки из переменных окружения LOGIN_MAX_FAILURES, LOGIN_IP_MAX_FAILURES,
+    /// LOGIN_FAILURE_WINDOW_MINUTES, LOGIN_LOCKOUT_BASE_SECONDS, LOGIN_LOCKOUT_MAX_SECONDS
+    pub fn from_env() -> Self {
+        let default = LockoutPolicy::default();
+        LockoutPolicy {
+            max_account_failures: env_i64("LOGIN_MAX_FAILURES")
+                .unwrap_or(default.max_account_failures),
+            max_ip_failures: env_i64("LOGIN_IP_MAX_FAILURES").unwrap_or(default.max_ip_failures),
+            window: env_i64("LOGIN_FAILURE_WINDOW_MINUTES")
+                .map(Duration::minutes)
+                .unwrap_or(default.window),
+            base_lockout: env_i64("LOGIN_LOCKOUT_BASE_SECONDS")
+                .map(Duration::seconds)
+                .unwrap_or(default.base_lockout),
+            max_lockout: env_i64("LOGIN_LOCKOUT_MAX_SECONDS")
+                .map(Duration::seconds)
+                .unwrap_or(default.max_lockout),
+        }
+    }
+
+    /// Длительность блокировки после `failures` неудач при пороге `threshold`:
+    /// base, 2*base, 4*base, ... но не больше max_lockout
+    pub fn lockout_duration(&self, failures: i64, threshold: i64) -> Option<Duration> {
+        if threshold <= 0 || failures < threshold {
+            return None;
+        }
+        let exponent = (failures - threshold).min(30) as u32;
+        let seconds = self
+            .base_lockout
+            .num_seconds()
+            .saturating_mul(1i64 << exponent);
+        Some(Duration::seconds(seconds).min(self.max_lockout))
+    }
+}
+
+/// Время окончания действующей блокировки, если она есть
+pub async fn active_lockout(
+    username: &str,
+    ip_address: IpAddr,
+) -> Result<Option<DateTime<Utc>>, Box<dyn StdError + Send + Sync>> {
+    find_active_lockout(username, ip_address).await
+}
+
+/// Записывает неудачную попытку и при превышении порога блокирует вход
+pub async fn register_failure(
+    policy: &LockoutPolicy,
+    username: &str,
+    ip_address: IpAddr,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    record_login_attempt(username, ip_address, false).await?;
+
+    let since = Utc::now() - policy.window;
+
+    let account_failures = count_account_failures(username, since).await?;
+    if let Some(duration) = policy.lockout_duration(account_failures, policy.max_account_failures) {
+        info!(
+            "Locking account {} for {}s after {} failed attempts",
+            username,
+            duration.num_seconds(),
+            account_failures
+        );
+        save_lockout(
+            "account",
+            username,
+            account_failures as i32,
+            Utc::now() + duration,
+        )
+        .await?;
+    }
+
+    let ip_failures = count_ip_failures(ip_address, since).await?;
+    if let Some(duration) = policy.lockout_duration(ip_failures, policy.max_ip_failures) {
+        info!(
+            "Locking IP {} for {}s after {} failed attempts",
+            ip_address,
+            duration.num_seconds(),
+            ip_failures
+        );
+        save_lockout(
+            "ip",
+            &ip_address.to_string(),
+            ip_failures as i32,
+            Utc::now() + duration,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Записывает успешный вход (сбрасывает счётчик неудач учётной записи)
+pub async fn register_success(
+    username: &str,
+    ip_address: IpAddr,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    record_login_attempt(username, ip_address, true).await
+}
@@ -1,21 +1,44 @@
-use crate::db::devices::find_device_by_ip_mac;
-use crate::db::devices::save_device_to_db;
+use crate::audit::{record, with_client_info, AuditEntry, ClientInfo};
 use crate::db::sessions::save_session_to_db;
-use crate::db::users::find_user_by_username;
+use crate::devices::{device_cookie, resolve_device};
+use crate::handlers::auth::login_alerts::alert_new_login;
+use crate::db::users::{find_user_by_username, update_last_login, update_password_hash};
+use crate::handlers::auth::lockout::{
+    active_lockout, register_failure, register_success, LockoutPolicy,
+};
+use crate::db::two_factor::{create_login_challenge, find_totp};
 use crate::handlers::auth::{
     map_validation_errors, LoginData, LoginResponse, LoginSuccessResponse,
+    TwoFactorRequiredResponse,
 };
-use crate::models::{Device, Session};
-use bcrypt::verify;
+use crate::models::{AuditAction, LoginChallenge, Session, User};
+use crate::password::{hash_password_async, needs_rehash, verify_password_async};
 use chrono::{Duration, Utc};
 use log::{debug, error, info};
 use std::net::SocketAddr;
+use tokio::sync::OnceCell;
 use uuid::Uuid;
 use validator::Validate;
 use warp::Reply;
 use warp::{http::StatusCode, reply::Response, Filter, Rejection};
 
-pub async fn login_handler(login: LoginData, peer_addr: SocketAddr) -> Result<Response, Rejection> {
+/// Хеш несуществующего пароля для проверки при неизвестном имени пользователя
+async fn dummy_password_hash() -> &'static str {
+    static DUMMY_HASH: OnceCell<String> = OnceCell::const_new();
+    DUMMY_HASH
+        .get_or_init(|| async {
+            hash_password_async(&Uuid::new_v4().to_string())
+                .await
+                .expect("Failed to hash dummy password")
+        })
+        .await
+}
+
+pub async fn login_handler(
+    login: LoginData,
+    peer_addr: SocketAddr,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
     debug!("Received login request: {:?}", login);
 
     // Валидация данных
@@ -32,12 +55,83 @@ pub async fn login_handler(login: LoginData, peer_addr: SocketAddr) -> Result<Re
         );
     }
 
-    let user = match find_user_by_username(&login.username).await {
-        Ok(user) => user,
+    let ip_address = peer_addr.ip();
+
+    match active_lockout(&login.username, ip_address).await {
+        Ok(Some(locked_until)) => {
+            error!("Login locked out for {} from {}", login.username, ip_address);
+            let response = LoginResponse {
+                message: "Too many failed login attempts. Try again later.".to_string(),
+                username: "".to_string(),
+            };
+            let retry_after = (locked_until - Utc::now()).num_seconds().max(1);
+            let mut resp = warp::reply::with_status(
+                warp::reply::json(&response),
+                StatusCode::TOO_MANY_REQUESTS,
+            )
+            .into_response();
+            resp.headers_mut()
+                .insert("Retry-After", retry_after.to_string().parse().unwrap());
+            return Ok(resp);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            error!("Failed to check login lockout: {}", e);
+            let response = LoginResponse {
+                message: "Failed to verify password.".to_string(),
+                username: "".to_string(),
+            };
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&response),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            )
+            .into_response());
+        }
+    }
+
+    // Для неизвестного пользователя проверяем пароль против фиктивного хеша,
+    // чтобы время ответа не выдавало существование учётной записи
+    let user = find_user_by_username(&login.username).await.map_err(|e| {
+        debug!("Failed to find user: {}", e);
+    });
+    let password_hash = match &user {
+        Ok(user) => user.password_hash.clone(),
+        Err(_) => dummy_password_hash().await.to_string(),
+    };
+
+    let valid = match verify_password_async(&login.password, &password_hash).await {
+        Ok(valid) => valid,
         Err(e) => {
-            error!("Failed to find user: {}", e);
+            error!("Failed to verify password: {}", e);
             let response = LoginResponse {
-                message: "Failed to find user.".to_string(),
+                message: "Failed to verify password.".to_string(),
+                username: "".to_string(),
+            };
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&response),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            )
+            .into_response());
+        }
+    };
+
+    let user = match user {
+        Ok(user) if valid => user,
+        user => {
+            error!("Invalid username or password for: {}", login.username);
+            let entry = AuditEntry {
+                action: AuditAction::LoginFailed,
+                actor_uuid: None,
+                target_uuid: user.ok().map(|user| user.user_uuid),
+                target: Some(login.username.clone()),
+                device_id: None,
+            };
+            record(entry, &client).await;
+            if let Err(e) = register_failure(&LockoutPolicy::from_env(), &login.username, ip_address).await {
+                error!("Failed to record failed login: {}", e);
+            }
+            let response = LoginResponse {
+                message: "Invalid username or password.".to_string(),
                 username: "".to_string(),
             };
             return Ok(warp::reply::with_status(
@@ -48,23 +142,68 @@ pub async fn login_handler(login: LoginData, peer_addr: SocketAddr) -> Result<Re
         }
     };
 
-    match verify(&login.password, &user.password_hash) {
-        Ok(valid) => {
-            if !valid {
-                error!("Invalid password.");
+    // Старый bcrypt-хеш или устаревшие параметры: пароль известен, пересчитываем
+    if needs_rehash(&user.password_hash) {
+        match hash_password_async(&login.password).await {
+            Ok(password_hash) => match update_password_hash(&user.user_uuid, &password_hash).await {
+                Ok(_) => info!("Rehashed password for user_uuid: {}", user.user_uuid),
+                Err(e) => error!("Failed to save rehashed password: {}", e),
+            },
+            Err(e) => error!("Failed to rehash password: {}", e),
+        }
+    }
+
+    if user.suspended_at.is_some() {
+        error!("Login attempt for suspended user: {}", user.username);
+        let response = LoginResponse {
+            message: "Account suspended.".to_string(),
+            username: "".to_string(),
+        };
+        return Ok(
+            warp::reply::with_status(warp::reply::json(&response), StatusCode::FORBIDDEN)
+                .into_response(),
+        );
+    }
+
+    // При включённой 2FA сессия выдаётся только после второго шага (/api/login/2fa).
+    // Счётчик неудач сбрасывается тоже только там: иначе верный пароль позволял бы
+    // перебирать коды, каждый раз получая новую попытку входа. Блокировка проверена выше,
+    // так что заблокированная учётная запись новых попыток не получает
+    match find_totp(&user.user_uuid).await {
+        Ok(Some(totp)) if totp.enabled_at.is_some() => {
+            let challenge = LoginChallenge {
+                challenge_id: Uuid::new_v4(),
+                user_uuid: user.user_uuid,
+                expires_at: Utc::now() + Duration::minutes(5),
+                attempts: 0,
+            };
+            if let Err(e) = create_login_challenge(&challenge).await {
+                error!("Failed to create login challenge: {}", e);
                 let response = LoginResponse {
-                    message: "Invalid password.".to_string(),
+                    message: "Failed to start two-factor authentication.".to_string(),
                     username: "".to_string(),
                 };
                 return Ok(warp::reply::with_status(
                     warp::reply::json(&response),
-                    StatusCode::UNAUTHORIZED,
+                    StatusCode::INTERNAL_SERVER_ERROR,
                 )
                 .into_response());
             }
+            info!("Password accepted, awaiting second factor: {}", user.username);
+            let response = TwoFactorRequiredResponse {
+                message: "Two-factor authentication required.".to_string(),
+                username: user.username,
+                two_factor_required: true,
+                challenge_id: challenge.challenge_id,
+            };
+            return Ok(
+                warp::reply::with_status(warp::reply::json(&response), StatusCode::OK)
+                    .into_response(),
+            );
         }
+        Ok(_) => {}
         Err(e) => {
-            error!("Failed to verify password: {}", e);
+            error!("Failed to check two-factor status: {}", e);
             let response = LoginResponse {
                 message: "Failed to verify password.".to_string(),
                 username: "".to_string(),
@@ -77,19 +216,17 @@ pub async fn login_handler(login: LoginData, peer_addr: SocketAddr) -> Result<Re
         }
     }
 
-    let device = match find_device_by_ip_mac(&peer_addr.ip().to_string()).await {
-        Ok(Some(device)) => device,
-        Ok(None) => {
-            let device = Device {
-                device_id: Uuid::new_v4(),
-                user_uuid: user.user_uuid,
-                ip_address: peer_addr.ip().to_string(),
-            };
-            if let Err(e) = save_device_to_db(device.clone()).await {
-                error!("Failed to save device to database: {}", e);
-            }
-            device
-        }
+    if let Err(e) = register_success(&login.username, ip_address).await {
+        error!("Failed to record successful login: {}", e);
+    }
+
+    issue_session(&user, &client).await
+}
+
+/// Находит или создаёт устройство, сохраняет новую сессию и выставляет cookie
+pub async fn issue_session(user: &User, client: &ClientInfo) -> Result<Response, Rejection> {
+    let resolved = match resolve_device(&user.user_uuid, client).await {
+        Ok(resolved) => resolved,
         Err(e) => {
             error!("Failed to find device: {}", e);
             let response = LoginResponse {
@@ -104,6 +241,8 @@ pub async fn login_handler(login: LoginData, peer_addr: SocketAddr) -> Result<Re
         }
     };
 
+    let device = &resolved.device;
+
     let session_id = Uuid::new_v4(); // Generate session ID
     let session = Session {
         session_id,
@@ -116,10 +255,22 @@ pub async fn login_handler(login: LoginData, peer_addr: SocketAddr) -> Result<Re
         error!("Failed to save session to database: {}", e);
     }
 
-    info!("User logged in successfully: {}", login.username);
+    if let Err(e) = update_last_login(&user.user_uuid).await {
+        error!("Failed to update last login time: {}", e);
+    }
+
+    info!("User logged in successfully: {}", user.username);
+    record(
+        AuditEntry::own(AuditAction::Login, user.user_uuid).with_device(device.device_id),
+        client,
+    )
+    .await;
+    if resolved.unfamiliar {
+        alert_new_login(user, device, session_id, client).await;
+    }
     let response = LoginSuccessResponse {
     message: "User logged in successfully.".to_string(),
-    username: login.username.to_string(),
+    username: user.username.clone(),
     session_id: session.session_id,
     user_uuid: user.user_uuid, // Добавляем user_uuid
     };
@@ -137,6 +288,10 @@ pub async fn login_handler(login: LoginData, peer_addr: SocketAddr) -> Result<Re
         .parse()
         .unwrap(),
     );
+    if let Some(device_token) = &resolved.new_token {
+        resp.headers_mut()
+            .append("Set-Cookie", device_cookie(device_token).parse().unwrap());
+    }
 
     Ok(resp)
 }
@@ -144,10 +299,15 @@ pub async fn login_handler(login: LoginData, peer_addr: SocketAddr) -> Result<Re
 pub fn login_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
     warp::path("api")
         .and(warp::path("login"))
+        .and(warp::path::end())
+        .and(warp::post())
         .and(warp::body::json())
         .and(warp::addr::remote())
-        .and_then(|login: LoginData, addr: Option<SocketAddr>| async move {
-            let peer_addr = addr.expect("Failed to get peer address");
-            login_handler(login, peer_addr).await
-        })
+        .and(with_client_info())
+        .and_then(
+            |login: LoginData, addr: Option<SocketAddr>, client: ClientInfo| async move {
+                let peer_addr = addr.expect("Failed to get peer address");
+                login_handler(login, peer_addr, client).await
+            },
+        )
 }
@@ -0,0 +1,231 @@
+// src/handlers/auth/login_alerts.rs
+//
+// Предупреждение о входе с незнакомого устройства или из незнакомой сети:
+// уведомление в приложении, системное сообщение в чат и письмо, если у
+// пользователя указан адрес. Ссылка «это был не я» завершает сессии устройства.
+use crate::audit::{record, with_client_info, AuditEntry, ClientInfo};
+use crate::db::login_alerts::{
+    find_login_alert, find_login_alert_by_token, mark_login_alert_revoked, save_login_alert,
+};
+use crate::db::sessions::delete_sessions_by_device;
+use crate::handlers::auth::password::app_base_url;
+use crate::mailer::{Email, Mailer};
+use crate::middleware::auth::with_auth;
+use crate::models::{AuditAction, Device, LoginAlert, NotificationKind, User};
+use crate::notifications::notify;
+use chrono::{Duration, Utc};
+use log::{debug, error, info};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error as StdError;
+use uuid::Uuid;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+/// Сколько дней действует ссылка из письма
+const ALERT_TTL_DAYS: i64 = 7;
+const MAX_DEVICE_DESCRIPTION_LENGTH: usize = 120;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LoginAlertResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RevokeByTokenRequest {
+    pub token: String,
+}
+
+fn login_alert_response(message: &str, status: StatusCode) -> Response {
+    let response = LoginAlertResponse {
+        message: message.to_string(),
+    };
+    warp::reply::with_status(warp::reply::json(&response), status).into_response()
+}
+
+fn internal_error(message: &str, e: Box<dyn StdError + Send + Sync>) -> Response {
+    error!("{}: {}", message, e);
+    login_alert_response(message, StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn hash_alert_token(token: &str) -> String {
+    data_encoding::HEXLOWER.encode(&Sha256::digest(token.trim().as_bytes()))
+}
+
+fn generate_alert_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    data_encoding::HEXLOWER.encode(&bytes)
+}
+
+fn describe_login(device: &Device) -> String {
+    let user_agent: String = device
+        .user_agent
+        .as_deref()
+        .unwrap_or("an unknown device")
+        .chars()
+        .take(MAX_DEVICE_DESCRIPTION_LENGTH)
+        .collect();
+    format!("{} at {}", user_agent, device.ip_address)
+}
+
+async fn send_alert_email(
+    user: &User,
+    email: &str,
+    description: &str,
+    token: &str,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let link = format!("{}/static/not_me.html?token={}", app_base_url(), token);
+    let email = Email {
+        to: email.to_string(),
+        subject: "New sign-in to your account".to_string(),
+        body: format!(
+            "Hello, {}!\n\nYour account was just signed in from {}.\n\n\
+             If this was you, no action is needed.\n\
+             If it wasn't, open this link to sign that device out, then change your password:\n{}\n\n\
+             The link expires in {} days.",
+            user.username, description, link, ALERT_TTL_DAYS
+        ),
+    };
+    Mailer::from_env()?.send(&email).await
+}
+
+/// Сообщает владельцу о входе. Ошибки только пишутся в журнал: вход не прерывается
+pub async fn alert_new_login(user: &User, device: &Device, session_id: Uuid, client: &ClientInfo) {
+    let alert = LoginAlert {
+        alert_id: Uuid::new_v4(),
+        user_uuid: user.user_uuid,
+        session_id,
+        device_id: device.device_id,
+        ip_address: client.ip_address,
+        user_agent: device.user_agent.clone(),
+        expires_at: Utc::now() + Duration::days(ALERT_TTL_DAYS),
+        revoked_at: None,
+    };
+    let token = generate_alert_token();
+    if let Err(e) = save_login_alert(&alert, &hash_alert_token(&token)).await {
+        error!("Failed to save login alert: {}", e);
+        return;
+    }
+
+    let description = describe_login(device);
+    info!(
+        "Sign-in from an unfamiliar device for user_uuid: {} ({})",
+        user.user_uuid, description
+    );
+    if let Err(e) = notify(
+        user.user_uuid,
+        NotificationKind::NewLogin,
+        format!("New sign-in from {}. Not you?", description),
+        Some(format!("/static/not_me.html?alert={}", alert.alert_id)),
+    )
+    .await
+    {
+        error!("Failed to notify about new sign-in: {}", e);
+    }
+    if let Some(email) = user.email.as_deref() {
+        if let Err(e) = send_alert_email(user, email, &description, &token).await {
+            error!("Failed to send new sign-in email: {}", e);
+        }
+    }
+}
+
+/// «Это был не я»: завершает сессии устройства из предупреждения
+async fn revoke_alert(alert: LoginAlert, client: &ClientInfo) -> Result<Response, Rejection> {
+    if alert.expires_at < Utc::now() {
+        return Ok(login_alert_response(
+            "Invalid or expired link.",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    match mark_login_alert_revoked(&alert.alert_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Ok(login_alert_response(
+                "This sign-in has already been reported.",
+                StatusCode::OK,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to revoke session.", e)),
+    }
+    let revoked = match delete_sessions_by_device(&alert.user_uuid, &alert.device_id).await {
+        Ok(revoked) => revoked,
+        Err(e) => return Ok(internal_error("Failed to revoke session.", e)),
+    };
+
+    info!(
+        "Sign-in {} reported by user_uuid: {}, revoked {} sessions",
+        alert.alert_id, alert.user_uuid, revoked
+    );
+    let entry = AuditEntry::own(AuditAction::SuspiciousLoginReported, alert.user_uuid)
+        .with_target(revoked.to_string())
+        .with_device(alert.device_id);
+    record(entry, client).await;
+    Ok(login_alert_response(
+        "That device has been signed out. Change your password now.",
+        StatusCode::OK,
+    ))
+}
+
+/// Ссылка из письма: токен подтверждает, что её открыл владелец почты
+pub async fn revoke_by_token_handler(
+    request: RevokeByTokenRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    debug!("Received sign-in report by token");
+
+    match find_login_alert_by_token(&hash_alert_token(&request.token)).await {
+        Ok(Some(alert)) => revoke_alert(alert, &client).await,
+        Ok(None) => Ok(login_alert_response(
+            "Invalid or expired link.",
+            StatusCode::BAD_REQUEST,
+        )),
+        Err(e) => Ok(internal_error("Failed to revoke session.", e)),
+    }
+}
+
+/// Кнопка в уведомлении
+pub async fn revoke_alert_handler(
+    user_uuid: Uuid,
+    alert_id: Uuid,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    debug!(
+        "Received sign-in report {} from user_uuid: {}",
+        alert_id, user_uuid
+    );
+
+    match find_login_alert(&alert_id, &user_uuid).await {
+        Ok(Some(alert)) => revoke_alert(alert, &client).await,
+        Ok(None) => Ok(login_alert_response(
+            "Sign-in alert not found.",
+            StatusCode::NOT_FOUND,
+        )),
+        Err(e) => Ok(internal_error("Failed to revoke session.", e)),
+    }
+}
+
+pub fn login_alerts_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let by_token = warp::path!("api" / "login-alerts" / "revoke")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_client_info())
+        .and_then(
+            |request: RevokeByTokenRequest, client: ClientInfo| async move {
+                revoke_by_token_handler(request, client).await
+            },
+        );
+
+    let by_id = warp::path!("api" / "login-alerts" / Uuid / "revoke")
+        .and(warp::post())
+        .and(with_auth())
+        .and(with_client_info())
+        .and_then(
+            |alert_id: Uuid, user_uuid: Uuid, client: ClientInfo| async move {
+                revoke_alert_handler(user_uuid, alert_id, client).await
+            },
+        );
+
+    by_token.or(by_id).unify()
+}
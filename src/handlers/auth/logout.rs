@@ -1,15 +1,23 @@
 // src/handlers/auth/logout.rs
 
+use crate::audit::{record, with_client_info, AuditEntry, ClientInfo};
+use crate::models::AuditAction;
 use log::{error, info};
 use uuid::Uuid;
 use warp::{http::StatusCode, reply::Response, Filter, Rejection, Reply};
 
-pub async fn logout_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
-    //  Принимаем user_uuid вместо session_id
+pub async fn logout_handler(
+    user_uuid: Uuid,
+    session_id: Uuid,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
     info!("Received logout request for user_uuid: {}", user_uuid);
 
-    // TODO: Удалить сессию из базы данных (реализуйте функцию в db/sessions.rs)
-    if let Err(e) = crate::db::sessions::delete_session_by_session_id(&user_uuid).await {
+    // Устройство в журнале берётся из сессии, поэтому запись — до её удаления
+    record(AuditEntry::own(AuditAction::Logout, user_uuid), &client).await;
+
+    // Удаляем текущую сессию (не все сессии пользователя)
+    if let Err(e) = crate::db::sessions::delete_session_by_session_id(&session_id).await {
         error!("Failed to delete session: {}", e);
         return Ok(warp::reply::with_status(
             warp::reply::json(&"Logout failed"),
@@ -38,9 +46,13 @@ pub async fn logout_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
 pub fn logout_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
     warp::path("api")
         .and(warp::path("logout"))
+        .and(warp::path::end())
+        .and(warp::post())
         .and(crate::middleware::auth::with_auth()) // Используем middleware для авторизации
-        .and_then(|user_uuid: Uuid| async move {
+        .and(warp::cookie::<Uuid>("session_id"))
+        .and(with_client_info())
+        .and_then(|user_uuid: Uuid, session_id: Uuid, client: ClientInfo| async move {
             // Получаем user_uuid из middleware
-            logout_handler(user_uuid).await
+            logout_handler(user_uuid, session_id, client).await
         })
 }
@@ -1,7 +1,14 @@
+pub mod api_tokens;
+pub mod lockout;
 pub mod login;
+pub mod login_alerts;
 pub mod logout;
+pub mod password;
 pub mod register;
+pub mod two_factor;
 
+use crate::password::{check_password_strength, MAX_PASSWORD_LENGTH};
+use crate::usernames::check_username_format;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use uuid::Uuid;
@@ -14,7 +21,9 @@ pub struct RegistrationData {
     pub repeat_password: String,
     pub invitation_code: String,
     pub ip_address: String,
-    pub mac_address: Option<String>,
+    /// Необязательный адрес для восстановления пароля
+    #[serde(default)]
+    pub email: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -42,37 +51,29 @@ pub struct LoginSuccessResponse {
     pub user_uuid: Uuid,
 }
 
+/// Ответ на верный пароль, когда нужен второй фактор
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TwoFactorRequiredResponse {
+    pub message: String,
+    pub username: String,
+    pub two_factor_required: bool,
+    pub challenge_id: Uuid,
+}
+
 impl Validate for RegistrationData {
     fn validate(&self) -> Result<(), ValidationErrors> {
         let mut errors = ValidationErrors::new();
 
-        if self.username.len() < 3 || self.username.len() > 16 {
-            let mut error = ValidationError::new("length");
-            error.message = Some(
-                "Username must be between 3 and 16 characters"
-                    .to_string()
-                    .into(),
-            );
+        if let Some(message) = check_username_format(&self.username) {
+            let mut error = ValidationError::new("username");
+            error.message = Some(message.into());
             errors.add("username", error);
         }
-        if self.password.len() < 6 || self.password.len() > 16 {
-            let mut error = ValidationError::new("length");
-            error.message = Some(
-                "Password must be between 6 and 16 characters"
-                    .to_string()
-                    .into(),
-            );
+        if let Some(message) = check_password_strength(&self.password, &self.username) {
+            let mut error = ValidationError::new("strength");
+            error.message = Some(message.into());
             errors.add("password", error);
         }
-        if self.repeat_password.len() < 6 || self.repeat_password.len() > 16 {
-            let mut error = ValidationError::new("length");
-            error.message = Some(
-                "Repeat password must be between 6 and 16 characters"
-                    .to_string()
-                    .into(),
-            );
-            errors.add("repeat_password", error);
-        }
         if self.invitation_code.len() < 3 || self.invitation_code.len() > 16 {
             let mut error = ValidationError::new("length");
             error.message = Some(
@@ -82,6 +83,13 @@ impl Validate for RegistrationData {
             );
             errors.add("invitation_code", error);
         }
+        if let Some(email) = self.email.as_deref().filter(|email| !email.is_empty()) {
+            if !validator::validate_email(email) {
+                let mut error = ValidationError::new("email");
+                error.message = Some("Invalid email address".to_string().into());
+                errors.add("email", error);
+            }
+        }
 
         if errors.is_empty() {
             Ok(())
@@ -104,11 +112,11 @@ impl Validate for LoginData {
             );
             errors.add("username", error);
         }
-        if self.password.len() < 6 || self.password.len() > 16 {
+        // Стойкость проверяется только у новых паролей: старые короткие пароли должны подходить
+        if self.password.is_empty() || self.password.chars().count() > MAX_PASSWORD_LENGTH {
             let mut error = ValidationError::new("length");
             error.message = Some(
-                "Password must be between 6 and 16 characters"
-                    .to_string()
+                format!("Password must be between 1 and {} characters", MAX_PASSWORD_LENGTH)
                     .into(),
             );
             errors.add("password", error);
@@ -122,6 +130,17 @@ impl Validate for LoginData {
     }
 }
 
+/// Проверяет новый пароль и его повтор. Возвращает текст ошибки
+fn validate_new_password(password: &str, repeat_password: &str, username: &str) -> Option<String> {
+    if let Some(message) = check_password_strength(password, username) {
+        return Some(message);
+    }
+    if password != repeat_password {
+        return Some("Passwords do not match.".to_string());
+    }
+    None
+}
+
 fn map_validation_errors(errors: ValidationErrors) -> String {
     let mut result = String::new();
     for (_, field_errors) in errors.field_errors() {
@@ -0,0 +1,451 @@
+// src/handlers/auth/password.rs
+use crate::audit::{record, with_client_info, AuditEntry, ClientInfo};
+use crate::db::password_resets::{
+    consume_password_reset_token, count_password_resets_from_ip, delete_password_reset_tokens,
+    find_password_reset_token, has_recent_password_reset_token, save_password_reset_token,
+};
+use crate::db::sessions::{delete_other_sessions, delete_sessions_by_user_uuid};
+use crate::db::users::{find_user_by_email, find_user_by_uuid, update_email, update_password_hash};
+use crate::handlers::auth::validate_new_password;
+use crate::mailer::{Email, Mailer};
+use crate::middleware::auth::with_auth;
+use crate::models::{AuditAction, User};
+use crate::password::{hash_password_async, verify_password_async};
+use chrono::{Duration, Utc};
+use log::{debug, error, info};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error as StdError;
+use std::net::IpAddr;
+use tokio_postgres::error::SqlState;
+use uuid::Uuid;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PasswordResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ChangePasswordRequest {
+    pub old_password: String,
+    pub new_password: String,
+    pub repeat_password: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub new_password: String,
+    pub repeat_password: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UpdateEmailRequest {
+    pub email: Option<String>,
+    pub password: String,
+}
+
+fn password_response(message: &str, status: StatusCode) -> Response {
+    let response = PasswordResponse {
+        message: message.to_string(),
+    };
+    warp::reply::with_status(warp::reply::json(&response), status).into_response()
+}
+
+fn internal_error(message: &str, e: Box<dyn StdError + Send + Sync>) -> Response {
+    error!("{}: {}", message, e);
+    password_response(message, StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Пока действует токен моложе этого, новое письмо тому же пользователю не уходит
+const RESET_RESEND_INTERVAL: Duration = Duration::minutes(5);
+/// Сколько писем для сброса можно запросить с одного адреса за час
+const MAX_RESETS_PER_IP_PER_HOUR: i64 = 10;
+
+/// Срок жизни ссылки для сброса пароля
+fn reset_token_ttl() -> Duration {
+    let minutes = std::env::var("PASSWORD_RESET_TTL_MINUTES")
+        .ok()
+        .and_then(|minutes| minutes.parse().ok())
+        .unwrap_or(60);
+    Duration::minutes(minutes)
+}
+
+pub(crate) fn app_base_url() -> String {
+    std::env::var("APP_BASE_URL").unwrap_or_else(|_| "https://cyb3ria.xyz".to_string())
+}
+
+/// В базе хранится только хеш токена
+fn hash_reset_token(token: &str) -> String {
+    data_encoding::HEXLOWER.encode(&Sha256::digest(token.trim().as_bytes()))
+}
+
+fn generate_reset_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    data_encoding::HEXLOWER.encode(&bytes)
+}
+
+/// Выдаёт токен сброса и отправляет ссылку на адрес email
+pub(crate) async fn send_reset_email(
+    user: &User,
+    email: &str,
+    requested_ip: Option<IpAddr>,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let token = generate_reset_token();
+    save_password_reset_token(
+        &hash_reset_token(&token),
+        &user.user_uuid,
+        Utc::now() + reset_token_ttl(),
+        requested_ip,
+    )
+    .await?;
+
+    let link = format!(
+        "{}/static/reset_password.html?token={}",
+        app_base_url(),
+        token
+    );
+    let email = Email {
+        to: email.to_string(),
+        subject: "Password reset".to_string(),
+        body: format!(
+            "Hello, {}!\n\nTo set a new password, open this link:\n{}\n\n\
+             The link expires in {} minutes and can be used once.\n\
+             If you did not request a password reset, ignore this email.\n\nToken: {}",
+            user.username,
+            link,
+            reset_token_ttl().num_minutes(),
+            token
+        ),
+    };
+    Mailer::from_env()?.send(&email).await
+}
+
+pub async fn change_password_handler(
+    user_uuid: Uuid,
+    session_id: Uuid,
+    request: ChangePasswordRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    debug!(
+        "Received change password request for user_uuid: {}",
+        user_uuid
+    );
+
+    let user = match find_user_by_uuid(&user_uuid).await {
+        Ok(user) => user,
+        Err(e) => return Ok(internal_error("Failed to find user.", e)),
+    };
+    if let Some(message) = validate_new_password(
+        &request.new_password,
+        &request.repeat_password,
+        &user.username,
+    ) {
+        return Ok(password_response(&message, StatusCode::BAD_REQUEST));
+    }
+    match verify_password_async(&request.old_password, &user.password_hash).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Ok(password_response(
+                "Invalid password.",
+                StatusCode::UNAUTHORIZED,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to verify password.", e)),
+    }
+
+    let password_hash = match hash_password_async(&request.new_password).await {
+        Ok(hash) => hash,
+        Err(e) => return Ok(internal_error("Failed to hash password.", e)),
+    };
+    if let Err(e) = update_password_hash(&user_uuid, &password_hash).await {
+        return Ok(internal_error("Failed to change password.", e));
+    }
+
+    // Остальные устройства должны войти заново, текущая сессия остаётся
+    record(
+        AuditEntry::own(AuditAction::PasswordChanged, user_uuid),
+        &client,
+    )
+    .await;
+    match delete_other_sessions(&user_uuid, &session_id).await {
+        Ok(revoked) => {
+            info!(
+                "Password changed for user_uuid: {}, revoked {} other sessions",
+                user_uuid, revoked
+            );
+            if revoked > 0 {
+                let entry = AuditEntry::own(AuditAction::SessionsRevoked, user_uuid)
+                    .with_target(revoked.to_string());
+                record(entry, &client).await;
+            }
+        }
+        Err(e) => error!("Failed to revoke sessions: {}", e),
+    }
+    if let Err(e) = delete_password_reset_tokens(&user_uuid).await {
+        error!("Failed to delete password reset tokens: {}", e);
+    }
+
+    Ok(password_response("Password changed.", StatusCode::OK))
+}
+
+/// Выдаёт токен и отправляет письмо, если адрес принадлежит активной учётной
+/// записи и лимиты частоты не исчерпаны. Все отказы молчаливые
+async fn issue_password_reset(
+    email: &str,
+    requested_ip: Option<IpAddr>,
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let user = match find_user_by_email(email).await? {
+        Some(user) if user.suspended_at.is_none() => user,
+        _ => {
+            debug!("No active account for password reset request");
+            return Ok(());
+        }
+    };
+    if let Some(ip) = requested_ip {
+        let since = Utc::now() - Duration::hours(1);
+        if count_password_resets_from_ip(ip, since).await? >= MAX_RESETS_PER_IP_PER_HOUR {
+            info!("Too many password reset requests from {}", ip);
+            return Ok(());
+        }
+    }
+    if has_recent_password_reset_token(&user.user_uuid, Utc::now() - RESET_RESEND_INTERVAL).await? {
+        debug!(
+            "Password reset email was sent recently to user_uuid: {}",
+            user.user_uuid
+        );
+        return Ok(());
+    }
+
+    // Письмо уходит на сохранённый адрес, а не на введённый
+    let stored_email = user.email.clone().unwrap_or_default();
+    send_reset_email(&user, &stored_email, requested_ip).await?;
+    info!("Password reset email sent to user_uuid: {}", user.user_uuid);
+    Ok(())
+}
+
+pub async fn forgot_password_handler(
+    request: ForgotPasswordRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    debug!("Received password reset request");
+
+    // Ответ одинаковый, чтобы по нему нельзя было узнать, зарегистрирован ли адрес
+    let response = password_response(
+        "If an account with this email exists, a reset link has been sent.",
+        StatusCode::OK,
+    );
+
+    let email = request.email.trim();
+    if !validator::validate_email(email) {
+        return Ok(password_response(
+            "Invalid email address",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    // Поиск учётной записи и отправка письма идут в фоне: время ответа
+    // не зависит от того, нашёлся ли адрес
+    let email = email.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = issue_password_reset(&email, client.ip_address).await {
+            error!("Failed to send password reset email: {}", e);
+        }
+    });
+
+    Ok(response)
+}
+
+pub async fn reset_password_handler(
+    request: ResetPasswordRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    debug!("Received password reset");
+
+    let token_hash = hash_reset_token(&request.token);
+    let invalid_token =
+        || password_response("Invalid or expired reset token.", StatusCode::BAD_REQUEST);
+
+    // Слабый пароль не должен сжигать токен, поэтому сначала только проверяем его
+    let user = match find_password_reset_token(&token_hash).await {
+        Ok(Some(user_uuid)) => match find_user_by_uuid(&user_uuid).await {
+            Ok(user) => user,
+            Err(e) => return Ok(internal_error("Failed to find user.", e)),
+        },
+        Ok(None) => return Ok(invalid_token()),
+        Err(e) => return Ok(internal_error("Failed to reset password.", e)),
+    };
+    if let Some(message) = validate_new_password(
+        &request.new_password,
+        &request.repeat_password,
+        &user.username,
+    ) {
+        return Ok(password_response(&message, StatusCode::BAD_REQUEST));
+    }
+
+    let user_uuid = match consume_password_reset_token(&token_hash).await {
+        Ok(Some(user_uuid)) => user_uuid,
+        Ok(None) => return Ok(invalid_token()),
+        Err(e) => return Ok(internal_error("Failed to reset password.", e)),
+    };
+
+    let password_hash = match hash_password_async(&request.new_password).await {
+        Ok(hash) => hash,
+        Err(e) => return Ok(internal_error("Failed to hash password.", e)),
+    };
+    if let Err(e) = update_password_hash(&user_uuid, &password_hash).await {
+        return Ok(internal_error("Failed to reset password.", e));
+    }
+
+    record(
+        AuditEntry::own(AuditAction::PasswordReset, user_uuid),
+        &client,
+    )
+    .await;
+    match delete_sessions_by_user_uuid(&user_uuid).await {
+        Ok(revoked) if revoked > 0 => {
+            let entry = AuditEntry::own(AuditAction::SessionsRevoked, user_uuid)
+                .with_target(revoked.to_string());
+            record(entry, &client).await;
+        }
+        Ok(_) => {}
+        Err(e) => error!("Failed to revoke sessions: {}", e),
+    }
+    if let Err(e) = delete_password_reset_tokens(&user_uuid).await {
+        error!("Failed to delete password reset tokens: {}", e);
+    }
+
+    info!("Password reset for user_uuid: {}", user_uuid);
+    Ok(password_response(
+        "Password has been reset. Please log in.",
+        StatusCode::OK,
+    ))
+}
+
+/// Задаёт или убирает адрес почты для восстановления пароля
+pub async fn update_email_handler(
+    user_uuid: Uuid,
+    request: UpdateEmailRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    debug!("Received email update for user_uuid: {}", user_uuid);
+
+    let email = request
+        .email
+        .as_deref()
+        .map(str::trim)
+        .filter(|email| !email.is_empty());
+    if let Some(email) = email {
+        if !validator::validate_email(email) {
+            return Ok(password_response(
+                "Invalid email address",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+    }
+
+    let user = match find_user_by_uuid(&user_uuid).await {
+        Ok(user) => user,
+        Err(e) => return Ok(internal_error("Failed to find user.", e)),
+    };
+    match verify_password_async(&request.password, &user.password_hash).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Ok(password_response(
+                "Invalid password.",
+                StatusCode::UNAUTHORIZED,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to verify password.", e)),
+    }
+
+    match update_email(&user_uuid, email).await {
+        Ok(_) => {
+            record(
+                AuditEntry::own(AuditAction::EmailChanged, user_uuid),
+                &client,
+            )
+            .await;
+            Ok(password_response("Email updated.", StatusCode::OK))
+        }
+        Err(e) => {
+            let duplicate = e
+                .downcast_ref::<tokio_postgres::Error>()
+                .and_then(|e| e.code())
+                == Some(&SqlState::UNIQUE_VIOLATION);
+            if duplicate {
+                Ok(password_response(
+                    "Email is already in use.",
+                    StatusCode::CONFLICT,
+                ))
+            } else {
+                Ok(internal_error("Failed to update email.", e))
+            }
+        }
+    }
+}
+
+pub fn password_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let change = warp::path!("api" / "password" / "change")
+        .and(warp::post())
+        .and(with_auth())
+        .and(warp::cookie::<Uuid>("session_id"))
+        .and(warp::body::json())
+        .and(with_client_info())
+        .and_then(
+            |user_uuid: Uuid,
+             session_id: Uuid,
+             request: ChangePasswordRequest,
+             client: ClientInfo| async move {
+                change_password_handler(user_uuid, session_id, request, client).await
+            },
+        );
+
+    let forgot = warp::path!("api" / "password" / "forgot")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_client_info())
+        .and_then(
+            |request: ForgotPasswordRequest, client: ClientInfo| async move {
+                forgot_password_handler(request, client).await
+            },
+        );
+
+    let reset = warp::path!("api" / "password" / "reset")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_client_info())
+        .and_then(
+            |request: ResetPasswordRequest, client: ClientInfo| async move {
+                reset_password_handler(request, client).await
+            },
+        );
+
+    let email = warp::path!("api" / "account" / "email")
+        .and(warp::put())
+        .and(with_auth())
+        .and(warp::body::json())
+        .and(with_client_info())
+        .and_then(
+            |user_uuid: Uuid, request: UpdateEmailRequest, client: ClientInfo| async move {
+                update_email_handler(user_uuid, request, client).await
+            },
+        );
+
+    change
+        .or(forgot)
+        .unify()
+        .or(reset)
+        .unify()
+        .or(email)
+        .unify()
+}
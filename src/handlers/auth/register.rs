@@ -1,8 +1,9 @@
 use crate::db::users::save_user_to_db;
 use crate::db::profiles::create_profile;
 use crate::handlers::auth::{map_validation_errors, RegistrationData, RegistrationResponse};
-use crate::models::User;
-use bcrypt::{hash, DEFAULT_COST};
+use crate::models::{Role, User};
+use crate::password::hash_password_async;
+use crate::usernames::check_username_available;
 use log::{debug, error, info};
 use std::net::SocketAddr;
 use uuid::Uuid;
@@ -40,7 +41,33 @@ pub async fn register_handler(
         );
     }
 
-    let password_hash = match hash(registration.password, DEFAULT_COST) {
+    // Занятые, зарезервированные и недавно освобождённые имена
+    match check_username_available(&registration.username, None).await {
+        Ok(None) => {}
+        Ok(Some(message)) => {
+            error!("Username {} is not available: {}", registration.username, message);
+            let response = RegistrationResponse {
+                message: message.to_string(),
+            };
+            return Ok(
+                warp::reply::with_status(warp::reply::json(&response), StatusCode::CONFLICT)
+                    .into_response(),
+            );
+        }
+        Err(e) => {
+            error!("Failed to check username: {}", e);
+            let response = RegistrationResponse {
+                message: "Failed to check username.".to_string(),
+            };
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&response),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            )
+            .into_response());
+        }
+    }
+
+    let password_hash = match hash_password_async(&registration.password).await {
         Ok(hash) => hash,
         Err(e) => {
             error!("Failed to hash password: {}", e);
@@ -62,6 +89,11 @@ pub async fn register_handler(
         password_hash,
         invitation_code: registration.invitation_code,
         user_uuid,
+        role: Role::User,
+        suspended_at: None,
+        email: registration.email.filter(|email| !email.is_empty()),
+        display_name: None,
+        username_changed_at: None,
     };
 
     match save_user_to_db(user).await {
@@ -107,6 +139,8 @@ pub async fn register_handler(
 pub fn register_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
     warp::path("api")
         .and(warp::path("register"))
+        .and(warp::path::end())
+        .and(warp::post())
         .and(warp::body::json())
         .and(warp::addr::remote())
         .and_then(
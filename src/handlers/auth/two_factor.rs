@@ -0,0 +1,459 @@
+// src/handlers/auth/two_factor.rs
+use crate::audit::{record, with_client_info, AuditEntry, ClientInfo};
+use crate::db::two_factor::{
+    count_unused_recovery_codes, delete_login_challenge, disable_totp, enable_totp, find_totp,
+    mark_totp_step_used, save_pending_totp, take_login_challenge_attempt, use_recovery_code,
+};
+use crate::db::users::find_user_by_uuid;
+use crate::handlers::auth::lockout::{
+    active_lockout, register_failure, register_success, LockoutPolicy,
+};
+use crate::handlers::auth::login::issue_session;
+use crate::middleware::auth::with_auth;
+use crate::models::{AuditAction, TotpSecret};
+use crate::password::verify_password_async;
+use crate::totp;
+use chrono::Utc;
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use std::error::Error as StdError;
+use std::net::{IpAddr, Ipv4Addr};
+use uuid::Uuid;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+/// Сколько раз можно ввести код для одного входа
+const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TwoFactorResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct EnrollResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ConfirmRequest {
+    pub code: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RecoveryCodesResponse {
+    pub message: String,
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DisableRequest {
+    pub password: String,
+    pub code: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TwoFactorStatusResponse {
+    pub enabled: bool,
+    pub recovery_codes_left: i64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TwoFactorLoginRequest {
+    pub challenge_id: Uuid,
+    pub code: String,
+}
+
+fn two_factor_response(message: &str, status: StatusCode) -> Response {
+    let response = TwoFactorResponse {
+        message: message.to_string(),
+    };
+    warp::reply::with_status(warp::reply::json(&response), status).into_response()
+}
+
+fn internal_error(message: &str, e: Box<dyn StdError + Send + Sync>) -> Response {
+    error!("{}: {}", message, e);
+    two_factor_response(message, StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn issuer() -> String {
+    std::env::var("TOTP_ISSUER").unwrap_or_else(|_| "cyb3ria".to_string())
+}
+
+/// Проверяет код приложения-аутентификатора или код восстановления
+pub async fn verify_second_factor(
+    totp_secret: &TotpSecret,
+    code: &str,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let is_totp = code.trim().chars().all(|c| c.is_ascii_digit());
+    if is_totp {
+        let secret =
+            totp::decode_secret(&totp_secret.secret).ok_or("Invalid stored TOTP secret")?;
+        match totp::verify_code(&secret, code, Utc::now().timestamp(), 1) {
+            Some(step) => mark_totp_step_used(&totp_secret.user_uuid, step).await,
+            None => Ok(false),
+        }
+    } else {
+        use_recovery_code(&totp_secret.user_uuid, &totp::hash_recovery_code(code)).await
+    }
+}
+
+pub async fn status_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
+    let enabled = match find_totp(&user_uuid).await {
+        Ok(totp) => totp.is_some_and(|totp| totp.enabled_at.is_some()),
+        Err(e) => return Ok(internal_error("Failed to get two-factor status.", e)),
+    };
+    let recovery_codes_left = match count_unused_recovery_codes(&user_uuid).await {
+        Ok(count) => count,
+        Err(e) => return Ok(internal_error("Failed to get two-factor status.", e)),
+    };
+    let response = TwoFactorStatusResponse {
+        enabled,
+        recovery_codes_left,
+    };
+    Ok(warp::reply::json(&response).into_response())
+}
+
+pub async fn enroll_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
+    debug!(
+        "Received 2FA enrollment request for user_uuid: {}",
+        user_uuid
+    );
+
+    match find_totp(&user_uuid).await {
+        Ok(Some(totp)) if totp.enabled_at.is_some() => {
+            return Ok(two_factor_response(
+                "Two-factor authentication is already enabled.",
+                StatusCode::CONFLICT,
+            ));
+        }
+        Ok(_) => {}
+        Err(e) => return Ok(internal_error("Failed to start enrollment.", e)),
+    }
+
+    let user = match find_user_by_uuid(&user_uuid).await {
+        Ok(user) => user,
+        Err(e) => return Ok(internal_error("Failed to find user.", e)),
+    };
+
+    let secret = totp::generate_secret();
+    let encoded = totp::encode_secret(&secret);
+    if let Err(e) = save_pending_totp(&user_uuid, &encoded).await {
+        return Ok(internal_error("Failed to start enrollment.", e));
+    }
+
+    let response = EnrollResponse {
+        otpauth_uri: totp::otpauth_uri(&issuer(), &user.username, &secret),
+        secret: encoded,
+    };
+    Ok(warp::reply::json(&response).into_response())
+}
+
+pub async fn confirm_handler(
+    user_uuid: Uuid,
+    request: ConfirmRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    let totp_secret = match find_totp(&user_uuid).await {
+        Ok(Some(totp)) if totp.enabled_at.is_none() => totp,
+        Ok(Some(_)) => {
+            return Ok(two_factor_response(
+                "Two-factor authentication is already enabled.",
+                StatusCode::CONFLICT,
+            ))
+        }
+        Ok(None) => {
+            return Ok(two_factor_response(
+                "Start enrollment first.",
+                StatusCode::BAD_REQUEST,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to confirm enrollment.", e)),
+    };
+
+    let secret = match totp::decode_secret(&totp_secret.secret) {
+        Some(secret) => secret,
+        None => {
+            return Ok(internal_error(
+                "Invalid stored secret.",
+                "decode failed".into(),
+            ))
+        }
+    };
+    let step = match totp::verify_code(&secret, &request.code, Utc::now().timestamp(), 1) {
+        Some(step) => step,
+        None => {
+            return Ok(two_factor_response(
+                "Invalid code.",
+                StatusCode::BAD_REQUEST,
+            ))
+        }
+    };
+
+    let recovery_codes = totp::generate_recovery_codes();
+    let hashes: Vec<String> = recovery_codes
+        .iter()
+        .map(|code| totp::hash_recovery_code(code))
+        .collect();
+    if let Err(e) = enable_totp(&user_uuid, step, &hashes).await {
+        return Ok(internal_error(
+            "Failed to enable two-factor authentication.",
+            e,
+        ));
+    }
+
+    info!(
+        "Two-factor authentication enabled for user_uuid: {}",
+        user_uuid
+    );
+    record(
+        AuditEntry::own(AuditAction::TwoFactorEnabled, user_uuid),
+        &client,
+    )
+    .await;
+    // Коды восстановления показываются один раз, в базе хранятся только хеши
+    let response = RecoveryCodesResponse {
+        message: "Two-factor authentication enabled.".to_string(),
+        recovery_codes,
+    };
+    Ok(warp::reply::json(&response).into_response())
+}
+
+pub async fn disable_handler(
+    user_uuid: Uuid,
+    request: DisableRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    let user = match find_user_by_uuid(&user_uuid).await {
+        Ok(user) => user,
+        Err(e) => return Ok(internal_error("Failed to find user.", e)),
+    };
+    match verify_password_async(&request.password, &user.password_hash).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Ok(two_factor_response(
+                "Invalid password or code.",
+                StatusCode::UNAUTHORIZED,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to verify password.", e)),
+    }
+
+    let totp_secret = match find_totp(&user_uuid).await {
+        Ok(Some(totp)) if totp.enabled_at.is_some() => totp,
+        Ok(_) => {
+            return Ok(two_factor_response(
+                "Two-factor authentication is not enabled.",
+                StatusCode::BAD_REQUEST,
+            ))
+        }
+        Err(e) => {
+            return Ok(internal_error(
+                "Failed to disable two-factor authentication.",
+                e,
+            ))
+        }
+    };
+
+    match verify_second_factor(&totp_secret, &request.code).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Ok(two_factor_response(
+                "Invalid password or code.",
+                StatusCode::UNAUTHORIZED,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to verify code.", e)),
+    }
+
+    if let Err(e) = disable_totp(&user_uuid).await {
+        return Ok(internal_error(
+            "Failed to disable two-factor authentication.",
+            e,
+        ));
+    }
+
+    info!(
+        "Two-factor authentication disabled for user_uuid: {}",
+        user_uuid
+    );
+    record(
+        AuditEntry::own(AuditAction::TwoFactorDisabled, user_uuid),
+        &client,
+    )
+    .await;
+    Ok(two_factor_response(
+        "Two-factor authentication disabled.",
+        StatusCode::OK,
+    ))
+}
+
+/// Второй шаг входа: проверяет код и выдаёт сессию
+pub async fn login_two_factor_handler(
+    request: TwoFactorLoginRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    debug!(
+        "Received second factor for challenge {}",
+        request.challenge_id
+    );
+
+    let challenge = match take_login_challenge_attempt(&request.challenge_id).await {
+        Ok(Some(challenge)) => challenge,
+        Ok(None) => {
+            return Ok(two_factor_response(
+                "Invalid or expired login attempt.",
+                StatusCode::UNAUTHORIZED,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to verify code.", e)),
+    };
+
+    if challenge.expires_at <= Utc::now() || challenge.attempts > MAX_CHALLENGE_ATTEMPTS {
+        if let Err(e) = delete_login_challenge(&challenge.challenge_id).await {
+            error!("Failed to delete login challenge: {}", e);
+        }
+        return Ok(two_factor_response(
+            "Invalid or expired login attempt.",
+            StatusCode::UNAUTHORIZED,
+        ));
+    }
+
+    let totp_secret = match find_totp(&challenge.user_uuid).await {
+        Ok(Some(totp)) if totp.enabled_at.is_some() => totp,
+        Ok(_) => {
+            return Ok(two_factor_response(
+                "Invalid or expired login attempt.",
+                StatusCode::UNAUTHORIZED,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to verify code.", e)),
+    };
+
+    let user = match find_user_by_uuid(&challenge.user_uuid).await {
+        Ok(user) => user,
+        Err(e) => return Ok(internal_error("Failed to find user.", e)),
+    };
+    let ip_address = client
+        .ip_address
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+    // Неверные коды считаются неудачными входами наравне с паролями
+    match active_lockout(&user.username, ip_address).await {
+        Ok(Some(locked_until)) => {
+            error!(
+                "Second factor locked out for {} from {}",
+                user.username, ip_address
+            );
+            let retry_after = (locked_until - Utc::now()).num_seconds().max(1);
+            let mut resp = two_factor_response(
+                "Too many failed login attempts. Try again later.",
+                StatusCode::TOO_MANY_REQUESTS,
+            );
+            resp.headers_mut()
+                .insert("Retry-After", retry_after.to_string().parse().unwrap());
+            return Ok(resp);
+        }
+        Ok(None) => {}
+        Err(e) => return Ok(internal_error("Failed to verify code.", e)),
+    }
+
+    match verify_second_factor(&totp_secret, &request.code).await {
+        Ok(true) => {}
+        Ok(false) => {
+            error!(
+                "Invalid second factor for user_uuid: {}",
+                challenge.user_uuid
+            );
+            let entry = AuditEntry {
+                action: AuditAction::LoginFailed,
+                actor_uuid: None,
+                target_uuid: Some(challenge.user_uuid),
+                target: Some("second factor".to_string()),
+                device_id: None,
+            };
+            record(entry, &client).await;
+            if let Err(e) =
+                register_failure(&LockoutPolicy::from_env(), &user.username, ip_address).await
+            {
+                error!("Failed to record failed login: {}", e);
+            }
+            return Ok(two_factor_response(
+                "Invalid code.",
+                StatusCode::UNAUTHORIZED,
+            ));
+        }
+        Err(e) => return Ok(internal_error("Failed to verify code.", e)),
+    }
+
+    if let Err(e) = delete_login_challenge(&challenge.challenge_id).await {
+        error!("Failed to delete login challenge: {}", e);
+    }
+    if let Err(e) = register_success(&user.username, ip_address).await {
+        error!("Failed to record successful login: {}", e);
+    }
+
+    if user.suspended_at.is_some() {
+        return Ok(two_factor_response(
+            "Account suspended.",
+            StatusCode::FORBIDDEN,
+        ));
+    }
+
+    issue_session(&user, &client).await
+}
+
+pub fn two_factor_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let status = warp::path!("api" / "2fa")
+        .and(warp::get())
+        .and(with_auth())
+        .and_then(|user_uuid: Uuid| async move { status_handler(user_uuid).await });
+
+    let enroll = warp::path!("api" / "2fa" / "enroll")
+        .and(warp::post())
+        .and(with_auth())
+        .and_then(|user_uuid: Uuid| async move { enroll_handler(user_uuid).await });
+
+    let confirm = warp::path!("api" / "2fa" / "confirm")
+        .and(warp::post())
+        .and(with_auth())
+        .and(warp::body::json())
+        .and(with_client_info())
+        .and_then(
+            |user_uuid: Uuid, request: ConfirmRequest, client: ClientInfo| async move {
+                confirm_handler(user_uuid, request, client).await
+            },
+        );
+
+    let disable = warp::path!("api" / "2fa" / "disable")
+        .and(warp::post())
+        .and(with_auth())
+        .and(warp::body::json())
+        .and(with_client_info())
+        .and_then(
+            |user_uuid: Uuid, request: DisableRequest, client: ClientInfo| async move {
+                disable_handler(user_uuid, request, client).await
+            },
+        );
+
+    let login = warp::path!("api" / "login" / "2fa")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_client_info())
+        .and_then(
+            |request: TwoFactorLoginRequest, client: ClientInfo| async move {
+                login_two_factor_handler(request, client).await
+            },
+        );
+
+    status
+        .or(enroll)
+        .unify()
+        .or(confirm)
+        .unify()
+        .or(disable)
+        .unify()
+        .or(login)
+        .unify()
+}
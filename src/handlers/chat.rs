@@ -1,32 +1,350 @@
-use crate::db::messages::save_message_to_db;
-use crate::db::send_message_history;
+use crate::attachments::{attachment, notify_file_shares, MAX_ATTACHMENTS};
+use crate::db::attachments::{attachments_for_messages, save_attachments};
+use crate::db::files::find_readable_files;
+use crate::db::mentions::{mentions_for_messages, save_mentions};
+use crate::db::messages::{find_payload, recent_messages, save_message_to_db};
+use crate::db::notifications::take_undelivered_notifications;
+use crate::db::reactions::reactions_for_messages;
+use crate::db::threads::{find_message_thread, thread_summaries};
+use crate::flood::{check_length, Flood, FloodState, Refusal, TokenBucket};
+use crate::handlers::reactions::apply_reaction;
+use crate::handlers::read_markers::apply_read;
+use crate::mentions::{notify_mentions, resolve_mentions};
+use crate::models::{ChatEvent, ChatPayload, File, Permission};
+use crate::notifications::{notification_event, subscribe_user_events, ChatPresence};
+use crate::usernames::chat_name;
 use crate::utils::generate_client_id;
-use futures_util::stream::StreamExt;
+use crate::{Clients, Sender};
+use chrono::Utc;
+use futures_util::stream::{SplitSink, StreamExt};
 use futures_util::SinkExt;
 use log::{debug, error, info};
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
-use tokio::sync::broadcast;
+use std::sync::Arc;
 use tokio::sync::Mutex as TokioMutex;
-use tokio::time::{interval, Duration as TokioDuration};
+use tokio::time::{interval, Duration as TokioDuration, Instant};
 use uuid::Uuid;
 use warp::ws::{Message, WebSocket};
 
-type Clients = Arc<Mutex<std::collections::HashMap<String, usize>>>;
-type Sender = Arc<Mutex<broadcast::Sender<String>>>;
+/// Сколько последних сообщений получает новый клиент
+const HISTORY_LIMIT: i64 = 50;
+/// Не чаще одного события «печатает» от соединения за этот срок
+const TYPING_THROTTLE: TokioDuration = TokioDuration::from_secs(3);
+/// Через сколько индикатор гаснет, если не пришло нового события
+const TYPING_TTL_SECONDS: i64 = 6;
+
+type WsSender = Arc<TokioMutex<SplitSink<WebSocket, Message>>>;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 struct ClientMessage {
     message: String,
+    #[serde(default)]
     ip: String,
-    mac: String,
+}
+
+/// Запросы клиентов с ?protocol=json
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientEvent {
+    Message {
+        message: String,
+        /// Цитируемое сообщение
+        #[serde(default)]
+        reply_to: Option<i64>,
+        /// Написать в ветку под этим сообщением
+        #[serde(default)]
+        thread_root: Option<i64>,
+        /// file_id вложенных файлов
+        #[serde(default)]
+        attachments: Vec<Uuid>,
+    },
+    React { message_id: i64, emoji: String },
+    Unreact { message_id: i64, emoji: String },
+    /// Пользователь печатает (в ленте или в ветке)
+    Typing {
+        #[serde(default)]
+        thread_root: Option<i64>,
+    },
+    /// Прочитано до этого сообщения
+    Read { message_id: i64 },
+}
+
+/// Параметры подключения к чату
+#[derive(Deserialize, Debug, Default)]
+pub struct ChatQuery {
+    pub protocol: Option<String>,
+}
+
+/// Формат сообщений чата для клиента
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChatProtocol {
+    /// Строки вида "имя: текст" — прежний формат
+    Text,
+    /// События ChatEvent в JSON
+    Json,
+}
+
+impl ChatProtocol {
+    pub fn from_query(query: &ChatQuery) -> Self {
+        match query.protocol.as_deref() {
+            Some("json") => ChatProtocol::Json,
+            _ => ChatProtocol::Text,
+        }
+    }
+
+    /// Готовит событие к отправке; None — событие этому клиенту не показывается
+    fn render(self, event: &ChatEvent) -> Option<String> {
+        match self {
+            ChatProtocol::Json => match serde_json::to_string(event) {
+                Ok(json) => Some(json),
+                Err(e) => {
+                    error!("Failed to serialize chat event: {}", e);
+                    None
+                }
+            },
+            ChatProtocol::Text => match event {
+                ChatEvent::Message(payload) => {
+                    let mut line = format!("{}: {}", payload.author, payload.text);
+                    for attachment in &payload.attachments {
+                        line.push_str(&format!(" [{} {}]", attachment.filename, attachment.url));
+                    }
+                    Some(line)
+                }
+                ChatEvent::System {
+                    message,
+                    link: Some(link),
+                } => Some(format!("system: {} {}", message, link)),
+                ChatEvent::System { message, link: None } => Some(format!("system: {}", message)),
+                ChatEvent::SlowMode(status) => Some(match status.interval_seconds {
+                    0 => "system: slow mode is off".to_string(),
+                    seconds => format!("system: slow mode: one message every {} s", seconds),
+                }),
+                ChatEvent::Error { message, .. } => Some(format!("error: {}", message)),
+                // Уведомления показываются так же, как системные сообщения
+                ChatEvent::Notification(notification) => match &notification.link {
+                    Some(link) => Some(format!("system: {} {}", notification.message, link)),
+                    None => Some(format!("system: {}", notification.message)),
+                },
+                _ => None,
+            },
+        }
+    }
+}
+
+async fn send_event(
+    ws_sender: &WsSender,
+    protocol: ChatProtocol,
+    event: &ChatEvent,
+) -> Result<(), warp::Error> {
+    match protocol.render(event) {
+        Some(text) => ws_sender.lock().await.send(Message::text(text)).await,
+        None => Ok(()),
+    }
+}
+
+/// Заполняет реакции (с отметкой viewer), сводки веток, упоминания и вложения
+pub async fn attach_details(
+    messages: &mut [ChatPayload],
+    viewer: &Uuid,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let ids: Vec<i64> = messages.iter().map(|payload| payload.message_id).collect();
+    let mut reactions = reactions_for_messages(&ids, Some(viewer)).await?;
+    let mut threads = thread_summaries(&ids).await?;
+    let mut mentions = mentions_for_messages(&ids).await?;
+    let mut attachments = attachments_for_messages(&ids).await?;
+    for payload in messages.iter_mut() {
+        payload.reactions = reactions.remove(&payload.message_id).unwrap_or_default();
+        payload.thread = threads.remove(&payload.message_id);
+        payload.mentions = mentions.remove(&payload.message_id).unwrap_or_default();
+        payload.attachments = attachments.remove(&payload.message_id).unwrap_or_default();
+    }
+    Ok(())
+}
+
+/// Отправляет историю сообщений клиенту
+async fn send_message_history(
+    ws_sender: &WsSender,
+    protocol: ChatProtocol,
+    viewer: &Uuid,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut messages = recent_messages(HISTORY_LIMIT).await?;
+
+    if protocol == ChatProtocol::Text {
+        // Прежний формат: по строке на сообщение, новые сначала; строки те же, что у новых сообщений
+        attach_details(&mut messages, viewer).await?;
+        for payload in messages {
+            let event = ChatEvent::Message(Box::new(payload));
+            if let Err(e) = send_event(ws_sender, protocol, &event).await {
+                error!("Failed to send message: {}", e);
+            }
+        }
+        return Ok(());
+    }
+
+    attach_details(&mut messages, viewer).await?;
+    messages.reverse();
+    send_event(ws_sender, protocol, &ChatEvent::History { messages }).await?;
+    Ok(())
+}
+
+/// В какую ветку попадёт сообщение. Ответ на сообщение из ветки остаётся в ней
+async fn resolve_thread(reply_to: Option<i64>, thread_root: Option<i64>) -> Result<Option<i64>, String> {
+    let lookup = |message_id: i64, missing: &'static str| async move {
+        match find_message_thread(message_id).await {
+            Ok(Some(root)) => Ok(root),
+            Ok(None) => Err(missing.to_string()),
+            Err(e) => {
+                error!("Failed to find message {}: {}", message_id, e);
+                Err("Failed to send message".to_string())
+            }
+        }
+    };
+
+    let mut root = match thread_root {
+        // Корнем ветки может быть только сообщение общей ленты
+        Some(message_id) => Some(lookup(message_id, "Thread not found").await?.unwrap_or(message_id)),
+        None => None,
+    };
+    if let Some(message_id) = reply_to {
+        let quoted_root = lookup(message_id, "Quoted message not found").await?;
+        match (root, quoted_root) {
+            (None, quoted_root) => root = quoted_root,
+            (Some(root), Some(quoted_root)) if root != quoted_root => {
+                return Err("Quoted message is in another thread".to_string());
+            }
+            _ => {}
+        }
+    }
+    Ok(root)
+}
+
+/// Файлы для вложения, в порядке file_ids. Вложить можно свой файл или файл,
+/// который уже вложен в видимое сообщение; о чужих файлах ответ тот же, что о несуществующих
+async fn resolve_attachments(user_uuid: Uuid, file_ids: &[Uuid]) -> Result<Vec<File>, String> {
+    let mut unique: Vec<Uuid> = Vec::new();
+    for file_id in file_ids {
+        if !unique.contains(file_id) {
+            unique.push(*file_id);
+        }
+    }
+    if unique.len() > MAX_ATTACHMENTS {
+        return Err(format!("At most {} attachments per message", MAX_ATTACHMENTS));
+    }
+
+    let mut readable = find_readable_files(&user_uuid, &unique).await.map_err(|e| {
+        error!("Failed to load attachments: {}", e);
+        "Failed to send message".to_string()
+    })?;
+    let mut files = Vec::with_capacity(unique.len());
+    for file_id in unique {
+        match readable.iter().position(|file| file.file_id == file_id) {
+            Some(index) => files.push(readable.swap_remove(index)),
+            None => return Err("Attachment not found".to_string()),
+        }
+    }
+    Ok(files)
+}
+
+/// Сохраняет сообщение и рассылает его всем в чате.
+/// moderator — не соблюдает медленный режим
+#[allow(clippy::too_many_arguments)]
+async fn publish_message(
+    sender: &Sender,
+    flood: &Flood,
+    user_uuid: Uuid,
+    moderator: bool,
+    text: String,
+    reply_to: Option<i64>,
+    thread_root: Option<i64>,
+    attachments: Vec<Uuid>,
+) -> Result<(), Refusal> {
+    check_length(&text, !attachments.is_empty())?;
+    let thread_root = resolve_thread(reply_to, thread_root).await?;
+    let files = resolve_attachments(user_uuid, &attachments).await?;
+
+    let recorded =
+        flood.check_and_record_message(user_uuid, thread_root, &text, &attachments, moderator)?;
+    let (message_id, _) = match save_message_to_db(&text, user_uuid, reply_to, thread_root).await {
+        Ok(saved) => saved,
+        Err(e) => {
+            error!("Failed to save message to database: {}", e);
+            // Несохранённое сообщение не должно считаться повтором
+            flood.forget_message(user_uuid, recorded);
+            return Err("Failed to send message".to_string().into());
+        }
+    };
+
+    // Сообщение уже сохранено: без упоминаний оно всё равно уходит в чат
+    let mentions = match resolve_mentions(&text).await {
+        Ok(mentions) => mentions,
+        Err(e) => {
+            error!("Failed to resolve mentions in message {}: {}", message_id, e);
+            Vec::new()
+        }
+    };
+    if let Err(e) = save_mentions(message_id, &mentions).await {
+        error!("Failed to save mentions in message {}: {}", message_id, e);
+    }
+    let file_ids: Vec<Uuid> = files.iter().map(|file| file.file_id).collect();
+    if let Err(e) = save_attachments(message_id, &user_uuid, &file_ids).await {
+        error!("Failed to save attachments in message {}: {}", message_id, e);
+    }
+
+    // Подпись берём из базы: имя могло смениться, пока открыт чат
+    let mut payload = match find_payload(message_id).await {
+        Ok(Some(payload)) => payload,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            error!("Failed to load message {}: {}", message_id, e);
+            return Err("Failed to send message".to_string().into());
+        }
+    };
+    payload.mentions = mentions;
+    payload.attachments = files.iter().map(attachment).collect();
+    let event = ChatEvent::Message(Box::new(payload.clone()));
+    if let Err(e) = sender.lock().unwrap().send(event) {
+        error!("Failed to send message to broadcast: {}", e);
+    }
+    notify_mentions(user_uuid, &payload).await;
+    notify_file_shares(user_uuid, &payload, &files).await;
+
+    // Тем, кто смотрит общую ленту, — обновлённая сводка ветки
+    if let Some(root) = thread_root {
+        match thread_summaries(&[root]).await {
+            Ok(mut summaries) => {
+                if let Some(thread) = summaries.remove(&root) {
+                    let event = ChatEvent::Thread {
+                        message_id: root,
+                        thread,
+                    };
+                    let _ = sender.lock().unwrap().send(event);
+                }
+            }
+            Err(e) => error!("Failed to summarize thread {}: {}", root, e),
+        }
+    }
+    Ok(())
+}
+
+/// Сообщает клиенту, почему запрос не выполнен
+async fn send_refusal(ws_sender: &WsSender, protocol: ChatProtocol, refusal: Refusal) {
+    let event = ChatEvent::Error {
+        retry_after: refusal.retry_after_seconds(),
+        message: refusal.message,
+    };
+    if let Err(e) = send_event(ws_sender, protocol, &event).await {
+        error!("Failed to send error: {}", e);
+    }
 }
 
 pub async fn client_connection(
     ws: WebSocket,
     clients: Clients,
     sender: Sender,
+    flood: FloodState,
     user_uuid: Option<String>,
+    can_write: bool,
+    protocol: ChatProtocol,
 ) {
     let (client_ws_sender, mut client_ws_rcv) = ws.split();
     let client_ws_sender = Arc::new(TokioMutex::new(client_ws_sender));
@@ -54,7 +372,11 @@ pub async fn client_connection(
             return;
         }
     };
+    let author = chat_name(&user.username, user.display_name.as_deref());
+    let moderator = user.role.has_permission(Permission::ModerateChat);
     let username = user.username;
+    let mut last_typing: Option<Instant> = None;
+    let mut bucket = TokenBucket::for_connection();
 
     let client_id = {
         let mut clients = clients.lock().unwrap();
@@ -68,12 +390,44 @@ pub async fn client_connection(
         client_id, username
     );
 
+    // Подписываемся до истории, чтобы не пропустить сообщения между ними
+    let mut rx = sender.lock().unwrap().subscribe();
+    let mut user_rx = subscribe_user_events();
+    // Пока соединение открыто, уведомления доставляются сразу
+    let _presence = ChatPresence::new(user_uuid_parsed);
+
+    let hello = ChatEvent::Hello {
+        user_uuid: user_uuid_parsed,
+        username: username.clone(),
+    };
+    if let Err(e) = send_event(&client_ws_sender, protocol, &hello).await {
+        error!("Failed to send hello: {}", e);
+    }
+
     // Отправляем историю сообщений
-    if let Err(e) = send_message_history(client_ws_sender.clone()).await {
+    if let Err(e) = send_message_history(&client_ws_sender, protocol, &user_uuid_parsed).await {
         error!("Failed to send message history: {}", e);
     }
 
-    let mut rx = sender.lock().unwrap().subscribe();
+    // Уведомления, пришедшие, пока чат был закрыт
+    match take_undelivered_notifications(&user_uuid_parsed).await {
+        Ok(notifications) => {
+            for notification in &notifications {
+                let event = notification_event(notification);
+                if let Err(e) = send_event(&client_ws_sender, protocol, &event).await {
+                    error!("Failed to send notification: {}", e);
+                }
+            }
+        }
+        Err(e) => error!("Failed to load undelivered notifications: {}", e),
+    }
+
+    for status in flood.active_slow_modes() {
+        if let Err(e) = send_event(&client_ws_sender, protocol, &ChatEvent::SlowMode(status)).await {
+            error!("Failed to send slow mode: {}", e);
+        }
+    }
+
     let username_clone = username.clone();
     let clients_clone = Arc::clone(&clients);
     let client_id_clone = client_id.clone();
@@ -95,9 +449,9 @@ pub async fn client_connection(
                         break;
                     }
                 }
-                Ok(message) = rx.recv() => {
-                    debug!("Broadcasting message: {}", message);
-                    if let Err(e) = client_ws_sender_task.lock().await.send(Message::text(message)).await {
+                Ok(event) = rx.recv() => {
+                    debug!("Broadcasting event: {:?}", event);
+                    if let Err(e) = send_event(&client_ws_sender_task, protocol, &event).await {
                         error!("Failed to send message: {}", e);
                         let mut clients = clients_clone.lock().unwrap();
                         clients.remove(&client_id_clone);
@@ -105,54 +459,145 @@ pub async fn client_connection(
                         break;
                     }
                 }
+                // Системные сообщения только для этого пользователя
+                Ok(event) = user_rx.recv() => {
+                    if event.user_uuid != user_uuid_parsed {
+                        continue;
+                    }
+                    if let Err(e) = send_event(&client_ws_sender_task, protocol, &event.event).await {
+                        error!("Failed to send system message: {}", e);
+                        let mut clients = clients_clone.lock().unwrap();
+                        clients.remove(&client_id_clone);
+                        info!("Client disconnected with ID: {}, username: {}", client_id_clone, username_clone);
+                        break;
+                    }
+                }
             }
         }
     });
 
     while let Some(result) = client_ws_rcv.next().await {
-        let msg = if let Ok(msg) = result {
-            if msg.is_text() {
-                let msg_str = msg.to_str().unwrap().to_owned();
-                debug!("Received raw message: {}", msg_str);
-
-                match serde_json::from_str::<ClientMessage>(&msg_str) {
-                    Ok(client_message) => {
-                        debug!(
-                            "Received message from client {}: {}",
-                            username, client_message.message
-                        );
-
-                        if let Err(e) =
-                            save_message_to_db(&client_message.message, user_uuid_parsed).await
-                        {
-                            error!("Failed to save message to database: {}", e);
-                        }
-
-                        let formatted_message = format!("{}: {}", username, client_message.message);
-                        formatted_message
-                    }
-                    Err(e) => {
-                        error!("Failed to deserialize message: {}", e);
-                        continue;
-                    }
+        let msg = match result {
+            Ok(msg) => msg,
+            Err(_) => break,
+        };
+        if msg.is_close() {
+            info!(
+                "Client disconnected with ID: {}, username: {}",
+                client_id, username
+            );
+            let mut clients = clients.lock().unwrap();
+            clients.remove(&client_id);
+            break;
+        }
+        if !msg.is_text() {
+            continue;
+        }
+        let msg_str = msg.to_str().unwrap().to_owned();
+        debug!("Received raw message: {}", msg_str);
+
+        // Прежние клиенты присылают {message, ip} без type
+        let event = match serde_json::from_str::<ClientEvent>(&msg_str) {
+            Ok(event) => event,
+            Err(_) => match serde_json::from_str::<ClientMessage>(&msg_str) {
+                Ok(client_message) => ClientEvent::Message {
+                    message: client_message.message,
+                    reply_to: None,
+                    thread_root: None,
+                    attachments: Vec::new(),
+                },
+                Err(e) => {
+                    error!("Failed to deserialize message: {}", e);
+                    continue;
+                }
+            },
+        };
+
+        // Отметку прочтения ставит и токен только для чтения
+        if let ClientEvent::Read { message_id } = event {
+            if let Err(e) = apply_read(&sender, user_uuid_parsed, message_id).await {
+                let event = ChatEvent::Error {
+                    message: e.to_string(),
+                    retry_after: None,
+                };
+                if let Err(e) = send_event(&client_ws_sender, protocol, &event).await {
+                    error!("Failed to send error: {}", e);
                 }
-            } else if msg.is_close() {
-                info!(
-                    "Client disconnected with ID: {}, username: {}",
-                    client_id, username
-                );
-                let mut clients = clients.lock().unwrap();
-                clients.remove(&client_id);
-                break;
-            } else {
+            }
+            continue;
+        }
+
+        // Токен без chat:write может только читать
+        if !can_write {
+            debug!("Ignoring message from read-only client {}", username);
+            continue;
+        }
+
+        // Сообщения и реакции расходуют жетоны; при превышении клиент получает ошибку
+        if matches!(
+            event,
+            ClientEvent::Message { .. } | ClientEvent::React { .. } | ClientEvent::Unreact { .. }
+        ) {
+            if let Err(refusal) = flood.take_token(user_uuid_parsed, &mut bucket) {
+                debug!("Rate limited client {}: {}", username, refusal.message);
+                send_refusal(&client_ws_sender, protocol, refusal).await;
                 continue;
             }
-        } else {
-            break;
-        };
+        }
 
-        if let Err(e) = sender.lock().unwrap().send(msg) {
-            error!("Failed to send message to broadcast: {}", e);
+        let result: Result<(), Refusal> = match event {
+            ClientEvent::Message {
+                message,
+                reply_to,
+                thread_root,
+                attachments,
+            } => {
+                debug!("Received message from client {}: {}", username, message);
+                // Клиенты гасят индикатор по сообщению; можно снова сообщать, что печатает
+                last_typing = None;
+                publish_message(
+                    &sender,
+                    &flood,
+                    user_uuid_parsed,
+                    moderator,
+                    message,
+                    reply_to,
+                    thread_root,
+                    attachments,
+                )
+                .await
+            }
+            ClientEvent::React { message_id, emoji } => {
+                apply_reaction(&sender, user_uuid_parsed, message_id, &emoji, true)
+                    .await
+                    .map(drop)
+                    .map_err(|e| e.to_string().into())
+            }
+            ClientEvent::Unreact { message_id, emoji } => {
+                apply_reaction(&sender, user_uuid_parsed, message_id, &emoji, false)
+                    .await
+                    .map(drop)
+                    .map_err(|e| e.to_string().into())
+            }
+            ClientEvent::Typing { thread_root } => {
+                // Ничего не сохраняется; лишние события просто отбрасываются
+                if last_typing.is_some_and(|at| at.elapsed() < TYPING_THROTTLE) {
+                    continue;
+                }
+                last_typing = Some(Instant::now());
+                let event = ChatEvent::Typing {
+                    user_uuid: user_uuid_parsed,
+                    author: author.clone(),
+                    thread_root,
+                    expires_at: Utc::now() + chrono::Duration::seconds(TYPING_TTL_SECONDS),
+                };
+                let _ = sender.lock().unwrap().send(event);
+                Ok(())
+            }
+            ClientEvent::Read { .. } => Ok(()),
+        };
+        if let Err(refusal) = result {
+            send_refusal(&client_ws_sender, protocol, refusal).await;
         }
     }
 
@@ -0,0 +1,179 @@
+// src/handlers/devices.rs
+use crate::audit::{record, with_client_info, AuditEntry, ClientInfo};
+use crate::db::devices::{
+    delete_device, find_device_ips, find_devices_by_user_uuid, update_device,
+};
+use crate::db::sessions::find_session_by_session_id;
+use crate::middleware::auth::with_auth;
+use crate::models::{AuditAction, Device, DeviceIp};
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use std::error::Error as StdError;
+use uuid::Uuid;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+const MAX_DEVICE_NAME_LENGTH: usize = 64;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DeviceResponse {
+    pub message: String,
+}
+
+/// Устройство в списке пользователя
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DeviceInfo {
+    #[serde(flatten)]
+    pub device: Device,
+    /// С этого устройства отправлен текущий запрос
+    pub current: bool,
+    pub ip_history: Vec<DeviceIp>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UpdateDeviceRequest {
+    pub name: Option<String>,
+    pub trusted: bool,
+}
+
+fn device_response(message: &str, status: StatusCode) -> Response {
+    let response = DeviceResponse {
+        message: message.to_string(),
+    };
+    warp::reply::with_status(warp::reply::json(&response), status).into_response()
+}
+
+fn internal_error(message: &str, e: Box<dyn StdError + Send + Sync>) -> Response {
+    error!("{}: {}", message, e);
+    device_response(message, StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+pub async fn list_devices_handler(
+    user_uuid: Uuid,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    debug!("Received devices request for user_uuid: {}", user_uuid);
+
+    let devices = match find_devices_by_user_uuid(&user_uuid).await {
+        Ok(devices) => devices,
+        Err(e) => return Ok(internal_error("Failed to list devices.", e)),
+    };
+    let current_device = match client.session_id {
+        Some(session_id) => match find_session_by_session_id(&session_id).await {
+            Ok(session) => session.map(|session| session.device_id),
+            Err(e) => return Ok(internal_error("Failed to list devices.", e)),
+        },
+        None => None,
+    };
+
+    let mut response = Vec::with_capacity(devices.len());
+    for device in devices {
+        let ip_history = match find_device_ips(&device.device_id).await {
+            Ok(ip_history) => ip_history,
+            Err(e) => return Ok(internal_error("Failed to list devices.", e)),
+        };
+        response.push(DeviceInfo {
+            current: current_device == Some(device.device_id),
+            device,
+            ip_history,
+        });
+    }
+    Ok(warp::reply::json(&response).into_response())
+}
+
+pub async fn update_device_handler(
+    user_uuid: Uuid,
+    device_id: Uuid,
+    request: UpdateDeviceRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    let name = request
+        .name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty());
+    if name
+        .as_ref()
+        .is_some_and(|name| name.chars().count() > MAX_DEVICE_NAME_LENGTH)
+    {
+        return Ok(device_response(
+            &format!(
+                "Device name must be at most {} characters",
+                MAX_DEVICE_NAME_LENGTH
+            ),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    match update_device(&device_id, &user_uuid, name.as_deref(), request.trusted).await {
+        Ok(true) => {
+            info!("Device {} updated by user_uuid: {}", device_id, user_uuid);
+            let entry = AuditEntry::own(AuditAction::DeviceUpdated, user_uuid)
+                .with_target(if request.trusted {
+                    "trusted"
+                } else {
+                    "untrusted"
+                })
+                .with_device(device_id);
+            record(entry, &client).await;
+            Ok(device_response("Device updated.", StatusCode::OK))
+        }
+        Ok(false) => Ok(device_response("Device not found.", StatusCode::NOT_FOUND)),
+        Err(e) => Ok(internal_error("Failed to update device.", e)),
+    }
+}
+
+/// Забыть устройство: его сессии завершаются, следующий вход с него — как с нового
+pub async fn delete_device_handler(
+    user_uuid: Uuid,
+    device_id: Uuid,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    match delete_device(&device_id, &user_uuid).await {
+        Ok(true) => {
+            info!("Device {} removed by user_uuid: {}", device_id, user_uuid);
+            let entry =
+                AuditEntry::own(AuditAction::DeviceRemoved, user_uuid).with_device(device_id);
+            record(entry, &client).await;
+            Ok(device_response("Device removed.", StatusCode::OK))
+        }
+        Ok(false) => Ok(device_response("Device not found.", StatusCode::NOT_FOUND)),
+        Err(e) => Ok(internal_error("Failed to remove device.", e)),
+    }
+}
+
+pub fn devices_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let list = warp::path!("api" / "devices")
+        .and(warp::get())
+        .and(with_auth())
+        .and(with_client_info())
+        .and_then(|user_uuid: Uuid, client: ClientInfo| async move {
+            list_devices_handler(user_uuid, client).await
+        });
+
+    let update =
+        warp::path!("api" / "devices" / Uuid)
+            .and(warp::put())
+            .and(with_auth())
+            .and(warp::body::json())
+            .and(with_client_info())
+            .and_then(
+                |device_id: Uuid,
+                 user_uuid: Uuid,
+                 request: UpdateDeviceRequest,
+                 client: ClientInfo| async move {
+                    update_device_handler(user_uuid, device_id, request, client).await
+                },
+            );
+
+    let delete = warp::path!("api" / "devices" / Uuid)
+        .and(warp::delete())
+        .and(with_auth())
+        .and(with_client_info())
+        .and_then(
+            |device_id: Uuid, user_uuid: Uuid, client: ClientInfo| async move {
+                delete_device_handler(user_uuid, device_id, client).await
+            },
+        );
+
+    list.or(update).unify().or(delete).unify()
+}
@@ -1,9 +1,29 @@
 // src/handlers/files.rs
-use crate::db::files::get_files_by_user_uuid;
-use crate::models::FileInfo;
+use crate::attachments::{file_path, has_thumbnail, is_image, make_thumbnail, mime_type};
+use crate::db::files::{find_readable_file, get_files_by_user_uuid};
+use crate::models::{File, FileInfo, Scope};
 use log::{debug, error};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use tokio_util::io::ReaderStream;
 use uuid::Uuid;
-use warp::{reply::Json, Filter, Rejection};
+use warp::http::header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE};
+use warp::hyper::Body;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Json, reply::Response, Filter, Rejection};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct FileResponse {
+    pub message: String,
+}
+
+fn file_response(message: &str, status: StatusCode) -> Response {
+    let response = FileResponse {
+        message: message.to_string(),
+    };
+    warp::reply::with_status(warp::reply::json(&response), status).into_response()
+}
+
 pub async fn get_files_handler(user_uuid: Uuid) -> Result<Json, Rejection> {
     debug!("Received request for files for user_uuid: {}", user_uuid);
 
@@ -19,9 +39,127 @@ pub async fn get_files_handler(user_uuid: Uuid) -> Result<Json, Rejection> {
     }
 }
 
-pub fn files_route() -> impl Filter<Extract = (Json,), Error = Rejection> + Clone {
-    warp::path("api")
+/// Файл, который пользователь может читать; иначе готовый ответ 404
+async fn readable_file(user_uuid: Uuid, file_id: Uuid) -> Result<File, Response> {
+    match find_readable_file(&user_uuid, &file_id).await {
+        Ok(Some(file)) => Ok(file),
+        Ok(None) => Err(file_response("File not found", StatusCode::NOT_FOUND)),
+        Err(e) => {
+            error!("Failed to find file {}: {}", file_id, e);
+            Err(file_response(
+                "Failed to load file",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ))
+        }
+    }
+}
+
+/// Картинки открываются в браузере, остальное — только скачивается
+fn content_disposition(file: &File) -> String {
+    let disposition = if is_image(file) { "inline" } else { "attachment" };
+    format!(
+        "{}; filename*=UTF-8''{}",
+        disposition,
+        utf8_percent_encode(&file.filename, NON_ALPHANUMERIC)
+    )
+}
+
+/// Содержимое файла: свой файл или вложенный в видимое сообщение
+pub async fn download_file_handler(file_id: Uuid, user_uuid: Uuid) -> Result<Response, Rejection> {
+    let file = match readable_file(user_uuid, file_id).await {
+        Ok(file) => file,
+        Err(response) => return Ok(response),
+    };
+    let disk_file = match tokio::fs::File::open(file_path(&file.file_id)).await {
+        Ok(disk_file) => disk_file,
+        Err(e) => {
+            error!("File {} is missing on disk: {}", file_id, e);
+            return Ok(file_response("File not found", StatusCode::NOT_FOUND));
+        }
+    };
+    let length = disk_file.metadata().await.map(|metadata| metadata.len()).ok();
+
+    let mut response = Response::new(Body::wrap_stream(ReaderStream::new(disk_file)));
+    let headers = response.headers_mut();
+    if let Ok(value) = mime_type(&file).parse() {
+        headers.insert(CONTENT_TYPE, value);
+    }
+    if let Some(length) = length {
+        headers.insert(CONTENT_LENGTH, length.into());
+    }
+    if let Ok(value) = content_disposition(&file).parse() {
+        headers.insert(CONTENT_DISPOSITION, value);
+    }
+    headers.insert(CACHE_CONTROL, "private, max-age=3600".parse().unwrap());
+    headers.insert("x-content-type-options", "nosniff".parse().unwrap());
+    Ok(response)
+}
+
+/// Уменьшенная картинка в PNG; у остальных файлов миниатюры нет
+pub async fn thumbnail_handler(file_id: Uuid, user_uuid: Uuid) -> Result<Response, Rejection> {
+    let file = match readable_file(user_uuid, file_id).await {
+        Ok(file) => file,
+        Err(response) => return Ok(response),
+    };
+    if !has_thumbnail(&file) {
+        return Ok(file_response(
+            "Thumbnail not available",
+            StatusCode::NOT_FOUND,
+        ));
+    }
+    let bytes = match tokio::fs::read(file_path(&file.file_id)).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("File {} is missing on disk: {}", file_id, e);
+            return Ok(file_response("File not found", StatusCode::NOT_FOUND));
+        }
+    };
+
+    // Разбор картинки занимает процессор — не держим им поток обработки запросов
+    let thumbnail = match tokio::task::spawn_blocking(move || make_thumbnail(&bytes)).await {
+        Ok(Ok(thumbnail)) => thumbnail,
+        Ok(Err(e)) => {
+            debug!("Failed to make thumbnail of file {}: {}", file_id, e);
+            return Ok(file_response(
+                "File is not a supported image",
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ));
+        }
+        Err(e) => {
+            error!("Thumbnail task failed: {}", e);
+            return Ok(file_response(
+                "Failed to make thumbnail",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+    };
+    let reply = warp::reply::with_header(thumbnail, CONTENT_TYPE, "image/png");
+    let reply = warp::reply::with_header(reply, CACHE_CONTROL, "private, max-age=86400");
+    Ok(warp::reply::with_header(reply, "x-content-type-options", "nosniff").into_response())
+}
+
+pub fn files_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let list = warp::path("api")
         .and(warp::path("files"))
-        .and(crate::middleware::auth::with_auth())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(crate::middleware::auth::with_scope(Scope::FilesRead))
         .and_then(|user_uuid: Uuid| async move { get_files_handler(user_uuid).await })
+        .map(Reply::into_response);
+
+    let download = warp::path!("api" / "files" / Uuid)
+        .and(warp::get())
+        .and(crate::middleware::auth::with_scope(Scope::FilesRead))
+        .and_then(|file_id: Uuid, user_uuid: Uuid| async move {
+            download_file_handler(file_id, user_uuid).await
+        });
+
+    let thumbnail = warp::path!("api" / "files" / Uuid / "thumbnail")
+        .and(warp::get())
+        .and(crate::middleware::auth::with_scope(Scope::FilesRead))
+        .and_then(|file_id: Uuid, user_uuid: Uuid| async move {
+            thumbnail_handler(file_id, user_uuid).await
+        });
+
+    list.or(download).unify().or(thumbnail).unify()
 }
@@ -1,5 +1,17 @@
+pub mod account;
+pub mod admin;
+pub mod audit;
 pub mod auth;
 pub mod chat;
+pub mod devices;
 pub mod files;
+pub mod moderation;
+pub mod notifications;
+pub mod oauth;
 pub mod profile;
+pub mod push;
+pub mod reactions;
+pub mod read_markers;
+pub mod search;
+pub mod threads;
 pub mod upload;
@@ -0,0 +1,180 @@
+// src/handlers/moderation.rs
+use crate::db::messages::delete_message;
+use crate::db::threads::find_message_thread;
+use crate::flood::{FloodState, MAX_SLOW_MODE_DURATION, MAX_SLOW_MODE_INTERVAL};
+use crate::middleware::auth::with_permission;
+use crate::models::{ChatEvent, Permission, SlowModeQuery, SlowModeRequest};
+use crate::Sender;
+use log::{error, info};
+use std::time::Duration;
+use uuid::Uuid;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+fn message_response(message: &str, status: StatusCode) -> Response {
+    warp::reply::with_status(warp::reply::json(&message), status).into_response()
+}
+
+pub async fn delete_message_handler(
+    moderator_uuid: Uuid,
+    message_id: i64,
+) -> Result<Response, Rejection> {
+    match delete_message(message_id, moderator_uuid).await {
+        Ok(true) => {
+            info!(
+                "Message {} deleted by moderator {}",
+                message_id, moderator_uuid
+            );
+            Ok(
+                warp::reply::with_status(warp::reply::json(&"Message deleted"), StatusCode::OK)
+                    .into_response(),
+            )
+        }
+        Ok(false) => Ok(warp::reply::with_status(
+            warp::reply::json(&"Message not found"),
+            StatusCode::NOT_FOUND,
+        )
+        .into_response()),
+        Err(e) => {
+            error!("Failed to delete message: {}", e);
+            Ok(warp::reply::with_status(
+                warp::reply::json(&"Failed to delete message"),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            )
+            .into_response())
+        }
+    }
+}
+
+/// Лента (None) или ветка для медленного режима. Ответ из ветки означает её корень
+async fn slow_mode_room(thread_root: Option<i64>) -> Result<Option<i64>, Response> {
+    let Some(message_id) = thread_root else {
+        return Ok(None);
+    };
+    match find_message_thread(message_id).await {
+        Ok(Some(root)) => Ok(Some(root.unwrap_or(message_id))),
+        Ok(None) => Err(message_response("Thread not found", StatusCode::NOT_FOUND)),
+        Err(e) => {
+            error!("Failed to find message {}: {}", message_id, e);
+            Err(message_response(
+                "Failed to update slow mode",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ))
+        }
+    }
+}
+
+/// Включает медленный режим на время и сообщает о нём всем в чате
+pub async fn set_slow_mode_handler(
+    sender: Sender,
+    flood: FloodState,
+    moderator_uuid: Uuid,
+    request: SlowModeRequest,
+) -> Result<Response, Rejection> {
+    let interval = Duration::from_secs(request.interval_seconds);
+    let duration = Duration::from_secs(request.duration_seconds);
+    if interval.is_zero() || interval > MAX_SLOW_MODE_INTERVAL {
+        return Ok(message_response(
+            &format!(
+                "Interval must be between 1 and {} seconds",
+                MAX_SLOW_MODE_INTERVAL.as_secs()
+            ),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    if duration.is_zero() || duration > MAX_SLOW_MODE_DURATION {
+        return Ok(message_response(
+            &format!(
+                "Duration must be between 1 and {} seconds",
+                MAX_SLOW_MODE_DURATION.as_secs()
+            ),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    let room = match slow_mode_room(request.thread_root).await {
+        Ok(room) => room,
+        Err(response) => return Ok(response),
+    };
+
+    let status = flood.set_slow_mode(room, interval, duration);
+    info!(
+        "Slow mode in {:?} set to {:?} for {:?} by moderator {}",
+        room, interval, duration, moderator_uuid
+    );
+    let _ = sender.lock().unwrap().send(ChatEvent::SlowMode(status.clone()));
+    Ok(warp::reply::json(&status).into_response())
+}
+
+/// Выключает медленный режим раньше срока
+pub async fn clear_slow_mode_handler(
+    sender: Sender,
+    flood: FloodState,
+    moderator_uuid: Uuid,
+    query: SlowModeQuery,
+) -> Result<Response, Rejection> {
+    let room = match slow_mode_room(query.thread_root).await {
+        Ok(room) => room,
+        Err(response) => return Ok(response),
+    };
+    match flood.clear_slow_mode(room) {
+        Some(status) => {
+            info!("Slow mode in {:?} cleared by moderator {}", room, moderator_uuid);
+            let _ = sender.lock().unwrap().send(ChatEvent::SlowMode(status));
+            Ok(message_response("Slow mode disabled", StatusCode::OK))
+        }
+        None => Ok(message_response("Slow mode is not enabled", StatusCode::NOT_FOUND)),
+    }
+}
+
+pub fn moderation_route(
+    sender: Sender,
+    flood: FloodState,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let with_sender = warp::any().map(move || sender.clone());
+    let with_flood = warp::any().map(move || flood.clone());
+
+    let delete = warp::path!("api" / "moderation" / "messages" / i64)
+        .and(warp::delete())
+        .and(with_permission(Permission::ModerateChat))
+        .and_then(|message_id: i64, moderator_uuid: Uuid| async move {
+            delete_message_handler(moderator_uuid, message_id).await
+        });
+
+    let list_slow_modes = warp::path!("api" / "moderation" / "slow-mode")
+        .and(warp::get())
+        .and(with_permission(Permission::ModerateChat))
+        .and(with_flood.clone())
+        .map(|_: Uuid, flood: FloodState| warp::reply::json(&flood.active_slow_modes()).into_response());
+
+    let set = warp::path!("api" / "moderation" / "slow-mode")
+        .and(warp::put())
+        .and(with_permission(Permission::ModerateChat))
+        .and(warp::body::json())
+        .and(with_sender.clone())
+        .and(with_flood.clone())
+        .and_then(
+            |moderator_uuid: Uuid, request: SlowModeRequest, sender: Sender, flood: FloodState| async move {
+                set_slow_mode_handler(sender, flood, moderator_uuid, request).await
+            },
+        );
+
+    let clear = warp::path!("api" / "moderation" / "slow-mode")
+        .and(warp::delete())
+        .and(with_permission(Permission::ModerateChat))
+        .and(warp::query::<SlowModeQuery>())
+        .and(with_sender)
+        .and(with_flood)
+        .and_then(
+            |moderator_uuid: Uuid, query: SlowModeQuery, sender: Sender, flood: FloodState| async move {
+                clear_slow_mode_handler(sender, flood, moderator_uuid, query).await
+            },
+        );
+
+    delete
+        .or(list_slow_modes)
+        .unify()
+        .or(set)
+        .unify()
+        .or(clear)
+        .unify()
+}
@@ -0,0 +1,280 @@
+// src/handlers/notifications.rs
+//
+// Центр уведомлений: список, отметки прочтения, очистка и настройки по видам.
+// Открытые чаты пользователя узнают о новом числе непрочитанных сразу.
+use crate::db::notifications::{
+    clear_notifications, count_unread_notifications, delete_notification,
+    list_notification_preferences, list_notifications, mark_all_notifications_read,
+    mark_notification_read, save_notification_preference,
+};
+use crate::middleware::auth::with_auth;
+use crate::models::{ChatEvent, NotificationKind, NotificationPreferenceUpdate};
+use crate::notifications::send_to_user;
+use chrono::{DateTime, Utc};
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 100;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NotificationResponse {
+    pub message: String,
+}
+
+/// Фильтры списка уведомлений
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct NotificationsQuery {
+    /// Только непрочитанные
+    pub unread: Option<bool>,
+    pub kind: Option<NotificationKind>,
+    /// Созданные раньше этого времени (следующая страница)
+    pub before: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+/// Что очистить: все уведомления или только прочитанные
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct ClearQuery {
+    pub read: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UnreadCountResponse {
+    pub unread_count: i64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UpdatedResponse {
+    pub updated: u64,
+    pub unread_count: i64,
+}
+
+fn notification_response(message: &str, status: StatusCode) -> Response {
+    let response = NotificationResponse {
+        message: message.to_string(),
+    };
+    warp::reply::with_status(warp::reply::json(&response), status).into_response()
+}
+
+fn internal_error(message: &str, e: impl std::fmt::Display) -> Response {
+    error!("{}: {}", message, e);
+    notification_response(message, StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Сообщает открытым чатам пользователя новое число непрочитанных
+async fn publish_unread_count(user_uuid: Uuid) -> Result<i64, Response> {
+    let unread_count = count_unread_notifications(&user_uuid)
+        .await
+        .map_err(|e| internal_error("Failed to count notifications", e))?;
+    send_to_user(user_uuid, ChatEvent::NotificationCount { unread_count });
+    Ok(unread_count)
+}
+
+/// Уведомления пользователя, новые сначала
+pub async fn list_notifications_handler(
+    user_uuid: Uuid,
+    query: NotificationsQuery,
+) -> Result<Response, Rejection> {
+    debug!(
+        "Received notifications request for user_uuid: {}",
+        user_uuid
+    );
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    match list_notifications(
+        &user_uuid,
+        query.unread.unwrap_or(false),
+        query.kind,
+        query.before,
+        limit,
+    )
+    .await
+    {
+        Ok(notifications) => Ok(warp::reply::json(&notifications).into_response()),
+        Err(e) => Ok(internal_error("Failed to list notifications.", e)),
+    }
+}
+
+pub async fn unread_count_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
+    match count_unread_notifications(&user_uuid).await {
+        Ok(unread_count) => {
+            Ok(warp::reply::json(&UnreadCountResponse { unread_count }).into_response())
+        }
+        Err(e) => Ok(internal_error("Failed to count notifications", e)),
+    }
+}
+
+pub async fn mark_read_handler(
+    notification_id: Uuid,
+    user_uuid: Uuid,
+) -> Result<Response, Rejection> {
+    let notification = match mark_notification_read(&user_uuid, &notification_id).await {
+        Ok(Some(notification)) => notification,
+        Ok(None) => {
+            return Ok(notification_response(
+                "Notification not found",
+                StatusCode::NOT_FOUND,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to update notification", e)),
+    };
+    if let Err(response) = publish_unread_count(user_uuid).await {
+        return Ok(response);
+    }
+    Ok(warp::reply::json(&notification).into_response())
+}
+
+pub async fn mark_all_read_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
+    let updated = match mark_all_notifications_read(&user_uuid).await {
+        Ok(updated) => updated,
+        Err(e) => return Ok(internal_error("Failed to update notifications", e)),
+    };
+    match publish_unread_count(user_uuid).await {
+        Ok(unread_count) => Ok(warp::reply::json(&UpdatedResponse {
+            updated,
+            unread_count,
+        })
+        .into_response()),
+        Err(response) => Ok(response),
+    }
+}
+
+pub async fn delete_notification_handler(
+    notification_id: Uuid,
+    user_uuid: Uuid,
+) -> Result<Response, Rejection> {
+    match delete_notification(&user_uuid, &notification_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Ok(notification_response(
+                "Notification not found",
+                StatusCode::NOT_FOUND,
+            ))
+        }
+        Err(e) => return Ok(internal_error("Failed to delete notification", e)),
+    }
+    if let Err(response) = publish_unread_count(user_uuid).await {
+        return Ok(response);
+    }
+    Ok(notification_response(
+        "Notification deleted",
+        StatusCode::OK,
+    ))
+}
+
+pub async fn clear_notifications_handler(
+    user_uuid: Uuid,
+    query: ClearQuery,
+) -> Result<Response, Rejection> {
+    let updated = match clear_notifications(&user_uuid, query.read.unwrap_or(false)).await {
+        Ok(deleted) => deleted,
+        Err(e) => return Ok(internal_error("Failed to clear notifications", e)),
+    };
+    match publish_unread_count(user_uuid).await {
+        Ok(unread_count) => Ok(warp::reply::json(&UpdatedResponse {
+            updated,
+            unread_count,
+        })
+        .into_response()),
+        Err(response) => Ok(response),
+    }
+}
+
+pub async fn preferences_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
+    match list_notification_preferences(&user_uuid).await {
+        Ok(preferences) => Ok(warp::reply::json(&preferences).into_response()),
+        Err(e) => Ok(internal_error("Failed to load notification preferences", e)),
+    }
+}
+
+/// Сохраняет переданные настройки; остальные виды не меняются
+pub async fn update_preferences_handler(
+    user_uuid: Uuid,
+    preferences: Vec<NotificationPreferenceUpdate>,
+) -> Result<Response, Rejection> {
+    for preference in &preferences {
+        if let Err(e) = save_notification_preference(&user_uuid, preference).await {
+            return Ok(internal_error("Failed to save notification preferences", e));
+        }
+    }
+    preferences_handler(user_uuid).await
+}
+
+pub fn notifications_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let list = warp::path!("api" / "notifications")
+        .and(warp::get())
+        .and(with_auth())
+        .and(warp::query::<NotificationsQuery>())
+        .and_then(|user_uuid: Uuid, query: NotificationsQuery| async move {
+            list_notifications_handler(user_uuid, query).await
+        });
+
+    let unread_count = warp::path!("api" / "notifications" / "unread-count")
+        .and(warp::get())
+        .and(with_auth())
+        .and_then(|user_uuid: Uuid| async move { unread_count_handler(user_uuid).await });
+
+    let mark_read = warp::path!("api" / "notifications" / Uuid / "read")
+        .and(warp::put())
+        .and(with_auth())
+        .and_then(|notification_id: Uuid, user_uuid: Uuid| async move {
+            mark_read_handler(notification_id, user_uuid).await
+        });
+
+    let mark_all_read = warp::path!("api" / "notifications" / "read")
+        .and(warp::put())
+        .and(with_auth())
+        .and_then(|user_uuid: Uuid| async move { mark_all_read_handler(user_uuid).await });
+
+    let delete = warp::path!("api" / "notifications" / Uuid)
+        .and(warp::delete())
+        .and(with_auth())
+        .and_then(|notification_id: Uuid, user_uuid: Uuid| async move {
+            delete_notification_handler(notification_id, user_uuid).await
+        });
+
+    let clear = warp::path!("api" / "notifications")
+        .and(warp::delete())
+        .and(with_auth())
+        .and(warp::query::<ClearQuery>())
+        .and_then(|user_uuid: Uuid, query: ClearQuery| async move {
+            clear_notifications_handler(user_uuid, query).await
+        });
+
+    let preferences = warp::path!("api" / "notifications" / "preferences")
+        .and(warp::get())
+        .and(with_auth())
+        .and_then(|user_uuid: Uuid| async move { preferences_handler(user_uuid).await });
+
+    let update_preferences = warp::path!("api" / "notifications" / "preferences")
+        .and(warp::put())
+        .and(with_auth())
+        .and(warp::body::json())
+        .and_then(
+            |user_uuid: Uuid, preferences: Vec<NotificationPreferenceUpdate>| async move {
+                update_preferences_handler(user_uuid, preferences).await
+            },
+        );
+
+    list.or(unread_count)
+        .unify()
+        .or(mark_read)
+        .unify()
+        .or(mark_all_read)
+        .unify()
+        .or(delete)
+        .unify()
+        .or(clear)
+        .unify()
+        .or(preferences)
+        .unify()
+        .or(update_preferences)
+        .unify()
+}
@@ -0,0 +1,861 @@
+// src/handlers/oauth.rs
+//
+// Провайдер OAuth2 / OpenID Connect: регистрация приложений, код авторизации
+// с PKCE, согласие пользователя, выдача ID-токенов и /oauth/userinfo.
+use crate::db::oauth::{
+    consume_authorization_code, delete_oauth_access_tokens, delete_oauth_client, find_consent,
+    find_oauth_access_token, find_oauth_client, find_used_authorization_code, list_oauth_clients,
+    save_authorization_code, save_consent, save_oauth_access_token, save_oauth_client,
+};
+use crate::db::profiles::get_profile_by_user_uuid;
+use crate::db::users::find_user_by_uuid;
+use crate::middleware::auth::{with_auth, with_permission};
+use crate::models::{AuthorizationCode, OAuthAccessToken, OAuthClient, Permission};
+use crate::oidc::{
+    hash_secret, id_token_claims, issuer, jwks, normalize_scope, sign_id_token, verify_pkce,
+    SUPPORTED_SCOPES,
+};
+use chrono::{Duration, Utc};
+use log::{debug, error, info, warn};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::error::Error as StdError;
+use uuid::Uuid;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+const AUTHORIZATION_CODE_TTL_SECONDS: i64 = 300;
+const ACCESS_TOKEN_TTL_SECONDS: i64 = 3600;
+const ID_TOKEN_TTL_SECONDS: i64 = 3600;
+const MAX_REDIRECT_URIS: usize = 10;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OAuthResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CreateOAuthClientRequest {
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    #[serde(default = "default_confidential")]
+    pub confidential: bool,
+}
+
+fn default_confidential() -> bool {
+    true
+}
+
+/// Ответ на регистрацию: секрет показывается только здесь
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CreatedOAuthClientResponse {
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub confidential: bool,
+}
+
+/// Параметры запроса авторизации (RFC 6749, 4.1.1 и RFC 7636)
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct AuthorizeQuery {
+    pub response_type: Option<String>,
+    pub client_id: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+/// Решение пользователя на странице согласия
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ConsentRequest {
+    #[serde(flatten)]
+    pub query: AuthorizeQuery,
+    pub approve: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ConsentInfoResponse {
+    pub client_id: String,
+    pub client_name: String,
+    pub scopes: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ConsentDecisionResponse {
+    pub redirect_to: String,
+}
+
+/// Запрос к /oauth/token (application/x-www-form-urlencoded)
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TokenRequest {
+    pub grant_type: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub code_verifier: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub scope: String,
+    pub id_token: String,
+}
+
+/// Проверенный запрос авторизации
+struct AuthorizationRequest {
+    client: OAuthClient,
+    redirect_uri: String,
+    scopes: Vec<String>,
+    state: Option<String>,
+    nonce: Option<String>,
+    code_challenge: String,
+}
+
+/// Ошибка запроса авторизации: либо ответить самим, либо вернуть в приложение
+enum AuthorizeError {
+    /// Приложение или redirect_uri не подтверждены — перенаправлять нельзя
+    Invalid(&'static str),
+    /// Адрес возврата с `error` и `state`
+    Redirect(String),
+    Internal(Box<dyn StdError + Send + Sync>),
+}
+
+fn oauth_response(message: &str, status: StatusCode) -> Response {
+    let response = OAuthResponse {
+        message: message.to_string(),
+    };
+    warp::reply::with_status(warp::reply::json(&response), status).into_response()
+}
+
+fn internal_error(message: &str, e: Box<dyn StdError + Send + Sync>) -> Response {
+    error!("{}: {}", message, e);
+    oauth_response(message, StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn redirect(location: &str) -> Response {
+    warp::reply::with_header(StatusCode::FOUND, "location", location).into_response()
+}
+
+/// Ответ эндпоинтов токенов: кешировать нельзя
+fn no_store(reply: impl Reply) -> Response {
+    let reply = warp::reply::with_header(reply, "cache-control", "no-store");
+    warp::reply::with_header(reply, "pragma", "no-cache").into_response()
+}
+
+/// Ошибка /oauth/token в формате RFC 6749, 5.2
+fn token_error(error: &str, description: &str, status: StatusCode) -> Response {
+    let body = json!({ "error": error, "error_description": description });
+    no_store(warp::reply::with_status(warp::reply::json(&body), status))
+}
+
+fn random_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    data_encoding::BASE64URL_NOPAD.encode(&bytes)
+}
+
+/// Добавляет параметры к адресу возврата, сохраняя его собственный query
+fn with_params(uri: &str, params: &[(&str, &str)]) -> String {
+    let query = serde_urlencoded::to_string(params).unwrap_or_default();
+    let separator = if uri.contains('?') { '&' } else { '?' };
+    format!("{}{}{}", uri, separator, query)
+}
+
+fn error_redirect(redirect_uri: &str, error: &str, state: Option<&str>) -> String {
+    let mut params = vec![("error", error)];
+    if let Some(state) = state {
+        params.push(("state", state));
+    }
+    with_params(redirect_uri, &params)
+}
+
+/// Адрес возврата: https, либо http только для локальной разработки; без фрагмента
+fn is_valid_redirect_uri(uri: &str) -> bool {
+    let rest = if let Some(rest) = uri.strip_prefix("https://") {
+        rest
+    } else if let Some(rest) = uri.strip_prefix("http://") {
+        let host = rest.split(['/', '?']).next().unwrap_or_default();
+        let host = host.rsplit_once(':').map_or(host, |(host, _)| host);
+        if host != "localhost" && host != "127.0.0.1" {
+            return false;
+        }
+        rest
+    } else {
+        return false;
+    };
+    !rest.is_empty()
+        && !rest.starts_with('/')
+        && !uri.contains('#')
+        && !uri.contains(char::is_whitespace)
+}
+
+/// Проверяет запрос авторизации по RFC 6749 (4.1.2.1): ошибки до проверки
+/// redirect_uri возвращаются пользователю, остальные — в приложение
+async fn validate_authorization(
+    query: &AuthorizeQuery,
+) -> Result<AuthorizationRequest, AuthorizeError> {
+    let Some(client_id) = query.client_id.as_deref() else {
+        return Err(AuthorizeError::Invalid("client_id is required"));
+    };
+    let client = match find_oauth_client(client_id).await {
+        Ok(Some(client)) => client,
+        Ok(None) => return Err(AuthorizeError::Invalid("Unknown client_id")),
+        Err(e) => return Err(AuthorizeError::Internal(e)),
+    };
+    let Some(redirect_uri) = query.redirect_uri.clone() else {
+        return Err(AuthorizeError::Invalid("redirect_uri is required"));
+    };
+    if !client.redirect_uris.contains(&redirect_uri) {
+        return Err(AuthorizeError::Invalid(
+            "redirect_uri is not registered for this client",
+        ));
+    }
+
+    let state = query.state.as_deref();
+    if query.response_type.as_deref() != Some("code") {
+        return Err(AuthorizeError::Redirect(error_redirect(
+            &redirect_uri,
+            "unsupported_response_type",
+            state,
+        )));
+    }
+    // PKCE обязателен для всех приложений, и только S256
+    let code_challenge = match (
+        query.code_challenge.as_deref(),
+        query.code_challenge_method.as_deref(),
+    ) {
+        (Some(challenge), Some("S256")) if challenge.len() == 43 => challenge.to_string(),
+        _ => {
+            return Err(AuthorizeError::Redirect(error_redirect(
+                &redirect_uri,
+                "invalid_request",
+                state,
+            )))
+        }
+    };
+    let scopes = normalize_scope(query.scope.as_deref().unwrap_or_default());
+    if !scopes.iter().any(|scope| scope == "openid") {
+        return Err(AuthorizeError::Redirect(error_redirect(
+            &redirect_uri,
+            "invalid_scope",
+            state,
+        )));
+    }
+
+    Ok(AuthorizationRequest {
+        client,
+        redirect_uri,
+        scopes,
+        state: query.state.clone(),
+        nonce: query.nonce.clone(),
+        code_challenge,
+    })
+}
+
+/// Выдаёт код и возвращает адрес возврата в приложение
+async fn issue_authorization_code(
+    request: &AuthorizationRequest,
+    user_uuid: Uuid,
+) -> Result<String, Box<dyn StdError + Send + Sync>> {
+    let code = random_secret();
+    let now = Utc::now();
+    let authorization_code = AuthorizationCode {
+        client_id: request.client.client_id.clone(),
+        user_uuid,
+        redirect_uri: request.redirect_uri.clone(),
+        scope: request.scopes.join(" "),
+        nonce: request.nonce.clone(),
+        code_challenge: request.code_challenge.clone(),
+        auth_time: now,
+        expires_at: now + Duration::seconds(AUTHORIZATION_CODE_TTL_SECONDS),
+    };
+    save_authorization_code(&hash_secret(&code), &authorization_code).await?;
+
+    info!(
+        "Authorization code issued to client {} for user_uuid: {}",
+        request.client.client_id, user_uuid
+    );
+    let mut params = vec![("code", code.as_str())];
+    if let Some(state) = request.state.as_deref() {
+        params.push(("state", state));
+    }
+    Ok(with_params(&request.redirect_uri, &params))
+}
+
+/// Согласие уже дано на все запрошенные области
+async fn has_consent(
+    user_uuid: &Uuid,
+    request: &AuthorizationRequest,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let granted = find_consent(user_uuid, &request.client.client_id).await?;
+    Ok(granted.is_some_and(|granted| {
+        let granted: Vec<&str> = granted.split_whitespace().collect();
+        request
+            .scopes
+            .iter()
+            .all(|scope| granted.contains(&scope.as_str()))
+    }))
+}
+
+pub async fn discovery_handler() -> Result<Response, Rejection> {
+    let issuer = issuer();
+    let document = json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{}/oauth/authorize", issuer),
+        "token_endpoint": format!("{}/oauth/token", issuer),
+        "userinfo_endpoint": format!("{}/oauth/userinfo", issuer),
+        "jwks_uri": format!("{}/oauth/jwks", issuer),
+        "scopes_supported": SUPPORTED_SCOPES,
+        "response_types_supported": ["code"],
+        "grant_types_supported": ["authorization_code"],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": ["RS256"],
+        "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post", "none"],
+        "code_challenge_methods_supported": ["S256"],
+        "claims_supported": ["sub", "iss", "aud", "exp", "iat", "auth_time", "nonce", "preferred_username", "picture", "email", "email_verified"],
+    });
+    Ok(warp::reply::json(&document).into_response())
+}
+
+pub async fn jwks_handler() -> Result<Response, Rejection> {
+    Ok(warp::reply::json(&jwks()).into_response())
+}
+
+pub async fn create_client_handler(
+    admin_uuid: Uuid,
+    request: CreateOAuthClientRequest,
+) -> Result<Response, Rejection> {
+    debug!("Received OAuth client registration from: {}", admin_uuid);
+
+    let name = request.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > 64 {
+        return Ok(oauth_response(
+            "Client name must be between 1 and 64 characters",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    if request.redirect_uris.is_empty() || request.redirect_uris.len() > MAX_REDIRECT_URIS {
+        return Ok(oauth_response(
+            &format!(
+                "Between 1 and {} redirect URIs are required",
+                MAX_REDIRECT_URIS
+            ),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    if let Some(uri) = request
+        .redirect_uris
+        .iter()
+        .find(|uri| !is_valid_redirect_uri(uri))
+    {
+        return Ok(oauth_response(
+            &format!("Invalid redirect URI: {}", uri),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let client_secret = request.confidential.then(random_secret);
+    let client = OAuthClient {
+        client_id: Uuid::new_v4().simple().to_string(),
+        client_secret_hash: client_secret.as_deref().map(hash_secret),
+        name,
+        redirect_uris: request.redirect_uris,
+        confidential: request.confidential,
+        created_at: Utc::now(),
+    };
+    if let Err(e) = save_oauth_client(&client, &admin_uuid).await {
+        return Ok(internal_error("Failed to register OAuth client.", e));
+    }
+
+    info!(
+        "OAuth client {} registered by {}",
+        client.client_id, admin_uuid
+    );
+    let response = CreatedOAuthClientResponse {
+        client_id: client.client_id,
+        client_secret,
+        name: client.name,
+        redirect_uris: client.redirect_uris,
+        confidential: client.confidential,
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&response), StatusCode::CREATED).into_response())
+}
+
+pub async fn list_clients_handler() -> Result<Response, Rejection> {
+    match list_oauth_clients().await {
+        Ok(clients) => Ok(warp::reply::json(&clients).into_response()),
+        Err(e) => Ok(internal_error("Failed to list OAuth clients.", e)),
+    }
+}
+
+pub async fn delete_client_handler(
+    admin_uuid: Uuid,
+    client_id: String,
+) -> Result<Response, Rejection> {
+    match delete_oauth_client(&client_id).await {
+        Ok(true) => {
+            info!("OAuth client {} deleted by {}", client_id, admin_uuid);
+            Ok(oauth_response("OAuth client deleted.", StatusCode::OK))
+        }
+        Ok(false) => Ok(oauth_response(
+            "OAuth client not found.",
+            StatusCode::NOT_FOUND,
+        )),
+        Err(e) => Ok(internal_error("Failed to delete OAuth client.", e)),
+    }
+}
+
+/// GET /oauth/authorize: без сессии — на вход, без согласия — на страницу согласия
+pub async fn authorize_handler(
+    query: AuthorizeQuery,
+    user_uuid: Option<Uuid>,
+) -> Result<Response, Rejection> {
+    let request = match validate_authorization(&query).await {
+        Ok(request) => request,
+        Err(AuthorizeError::Invalid(message)) => {
+            return Ok(oauth_response(message, StatusCode::BAD_REQUEST))
+        }
+        Err(AuthorizeError::Redirect(location)) => return Ok(redirect(&location)),
+        Err(AuthorizeError::Internal(e)) => {
+            return Ok(internal_error(
+                "Failed to process authorization request.",
+                e,
+            ))
+        }
+    };
+    let query_string = serde_urlencoded::to_string(&query).unwrap_or_default();
+
+    let Some(user_uuid) = user_uuid else {
+        let next = format!("/oauth/authorize?{}", query_string);
+        return Ok(redirect(&with_params(
+            "/static/login.html",
+            &[("next", next.as_str())],
+        )));
+    };
+
+    match has_consent(&user_uuid, &request).await {
+        Ok(true) => match issue_authorization_code(&request, user_uuid).await {
+            Ok(location) => Ok(redirect(&location)),
+            Err(e) => Ok(internal_error("Failed to issue authorization code.", e)),
+        },
+        Ok(false) => Ok(redirect(&format!("/static/consent.html?{}", query_string))),
+        Err(e) => Ok(internal_error(
+            "Failed to process authorization request.",
+            e,
+        )),
+    }
+}
+
+/// Что показать на странице согласия
+pub async fn consent_info_handler(
+    _user_uuid: Uuid,
+    query: AuthorizeQuery,
+) -> Result<Response, Rejection> {
+    match validate_authorization(&query).await {
+        Ok(request) => {
+            let response = ConsentInfoResponse {
+                client_id: request.client.client_id,
+                client_name: request.client.name,
+                scopes: request.scopes,
+            };
+            Ok(warp::reply::json(&response).into_response())
+        }
+        Err(AuthorizeError::Invalid(message)) => {
+            Ok(oauth_response(message, StatusCode::BAD_REQUEST))
+        }
+        Err(AuthorizeError::Redirect(_)) => Ok(oauth_response(
+            "Invalid authorization request",
+            StatusCode::BAD_REQUEST,
+        )),
+        Err(AuthorizeError::Internal(e)) => Ok(internal_error(
+            "Failed to process authorization request.",
+            e,
+        )),
+    }
+}
+
+/// Решение пользователя: код для приложения или access_denied
+pub async fn consent_decision_handler(
+    user_uuid: Uuid,
+    decision: ConsentRequest,
+) -> Result<Response, Rejection> {
+    let request = match validate_authorization(&decision.query).await {
+        Ok(request) => request,
+        Err(AuthorizeError::Invalid(message)) => {
+            return Ok(oauth_response(message, StatusCode::BAD_REQUEST))
+        }
+        Err(AuthorizeError::Redirect(location)) => {
+            return Ok(warp::reply::json(&ConsentDecisionResponse {
+                redirect_to: location,
+            })
+            .into_response())
+        }
+        Err(AuthorizeError::Internal(e)) => {
+            return Ok(internal_error("Failed to process consent.", e))
+        }
+    };
+
+    let redirect_to = if decision.approve {
+        if let Err(e) = save_consent(
+            &user_uuid,
+            &request.client.client_id,
+            &request.scopes.join(" "),
+        )
+        .await
+        {
+            return Ok(internal_error("Failed to process consent.", e));
+        }
+        match issue_authorization_code(&request, user_uuid).await {
+            Ok(location) => location,
+            Err(e) => return Ok(internal_error("Failed to issue authorization code.", e)),
+        }
+    } else {
+        info!(
+            "User {} denied access to client {}",
+            user_uuid, request.client.client_id
+        );
+        error_redirect(
+            &request.redirect_uri,
+            "access_denied",
+            request.state.as_deref(),
+        )
+    };
+
+    Ok(warp::reply::json(&ConsentDecisionResponse { redirect_to }).into_response())
+}
+
+/// client_id и client_secret из `Authorization: Basic`
+fn basic_credentials(authorization: Option<&str>) -> Option<(String, String)> {
+    let encoded = authorization?.strip_prefix("Basic ")?;
+    let decoded = data_encoding::BASE64
+        .decode(encoded.trim().as_bytes())
+        .ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+    Some((client_id.to_string(), client_secret.to_string()))
+}
+
+/// POST /oauth/token: обмен кода авторизации на токены
+pub async fn token_handler(
+    authorization: Option<String>,
+    request: TokenRequest,
+) -> Result<Response, Rejection> {
+    if request.grant_type.as_deref() != Some("authorization_code") {
+        return Ok(token_error(
+            "unsupported_grant_type",
+            "Only authorization_code is supported",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let (client_id, client_secret) = match basic_credentials(authorization.as_deref()) {
+        Some((client_id, client_secret)) => (Some(client_id), Some(client_secret)),
+        None => (request.client_id.clone(), request.client_secret.clone()),
+    };
+    let Some(client_id) = client_id else {
+        return Ok(token_error(
+            "invalid_client",
+            "Client authentication failed",
+            StatusCode::UNAUTHORIZED,
+        ));
+    };
+    let client = match find_oauth_client(&client_id).await {
+        Ok(Some(client)) => client,
+        Ok(None) => {
+            return Ok(token_error(
+                "invalid_client",
+                "Client authentication failed",
+                StatusCode::UNAUTHORIZED,
+            ))
+        }
+        Err(e) => {
+            error!("Failed to find OAuth client: {}", e);
+            return Ok(token_error(
+                "server_error",
+                "Failed to issue tokens",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+    };
+    if let Some(secret_hash) = client.client_secret_hash.as_deref() {
+        if client_secret.as_deref().map(hash_secret).as_deref() != Some(secret_hash) {
+            warn!("OAuth client {} failed authentication", client_id);
+            return Ok(token_error(
+                "invalid_client",
+                "Client authentication failed",
+                StatusCode::UNAUTHORIZED,
+            ));
+        }
+    }
+
+    let (Some(code), Some(redirect_uri), Some(code_verifier)) = (
+        request.code.as_deref(),
+        request.redirect_uri.as_deref(),
+        request.code_verifier.as_deref(),
+    ) else {
+        return Ok(token_error(
+            "invalid_request",
+            "code, redirect_uri and code_verifier are required",
+            StatusCode::BAD_REQUEST,
+        ));
+    };
+
+    let code_hash = hash_secret(code);
+    let authorization_code = match consume_authorization_code(&code_hash).await {
+        Ok(Some(authorization_code)) => authorization_code,
+        Ok(None) => {
+            // Повторное использование кода: отзываем всё, что по нему выдано (RFC 6749, 4.1.2)
+            match find_used_authorization_code(&code_hash).await {
+                Ok(Some((user_uuid, used_client_id))) => {
+                    warn!(
+                        "Authorization code reused by client {}, revoking tokens of {}",
+                        used_client_id, user_uuid
+                    );
+                    if let Err(e) = delete_oauth_access_tokens(&user_uuid, &used_client_id).await {
+                        error!("Failed to revoke OAuth access tokens: {}", e);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => error!("Failed to check authorization code: {}", e),
+            }
+            return Ok(token_error(
+                "invalid_grant",
+                "Invalid authorization code",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+        Err(e) => {
+            error!("Failed to consume authorization code: {}", e);
+            return Ok(token_error(
+                "server_error",
+                "Failed to issue tokens",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+    };
+
+    if authorization_code.client_id != client.client_id
+        || authorization_code.redirect_uri != redirect_uri
+        || authorization_code.expires_at <= Utc::now()
+        || !verify_pkce(code_verifier, &authorization_code.code_challenge)
+    {
+        return Ok(token_error(
+            "invalid_grant",
+            "Invalid authorization code",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    match issue_tokens(&authorization_code).await {
+        Ok(response) => Ok(no_store(warp::reply::json(&response))),
+        Err(TokenError::Suspended) => Ok(token_error(
+            "invalid_grant",
+            "User is suspended",
+            StatusCode::BAD_REQUEST,
+        )),
+        Err(TokenError::Internal(e)) => {
+            error!("Failed to issue tokens: {}", e);
+            Ok(token_error(
+                "server_error",
+                "Failed to issue tokens",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ))
+        }
+    }
+}
+
+enum TokenError {
+    Suspended,
+    Internal(Box<dyn StdError + Send + Sync>),
+}
+
+impl From<Box<dyn StdError + Send + Sync>> for TokenError {
+    fn from(e: Box<dyn StdError + Send + Sync>) -> Self {
+        TokenError::Internal(e)
+    }
+}
+
+async fn issue_tokens(code: &AuthorizationCode) -> Result<TokenResponse, TokenError> {
+    let user = find_user_by_uuid(&code.user_uuid).await?;
+    if user.suspended_at.is_some() {
+        return Err(TokenError::Suspended);
+    }
+    let scopes: Vec<&str> = code.scope.split_whitespace().collect();
+
+    let access_token = random_secret();
+    let now = Utc::now();
+    let token = OAuthAccessToken {
+        client_id: code.client_id.clone(),
+        user_uuid: code.user_uuid,
+        scope: code.scope.clone(),
+        expires_at: now + Duration::seconds(ACCESS_TOKEN_TTL_SECONDS),
+    };
+    save_oauth_access_token(&hash_secret(&access_token), &token).await?;
+
+    let preferred_username = scopes.contains(&"profile").then_some(user.username);
+    let claims = id_token_claims(
+        &code.client_id,
+        &code.user_uuid,
+        code.auth_time,
+        code.nonce.clone(),
+        preferred_username,
+        ID_TOKEN_TTL_SECONDS,
+    );
+    let id_token = sign_id_token(&claims)?;
+
+    info!(
+        "OAuth tokens issued to client {} for user_uuid: {}",
+        code.client_id, code.user_uuid
+    );
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: ACCESS_TOKEN_TTL_SECONDS,
+        scope: code.scope.clone(),
+        id_token,
+    })
+}
+
+fn invalid_token() -> Response {
+    let body = json!({ "error": "invalid_token" });
+    let reply = warp::reply::with_status(warp::reply::json(&body), StatusCode::UNAUTHORIZED);
+    warp::reply::with_header(reply, "www-authenticate", "Bearer error=\"invalid_token\"")
+        .into_response()
+}
+
+/// GET /oauth/userinfo: сведения из профиля в пределах выданных областей
+pub async fn userinfo_handler(authorization: Option<String>) -> Result<Response, Rejection> {
+    let Some(token) = authorization
+        .as_deref()
+        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+    else {
+        return Ok(invalid_token());
+    };
+    let token = match find_oauth_access_token(&hash_secret(token.trim())).await {
+        Ok(Some(token)) if token.expires_at > Utc::now() => token,
+        Ok(_) => return Ok(invalid_token()),
+        Err(e) => return Ok(internal_error("Failed to load user info.", e)),
+    };
+    let user = match find_user_by_uuid(&token.user_uuid).await {
+        Ok(user) if user.suspended_at.is_none() => user,
+        Ok(_) => return Ok(invalid_token()),
+        Err(e) => return Ok(internal_error("Failed to load user info.", e)),
+    };
+
+    let scopes: Vec<&str> = token.scope.split_whitespace().collect();
+    let mut claims = json!({ "sub": token.user_uuid.to_string() });
+    if scopes.contains(&"profile") {
+        let profile = match get_profile_by_user_uuid(&token.user_uuid).await {
+            Ok(profile) => profile,
+            Err(e) => return Ok(internal_error("Failed to load user info.", e)),
+        };
+        claims["preferred_username"] = json!(user.username);
+        claims["picture"] = json!(profile.as_ref().and_then(|p| p.avatar.clone()));
+        claims["bio"] = json!(profile.as_ref().and_then(|p| p.bio.clone()));
+    }
+    if scopes.contains(&"email") {
+        if let Some(email) = user.email {
+            claims["email"] = json!(email);
+            // Адрес не подтверждался письмом
+            claims["email_verified"] = json!(false);
+        }
+    }
+
+    Ok(no_store(warp::reply::json(&claims)))
+}
+
+/// Сессия, если она есть: /oauth/authorize открывают и без входа
+fn with_optional_auth(
+) -> impl Filter<Extract = (Option<Uuid>,), Error = std::convert::Infallible> + Clone {
+    with_auth().map(Some).or(warp::any().map(|| None)).unify()
+}
+
+pub fn oauth_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let discovery = warp::path!(".well-known" / "openid-configuration")
+        .and(warp::get())
+        .and_then(discovery_handler);
+
+    let jwks = warp::path!("oauth" / "jwks")
+        .and(warp::get())
+        .and_then(jwks_handler);
+
+    let create_client = warp::path!("api" / "oauth" / "clients")
+        .and(warp::post())
+        .and(with_permission(Permission::ManageOAuthClients))
+        .and(warp::body::json())
+        .and_then(
+            |admin_uuid: Uuid, request: CreateOAuthClientRequest| async move {
+                create_client_handler(admin_uuid, request).await
+            },
+        );
+
+    let list_clients = warp::path!("api" / "oauth" / "clients")
+        .and(warp::get())
+        .and(with_permission(Permission::ManageOAuthClients))
+        .and_then(|_admin_uuid: Uuid| async move { list_clients_handler().await });
+
+    let delete_client = warp::path!("api" / "oauth" / "clients" / String)
+        .and(warp::delete())
+        .and(with_permission(Permission::ManageOAuthClients))
+        .and_then(|client_id: String, admin_uuid: Uuid| async move {
+            delete_client_handler(admin_uuid, client_id).await
+        });
+
+    let authorize = warp::path!("oauth" / "authorize")
+        .and(warp::get())
+        .and(warp::query::<AuthorizeQuery>())
+        .and(with_optional_auth())
+        .and_then(authorize_handler);
+
+    let consent_info = warp::path!("api" / "oauth" / "consent")
+        .and(warp::get())
+        .and(with_auth())
+        .and(warp::query::<AuthorizeQuery>())
+        .and_then(consent_info_handler);
+
+    let consent_decision = warp::path!("api" / "oauth" / "consent")
+        .and(warp::post())
+        .and(with_auth())
+        .and(warp::body::json())
+        .and_then(consent_decision_handler);
+
+    let token = warp::path!("oauth" / "token")
+        .and(warp::post())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::body::form())
+        .and_then(token_handler);
+
+    let userinfo = warp::path!("oauth" / "userinfo")
+        .and(warp::get().or(warp::post()).unify())
+        .and(warp::header::optional::<String>("authorization"))
+        .and_then(userinfo_handler);
+
+    discovery
+        .or(jwks)
+        .unify()
+        .or(create_client)
+        .unify()
+        .or(list_clients)
+        .unify()
+        .or(delete_client)
+        .unify()
+        .or(authorize)
+        .unify()
+        .or(consent_info)
+        .unify()
+        .or(consent_decision)
+        .unify()
+        .or(token)
+        .unify()
+        .or(userinfo)
+        .unify()
+}
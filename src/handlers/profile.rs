@@ -1,8 +1,11 @@
 // src/handlers/profile.rs
 
+use crate::audit::{record, with_client_info, AuditEntry, ClientInfo};
 use crate::db::profiles::{create_profile, get_profile_by_user_uuid, update_profile};
 use crate::db::users::find_user_by_uuid;
-use crate::models::{ProfileResponse, UpdateProfileRequest, UpdateProfileResponse};
+use crate::models::{
+    AuditAction, ProfileResponse, Scope, UpdateProfileRequest, UpdateProfileResponse,
+};
 use log::{debug, error};
 use uuid::Uuid;
 use warp::Reply;
@@ -46,6 +49,7 @@ pub async fn profile_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
 
     let profile_response = ProfileResponse {
         username: user.username,
+        display_name: user.display_name,
         bio: profile.bio,
         avatar: profile.avatar,
         profile_banner: profile.profile_banner,
@@ -64,6 +68,7 @@ pub async fn profile_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
 pub async fn update_profile_handler(
     user_uuid: Uuid,
     request: UpdateProfileRequest,
+    client: ClientInfo,
 ) -> Result<Response, Rejection> {
     debug!(
         "Received update profile request for user_uuid: {}, request: {:?}",
@@ -72,6 +77,23 @@ pub async fn update_profile_handler(
 
     println!("Received bio: {:?}", request.bio);
 
+    // Прежняя видимость файлов — чтобы записать её изменение в журнал
+    let previous = match get_profile_by_user_uuid(&user_uuid).await {
+        Ok(profile) => profile,
+        Err(e) => {
+            error!("Failed to get profile: {}", e);
+            None
+        }
+    };
+    let visibility_changed = previous.is_some_and(|profile| {
+        profile.storage_access != request.storage_access
+            || request
+                .allowed_viewers
+                .as_ref()
+                .is_some_and(|viewers| *viewers != profile.allowed_viewers)
+    });
+    let storage_access = request.storage_access.to_string();
+
     if let Err(e) = update_profile(&user_uuid, request).await {
         error!("Failed to update profile: {}", e);
         let response = UpdateProfileResponse {
@@ -84,6 +106,12 @@ pub async fn update_profile_handler(
         .into_response());
     }
 
+    if visibility_changed {
+        let entry = AuditEntry::own(AuditAction::ProfileVisibilityChanged, user_uuid)
+            .with_target(storage_access);
+        record(entry, &client).await;
+    }
+
     let response = UpdateProfileResponse {
         message: "Profile updated successfully".to_string(),
     };
@@ -97,7 +125,10 @@ pub fn profile_route() -> impl Filter<Extract = (impl Reply,), Error = Rejection
         .and(warp::path("profile"))
         .and(warp::get())
         .and(warp::path::param::<Uuid>())
-        .and_then(|user_uuid: Uuid| async move {
+        .and(warp::path::end())
+        // Профили видны только вошедшим; API-токену нужна область profile:read
+        .and(crate::middleware::auth::with_scope(Scope::ProfileRead))
+        .and_then(|user_uuid: Uuid, _viewer_uuid: Uuid| async move {
             let result = profile_handler(user_uuid).await;
             match result {
                 Ok(response) => Ok(response),
@@ -107,12 +138,16 @@ pub fn profile_route() -> impl Filter<Extract = (impl Reply,), Error = Rejection
 
     let update_profile = warp::path("api")
         .and(warp::path("profile"))
+        .and(warp::path::end())
         .and(warp::put())
-        .and(crate::middleware::auth::with_auth())
+        .and(crate::middleware::auth::with_scope(Scope::ProfileWrite))
         .and(warp::body::json())
-        .and_then(|user_uuid: Uuid, request: UpdateProfileRequest| async move {
-            update_profile_handler(user_uuid, request).await
-        });
+        .and(with_client_info())
+        .and_then(
+            |user_uuid: Uuid, request: UpdateProfileRequest, client: ClientInfo| async move {
+                update_profile_handler(user_uuid, request, client).await
+            },
+        );
 
     get_profile.or(update_profile)
 }
\ No newline at end of file
@@ -0,0 +1,180 @@
+// src/handlers/push.rs
+//
+// Подписки Web Push: открытый ключ VAPID для браузера, регистрация подписки
+// (тело — результат PushSubscription.toJSON()), список и удаление.
+use crate::audit::{with_client_info, ClientInfo};
+use crate::db::push_subscriptions::{
+    delete_push_subscription, find_push_subscription_by_endpoint, list_push_subscriptions,
+    save_push_subscription,
+};
+use crate::middleware::auth::with_auth;
+use crate::models::PushSubscription;
+use crate::push::{validate_endpoint_host, validate_subscription_keys, vapid_key};
+use chrono::Utc;
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+const MAX_SUBSCRIPTIONS_PER_USER: usize = 20;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PushResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct VapidPublicKeyResponse {
+    pub public_key: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SubscriptionKeys {
+    pub p256dh: String,
+    pub auth: String,
+}
+
+/// Подписка браузера в виде PushSubscription.toJSON()
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SubscribeRequest {
+    pub endpoint: String,
+    pub keys: SubscriptionKeys,
+}
+
+fn push_response(message: &str, status: StatusCode) -> Response {
+    let response = PushResponse {
+        message: message.to_string(),
+    };
+    warp::reply::with_status(warp::reply::json(&response), status).into_response()
+}
+
+fn internal_error(message: &str, e: impl std::fmt::Display) -> Response {
+    error!("{}: {}", message, e);
+    push_response(message, StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+pub async fn vapid_public_key_handler() -> Result<Response, Rejection> {
+    let response = VapidPublicKeyResponse {
+        public_key: vapid_key().public_key.clone(),
+    };
+    Ok(warp::reply::json(&response).into_response())
+}
+
+pub async fn subscribe_handler(
+    user_uuid: Uuid,
+    request: SubscribeRequest,
+    client: ClientInfo,
+) -> Result<Response, Rejection> {
+    debug!("Received push subscription for user_uuid: {}", user_uuid);
+
+    if let Err(message) = validate_endpoint_host(&request.endpoint).await {
+        return Ok(push_response(message, StatusCode::BAD_REQUEST));
+    }
+    if let Err(message) = validate_subscription_keys(&request.keys.p256dh, &request.keys.auth) {
+        return Ok(push_response(message, StatusCode::BAD_REQUEST));
+    }
+
+    // Повторная регистрация того же браузера не занимает новое место
+    let existing = match find_push_subscription_by_endpoint(&request.endpoint).await {
+        Ok(existing) => existing,
+        Err(e) => return Ok(internal_error("Failed to save push subscription.", e)),
+    };
+    if existing.is_none_or(|existing| existing.user_uuid != user_uuid) {
+        match list_push_subscriptions(&user_uuid).await {
+            Ok(subscriptions) if subscriptions.len() >= MAX_SUBSCRIPTIONS_PER_USER => {
+                return Ok(push_response(
+                    "Too many push subscriptions. Remove unused ones first.",
+                    StatusCode::BAD_REQUEST,
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => return Ok(internal_error("Failed to save push subscription.", e)),
+        }
+    }
+
+    let subscription = PushSubscription {
+        subscription_id: Uuid::new_v4(),
+        user_uuid,
+        endpoint: request.endpoint,
+        p256dh: request.keys.p256dh,
+        auth: request.keys.auth,
+        user_agent: client.user_agent,
+        created_at: Utc::now(),
+        last_push_at: None,
+    };
+    match save_push_subscription(&subscription).await {
+        Ok(subscription) => {
+            info!(
+                "Push subscription {} saved for user_uuid: {}",
+                subscription.subscription_id, user_uuid
+            );
+            Ok(
+                warp::reply::with_status(warp::reply::json(&subscription), StatusCode::CREATED)
+                    .into_response(),
+            )
+        }
+        Err(e) => Ok(internal_error("Failed to save push subscription.", e)),
+    }
+}
+
+pub async fn list_subscriptions_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
+    match list_push_subscriptions(&user_uuid).await {
+        Ok(subscriptions) => Ok(warp::reply::json(&subscriptions).into_response()),
+        Err(e) => Ok(internal_error("Failed to list push subscriptions.", e)),
+    }
+}
+
+pub async fn unsubscribe_handler(
+    subscription_id: Uuid,
+    user_uuid: Uuid,
+) -> Result<Response, Rejection> {
+    match delete_push_subscription(&user_uuid, &subscription_id).await {
+        Ok(true) => Ok(push_response(
+            "Push subscription removed.",
+            StatusCode::OK,
+        )),
+        Ok(false) => Ok(push_response(
+            "Push subscription not found.",
+            StatusCode::NOT_FOUND,
+        )),
+        Err(e) => Ok(internal_error("Failed to remove push subscription.", e)),
+    }
+}
+
+pub fn push_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let public_key = warp::path!("api" / "push" / "vapid-public-key")
+        .and(warp::get())
+        .and_then(vapid_public_key_handler);
+
+    let subscribe = warp::path!("api" / "push" / "subscriptions")
+        .and(warp::post())
+        .and(with_auth())
+        .and(warp::body::json())
+        .and(with_client_info())
+        .and_then(
+            |user_uuid: Uuid, request: SubscribeRequest, client: ClientInfo| async move {
+                subscribe_handler(user_uuid, request, client).await
+            },
+        );
+
+    let list = warp::path!("api" / "push" / "subscriptions")
+        .and(warp::get())
+        .and(with_auth())
+        .and_then(|user_uuid: Uuid| async move { list_subscriptions_handler(user_uuid).await });
+
+    let unsubscribe = warp::path!("api" / "push" / "subscriptions" / Uuid)
+        .and(warp::delete())
+        .and(with_auth())
+        .and_then(|subscription_id: Uuid, user_uuid: Uuid| async move {
+            unsubscribe_handler(subscription_id, user_uuid).await
+        });
+
+    public_key
+        .or(subscribe)
+        .unify()
+        .or(list)
+        .unify()
+        .or(unsubscribe)
+        .unify()
+}
@@ -0,0 +1,210 @@
+// src/handlers/reactions.rs
+//
+// Реакции на сообщения чата. Ставить и снимать их можно и через сокет чата,
+// и через REST; в обоих случаях событие получают все, кто открыл чат.
+use crate::db::reactions::{add_reaction, reactions_for_messages, remove_reaction};
+use crate::middleware::auth::with_scope;
+use crate::models::{ChatEvent, ReactionCount, Scope};
+use crate::Sender;
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+/// Не длиннее этого (в символах): эмодзи с модификаторами и ZWJ-последовательности
+pub const MAX_EMOJI_LENGTH: usize = 16;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ReactionsResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ReactionRequest {
+    pub emoji: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ReactionQuery {
+    pub emoji: String,
+}
+
+/// Итог реакции: сколько таких реакций теперь у сообщения
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ReactionResult {
+    pub message_id: i64,
+    pub emoji: String,
+    pub count: i64,
+}
+
+/// Почему реакцию не удалось поставить или снять
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReactionError {
+    InvalidEmoji,
+    MessageNotFound,
+    Internal,
+}
+
+impl fmt::Display for ReactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            ReactionError::InvalidEmoji => "Reaction must be a single emoji",
+            ReactionError::MessageNotFound => "Message not found",
+            ReactionError::Internal => "Failed to update reaction",
+        };
+        f.write_str(message)
+    }
+}
+
+/// Похоже ли на эмодзи: без букв ASCII, пробелов и управляющих символов
+pub fn is_valid_emoji(emoji: &str) -> bool {
+    !emoji.is_empty()
+        && emoji.chars().count() <= MAX_EMOJI_LENGTH
+        && emoji
+            .chars()
+            .all(|c| !c.is_ascii() && !c.is_whitespace() && !c.is_control())
+}
+
+/// Ставит (add) или снимает реакцию и рассылает событие, если что-то изменилось
+pub async fn apply_reaction(
+    sender: &Sender,
+    user_uuid: Uuid,
+    message_id: i64,
+    emoji: &str,
+    add: bool,
+) -> Result<i64, ReactionError> {
+    if !is_valid_emoji(emoji) {
+        return Err(ReactionError::InvalidEmoji);
+    }
+
+    let result = if add {
+        add_reaction(message_id, &user_uuid, emoji).await
+    } else {
+        remove_reaction(message_id, &user_uuid, emoji).await
+    };
+    let (changed, count) = match result {
+        Ok(Some(result)) => result,
+        Ok(None) => return Err(ReactionError::MessageNotFound),
+        Err(e) => {
+            error!("Failed to update reaction: {}", e);
+            return Err(ReactionError::Internal);
+        }
+    };
+
+    if changed {
+        debug!(
+            "Reaction {} on message {} by {} (added: {})",
+            emoji, message_id, user_uuid, add
+        );
+        let event = ChatEvent::Reaction {
+            message_id,
+            emoji: emoji.to_string(),
+            user_uuid,
+            added: add,
+            count,
+        };
+        // Ошибка означает лишь, что сейчас чат никто не открыл
+        let _ = sender.lock().unwrap().send(event);
+    }
+    Ok(count)
+}
+
+fn reactions_response(message: &str, status: StatusCode) -> Response {
+    let response = ReactionsResponse {
+        message: message.to_string(),
+    };
+    warp::reply::with_status(warp::reply::json(&response), status).into_response()
+}
+
+fn error_response(e: ReactionError) -> Response {
+    let status = match e {
+        ReactionError::InvalidEmoji => StatusCode::BAD_REQUEST,
+        ReactionError::MessageNotFound => StatusCode::NOT_FOUND,
+        ReactionError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    reactions_response(&e.to_string(), status)
+}
+
+pub async fn list_reactions_handler(
+    user_uuid: Uuid,
+    message_id: i64,
+) -> Result<Response, Rejection> {
+    match reactions_for_messages(&[message_id], Some(&user_uuid)).await {
+        Ok(mut reactions) => {
+            let reactions: Vec<ReactionCount> = reactions.remove(&message_id).unwrap_or_default();
+            Ok(
+                warp::reply::with_status(warp::reply::json(&reactions), StatusCode::OK)
+                    .into_response(),
+            )
+        }
+        Err(e) => {
+            error!("Failed to list reactions: {}", e);
+            Ok(reactions_response(
+                "Failed to list reactions",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ))
+        }
+    }
+}
+
+pub async fn update_reaction_handler(
+    sender: Sender,
+    user_uuid: Uuid,
+    message_id: i64,
+    emoji: String,
+    add: bool,
+) -> Result<Response, Rejection> {
+    match apply_reaction(&sender, user_uuid, message_id, &emoji, add).await {
+        Ok(count) => {
+            let response = ReactionResult {
+                message_id,
+                emoji,
+                count,
+            };
+            Ok(
+                warp::reply::with_status(warp::reply::json(&response), StatusCode::OK)
+                    .into_response(),
+            )
+        }
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub fn reactions_route(
+    sender: Sender,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let with_sender = warp::any().map(move || sender.clone());
+
+    let list = warp::path!("api" / "messages" / i64 / "reactions")
+        .and(warp::get())
+        .and(with_scope(Scope::ChatRead))
+        .and_then(|message_id: i64, user_uuid: Uuid| async move {
+            list_reactions_handler(user_uuid, message_id).await
+        });
+
+    let add = warp::path!("api" / "messages" / i64 / "reactions")
+        .and(warp::post())
+        .and(with_scope(Scope::ChatWrite))
+        .and(warp::body::json())
+        .and(with_sender.clone())
+        .and_then(
+            |message_id: i64, user_uuid: Uuid, request: ReactionRequest, sender: Sender| async move {
+                update_reaction_handler(sender, user_uuid, message_id, request.emoji, true).await
+            },
+        );
+
+    let remove = warp::path!("api" / "messages" / i64 / "reactions")
+        .and(warp::delete())
+        .and(with_scope(Scope::ChatWrite))
+        .and(warp::query::<ReactionQuery>())
+        .and(with_sender)
+        .and_then(
+            |message_id: i64, user_uuid: Uuid, query: ReactionQuery, sender: Sender| async move {
+                update_reaction_handler(sender, user_uuid, message_id, query.emoji, false).await
+            },
+        );
+
+    list.or(add).unify().or(remove).unify()
+}
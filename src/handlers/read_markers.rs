@@ -0,0 +1,157 @@
+// src/handlers/read_markers.rs
+//
+// Отметки прочтения и счётчики непрочитанного. Отметку можно поставить и через
+// сокет чата, и через REST; уведомление о прочтении получают все, кто открыл чат.
+use crate::db::read_markers::{conversation_key, mark_read, unread_counts};
+use crate::db::threads::find_message_thread;
+use crate::middleware::auth::with_scope;
+use crate::models::{ChatEvent, Scope};
+use crate::Sender;
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ReadMarkersResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MarkReadRequest {
+    pub message_id: i64,
+}
+
+/// Отметка после обновления
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MarkReadResult {
+    pub conversation: String,
+    pub last_read_message_id: i64,
+}
+
+/// Почему отметку не удалось поставить
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReadError {
+    MessageNotFound,
+    Internal,
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            ReadError::MessageNotFound => "Message not found",
+            ReadError::Internal => "Failed to update read marker",
+        };
+        f.write_str(message)
+    }
+}
+
+/// Отмечает разговор с message_id прочитанным до него и рассылает отметку
+pub async fn apply_read(
+    sender: &Sender,
+    user_uuid: Uuid,
+    message_id: i64,
+) -> Result<MarkReadResult, ReadError> {
+    let conversation = match find_message_thread(message_id).await {
+        Ok(Some(thread_root)) => conversation_key(thread_root),
+        Ok(None) => return Err(ReadError::MessageNotFound),
+        Err(e) => {
+            error!("Failed to find message {}: {}", message_id, e);
+            return Err(ReadError::Internal);
+        }
+    };
+
+    let last_read_message_id = match mark_read(&user_uuid, &conversation, message_id).await {
+        Ok(last_read_message_id) => last_read_message_id,
+        Err(e) => {
+            error!("Failed to update read marker: {}", e);
+            return Err(ReadError::Internal);
+        }
+    };
+
+    // Более старое сообщение отметку не сдвигает — и рассылать нечего
+    if last_read_message_id == message_id {
+        debug!(
+            "User {} read {} up to {}",
+            user_uuid, conversation, message_id
+        );
+        let event = ChatEvent::Read {
+            user_uuid,
+            conversation: conversation.clone(),
+            message_id,
+        };
+        // Ошибка означает лишь, что сейчас чат никто не открыл
+        let _ = sender.lock().unwrap().send(event);
+    }
+    Ok(MarkReadResult {
+        conversation,
+        last_read_message_id,
+    })
+}
+
+fn read_markers_response(message: &str, status: StatusCode) -> Response {
+    let response = ReadMarkersResponse {
+        message: message.to_string(),
+    };
+    warp::reply::with_status(warp::reply::json(&response), status).into_response()
+}
+
+pub async fn mark_read_handler(
+    sender: Sender,
+    user_uuid: Uuid,
+    request: MarkReadRequest,
+) -> Result<Response, Rejection> {
+    match apply_read(&sender, user_uuid, request.message_id).await {
+        Ok(result) => Ok(
+            warp::reply::with_status(warp::reply::json(&result), StatusCode::OK).into_response(),
+        ),
+        Err(e @ ReadError::MessageNotFound) => {
+            Ok(read_markers_response(&e.to_string(), StatusCode::NOT_FOUND))
+        }
+        Err(e @ ReadError::Internal) => Ok(read_markers_response(
+            &e.to_string(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )),
+    }
+}
+
+pub async fn unread_handler(user_uuid: Uuid) -> Result<Response, Rejection> {
+    match unread_counts(&user_uuid).await {
+        Ok(counts) => Ok(
+            warp::reply::with_status(warp::reply::json(&counts), StatusCode::OK).into_response(),
+        ),
+        Err(e) => {
+            error!("Failed to count unread messages: {}", e);
+            Ok(read_markers_response(
+                "Failed to count unread messages",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ))
+        }
+    }
+}
+
+pub fn read_markers_route(
+    sender: Sender,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let with_sender = warp::any().map(move || sender.clone());
+
+    let mark = warp::path!("api" / "chat" / "read")
+        .and(warp::put())
+        .and(with_scope(Scope::ChatRead))
+        .and(warp::body::json())
+        .and(with_sender)
+        .and_then(
+            |user_uuid: Uuid, request: MarkReadRequest, sender: Sender| async move {
+                mark_read_handler(sender, user_uuid, request).await
+            },
+        );
+
+    let unread = warp::path!("api" / "chat" / "unread")
+        .and(warp::get())
+        .and(with_scope(Scope::ChatRead))
+        .and_then(|user_uuid: Uuid| async move { unread_handler(user_uuid).await });
+
+    mark.or(unread).unify()
+}
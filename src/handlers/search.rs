@@ -0,0 +1,142 @@
+// src/handlers/search.rs
+//
+// Поиск по истории чата. Ищется всё, что пользователь может прочитать в чате:
+// общая лента и ветки, кроме сообщений, удалённых модератором.
+use crate::db::search::search_messages;
+use crate::handlers::chat::attach_details;
+use crate::middleware::auth::with_scope;
+use crate::models::{ChatPayload, Scope, SearchFilters, SearchResult};
+use chrono::{DateTime, Utc};
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
+const MAX_QUERY_LENGTH: usize = 200;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SearchResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// Запрос: слова, "точная фраза", -исключение, or
+    pub q: String,
+    /// Имя пользователя или его user_uuid
+    pub author: Option<String>,
+    /// main или thread:<message_id>
+    pub conversation: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// true — только сообщения с вложениями, false — только без них
+    pub has_attachment: Option<bool>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+fn search_response(message: &str, status: StatusCode) -> Response {
+    let response = SearchResponse {
+        message: message.to_string(),
+    };
+    warp::reply::with_status(warp::reply::json(&response), status).into_response()
+}
+
+/// Разговор из параметра conversation; Err — непонятное значение
+fn parse_conversation(conversation: &str) -> Result<Option<i64>, ()> {
+    match conversation {
+        "main" => Ok(None),
+        _ => conversation
+            .strip_prefix("thread:")
+            .and_then(|root| root.parse().ok())
+            .map(Some)
+            .ok_or(()),
+    }
+}
+
+pub async fn search_handler(user_uuid: Uuid, query: SearchQuery) -> Result<Response, Rejection> {
+    debug!("Received search request from {}: {:?}", user_uuid, query);
+
+    let text = query.q.trim();
+    if text.is_empty() || text.chars().count() > MAX_QUERY_LENGTH {
+        return Ok(search_response(
+            &format!(
+                "Search query must be between 1 and {} characters",
+                MAX_QUERY_LENGTH
+            ),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    let thread_root = match query.conversation.as_deref().map(parse_conversation) {
+        None => None,
+        Some(Ok(thread_root)) => Some(thread_root),
+        Some(Err(())) => {
+            return Ok(search_response(
+                "Conversation must be main or thread:<message_id>",
+                StatusCode::BAD_REQUEST,
+            ))
+        }
+    };
+    let (author_uuid, author_username) = match query.author {
+        Some(author) => match Uuid::parse_str(&author) {
+            Ok(author_uuid) => (Some(author_uuid), None),
+            Err(_) => (None, Some(author)),
+        },
+        None => (None, None),
+    };
+    let filters = SearchFilters {
+        author_uuid,
+        author_username,
+        thread_root,
+        from: query.from,
+        to: query.to,
+        has_attachment: query.has_attachment,
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let mut results = match search_messages(text, &filters, limit, offset).await {
+        Ok(results) => results,
+        Err(e) => {
+            error!("Failed to search messages: {}", e);
+            return Ok(search_response(
+                "Failed to search messages",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+    };
+
+    // Реакции и сводки веток — как в истории чата
+    let mut messages: Vec<ChatPayload> = results
+        .iter()
+        .map(|result| result.message.clone())
+        .collect();
+    if let Err(e) = attach_details(&mut messages, &user_uuid).await {
+        error!("Failed to load message details: {}", e);
+    }
+    for (result, message) in results.iter_mut().zip(messages) {
+        result.message = message;
+    }
+
+    Ok(warp::reply::with_status(
+        warp::reply::json::<Vec<SearchResult>>(&results),
+        StatusCode::OK,
+    )
+    .into_response())
+}
+
+pub fn search_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("api" / "messages" / "search")
+        .and(warp::get())
+        .and(with_scope(Scope::ChatRead))
+        .and(warp::query::<SearchQuery>())
+        .and_then(|user_uuid: Uuid, query: SearchQuery| async move {
+            search_handler(user_uuid, query).await
+        })
+}
@@ -0,0 +1,92 @@
+// src/handlers/threads.rs
+//
+// История ветки. Новые ответы пишутся через сокет чата (thread_root в сообщении).
+use crate::db::messages::find_payload;
+use crate::db::threads::{find_message_thread, list_thread_replies};
+use crate::handlers::chat::attach_details;
+use crate::middleware::auth::with_scope;
+use crate::models::{Scope, ThreadHistory};
+use log::error;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use warp::Reply;
+use warp::{http::StatusCode, reply::Response, Filter, Rejection};
+
+const DEFAULT_THREAD_LIMIT: i64 = 100;
+const MAX_THREAD_LIMIT: i64 = 500;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ThreadsResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct ThreadQuery {
+    /// Сколько последних ответов вернуть
+    pub limit: Option<i64>,
+}
+
+fn threads_response(message: &str, status: StatusCode) -> Response {
+    let response = ThreadsResponse {
+        message: message.to_string(),
+    };
+    warp::reply::with_status(warp::reply::json(&response), status).into_response()
+}
+
+fn internal_error(message: &str, e: impl std::fmt::Display) -> Response {
+    error!("{}: {}", message, e);
+    threads_response(message, StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Ветка по корню или по любому ответу в ней
+pub async fn thread_handler(
+    user_uuid: Uuid,
+    message_id: i64,
+    query: ThreadQuery,
+) -> Result<Response, Rejection> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_THREAD_LIMIT)
+        .clamp(1, MAX_THREAD_LIMIT);
+
+    let root_id = match find_message_thread(message_id).await {
+        Ok(Some(root)) => root.unwrap_or(message_id),
+        Ok(None) => return Ok(threads_response("Message not found", StatusCode::NOT_FOUND)),
+        Err(e) => return Ok(internal_error("Failed to load thread", e)),
+    };
+    let root = match find_payload(root_id).await {
+        Ok(Some(root)) => root,
+        // Корень удалён модератором
+        Ok(None) => return Ok(threads_response("Message not found", StatusCode::NOT_FOUND)),
+        Err(e) => return Ok(internal_error("Failed to load thread", e)),
+    };
+    let replies = match list_thread_replies(root_id, limit).await {
+        Ok(replies) => replies,
+        Err(e) => return Ok(internal_error("Failed to load thread", e)),
+    };
+
+    let mut messages = Vec::with_capacity(replies.len() + 1);
+    messages.push(root);
+    messages.extend(replies);
+    if let Err(e) = attach_details(&mut messages, &user_uuid).await {
+        return Ok(internal_error("Failed to load thread", e));
+    }
+    let root = messages.remove(0);
+    let history = ThreadHistory {
+        root,
+        replies: messages,
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&history), StatusCode::OK).into_response())
+}
+
+pub fn threads_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("api" / "messages" / i64 / "thread")
+        .and(warp::get())
+        .and(with_scope(Scope::ChatRead))
+        .and(warp::query::<ThreadQuery>())
+        .and_then(
+            |message_id: i64, user_uuid: Uuid, query: ThreadQuery| async move {
+                thread_handler(user_uuid, message_id, query).await
+            },
+        )
+}
@@ -1,20 +1,37 @@
 // src/handlers/upload.rs
 use warp::Reply;
 use warp::{Filter, Rejection, http::StatusCode, reply::Response};
-use tokio::fs::File;
+use tokio::fs::OpenOptions;
 use tokio::io::AsyncWriteExt;
 use futures_util::StreamExt;
-use std::path::Path;
 use serde::{Deserialize, Serialize};
 use log::{info, error, debug};
 use bytes::Buf;
 use uuid::Uuid;
+use crate::attachments::{file_path, upload_mime_type};
 use crate::db::files::save_file_info;
+use crate::models::Scope;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 struct UploadResponse {
     message: String,
+    /// Идентификатор загруженного файла — по нему файл вкладывают в сообщения
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file_id: Option<Uuid>,
 }
+
+/// Каталог для загруженных файлов (переменная окружения UPLOAD_DIR)
+pub fn upload_dir() -> String {
+    std::env::var("UPLOAD_DIR")
+        .unwrap_or_else(|_| "/var/www/rust_server_cyb3ria_xyz/uploaded".to_string())
+}
+/// Удаляет файл, загрузка которого не завершилась
+async fn remove_partial_upload(path: &std::path::Path) {
+    if let Err(e) = tokio::fs::remove_file(path).await {
+        error!("Failed to remove partial upload {}: {}", path.display(), e);
+    }
+}
+
 pub async fn upload_handler(
     mut form: warp::multipart::FormData,
     user_uuid: Uuid,
@@ -28,6 +45,7 @@ pub async fn upload_handler(
                 error!("Failed to parse form data: {}", e);
                 let response = UploadResponse {
                     message: "Failed to parse form data".to_string(),
+                    file_id: None,
                 };
                 return Ok(warp::reply::with_status(
                     warp::reply::json(&response),
@@ -38,12 +56,14 @@ pub async fn upload_handler(
         };
 
         if part.name() == "file" {
+            let content_type = part.content_type().map(str::to_string);
             let file_name = match part.filename() {
                 Some(file_name) => file_name.to_string(),
                 None => {
                     error!("Failed to extract filename");
                     let response = UploadResponse {
                         message: "Failed to extract filename".to_string(),
+                        file_id: None,
                     };
                     return Ok(warp::reply::with_status(
                         warp::reply::json(&response),
@@ -52,16 +72,23 @@ pub async fn upload_handler(
                     .into_response());
                 }
             };
-            let file_path =
-                Path::new("/var/www/rust_server_cyb3ria_xyz/uploaded").join(&file_name); // Использование абсолютного пути
-            let file_path_str = file_path.to_str().unwrap();
+            // На диске файл лежит под своим file_id; присланное имя хранится
+            // только в базе и нужно для Content-Disposition
+            let file_id = Uuid::new_v4();
+            let file_path = file_path(&file_id);
 
-            let mut file = match File::create(&file_path).await {
+            let mut file = match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&file_path)
+                .await
+            {
                 Ok(file) => file,
                 Err(e) => {
                     error!("Failed to create file: {}", e);
                     let response = UploadResponse {
                         message: "Failed to create file.".to_string(),
+                        file_id: None,
                     };
                     return Ok(warp::reply::with_status(
                         warp::reply::json(&response),
@@ -71,13 +98,16 @@ pub async fn upload_handler(
                 }
             };
 
+            let mut size_bytes: i64 = 0;
             while let Some(chunk) = part.data().await {
                 let chunk = match chunk {
                     Ok(chunk) => chunk,
                     Err(e) => {
                         error!("Failed to read chunk: {}", e);
+                        remove_partial_upload(&file_path).await;
                         let response = UploadResponse {
                             message: "Failed to read chunk".to_string(),
+                            file_id: None,
                         };
                         return Ok(warp::reply::with_status(
                             warp::reply::json(&response),
@@ -87,10 +117,13 @@ pub async fn upload_handler(
                     }
                 };
 
+                size_bytes += chunk.remaining() as i64;
                 if let Err(e) = file.write_all(chunk.chunk()).await {
                     error!("Failed to write to file: {}", e);
+                    remove_partial_upload(&file_path).await;
                     let response = UploadResponse {
                         message: "Failed to write to file".to_string(),
+                        file_id: None,
                     };
                     return Ok(warp::reply::with_status(
                         warp::reply::json(&response),
@@ -100,15 +133,22 @@ pub async fn upload_handler(
                 }
             }
 
-            info!("File saved successfully: {}", file_path_str);
+            info!("File saved successfully: {}", file_path.display());
 
             // Save file info to database
-            if let Err(e) = save_file_info(&file_name, user_uuid).await {
+            let mime_type = upload_mime_type(content_type.as_deref(), &file_name);
+            let file_id = match save_file_info(&file_id, &file_name, user_uuid, size_bytes, &mime_type).await {
                 // Pass user_uuid
-                error!("Failed to save file info to database: {}", e);
-            }
+                Ok(()) => Some(file_id),
+                Err(e) => {
+                    error!("Failed to save file info to database: {}", e);
+                    remove_partial_upload(&file_path).await;
+                    None
+                }
+            };
             let response = UploadResponse {
                 message: "Uploaded succesfully!".to_string(),
+                file_id,
             };
             return Ok(warp::reply::with_status(
                 warp::reply::json(&response),
@@ -120,6 +160,7 @@ pub async fn upload_handler(
 
     let response = UploadResponse {
         message: "No file found in the form data".to_string(),
+        file_id: None,
     };
     Ok(
         warp::reply::with_status(warp::reply::json(&response), StatusCode::BAD_REQUEST)
@@ -131,8 +172,10 @@ pub async fn upload_handler(
 pub fn upload_route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
     warp::path("api")
         .and(warp::path("upload"))
+        .and(warp::path::end())
+        .and(warp::post())
         .and(warp::multipart::form())
-        .and(crate::middleware::auth::with_auth()) // Add auth middleware
+        .and(crate::middleware::auth::with_scope(Scope::FilesWrite)) // Add auth middleware
         .and_then(|form: warp::multipart::FormData, user_uuid: Uuid| async move {
             // Get user_uuid
             upload_handler(form, user_uuid).await // Pass user_uuid
@@ -0,0 +1,146 @@
+// src/lib.rs
+pub mod account;
+pub mod attachments;
+pub mod audit;
+pub mod db;
+pub mod devices;
+pub mod flood;
+pub mod handlers;
+pub mod mailer;
+pub mod markdown;
+pub mod mentions;
+pub mod middleware;
+pub mod models;
+pub mod notifications;
+pub mod oidc;
+pub mod password;
+pub mod push;
+pub mod totp;
+pub mod usernames;
+pub mod utils;
+
+use flood::{Flood, FloodState};
+use handlers::account::account_route;
+use handlers::admin::admin_route;
+use handlers::audit::audit_route;
+use handlers::auth::{
+    api_tokens::api_tokens_route,
+    login::login_route, login_alerts::login_alerts_route, logout::logout_route, password::password_route, register,
+    two_factor::two_factor_route,
+};
+use handlers::chat::{client_connection, ChatProtocol, ChatQuery};
+use handlers::devices::devices_route;
+use handlers::files::files_route;
+use handlers::moderation::moderation_route;
+use handlers::notifications::notifications_route;
+use handlers::oauth::oauth_route;
+use handlers::profile::profile_route;
+use handlers::push::push_route;
+use handlers::reactions::reactions_route;
+use handlers::read_markers::read_markers_route;
+use handlers::search::search_route;
+use handlers::threads::threads_route;
+use handlers::upload::upload_route;
+use middleware::auth::AuthContext;
+use middleware::csrf::with_csrf_protection;
+use middleware::rejection::handle_rejection;
+use models::{ChatEvent, Scope};
+use std::sync::Arc;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+use warp::{Filter, Rejection, Reply};
+
+pub type Clients = Arc<Mutex<std::collections::HashMap<String, usize>>>;
+pub type Sender = Arc<Mutex<broadcast::Sender<ChatEvent>>>;
+
+/// Собирает все маршруты приложения в один фильтр
+pub fn routes() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let clients: Clients = Arc::new(Mutex::new(std::collections::HashMap::new()));
+    let sender: Sender = Arc::new(Mutex::new(broadcast::channel(100).0));
+    let flood: FloodState = Arc::new(Flood::default());
+    let reactions_route = reactions_route(Arc::clone(&sender)).boxed();
+    let read_markers_route = read_markers_route(Arc::clone(&sender)).boxed();
+    let moderation_route = moderation_route(Arc::clone(&sender), Arc::clone(&flood)).boxed();
+
+    let chat_route = warp::path("api")
+        .and(warp::path("ws"))
+        .and(warp::path::end())
+        .and(warp::ws())
+        .and(warp::addr::remote())
+        .and(crate::middleware::auth::with_scope_context(Scope::ChatRead)) // Сессия или токен с chat:read
+        .and(warp::query::<ChatQuery>())
+        .map(
+            move |ws: warp::ws::Ws,
+                  _addr: Option<std::net::SocketAddr>,
+                  context: AuthContext,
+                  query: ChatQuery| {
+                let protocol = ChatProtocol::from_query(&query);
+                let user_uuid = context.user_uuid;
+                //user_uuid получаем из middleware
+                let clients_clone = Arc::clone(&clients);
+                let sender_clone = Arc::clone(&sender);
+                let flood_clone = Arc::clone(&flood);
+                ws.on_upgrade(move |socket| {
+                    client_connection(
+                        socket,
+                        clients_clone,
+                        sender_clone,
+                        flood_clone,
+                        Some(user_uuid.to_string()),
+                        context.allows(Scope::ChatWrite),
+                        protocol,
+                    ) //Передаём user_uuid в client_connection
+                })
+            },
+        )
+        .boxed();
+
+    let register_route = register::register_route().boxed();
+    let login_route = login_route().boxed();
+    let two_factor_route = two_factor_route().boxed();
+    let login_alerts_route = login_alerts_route().boxed();
+    let oauth_route = oauth_route().boxed();
+    let password_route = password_route().boxed();
+    let api_tokens_route = api_tokens_route().boxed();
+    let devices_route = devices_route().boxed();
+    let account_route = account_route().boxed();
+    let threads_route = threads_route().boxed();
+    let search_route = search_route().boxed();
+    let upload_route = upload_route().boxed();
+    let files_route = files_route().boxed();
+    let logout_route = logout_route().boxed();
+    let profile_route = profile_route().boxed();
+    let admin_route = admin_route().boxed();
+    let audit_route = audit_route().boxed();
+    let notifications_route = notifications_route().boxed();
+    let push_route = push_route().boxed();
+
+    // Проверка источника идёт до всех маршрутов, включая вход и регистрацию
+    with_csrf_protection()
+        .and(
+            chat_route
+                .or(register_route)
+                .or(two_factor_route)
+                .or(oauth_route)
+                .or(login_route)
+                .or(login_alerts_route)
+                .or(password_route)
+                .or(api_tokens_route)
+                .or(devices_route)
+                .or(account_route)
+                .or(reactions_route)
+                .or(search_route)
+                .or(threads_route)
+                .or(read_markers_route)
+                .or(upload_route)
+                .or(files_route)
+                .or(profile_route)
+                .or(logout_route)
+                .or(admin_route)
+                .or(audit_route)
+                .or(moderation_route)
+                .or(notifications_route)
+                .or(push_route),
+        )
+        .recover(handle_rejection)
+}
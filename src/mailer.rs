@@ -0,0 +1,92 @@
+// src/mailer.rs
+//
+// Отправка писем. Способ доставки выбирается переменной MAILER:
+// smtp — через SMTP-сервер, file — в файлы каталога MAIL_DIR (для локальной
+// проверки), log — только в журнал (по умолчанию).
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use log::info;
+use std::env;
+use std::error::Error as StdError;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+const DEFAULT_FROM: &str = "cyb3ria <noreply@cyb3ria.xyz>";
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub enum Mailer {
+    Smtp {
+        transport: Box<AsyncSmtpTransport<Tokio1Executor>>,
+        from: Mailbox,
+    },
+    File(PathBuf),
+    Log,
+}
+
+impl Mailer {
+    /// Настраивает отправку по переменным окружения
+    pub fn from_env() -> Result<Mailer, Box<dyn StdError + Send + Sync>> {
+        match env::var("MAILER").unwrap_or_default().as_str() {
+            "smtp" => {
+                let host = env::var("SMTP_HOST").map_err(|_| "SMTP_HOST must be set")?;
+                let port = env::var("SMTP_PORT")
+                    .ok()
+                    .and_then(|port| port.parse().ok())
+                    .unwrap_or(587);
+                let mut builder =
+                    AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)?.port(port);
+                if let (Ok(username), Ok(password)) =
+                    (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD"))
+                {
+                    builder = builder.credentials(Credentials::new(username, password));
+                }
+                let from = env::var("MAIL_FROM")
+                    .unwrap_or_else(|_| DEFAULT_FROM.to_string())
+                    .parse()?;
+                Ok(Mailer::Smtp {
+                    transport: Box::new(builder.build()),
+                    from,
+                })
+            }
+            "file" => {
+                let dir = env::var("MAIL_DIR").map_err(|_| "MAIL_DIR must be set")?;
+                Ok(Mailer::File(PathBuf::from(dir)))
+            }
+            "" | "log" => Ok(Mailer::Log),
+            other => Err(format!("Unknown MAILER: {}", other).into()),
+        }
+    }
+
+    pub async fn send(&self, email: &Email) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        match self {
+            Mailer::Smtp { transport, from } => {
+                let message = Message::builder()
+                    .from(from.clone())
+                    .to(email.to.parse()?)
+                    .subject(email.subject.clone())
+                    .body(email.body.clone())?;
+                transport.send(message).await?;
+            }
+            Mailer::File(dir) => {
+                tokio::fs::create_dir_all(dir).await?;
+                let path = dir.join(format!("{}.txt", Uuid::new_v4()));
+                let contents = format!(
+                    "To: {}\nSubject: {}\n\n{}\n",
+                    email.to, email.subject, email.body
+                );
+                tokio::fs::write(path, contents).await?;
+            }
+            Mailer::Log => {
+                info!("Mail to {}: {}\n{}", email.to, email.subject, email.body);
+            }
+        }
+        Ok(())
+    }
+}
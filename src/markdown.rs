@@ -0,0 +1,70 @@
+// src/markdown.rs
+//
+// Markdown в сообщениях чата. Текст сообщения хранится как есть, а рядом —
+// готовый HTML из небольшого набора тегов: жирный, курсив, зачёркнутый, код,
+// блоки кода, ссылки и списки. Встроенный HTML показывается как текст, а
+// результат ещё раз проходит через санитайзер со строгим списком тегов.
+use ammonia::{Builder, UrlRelative};
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+/// Теги, которые могут попасть в HTML сообщения
+const ALLOWED_TAGS: &[&str] = &[
+    "p", "br", "strong", "em", "del", "code", "pre", "a", "ul", "ol", "li",
+];
+/// Схемы адресов в ссылках; относительные ссылки не допускаются
+const ALLOWED_URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+fn sanitizer() -> &'static Builder<'static> {
+    static SANITIZER: OnceLock<Builder<'static>> = OnceLock::new();
+    SANITIZER.get_or_init(|| {
+        let mut builder = Builder::empty();
+        builder
+            .tags(ALLOWED_TAGS.iter().copied().collect())
+            .tag_attributes(
+                [("a", HashSet::from(["href"])), ("ol", HashSet::from(["start"]))]
+                    .into_iter()
+                    .collect(),
+            )
+            .generic_attributes(HashSet::new())
+            .url_schemes(ALLOWED_URL_SCHEMES.iter().copied().collect())
+            .url_relative(UrlRelative::Deny)
+            .link_rel(Some("noopener noreferrer nofollow"))
+            .clean_content_tags(HashSet::from(["script", "style"]))
+            .strip_comments(true);
+        builder
+    })
+}
+
+/// Сводит разметку к разрешённому набору: встроенный HTML — в текст,
+/// заголовки — в абзацы, картинки — в ссылки, переносы строк сохраняются
+fn restrict(event: Event<'_>) -> Event<'_> {
+    match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        Event::SoftBreak => Event::HardBreak,
+        Event::Start(Tag::Heading { .. }) => Event::Start(Tag::Paragraph),
+        Event::End(TagEnd::Heading(_)) => Event::End(TagEnd::Paragraph),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }),
+        Event::End(TagEnd::Image) => Event::End(TagEnd::Link),
+        event => event,
+    }
+}
+
+/// HTML сообщения из Markdown; безопасен для вставки в страницу как есть
+pub fn render_markdown(source: &str) -> String {
+    let parser = Parser::new_ext(source, Options::ENABLE_STRIKETHROUGH).map(restrict);
+    let mut unsafe_html = String::with_capacity(source.len() * 3 / 2);
+    html::push_html(&mut unsafe_html, parser);
+    sanitizer().clean(&unsafe_html).to_string().trim_end().to_string()
+}
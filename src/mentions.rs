@@ -0,0 +1,126 @@
+// src/mentions.rs
+//
+// Упоминания @username: разбор текста сообщения и уведомления упомянутым.
+// Упоминание начинается с @ в начале слова (адреса почты не считаются) и
+// длится до пробела; знаки препинания в конце отбрасываются, если без них
+// находится пользователь.
+use crate::db::mentions::find_users_by_usernames;
+use crate::models::{ChatPayload, Mention, NotificationKind};
+use crate::notifications::notify;
+use crate::usernames::{MAX_USERNAME_LENGTH, MIN_USERNAME_LENGTH};
+use log::error;
+use std::error::Error as StdError;
+use uuid::Uuid;
+
+/// Скольким пользователям уходит уведомление об одном сообщении
+const MAX_NOTIFIED_MENTIONS: usize = 10;
+/// Сколько символов сообщения показывается в уведомлении
+const SNIPPET_LENGTH: usize = 80;
+
+/// Возможное упоминание: позиция @ и варианты имени (в нижнем регистре
+/// и их длина в символах), длинные сначала
+struct MentionToken {
+    start: usize,
+    names: Vec<(String, usize)>,
+}
+
+fn mention_tokens(text: &str) -> Vec<MentionToken> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let in_word = i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '@');
+        if chars[i] != '@' || in_word {
+            i += 1;
+            continue;
+        }
+        let end = chars[i + 1..]
+            .iter()
+            .position(|c| c.is_whitespace() || *c == '@')
+            .map_or(chars.len(), |length| i + 1 + length);
+
+        let mut name: String = chars[i + 1..end].iter().collect();
+        let mut names = Vec::new();
+        loop {
+            if (MIN_USERNAME_LENGTH..=MAX_USERNAME_LENGTH).contains(&name.len()) {
+                names.push((name.to_lowercase(), name.chars().count()));
+            }
+            match name.chars().last() {
+                Some(c) if c.is_ascii_punctuation() => {
+                    name.pop();
+                }
+                _ => break,
+            }
+        }
+        if !names.is_empty() {
+            tokens.push(MentionToken { start: i, names });
+        }
+        i = end;
+    }
+    tokens
+}
+
+/// Находит в тексте упоминания существующих пользователей
+pub async fn resolve_mentions(text: &str) -> Result<Vec<Mention>, Box<dyn StdError + Send + Sync>> {
+    let tokens = mention_tokens(text);
+    let names: Vec<String> = tokens
+        .iter()
+        .flat_map(|token| token.names.iter().map(|(name, _)| name.clone()))
+        .collect();
+    let users = find_users_by_usernames(&names).await?;
+
+    Ok(tokens
+        .iter()
+        .filter_map(|token| {
+            token.names.iter().find_map(|(name, length)| {
+                users.get(name).map(|(user_uuid, username)| Mention {
+                    user_uuid: *user_uuid,
+                    username: username.clone(),
+                    start: token.start as i32,
+                    end: (token.start + 1 + length) as i32,
+                })
+            })
+        })
+        .collect())
+}
+
+fn snippet(text: &str) -> String {
+    if text.chars().count() <= SNIPPET_LENGTH {
+        return text.to_string();
+    }
+    let mut snippet: String = text.chars().take(SNIPPET_LENGTH).collect();
+    snippet.push('…');
+    snippet
+}
+
+/// Уведомляет упомянутых в сообщении; автор себе уведомлений не получает
+pub async fn notify_mentions(author_uuid: Uuid, payload: &ChatPayload) {
+    let mut notified: Vec<Uuid> = Vec::new();
+    for mention in &payload.mentions {
+        if mention.user_uuid == author_uuid || notified.contains(&mention.user_uuid) {
+            continue;
+        }
+        if notified.len() == MAX_NOTIFIED_MENTIONS {
+            break;
+        }
+        notified.push(mention.user_uuid);
+
+        if let Err(e) = notify(
+            mention.user_uuid,
+            NotificationKind::Mention,
+            format!(
+                "{} mentioned you: {}",
+                payload.author,
+                snippet(&payload.text)
+            ),
+            Some(format!("/static/chat.html#message-{}", payload.message_id)),
+        )
+        .await
+        {
+            error!(
+                "Failed to notify {} about a mention: {}",
+                mention.user_uuid, e
+            );
+        }
+    }
+}
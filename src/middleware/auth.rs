@@ -1,35 +1,208 @@
 // src/middleware/auth.rs
+use crate::db::api_tokens::{find_api_token_by_hash, touch_api_token};
 use crate::db::sessions::find_session_by_session_id;
+use crate::db::users::find_user_by_uuid;
+use crate::models::{Permission, Scope};
+use chrono::Utc;
 use log::{debug, error};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 use warp::{Filter, Rejection};
 
-pub fn with_auth() -> impl Filter<Extract = (Uuid,), Error = Rejection> + Clone {
-    warp::cookie("session_id").and_then(|session_id: String| async move {
-        debug!("with_auth: session_id from cookie: {}", session_id);
-        let session_uuid = match Uuid::parse_str(&session_id) {
-            Ok(uuid) => {
-                debug!("with_auth: Parsed session_uuid: {}", uuid);
-                uuid
-            }
-            Err(e) => {
-                error!("with_auth: Failed to parse session_id: {}", e);
-                return Err(warp::reject::reject());
-            }
-        };
+/// Кто выполняет запрос: владелец сессии или API-токена
+#[derive(Debug, Clone)]
+pub struct AuthContext {
+    pub user_uuid: Uuid,
+    /// None для сессии (доступно всё), иначе области токена
+    pub scopes: Option<Vec<Scope>>,
+}
+
+impl AuthContext {
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes
+            .as_ref()
+            .is_none_or(|scopes| scopes.contains(&scope))
+    }
+}
+
+/// Запрос без действующей сессии
+#[derive(Debug)]
+pub struct Unauthorized;
+impl warp::reject::Reject for Unauthorized {}
+
+/// Сессия есть, но прав недостаточно
+#[derive(Debug)]
+pub struct Forbidden;
+impl warp::reject::Reject for Forbidden {}
+
+/// Проверяет cookie сессии и возвращает владельца
+async fn authenticate_session(session_id: Option<String>) -> Result<Uuid, Rejection> {
+    let session_id = match session_id {
+        Some(session_id) => session_id,
+        None => {
+            debug!("with_auth: session_id cookie is missing");
+            return Err(warp::reject::custom(Unauthorized));
+        }
+    };
+    debug!("with_auth: session_id from cookie: {}", session_id);
+    let session_uuid = match Uuid::parse_str(&session_id) {
+        Ok(uuid) => {
+            debug!("with_auth: Parsed session_uuid: {}", uuid);
+            uuid
+        }
+        Err(e) => {
+            error!("with_auth: Failed to parse session_id: {}", e);
+            return Err(warp::reject::custom(Unauthorized));
+        }
+    };
 
-        match find_session_by_session_id(&session_uuid).await {
-            Ok(Some(session)) => {
-                debug!("with_auth: Session found in DB: {:?}", session);
-                Ok(session.user_uuid) // Return user_uuid
+    match find_session_by_session_id(&session_uuid).await {
+        Ok(Some(session)) => {
+            debug!("with_auth: Session found in DB: {:?}", session);
+            if session
+                .expires_at
+                .is_some_and(|expires_at| expires_at <= Utc::now())
+            {
+                error!("with_auth: Session expired: {}", session.session_id);
+                return Err(warp::reject::custom(Unauthorized));
             }
-            Ok(None) => {
-                error!("with_auth: Session not found in DB");
-                Err(warp::reject::reject())
+            Ok(session.user_uuid) // Return user_uuid
+        }
+        Ok(None) => {
+            error!("with_auth: Session not found in DB");
+            Err(warp::reject::custom(Unauthorized))
+        }
+        Err(e) => {
+            error!("with_auth: Error finding session in DB: {}", e);
+            Err(warp::reject::reject())
+        }
+    }
+}
+
+pub fn with_auth() -> impl Filter<Extract = (Uuid,), Error = Rejection> + Clone {
+    warp::cookie::optional("session_id").and_then(authenticate_session)
+}
+
+/// Хеш секрета API-токена (в базе хранится только он)
+pub fn hash_api_token(token: &str) -> String {
+    data_encoding::HEXLOWER.encode(&Sha256::digest(token.trim().as_bytes()))
+}
+
+/// Проверяет заголовок `Authorization: Bearer <токен>`
+async fn authenticate_token(authorization: String) -> Result<AuthContext, Rejection> {
+    let token = match authorization.strip_prefix("Bearer ") {
+        Some(token) => token,
+        None => {
+            debug!("with_auth_context: Authorization is not a bearer token");
+            return Err(warp::reject::custom(Unauthorized));
+        }
+    };
+
+    let api_token = match find_api_token_by_hash(&hash_api_token(token)).await {
+        Ok(Some(api_token)) => api_token,
+        Ok(None) => {
+            error!("with_auth_context: API token not found");
+            return Err(warp::reject::custom(Unauthorized));
+        }
+        Err(e) => {
+            error!("with_auth_context: Error finding API token: {}", e);
+            return Err(warp::reject::reject());
+        }
+    };
+    if api_token.expires_at <= Utc::now() {
+        error!(
+            "with_auth_context: API token expired: {}",
+            api_token.token_id
+        );
+        return Err(warp::reject::custom(Unauthorized));
+    }
+
+    // Токены приостановленного пользователя не действуют
+    match find_user_by_uuid(&api_token.user_uuid).await {
+        Ok(user) if user.suspended_at.is_none() => {}
+        Ok(_) => {
+            error!(
+                "with_auth_context: API token of suspended user: {}",
+                api_token.user_uuid
+            );
+            return Err(warp::reject::custom(Unauthorized));
+        }
+        Err(e) => {
+            error!("with_auth_context: Failed to find token owner: {}", e);
+            return Err(warp::reject::custom(Unauthorized));
+        }
+    }
+
+    if let Err(e) = touch_api_token(&api_token.token_id).await {
+        error!("with_auth_context: Failed to update last_used_at: {}", e);
+    }
+
+    Ok(AuthContext {
+        user_uuid: api_token.user_uuid,
+        scopes: Some(api_token.scopes),
+    })
+}
+
+/// Сессия или API-токен. Токен проверяется первым, если передан
+pub fn with_auth_context() -> impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and(warp::cookie::optional("session_id"))
+        .and_then(
+            |authorization: Option<String>, session_id: Option<String>| async move {
+                match authorization {
+                    Some(authorization) => authenticate_token(authorization).await,
+                    None => authenticate_session(session_id)
+                        .await
+                        .map(|user_uuid| AuthContext {
+                            user_uuid,
+                            scopes: None,
+                        }),
+                }
+            },
+        )
+}
+
+/// Пропускает сессию или API-токен с областью `scope`
+pub fn with_scope_context(
+    scope: Scope,
+) -> impl Filter<Extract = (AuthContext,), Error = Rejection> + Clone {
+    with_auth_context().and_then(move |context: AuthContext| async move {
+        if context.allows(scope) {
+            Ok(context)
+        } else {
+            error!(
+                "with_scope: token of user {} lacks scope {}",
+                context.user_uuid, scope
+            );
+            Err(warp::reject::custom(Forbidden))
+        }
+    })
+}
+
+/// То же, что `with_scope_context`, но отдаёт только владельца
+pub fn with_scope(scope: Scope) -> impl Filter<Extract = (Uuid,), Error = Rejection> + Clone {
+    with_scope_context(scope).map(|context: AuthContext| context.user_uuid)
+}
+
+/// Пропускает пользователей, чья роль даёт право `permission`.
+/// Это единственная проверка роли: какие права у какой роли, решает
+/// `Role::permissions`, а маршруты требуют права, а не роли
+pub fn with_permission(
+    permission: Permission,
+) -> impl Filter<Extract = (Uuid,), Error = Rejection> + Clone {
+    with_auth().and_then(move |user_uuid: Uuid| async move {
+        match find_user_by_uuid(&user_uuid).await {
+            Ok(user) if user.role.has_permission(permission) => Ok(user_uuid),
+            Ok(user) => {
+                error!(
+                    "with_permission: user {} with role {} lacks {:?}",
+                    user_uuid, user.role, permission
+                );
+                Err(warp::reject::custom(Forbidden))
             }
             Err(e) => {
-                error!("with_auth: Error finding session in DB: {}", e);
-                Err(warp::reject::reject())
+                error!("with_permission: Failed to find user: {}", e);
+                Err(warp::reject::custom(Unauthorized))
             }
         }
     })
@@ -0,0 +1,97 @@
+// src/middleware/csrf.rs
+//
+// Защита от CSRF для запросов с cookie сессии: изменяющие запросы (и
+// WebSocket-рукопожатие) из браузера должны приходить со своего источника.
+// Источник берётся из Origin, а без него — из Referer; доверенными считаются
+// адрес из заголовка Host, APP_BASE_URL и список CSRF_TRUSTED_ORIGINS.
+use log::warn;
+use warp::http::{HeaderMap, Method};
+use warp::{Filter, Rejection};
+
+/// Запрос пришёл с чужого сайта
+#[derive(Debug)]
+pub struct CsrfRejected;
+impl warp::reject::Reject for CsrfRejected {}
+
+/// `scheme://host[:port]` из полного адреса
+fn origin_of(url: &str) -> Option<&str> {
+    let scheme_end = url.find("://")? + 3;
+    let host_end = url[scheme_end..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |end| scheme_end + end);
+    Some(&url[..host_end])
+}
+
+fn trusted_origins() -> Vec<String> {
+    let mut origins: Vec<String> = std::env::var("CSRF_TRUSTED_ORIGINS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|origin| origin.trim().trim_end_matches('/').to_string())
+        .filter(|origin| !origin.is_empty())
+        .collect();
+    if let Some(origin) = std::env::var("APP_BASE_URL")
+        .ok()
+        .as_deref()
+        .and_then(origin_of)
+    {
+        origins.push(origin.to_string());
+    }
+    origins
+}
+
+fn is_trusted(origin: &str, host: Option<&str>) -> bool {
+    let origin = origin.trim_end_matches('/');
+    let same_host = host.is_some_and(|host| {
+        origin
+            .split_once("://")
+            .is_some_and(|(_, origin_host)| origin_host.eq_ignore_ascii_case(host))
+    });
+    same_host || trusted_origins().iter().any(|trusted| trusted == origin)
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn check_request(method: &Method, path: &str, headers: &HeaderMap) -> Result<(), Rejection> {
+    let websocket =
+        header(headers, "upgrade").is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+    let safe = matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS);
+    if safe && !websocket {
+        return Ok(());
+    }
+    // Bearer-токен браузер сам не подставит, а /oauth/ проверяет приложение, не cookie
+    if header(headers, "authorization").is_some_and(|value| value.starts_with("Bearer "))
+        || path.starts_with("/oauth/")
+    {
+        return Ok(());
+    }
+
+    let origin = match header(headers, "origin") {
+        Some(origin) => Some(origin),
+        None => header(headers, "referer").and_then(origin_of),
+    };
+    // Без обоих заголовков запрос пришёл не из браузера (curl, мобильный клиент)
+    let Some(origin) = origin else {
+        return Ok(());
+    };
+    if is_trusted(origin, header(headers, "host")) {
+        Ok(())
+    } else {
+        warn!("csrf: rejected {} {} from origin {}", method, path, origin);
+        Err(warp::reject::custom(CsrfRejected))
+    }
+}
+
+/// Отклоняет изменяющие запросы с чужих источников
+pub fn with_csrf_protection() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::method()
+        .and(warp::path::full())
+        .and(warp::header::headers_cloned())
+        .and_then(
+            |method: Method, path: warp::path::FullPath, headers: HeaderMap| async move {
+                check_request(&method, path.as_str(), &headers)
+            },
+        )
+        .untuple_one()
+}
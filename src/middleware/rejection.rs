@@ -0,0 +1,31 @@
+// src/middleware/rejection.rs
+use crate::middleware::auth::{Forbidden, Unauthorized};
+use crate::middleware::csrf::CsrfRejected;
+use serde::Serialize;
+use warp::{http::StatusCode, Rejection, Reply};
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+/// Превращает отказы авторизации и CSRF в ответы 401/403, остальные отдаёт warp
+pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
+    let (status, message) = if err.find::<Unauthorized>().is_some() {
+        (StatusCode::UNAUTHORIZED, "Authentication required.")
+    } else if err.find::<Forbidden>().is_some() {
+        (StatusCode::FORBIDDEN, "Access denied.")
+    } else if err.find::<CsrfRejected>().is_some() {
+        (StatusCode::FORBIDDEN, "Cross-site request rejected.")
+    } else {
+        return Err(err);
+    };
+
+    let response = ErrorResponse {
+        message: message.to_string(),
+    };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&response),
+        status,
+    ))
+}
@@ -1,7 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use tokio_postgres::types::{FromSql, Type, IsNull};
+use tokio_postgres::types::{FromSql, Type};
 use std::error::Error as StdError;
 use std::fmt;
 
@@ -13,13 +13,89 @@ pub struct User {
     pub password_hash: String,
     pub invitation_code: String,
     pub user_uuid: Uuid,
+    pub role: Role,
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+    pub username_changed_at: Option<DateTime<Utc>>,
 }
 
+/// Краткие сведения о пользователе для администраторов
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AdminUserSummary {
+    pub user_uuid: Uuid,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub role: Role,
+    pub registration_date: Option<DateTime<Utc>>,
+    pub last_activity: Option<DateTime<Utc>>,
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub suspended_reason: Option<String>,
+}
+
+/// Подробные сведения о пользователе для администраторов
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AdminUserDetails {
+    #[serde(flatten)]
+    pub user: AdminUserSummary,
+    pub file_count: i64,
+    pub storage_bytes: i64,
+    pub message_count: i64,
+    pub devices: Vec<Device>,
+}
+
+/// Блокировка входа после серии неудачных попыток
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LoginLockout {
+    pub lockout_id: i64,
+    pub scope: String, // "account" или "ip"
+    pub key: String,
+    pub failed_attempts: i32,
+    pub locked_until: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Секрет TOTP пользователя; enabled_at пуст, пока настройка не подтверждена
+#[derive(Debug, Clone)]
+pub struct TotpSecret {
+    pub user_uuid: Uuid,
+    pub secret: String,
+    pub enabled_at: Option<DateTime<Utc>>,
+    pub last_used_step: Option<i64>,
+}
+
+/// Незавершённый вход: пароль принят, ждём код второго фактора
+#[derive(Debug, Clone)]
+pub struct LoginChallenge {
+    pub challenge_id: Uuid,
+    pub user_uuid: Uuid,
+    pub expires_at: DateTime<Utc>,
+    pub attempts: i32,
+}
+
+/// Браузер или клиент, с которого входил пользователь
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Device {
     pub device_id: Uuid,
     pub user_uuid: Uuid,
+    /// Последний известный адрес; вся история — в DeviceIp
+    pub ip_address: String,
+    pub name: Option<String>,
+    pub user_agent: Option<String>,
+    pub trusted: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub last_seen_at: Option<DateTime<Utc>>,
+    /// Отпечаток User-Agent, наружу не отдаётся
+    #[serde(skip)]
+    pub fingerprint: Option<String>,
+}
+
+/// Адрес, с которого приходило устройство
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DeviceIp {
     pub ip_address: String,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -43,6 +119,7 @@ pub struct Profile {
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ProfileResponse {
     pub username: String,
+    pub display_name: Option<String>,
     pub bio: Option<String>,
     pub avatar: Option<String>,
     pub profile_banner: Option<String>,
@@ -57,6 +134,21 @@ pub struct File {
     pub user_uuid: Uuid,
     pub filename: String,
     pub upload_time: Option<DateTime<Utc>>,
+    pub size_bytes: i64,
+    /// None у файлов, загруженных до учёта типа
+    pub mime_type: Option<String>,
+}
+
+/// Сообщение чата
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ChatMessage {
+    pub message_id: i64,
+    pub user_uuid: Option<Uuid>,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub reply_to: Option<i64>,
+    pub thread_root: Option<i64>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -98,8 +190,72 @@ impl fmt::Display for StorageAccess {
     }
 }
 
+/// Роль пользователя. Порядок вариантов задаёт старшинство: User < Moderator < Admin
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    User,
+    Moderator,
+    Admin,
+}
+
+/// Отдельные права, которые выдаются ролям
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    ModerateChat, // Удаление чужих сообщений
+    ManageUsers,  // Управление учётными записями
+    ManageRoles,  // Назначение ролей
+    ManageOAuthClients, // Регистрация приложений для входа через cyb3ria
+    ViewAuditLog, // Журнал безопасности всех пользователей
+}
+
+impl Role {
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::User => &[],
+            Role::Moderator => &[Permission::ModerateChat],
+            Role::Admin => &[
+                Permission::ModerateChat,
+                Permission::ManageUsers,
+                Permission::ManageRoles,
+                Permission::ManageOAuthClients,
+                Permission::ViewAuditLog,
+            ],
+        }
+    }
+
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::User => write!(f, "User"),
+            Role::Moderator => write!(f, "Moderator"),
+            Role::Admin => write!(f, "Admin"),
+        }
+    }
+}
+
+impl<'a> FromSql<'a> for Role {
+    fn from_sql(_ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+        let s = String::from_utf8(raw.to_vec())?;
+        match s.as_str() {
+            "User" => Ok(Role::User),
+            "Moderator" => Ok(Role::Moderator),
+            "Admin" => Ok(Role::Admin),
+            _ => Err(format!("Invalid role value: {}", s).into()),
+        }
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        matches!(*ty, Type::TEXT | Type::VARCHAR | Type::NAME)
+    }
+}
+
 impl<'a> FromSql<'a> for StorageAccess {
-    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+    fn from_sql(_ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn StdError + Sync + Send>> {
          let s = String::from_utf8(raw.to_vec())?;
             match s.as_str() {
                 "Private" => Ok(StorageAccess::Private),
@@ -110,9 +266,649 @@ impl<'a> FromSql<'a> for StorageAccess {
     }
 
     fn accepts(ty: &Type) -> bool {
-        match *ty {
-            Type::TEXT | Type::VARCHAR | Type::NAME  => true,
-            _ => false,
+        matches!(*ty, Type::TEXT | Type::VARCHAR | Type::NAME)
+    }
+}
+
+/// Область доступа API-токена
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    #[serde(rename = "chat:read")]
+    ChatRead,
+    #[serde(rename = "chat:write")]
+    ChatWrite,
+    #[serde(rename = "files:read")]
+    FilesRead,
+    #[serde(rename = "files:write")]
+    FilesWrite,
+    #[serde(rename = "profile:read")]
+    ProfileRead,
+    #[serde(rename = "profile:write")]
+    ProfileWrite,
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::ChatRead => write!(f, "chat:read"),
+            Scope::ChatWrite => write!(f, "chat:write"),
+            Scope::FilesRead => write!(f, "files:read"),
+            Scope::FilesWrite => write!(f, "files:write"),
+            Scope::ProfileRead => write!(f, "profile:read"),
+            Scope::ProfileWrite => write!(f, "profile:write"),
+        }
+    }
+}
+
+impl<'a> FromSql<'a> for Scope {
+    fn from_sql(_ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+        let s = String::from_utf8(raw.to_vec())?;
+        match s.as_str() {
+            "chat:read" => Ok(Scope::ChatRead),
+            "chat:write" => Ok(Scope::ChatWrite),
+            "files:read" => Ok(Scope::FilesRead),
+            "files:write" => Ok(Scope::FilesWrite),
+            "profile:read" => Ok(Scope::ProfileRead),
+            "profile:write" => Ok(Scope::ProfileWrite),
+            _ => Err(format!("Invalid scope value: {}", s).into()),
+        }
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        matches!(*ty, Type::TEXT | Type::VARCHAR | Type::NAME)
+    }
+}
+
+/// Личный API-токен. Сам токен не хранится, только его хеш
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ApiToken {
+    pub token_id: Uuid,
+    #[serde(skip_serializing)]
+    pub user_uuid: Uuid,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Приложение, которое входит через учётные записи cyb3ria (OAuth2 / OIDC)
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OAuthClient {
+    pub client_id: String,
+    #[serde(skip_serializing)]
+    pub client_secret_hash: Option<String>,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub confidential: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Выданный, но ещё не обменянный код авторизации
+#[derive(Debug, Clone)]
+pub struct AuthorizationCode {
+    pub client_id: String,
+    pub user_uuid: Uuid,
+    pub redirect_uri: String,
+    pub scope: String,
+    pub nonce: Option<String>,
+    pub code_challenge: String,
+    pub auth_time: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Токен доступа OAuth2 (для /oauth/userinfo)
+#[derive(Debug, Clone)]
+pub struct OAuthAccessToken {
+    pub client_id: String,
+    pub user_uuid: Uuid,
+    pub scope: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Действие, которое попадает в журнал безопасности
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Login,
+    LoginFailed,
+    Logout,
+    PasswordChanged,
+    PasswordReset,
+    EmailChanged,
+    SessionsRevoked,
+    TwoFactorEnabled,
+    TwoFactorDisabled,
+    ApiTokenCreated,
+    ApiTokenRevoked,
+    ProfileVisibilityChanged,
+    FileDeleted,
+    RoleChanged,
+    UserSuspended,
+    UserUnsuspended,
+    UserDeleted,
+    DeviceUpdated,
+    DeviceRemoved,
+    SuspiciousLoginReported,
+    DataExportRequested,
+    AccountDeletionRequested,
+    AccountDeletionCancelled,
+    UsernameChanged,
+    DisplayNameChanged,
+    ReservedUsernameAdded,
+    ReservedUsernameRemoved,
+}
+
+impl AuditAction {
+    pub const ALL: &'static [AuditAction] = &[
+        AuditAction::Login,
+        AuditAction::LoginFailed,
+        AuditAction::Logout,
+        AuditAction::PasswordChanged,
+        AuditAction::PasswordReset,
+        AuditAction::EmailChanged,
+        AuditAction::SessionsRevoked,
+        AuditAction::TwoFactorEnabled,
+        AuditAction::TwoFactorDisabled,
+        AuditAction::ApiTokenCreated,
+        AuditAction::ApiTokenRevoked,
+        AuditAction::ProfileVisibilityChanged,
+        AuditAction::FileDeleted,
+        AuditAction::RoleChanged,
+        AuditAction::UserSuspended,
+        AuditAction::UserUnsuspended,
+        AuditAction::UserDeleted,
+        AuditAction::DeviceUpdated,
+        AuditAction::DeviceRemoved,
+        AuditAction::SuspiciousLoginReported,
+        AuditAction::DataExportRequested,
+        AuditAction::AccountDeletionRequested,
+        AuditAction::AccountDeletionCancelled,
+        AuditAction::UsernameChanged,
+        AuditAction::DisplayNameChanged,
+        AuditAction::ReservedUsernameAdded,
+        AuditAction::ReservedUsernameRemoved,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Login => "login",
+            AuditAction::LoginFailed => "login_failed",
+            AuditAction::Logout => "logout",
+            AuditAction::PasswordChanged => "password_changed",
+            AuditAction::PasswordReset => "password_reset",
+            AuditAction::EmailChanged => "email_changed",
+            AuditAction::SessionsRevoked => "sessions_revoked",
+            AuditAction::TwoFactorEnabled => "two_factor_enabled",
+            AuditAction::TwoFactorDisabled => "two_factor_disabled",
+            AuditAction::ApiTokenCreated => "api_token_created",
+            AuditAction::ApiTokenRevoked => "api_token_revoked",
+            AuditAction::ProfileVisibilityChanged => "profile_visibility_changed",
+            AuditAction::FileDeleted => "file_deleted",
+            AuditAction::RoleChanged => "role_changed",
+            AuditAction::UserSuspended => "user_suspended",
+            AuditAction::UserUnsuspended => "user_unsuspended",
+            AuditAction::UserDeleted => "user_deleted",
+            AuditAction::DeviceUpdated => "device_updated",
+            AuditAction::DeviceRemoved => "device_removed",
+            AuditAction::SuspiciousLoginReported => "suspicious_login_reported",
+            AuditAction::DataExportRequested => "data_export_requested",
+            AuditAction::AccountDeletionRequested => "account_deletion_requested",
+            AuditAction::AccountDeletionCancelled => "account_deletion_cancelled",
+            AuditAction::UsernameChanged => "username_changed",
+            AuditAction::DisplayNameChanged => "display_name_changed",
+            AuditAction::ReservedUsernameAdded => "reserved_username_added",
+            AuditAction::ReservedUsernameRemoved => "reserved_username_removed",
         }
     }
 }
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl<'a> FromSql<'a> for AuditAction {
+    fn from_sql(_ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+        let s = String::from_utf8(raw.to_vec())?;
+        AuditAction::ALL
+            .iter()
+            .find(|action| action.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("Invalid audit action value: {}", s).into())
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        matches!(*ty, Type::TEXT | Type::VARCHAR | Type::NAME)
+    }
+}
+
+/// Запись журнала безопасности
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AuditEvent {
+    pub event_id: i64,
+    pub actor_uuid: Option<Uuid>,
+    pub action: AuditAction,
+    pub target_uuid: Option<Uuid>,
+    pub target: Option<String>,
+    pub device_id: Option<Uuid>,
+    pub ip_address: Option<std::net::IpAddr>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Вид уведомления
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    NewLogin,
+    Mention,
+    /// Другой пользователь вложил ваш файл в своё сообщение
+    FileShared,
+}
+
+impl NotificationKind {
+    pub const ALL: &'static [NotificationKind] = &[
+        NotificationKind::NewLogin,
+        NotificationKind::Mention,
+        NotificationKind::FileShared,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::NewLogin => "new_login",
+            NotificationKind::Mention => "mention",
+            NotificationKind::FileShared => "file_shared",
+        }
+    }
+
+    /// Отправляется ли вид через Web Push, пока пользователь не выбрал сам
+    pub fn push_by_default(&self) -> bool {
+        match self {
+            NotificationKind::NewLogin => false,
+            NotificationKind::Mention => true,
+            NotificationKind::FileShared => false,
+        }
+    }
+}
+
+impl fmt::Display for NotificationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl<'a> FromSql<'a> for NotificationKind {
+    fn from_sql(_ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+        let s = String::from_utf8(raw.to_vec())?;
+        NotificationKind::ALL
+            .iter()
+            .find(|kind| kind.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("Invalid notification kind value: {}", s).into())
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        matches!(*ty, Type::TEXT | Type::VARCHAR | Type::NAME)
+    }
+}
+
+/// Уведомление пользователя
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Notification {
+    pub notification_id: Uuid,
+    pub user_uuid: Uuid,
+    pub kind: NotificationKind,
+    pub message: String,
+    pub link: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// Когда показано в чате (None — пользователь ещё не подключался)
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+/// Настройка вида уведомлений
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct NotificationPreference {
+    pub kind: NotificationKind,
+    /// Сохранять в центре уведомлений и показывать в чате
+    pub in_app: bool,
+    /// Отправлять через Web Push, когда чат не открыт
+    pub push: bool,
+}
+
+/// Изменение настройки вида уведомлений; не указанное остаётся как было
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NotificationPreferenceUpdate {
+    pub kind: NotificationKind,
+    pub in_app: Option<bool>,
+    pub push: Option<bool>,
+}
+
+/// Подписка браузера на Web Push
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PushSubscription {
+    pub subscription_id: Uuid,
+    #[serde(skip_serializing)]
+    pub user_uuid: Uuid,
+    pub endpoint: String,
+    /// Открытый ключ P-256 браузера, base64url
+    #[serde(skip_serializing)]
+    pub p256dh: String,
+    /// Секрет аутентификации браузера, base64url
+    #[serde(skip_serializing)]
+    pub auth: String,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_push_at: Option<DateTime<Utc>>,
+}
+
+/// Предупреждение о входе с незнакомого устройства
+#[derive(Debug, Clone)]
+pub struct LoginAlert {
+    pub alert_id: Uuid,
+    pub user_uuid: Uuid,
+    pub session_id: Uuid,
+    pub device_id: Uuid,
+    pub ip_address: Option<std::net::IpAddr>,
+    pub user_agent: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Состояние выгрузки личных данных
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportStatus {
+    Pending,
+    Ready,
+    Failed,
+}
+
+impl ExportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportStatus::Pending => "pending",
+            ExportStatus::Ready => "ready",
+            ExportStatus::Failed => "failed",
+        }
+    }
+}
+
+impl<'a> FromSql<'a> for ExportStatus {
+    fn from_sql(_ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+        let s = String::from_utf8(raw.to_vec())?;
+        match s.as_str() {
+            "pending" => Ok(ExportStatus::Pending),
+            "ready" => Ok(ExportStatus::Ready),
+            "failed" => Ok(ExportStatus::Failed),
+            _ => Err(format!("Invalid export status value: {}", s).into()),
+        }
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        matches!(*ty, Type::TEXT | Type::VARCHAR | Type::NAME)
+    }
+}
+
+/// Архив с личными данными пользователя
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DataExport {
+    pub export_id: Uuid,
+    pub user_uuid: Uuid,
+    pub status: ExportStatus,
+    pub size_bytes: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Прежнее имя пользователя
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UsernameChange {
+    pub old_username: String,
+    pub new_username: String,
+    pub changed_by: Option<Uuid>,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Имя, которое нельзя занять
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ReservedUsername {
+    pub username: String,
+    pub reason: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Сколько пользователей поставили эмодзи на сообщение
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: i64,
+    /// Среди них тот, кому отправлена сводка
+    #[serde(default)]
+    pub reacted: bool,
+}
+
+/// Реакция пользователя (для выгрузки данных)
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Reaction {
+    pub message_id: i64,
+    pub user_uuid: Uuid,
+    pub emoji: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Сообщение чата в том виде, в каком его получают клиенты
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ChatPayload {
+    pub message_id: i64,
+    pub user_uuid: Option<Uuid>,
+    /// Подпись автора; "Unknown User" для удалённых учётных записей
+    pub author: String,
+    /// Исходный текст в Markdown
+    pub text: String,
+    /// Текст, отрисованный в безопасный HTML
+    #[serde(default)]
+    pub html: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub reactions: Vec<ReactionCount>,
+    /// Процитированное сообщение
+    #[serde(default)]
+    pub reply_to: Option<QuotedMessage>,
+    /// Корень ветки, если сообщение написано в ветке
+    #[serde(default)]
+    pub thread_root: Option<i64>,
+    /// Сводка ветки под этим сообщением (None — ответов нет)
+    #[serde(default)]
+    pub thread: Option<ThreadSummary>,
+    /// Упоминания @username в тексте
+    #[serde(default)]
+    pub mentions: Vec<Mention>,
+    /// Вложенные файлы, по порядку
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+/// Файл, вложенный в сообщение
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub file_id: Uuid,
+    pub filename: String,
+    pub size_bytes: i64,
+    pub mime_type: String,
+    pub url: String,
+    /// Только у картинок
+    pub thumbnail_url: Option<String>,
+}
+
+/// Упоминание пользователя в сообщении; start и end — границы в символах текста
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Mention {
+    pub user_uuid: Uuid,
+    /// Текущее имя упомянутого
+    pub username: String,
+    pub start: i32,
+    pub end: i32,
+}
+
+/// Краткий вид сообщения для цитаты
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct QuotedMessage {
+    pub message_id: i64,
+    pub user_uuid: Option<Uuid>,
+    pub author: String,
+    /// None, если сообщение удалено модератором
+    pub text: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Кто отвечал в ветке
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ThreadParticipant {
+    pub user_uuid: Uuid,
+    pub author: String,
+}
+
+/// Сводка ветки: число ответов, последний ответ и участники
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ThreadSummary {
+    pub reply_count: i64,
+    pub last_reply: QuotedMessage,
+    /// В порядке первого ответа
+    pub participants: Vec<ThreadParticipant>,
+}
+
+/// Ветка целиком: корень и ответы, старые сначала
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ThreadHistory {
+    pub root: ChatPayload,
+    pub replies: Vec<ChatPayload>,
+}
+
+/// Последнее прочитанное сообщение в разговоре
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ReadMarker {
+    pub conversation: String,
+    pub last_read_message_id: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Непрочитанное в разговоре: общая лента или ветка, где пользователь участвует
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UnreadCount {
+    pub conversation: String,
+    /// Корень ветки (None — общая лента)
+    pub thread_root: Option<i64>,
+    pub last_read_message_id: Option<i64>,
+    pub last_message_id: Option<i64>,
+    /// Сообщения других пользователей после отметки
+    pub unread_count: i64,
+}
+
+/// Фильтры поиска по сообщениям; None — без ограничения
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilters {
+    pub author_uuid: Option<Uuid>,
+    /// Имя автора (без учёта регистра)
+    pub author_username: Option<String>,
+    /// Только общая лента (Some(None)) или только ветка (Some(Some(root)))
+    pub thread_root: Option<Option<i64>>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Только сообщения с вложениями (true) или без них (false)
+    pub has_attachment: Option<bool>,
+}
+
+/// Найденное сообщение
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SearchResult {
+    pub message: ChatPayload,
+    pub rank: f32,
+    /// Фрагменты текста: HTML, совпадения в <mark>
+    pub highlight: String,
+}
+
+/// Событие чата. Клиенты с ?protocol=json получают его как JSON,
+/// остальные — как строку текста (см. handlers::chat)
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChatEvent {
+    /// Первое сообщение после подключения
+    Hello { user_uuid: Uuid, username: String },
+    /// Последние сообщения, старые сначала
+    History { messages: Vec<ChatPayload> },
+    Message(Box<ChatPayload>),
+    Reaction {
+        message_id: i64,
+        emoji: String,
+        user_uuid: Uuid,
+        /// true — реакция добавлена, false — снята
+        added: bool,
+        count: i64,
+    },
+    /// Сводка ветки изменилась (новый ответ)
+    Thread {
+        message_id: i64,
+        thread: ThreadSummary,
+    },
+    /// Пользователь печатает; индикатор гаснет сам после expires_at
+    Typing {
+        user_uuid: Uuid,
+        author: String,
+        thread_root: Option<i64>,
+        expires_at: DateTime<Utc>,
+    },
+    /// Пользователь прочитал разговор до message_id
+    Read {
+        user_uuid: Uuid,
+        conversation: String,
+        message_id: i64,
+    },
+    /// Системное сообщение для одного пользователя
+    System {
+        message: String,
+        link: Option<String>,
+    },
+    /// Новое уведомление пользователя
+    Notification(Box<Notification>),
+    /// Изменилось число непрочитанных уведомлений (прочитаны или удалены)
+    NotificationCount { unread_count: i64 },
+    /// Включён или выключен медленный режим в ленте или ветке
+    SlowMode(SlowModeStatus),
+    /// Ошибка в ответ на запрос этого клиента
+    Error {
+        message: String,
+        /// Через сколько секунд можно повторить, если превышен лимит
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retry_after: Option<u64>,
+    },
+}
+
+/// Медленный режим: в ленте (thread_root = None) или ветке не чаще одного
+/// сообщения от пользователя за interval_seconds; 0 — режим выключен
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct SlowModeStatus {
+    pub thread_root: Option<i64>,
+    pub interval_seconds: u64,
+    /// Когда режим выключится сам
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Запрос модератора на медленный режим
+#[derive(Deserialize, Debug, Clone)]
+pub struct SlowModeRequest {
+    #[serde(default)]
+    pub thread_root: Option<i64>,
+    pub interval_seconds: u64,
+    pub duration_seconds: u64,
+}
+
+/// Какой медленный режим выключить
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SlowModeQuery {
+    pub thread_root: Option<i64>,
+}
@@ -0,0 +1,119 @@
+// src/notifications.rs
+//
+// Уведомления пользователя: сохраняются в центре уведомлений и сразу приходят
+// во все открытые чаты этого пользователя. Если чат не открыт, уведомление
+// придёт при следующем подключении, а ещё уходит через Web Push (см.
+// crate::push). Отключённые виды не сохраняются и не отправляются.
+use crate::db::notifications::{find_notification_preference, save_notification};
+use crate::models::{ChatEvent, Notification, NotificationKind};
+use crate::push::push_notification;
+use chrono::Utc;
+use log::debug;
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Сообщение для чатов одного пользователя
+#[derive(Debug, Clone)]
+pub struct UserEvent {
+    pub user_uuid: Uuid,
+    pub event: ChatEvent,
+}
+
+fn user_events() -> &'static broadcast::Sender<UserEvent> {
+    static USER_EVENTS: OnceLock<broadcast::Sender<UserEvent>> = OnceLock::new();
+    USER_EVENTS.get_or_init(|| broadcast::channel(100).0)
+}
+
+/// Подписка на личные сообщения (её держит каждое соединение чата)
+pub fn subscribe_user_events() -> broadcast::Receiver<UserEvent> {
+    user_events().subscribe()
+}
+
+fn connections() -> &'static Mutex<HashMap<Uuid, usize>> {
+    static CONNECTIONS: OnceLock<Mutex<HashMap<Uuid, usize>>> = OnceLock::new();
+    CONNECTIONS.get_or_init(Default::default)
+}
+
+/// Открытый чат пользователя; пока он жив, уведомления считаются доставленными
+pub struct ChatPresence {
+    user_uuid: Uuid,
+}
+
+impl ChatPresence {
+    pub fn new(user_uuid: Uuid) -> Self {
+        *connections().lock().unwrap().entry(user_uuid).or_default() += 1;
+        ChatPresence { user_uuid }
+    }
+}
+
+impl Drop for ChatPresence {
+    fn drop(&mut self) {
+        let mut connections = connections().lock().unwrap();
+        if let Some(count) = connections.get_mut(&self.user_uuid) {
+            *count -= 1;
+            if *count == 0 {
+                connections.remove(&self.user_uuid);
+            }
+        }
+    }
+}
+
+/// Открыт ли у пользователя чат
+pub fn is_connected(user_uuid: &Uuid) -> bool {
+    connections().lock().unwrap().contains_key(user_uuid)
+}
+
+/// Отправляет сообщение в открытые чаты пользователя, если они есть
+pub fn send_to_user(user_uuid: Uuid, event: ChatEvent) {
+    // Ошибка означает лишь, что сейчас никто не подключён
+    let _ = user_events().send(UserEvent { user_uuid, event });
+}
+
+/// Сохраняет уведомление и показывает его в чате; без открытого чата
+/// отправляет через Web Push. None — уведомление не сохранено в центре
+pub async fn notify(
+    user_uuid: Uuid,
+    kind: NotificationKind,
+    message: String,
+    link: Option<String>,
+) -> Result<Option<Notification>, Box<dyn StdError + Send + Sync>> {
+    let preference = find_notification_preference(&user_uuid, kind).await?;
+    if !preference.in_app && !preference.push {
+        debug!(
+            "{} notifications are off for user_uuid: {}",
+            kind, user_uuid
+        );
+        return Ok(None);
+    }
+    let connected = is_connected(&user_uuid);
+    let notification = Notification {
+        notification_id: Uuid::new_v4(),
+        user_uuid,
+        kind,
+        message,
+        link,
+        created_at: Utc::now(),
+        delivered_at: connected.then(Utc::now),
+        read_at: None,
+    };
+    if preference.push && !connected {
+        // Служба доставки может отвечать долго — не задерживаем отправителя
+        let notification = notification.clone();
+        tokio::spawn(async move { push_notification(&notification).await });
+    }
+    if !preference.in_app {
+        return Ok(None);
+    }
+    save_notification(&notification).await?;
+
+    send_to_user(user_uuid, notification_event(&notification));
+    Ok(Some(notification))
+}
+
+/// Событие чата для уведомления
+pub fn notification_event(notification: &Notification) -> ChatEvent {
+    ChatEvent::Notification(Box::new(notification.clone()))
+}
@@ -0,0 +1,154 @@
+// src/oidc.rs
+//
+// Ключ подписи ID-токенов (RS256), JWKS и проверка PKCE для провайдера
+// OpenID Connect. Ключ читается из OIDC_SIGNING_KEY_FILE (PKCS#8 PEM); без
+// него создаётся временный ключ, и выданные токены перестают проверяться
+// после перезапуска.
+use chrono::{DateTime, Utc};
+use data_encoding::BASE64URL_NOPAD;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use log::warn;
+use rsa::pkcs8::{DecodePrivateKey, EncodePrivateKey, LineEnding};
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPrivateKey;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::error::Error as StdError;
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+/// Области, которые понимает провайдер
+pub const SUPPORTED_SCOPES: &[&str] = &["openid", "profile", "email"];
+
+pub struct SigningKey {
+    pub kid: String,
+    encoding_key: EncodingKey,
+    jwk: Value,
+}
+
+/// Содержимое ID-токена
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: i64,
+    pub iat: i64,
+    pub auth_time: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+}
+
+/// Идентификатор провайдера (iss). По умолчанию совпадает с APP_BASE_URL
+pub fn issuer() -> String {
+    std::env::var("OIDC_ISSUER")
+        .or_else(|_| std::env::var("APP_BASE_URL"))
+        .unwrap_or_else(|_| "https://cyb3ria.xyz".to_string())
+}
+
+fn load_private_key() -> Result<RsaPrivateKey, Box<dyn StdError + Send + Sync>> {
+    match std::env::var("OIDC_SIGNING_KEY_FILE") {
+        Ok(path) => {
+            let pem = std::fs::read_to_string(&path)?;
+            Ok(RsaPrivateKey::from_pkcs8_pem(&pem)?)
+        }
+        Err(_) => {
+            warn!("OIDC_SIGNING_KEY_FILE is not set, using a temporary signing key");
+            Ok(RsaPrivateKey::new(&mut rand::thread_rng(), 2048)?)
+        }
+    }
+}
+
+fn build_signing_key() -> Result<SigningKey, Box<dyn StdError + Send + Sync>> {
+    let private_key = load_private_key()?;
+    let pem = private_key.to_pkcs8_pem(LineEnding::LF)?;
+    let encoding_key = EncodingKey::from_rsa_pem(pem.as_bytes())?;
+
+    let n = BASE64URL_NOPAD.encode(&private_key.n().to_bytes_be());
+    let e = BASE64URL_NOPAD.encode(&private_key.e().to_bytes_be());
+    // kid — отпечаток открытого ключа, меняется вместе с ключом
+    let kid = BASE64URL_NOPAD.encode(&Sha256::digest(format!("{}.{}", n, e).as_bytes())[..16]);
+    let jwk = json!({
+        "kty": "RSA",
+        "use": "sig",
+        "alg": "RS256",
+        "kid": kid,
+        "n": n,
+        "e": e,
+    });
+
+    Ok(SigningKey {
+        kid,
+        encoding_key,
+        jwk,
+    })
+}
+
+pub fn signing_key() -> &'static SigningKey {
+    static KEY: OnceLock<SigningKey> = OnceLock::new();
+    KEY.get_or_init(|| build_signing_key().expect("Failed to load OIDC signing key"))
+}
+
+/// Набор открытых ключей для /oauth/jwks
+pub fn jwks() -> Value {
+    json!({ "keys": [signing_key().jwk.clone()] })
+}
+
+/// Подписывает ID-токен
+pub fn sign_id_token(claims: &IdTokenClaims) -> Result<String, Box<dyn StdError + Send + Sync>> {
+    let key = signing_key();
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some(key.kid.clone());
+    Ok(jsonwebtoken::encode(&header, claims, &key.encoding_key)?)
+}
+
+pub fn id_token_claims(
+    client_id: &str,
+    user_uuid: &Uuid,
+    auth_time: DateTime<Utc>,
+    nonce: Option<String>,
+    preferred_username: Option<String>,
+    lifetime_seconds: i64,
+) -> IdTokenClaims {
+    let now = Utc::now().timestamp();
+    IdTokenClaims {
+        iss: issuer(),
+        sub: user_uuid.to_string(),
+        aud: client_id.to_string(),
+        exp: now + lifetime_seconds,
+        iat: now,
+        auth_time: auth_time.timestamp(),
+        nonce,
+        preferred_username,
+    }
+}
+
+/// PKCE S256: BASE64URL(SHA256(code_verifier)) == code_challenge
+pub fn verify_pkce(code_verifier: &str, code_challenge: &str) -> bool {
+    // RFC 7636: 43–128 символов из [A-Za-z0-9-._~]
+    let valid_verifier = (43..=128).contains(&code_verifier.len())
+        && code_verifier
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-._~".contains(&b));
+    valid_verifier
+        && BASE64URL_NOPAD.encode(&Sha256::digest(code_verifier.as_bytes())) == code_challenge
+}
+
+/// Хеш секрета (кода, токена доступа, секрета клиента) для хранения в базе
+pub fn hash_secret(secret: &str) -> String {
+    data_encoding::HEXLOWER.encode(&Sha256::digest(secret.as_bytes()))
+}
+
+/// Нормализует запрошенные области: только известные, без повторов, в исходном порядке
+pub fn normalize_scope(scope: &str) -> Vec<String> {
+    let mut scopes: Vec<String> = Vec::new();
+    for scope in scope.split_whitespace() {
+        if SUPPORTED_SCOPES.contains(&scope) && !scopes.iter().any(|s| s == scope) {
+            scopes.push(scope.to_string());
+        }
+    }
+    scopes
+}
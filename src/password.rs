@@ -0,0 +1,163 @@
+// src/password.rs
+//
+// Хеширование паролей. Новые хеши — Argon2id с параметрами из окружения
+// (ARGON2_MEMORY_KIB, ARGON2_ITERATIONS, ARGON2_PARALLELISM); старые хеши
+// bcrypt по-прежнему проверяются и заменяются при следующем входе.
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use std::error::Error as StdError;
+
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+pub const MAX_PASSWORD_LENGTH: usize = 128;
+
+/// Пароли, которые отклоняются независимо от длины
+const COMMON_PASSWORDS: &[&str] = &[
+    "password",
+    "password1",
+    "password123",
+    "12345678",
+    "123456789",
+    "1234567890",
+    "qwerty123",
+    "qwertyuiop",
+    "iloveyou",
+    "11111111",
+    "00000000",
+    "abc12345",
+    "letmein1",
+    "welcome1",
+    "admin123",
+    "football",
+    "baseball",
+    "sunshine",
+];
+
+fn env_param(name: &str, default: u32) -> u32 {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Текущие параметры Argon2id (по умолчанию — рекомендация OWASP: 19 МиБ, 2 прохода)
+pub fn argon2_params() -> Result<Params, Box<dyn StdError + Send + Sync>> {
+    Ok(Params::new(
+        env_param("ARGON2_MEMORY_KIB", 19 * 1024),
+        env_param("ARGON2_ITERATIONS", 2),
+        env_param("ARGON2_PARALLELISM", 1),
+        None,
+    )?)
+}
+
+fn argon2() -> Result<Argon2<'static>, Box<dyn StdError + Send + Sync>> {
+    Ok(Argon2::new(
+        Algorithm::Argon2id,
+        Version::V0x13,
+        argon2_params()?,
+    ))
+}
+
+/// Хеширует пароль текущим алгоритмом (строка PHC `$argon2id$...`)
+pub fn hash_password(password: &str) -> Result<String, Box<dyn StdError + Send + Sync>> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(argon2()?
+        .hash_password(password.as_bytes(), &salt)?
+        .to_string())
+}
+
+/// Проверяет пароль по хешу Argon2 или bcrypt
+pub fn verify_password(
+    password: &str,
+    password_hash: &str,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    if password_hash.starts_with("$argon2") {
+        let parsed = PasswordHash::new(password_hash)?;
+        match Argon2::default().verify_password(password.as_bytes(), &parsed) {
+            Ok(()) => Ok(true),
+            Err(argon2::password_hash::Error::Password) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    } else {
+        Ok(bcrypt::verify(password, password_hash)?)
+    }
+}
+
+/// hash_password в пуле блокирующих задач: Argon2 занимает процессор и память
+/// на десятки миллисекунд, и поток tokio не должен на это время останавливаться
+pub async fn hash_password_async(password: &str) -> Result<String, Box<dyn StdError + Send + Sync>> {
+    let password = password.to_string();
+    tokio::task::spawn_blocking(move || hash_password(&password)).await?
+}
+
+/// verify_password в пуле блокирующих задач
+pub async fn verify_password_async(
+    password: &str,
+    password_hash: &str,
+) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+    let password = password.to_string();
+    let password_hash = password_hash.to_string();
+    tokio::task::spawn_blocking(move || verify_password(&password, &password_hash)).await?
+}
+
+/// Нужно ли пересчитать хеш: другой алгоритм или устаревшие параметры
+pub fn needs_rehash(password_hash: &str) -> bool {
+    let Ok(parsed) = PasswordHash::new(password_hash) else {
+        return true;
+    };
+    if parsed.algorithm != Algorithm::Argon2id.ident() {
+        return true;
+    }
+    match (Params::try_from(&parsed), argon2_params()) {
+        (Ok(stored), Ok(current)) => {
+            stored.m_cost() != current.m_cost()
+                || stored.t_cost() != current.t_cost()
+                || stored.p_cost() != current.p_cost()
+        }
+        _ => true,
+    }
+}
+
+/// Проверяет стойкость нового пароля. Возвращает текст ошибки
+pub fn check_password_strength(password: &str, username: &str) -> Option<String> {
+    let length = password.chars().count();
+    if !(MIN_PASSWORD_LENGTH..=MAX_PASSWORD_LENGTH).contains(&length) {
+        return Some(format!(
+            "Password must be between {} and {} characters",
+            MIN_PASSWORD_LENGTH, MAX_PASSWORD_LENGTH
+        ));
+    }
+
+    let lowercase = password.to_lowercase();
+    if COMMON_PASSWORDS.contains(&lowercase.as_str()) {
+        return Some("Password is too common".to_string());
+    }
+    if !username.is_empty() && lowercase.contains(&username.to_lowercase()) {
+        return Some("Password must not contain the username".to_string());
+    }
+
+    let mut unique = password.chars().collect::<Vec<_>>();
+    unique.sort_unstable();
+    unique.dedup();
+    if unique.len() < 4 {
+        return Some("Password has too many repeated characters".to_string());
+    }
+
+    // Короткий пароль должен сочетать разные типы символов; длинная фраза — нет
+    let classes = [
+        password.chars().any(|c| c.is_lowercase()),
+        password.chars().any(|c| c.is_uppercase()),
+        password.chars().any(|c| c.is_numeric()),
+        password.chars().any(|c| !c.is_alphanumeric()),
+    ]
+    .iter()
+    .filter(|&&present| present)
+    .count();
+    if length < 16 && classes < 2 {
+        return Some(
+            "Password shorter than 16 characters must mix letters, digits or symbols".to_string(),
+        );
+    }
+
+    None
+}
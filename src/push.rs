@@ -0,0 +1,453 @@
+// src/push.rs
+//
+// Web Push (RFC 8030) для пользователей, у которых не открыт чат. Содержимое
+// шифруется ключами подписки браузера (RFC 8291, aes128gcm), запрос к службе
+// доставки подписывается ключом VAPID (RFC 8292). Ключ читается из
+// VAPID_PRIVATE_KEY_FILE (PKCS#8 PEM, P-256); без него создаётся временный
+// ключ, и после перезапуска браузерам придётся подписаться заново.
+// PUSH_ALLOW_HTTP=1 разрешает адреса http:// — для локальной службы доставки.
+// Адреса служб, ведущие во внутреннюю сеть (loopback, частные, link-local),
+// отклоняются и при подписке, и при каждой доставке; PUSH_TRUSTED_HOSTS —
+// список хостов через запятую, которым это разрешено (локальная служба).
+use crate::db::push_subscriptions::{
+    delete_push_subscription_by_endpoint, list_push_subscriptions, touch_push_subscription,
+};
+use crate::handlers::auth::password::app_base_url;
+use crate::models::{Notification, NotificationKind, PushSubscription};
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes128Gcm, KeyInit, Nonce};
+use chrono::Utc;
+use data_encoding::BASE64URL_NOPAD;
+use hkdf::Hkdf;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use log::{debug, info, warn};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::pkcs8::{DecodePrivateKey, EncodePrivateKey, LineEnding};
+use p256::{PublicKey, SecretKey};
+use rand::RngCore;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::error::Error as StdError;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Размер записи aes128gcm; всё сообщение умещается в одну запись
+const RECORD_SIZE: u32 = 4096;
+/// Заголовок: соль (16), размер записи (4), длина ключа (1), ключ сервера (65)
+const HEADER_SIZE: usize = 86;
+/// Наибольший открытый текст: запись без заголовка, тега AES-GCM и разделителя
+pub const MAX_PAYLOAD_SIZE: usize = RECORD_SIZE as usize - HEADER_SIZE - 16 - 1;
+/// Сколько служба доставки хранит сообщение, пока браузер недоступен
+const PUSH_TTL_SECONDS: u32 = 24 * 60 * 60;
+/// Срок действия подписи VAPID (RFC 8292 — не больше суток)
+const VAPID_LIFETIME_SECONDS: i64 = 12 * 60 * 60;
+const MAX_ENDPOINT_LENGTH: usize = 2048;
+
+pub struct VapidKey {
+    encoding_key: EncodingKey,
+    /// Открытый ключ (несжатая точка P-256, base64url) — applicationServerKey браузера
+    pub public_key: String,
+}
+
+/// Что получает service worker браузера
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PushMessage {
+    pub notification_id: Uuid,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+    pub link: Option<String>,
+}
+
+#[derive(Serialize)]
+struct VapidClaims {
+    aud: String,
+    exp: i64,
+    sub: String,
+}
+
+fn load_private_key() -> Result<SecretKey, Box<dyn StdError + Send + Sync>> {
+    match std::env::var("VAPID_PRIVATE_KEY_FILE") {
+        Ok(path) => {
+            let pem = std::fs::read_to_string(&path)?;
+            Ok(SecretKey::from_pkcs8_pem(&pem)?)
+        }
+        Err(_) => {
+            warn!("VAPID_PRIVATE_KEY_FILE is not set, using a temporary VAPID key");
+            Ok(SecretKey::random(&mut rand::thread_rng()))
+        }
+    }
+}
+
+fn build_vapid_key() -> Result<VapidKey, Box<dyn StdError + Send + Sync>> {
+    let private_key = load_private_key()?;
+    let pem = private_key.to_pkcs8_pem(LineEnding::LF)?;
+    let encoding_key = EncodingKey::from_ec_pem(pem.as_bytes())?;
+    let public_key =
+        BASE64URL_NOPAD.encode(private_key.public_key().to_encoded_point(false).as_bytes());
+    Ok(VapidKey {
+        encoding_key,
+        public_key,
+    })
+}
+
+pub fn vapid_key() -> &'static VapidKey {
+    static KEY: OnceLock<VapidKey> = OnceLock::new();
+    KEY.get_or_init(|| build_vapid_key().expect("Failed to load VAPID key"))
+}
+
+/// Контакт для служб доставки (sub в подписи VAPID)
+fn vapid_subject() -> String {
+    std::env::var("VAPID_SUBJECT").unwrap_or_else(|_| app_base_url())
+}
+
+/// Заголовок Authorization для службы доставки (RFC 8292)
+fn vapid_authorization(endpoint: &Url) -> Result<String, Box<dyn StdError + Send + Sync>> {
+    let key = vapid_key();
+    let claims = VapidClaims {
+        aud: endpoint.origin().ascii_serialization(),
+        exp: Utc::now().timestamp() + VAPID_LIFETIME_SECONDS,
+        sub: vapid_subject(),
+    };
+    let token = jsonwebtoken::encode(&Header::new(Algorithm::ES256), &claims, &key.encoding_key)?;
+    Ok(format!("vapid t={}, k={}", token, key.public_key))
+}
+
+/// base64url с выравниванием или без (браузеры отдают без)
+fn decode_base64url(value: &str) -> Option<Vec<u8>> {
+    BASE64URL_NOPAD
+        .decode(value.trim_end_matches('=').as_bytes())
+        .ok()
+}
+
+fn decode_p256dh(p256dh: &str) -> Result<PublicKey, &'static str> {
+    let bytes = decode_base64url(p256dh).ok_or("p256dh must be base64url")?;
+    PublicKey::from_sec1_bytes(&bytes).map_err(|_| "p256dh must be a P-256 public key")
+}
+
+fn decode_auth(auth: &str) -> Result<Vec<u8>, &'static str> {
+    match decode_base64url(auth) {
+        Some(bytes) if bytes.len() == 16 => Ok(bytes),
+        _ => Err("auth must be 16 bytes of base64url"),
+    }
+}
+
+/// Проверяет ключи подписки: p256dh — точка P-256, auth — 16 байт
+pub fn validate_subscription_keys(p256dh: &str, auth: &str) -> Result<(), &'static str> {
+    decode_p256dh(p256dh)?;
+    decode_auth(auth)?;
+    Ok(())
+}
+
+/// Проверяет адрес службы доставки: только https (http — при PUSH_ALLOW_HTTP)
+/// и не внутренний адрес или имя, если хост не из PUSH_TRUSTED_HOSTS
+pub fn validate_endpoint(endpoint: &str) -> Result<Url, &'static str> {
+    if endpoint.len() > MAX_ENDPOINT_LENGTH {
+        return Err("Push endpoint is too long");
+    }
+    let url = Url::parse(endpoint).map_err(|_| "Push endpoint must be a URL")?;
+    let allow_http = std::env::var("PUSH_ALLOW_HTTP").is_ok_and(|value| value == "1");
+    match url.scheme() {
+        "https" => {}
+        "http" if allow_http => {}
+        _ => return Err("Push endpoint must use https"),
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err("Push endpoint must be a URL");
+    }
+    let Some(host) = url.host_str() else {
+        return Err("Push endpoint must be a URL");
+    };
+    if is_trusted_host(host) {
+        return Ok(url);
+    }
+    let internal = match host_address(host) {
+        Some(ip) => is_internal_address(ip),
+        None => is_internal_domain(host),
+    };
+    if internal {
+        return Err(INTERNAL_ENDPOINT);
+    }
+    Ok(url)
+}
+
+/// То же, что validate_endpoint, и вдобавок имя хоста разрешается, а все его
+/// адреса должны быть публичными. Для новых подписок; при доставке адреса
+/// проверяет resolver клиента
+pub async fn validate_endpoint_host(endpoint: &str) -> Result<Url, &'static str> {
+    let url = validate_endpoint(endpoint)?;
+    let (Some(domain), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return Ok(url);
+    };
+    if host_address(domain).is_some() || is_trusted_host(domain) {
+        return Ok(url);
+    }
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((domain, port))
+        .await
+        .map_err(|_| "Push endpoint host could not be resolved")?
+        .collect();
+    if addresses.is_empty() {
+        return Err("Push endpoint host could not be resolved");
+    }
+    if addresses.iter().any(|address| is_internal_address(address.ip())) {
+        return Err(INTERNAL_ENDPOINT);
+    }
+    Ok(url)
+}
+
+const INTERNAL_ENDPOINT: &str = "Push endpoint must be a public address";
+
+/// Адрес из хоста URL, если хост записан адресом (IPv6 — в квадратных скобках)
+fn host_address(host: &str) -> Option<IpAddr> {
+    host.trim_start_matches('[').trim_end_matches(']').parse().ok()
+}
+
+/// Хосты из PUSH_TRUSTED_HOSTS, для которых внутренние адреса допустимы
+fn is_trusted_host(host: &str) -> bool {
+    std::env::var("PUSH_TRUSTED_HOSTS").is_ok_and(|hosts| {
+        hosts
+            .split(',')
+            .any(|trusted| trusted.trim().eq_ignore_ascii_case(host))
+    })
+}
+
+/// Имена, которые заведомо указывают внутрь сети: localhost, служебные зоны
+/// и имена без точки, которые дополняются локальными доменами поиска
+fn is_internal_domain(domain: &str) -> bool {
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    domain == "localhost"
+        || !domain.contains('.')
+        || [".localhost", ".internal", ".local", ".lan", ".home.arpa"]
+            .iter()
+            .any(|suffix| domain.ends_with(suffix))
+}
+
+/// Адрес, который не должен быть доступен снаружи: loopback, частные сети,
+/// link-local (включая 169.254.169.254 облачных метаданных), unique-local,
+/// общий адрес провайдера, широковещательные и зарезервированные
+pub fn is_internal_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (b & 0xc0) == 64)
+                || (a == 192 && b == 0 && c == 0)
+                || (a == 198 && (b & 0xfe) == 18)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_internal_address(IpAddr::V4(mapped));
+            }
+            let first = ip.segments()[0];
+            ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first & 0xffc0) == 0xfec0
+                || (first == 0x2001 && ip.segments()[1] == 0x0db8)
+        }
+    }
+}
+
+/// Разрешает имена служб доставки, отбрасывая внутренние адреса. Проверка
+/// идёт при каждом соединении, так что имя, которое после подписки стало
+/// указывать внутрь сети, не даёт отправить туда запрос
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let trusted = is_trusted_host(&host);
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|address| trusted || !is_internal_address(address.ip()))
+                .collect();
+            if addresses.is_empty() {
+                return Err(format!("{} has no public addresses", host).into());
+            }
+            let addresses: Addrs = Box::new(addresses.into_iter());
+            Ok(addresses)
+        })
+    }
+}
+
+/// Шифрует содержимое для подписки (RFC 8291) новым ключом сервера и солью
+pub fn encrypt_payload(
+    plaintext: &[u8],
+    p256dh: &str,
+    auth: &str,
+) -> Result<Vec<u8>, Box<dyn StdError + Send + Sync>> {
+    let ua_public = decode_p256dh(p256dh)?;
+    let auth_secret = decode_auth(auth)?;
+    let as_secret = SecretKey::random(&mut rand::thread_rng());
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    encrypt_payload_with(plaintext, &ua_public, &auth_secret, &as_secret, &salt)
+}
+
+/// Шифрование с заданными ключом сервера и солью. Возвращает тело запроса:
+/// заголовок aes128gcm (RFC 8188) и единственную запись
+pub fn encrypt_payload_with(
+    plaintext: &[u8],
+    ua_public: &PublicKey,
+    auth_secret: &[u8],
+    as_secret: &SecretKey,
+    salt: &[u8; 16],
+) -> Result<Vec<u8>, Box<dyn StdError + Send + Sync>> {
+    if plaintext.len() > MAX_PAYLOAD_SIZE {
+        return Err("Push payload is too large".into());
+    }
+    let ua_point = ua_public.to_encoded_point(false);
+    let as_point = as_secret.public_key().to_encoded_point(false);
+    let shared_secret =
+        p256::ecdh::diffie_hellman(as_secret.to_nonzero_scalar(), ua_public.as_affine());
+
+    // IKM = HKDF(auth_secret, ecdh_secret, "WebPush: info" || 0 || ua_public || as_public)
+    let mut key_info = b"WebPush: info\0".to_vec();
+    key_info.extend_from_slice(ua_point.as_bytes());
+    key_info.extend_from_slice(as_point.as_bytes());
+    let mut ikm = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(auth_secret), shared_secret.raw_secret_bytes())
+        .expand(&key_info, &mut ikm)
+        .map_err(|e| e.to_string())?;
+
+    let hkdf = Hkdf::<Sha256>::new(Some(salt), &ikm);
+    let mut cek = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    hkdf.expand(b"Content-Encoding: aes128gcm\0", &mut cek)
+        .map_err(|e| e.to_string())?;
+    hkdf.expand(b"Content-Encoding: nonce\0", &mut nonce)
+        .map_err(|e| e.to_string())?;
+
+    // Разделитель 0x02 отмечает последнюю запись; дополнение не нужно
+    let mut record = plaintext.to_vec();
+    record.push(0x02);
+    let cipher = Aes128Gcm::new_from_slice(&cek).map_err(|e| e.to_string())?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), record.as_slice())
+        .map_err(|_| "Failed to encrypt push payload")?;
+
+    let mut body = Vec::with_capacity(HEADER_SIZE + ciphertext.len());
+    body.extend_from_slice(salt);
+    body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+    body.push(as_point.as_bytes().len() as u8);
+    body.extend_from_slice(as_point.as_bytes());
+    body.extend_from_slice(&ciphertext);
+    Ok(body)
+}
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()
+            .expect("Failed to build push HTTP client")
+    })
+}
+
+/// Отправляет зашифрованное сообщение в службу доставки подписки.
+/// Подписки, которые служба больше не знает (404, 410), удаляются
+pub async fn send_push(
+    subscription: &PushSubscription,
+    payload: &[u8],
+) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let endpoint = validate_endpoint(&subscription.endpoint)?;
+    let body = encrypt_payload(payload, &subscription.p256dh, &subscription.auth)?;
+    let response = http_client()
+        .post(endpoint.clone())
+        .header("TTL", PUSH_TTL_SECONDS.to_string())
+        .header("Content-Encoding", "aes128gcm")
+        .header("Content-Type", "application/octet-stream")
+        .header("Urgency", "normal")
+        .header("Authorization", vapid_authorization(&endpoint)?)
+        .body(body)
+        .send()
+        .await?;
+
+    match response.status() {
+        status if status.is_success() => {
+            touch_push_subscription(&subscription.subscription_id).await?;
+            Ok(())
+        }
+        StatusCode::NOT_FOUND | StatusCode::GONE => {
+            info!(
+                "Push subscription {} expired, removing it",
+                subscription.subscription_id
+            );
+            delete_push_subscription_by_endpoint(&subscription.endpoint).await?;
+            Ok(())
+        }
+        status => Err(format!("Push service responded with {}", status).into()),
+    }
+}
+
+fn push_title(kind: NotificationKind) -> &'static str {
+    match kind {
+        NotificationKind::NewLogin => "New sign-in to your account",
+        NotificationKind::Mention => "You were mentioned",
+        NotificationKind::FileShared => "Your file was shared",
+    }
+}
+
+/// Отправляет уведомление во все подписки пользователя
+pub async fn push_notification(notification: &Notification) {
+    let subscriptions = match list_push_subscriptions(&notification.user_uuid).await {
+        Ok(subscriptions) => subscriptions,
+        Err(e) => {
+            warn!(
+                "Failed to load push subscriptions for user_uuid {}: {}",
+                notification.user_uuid, e
+            );
+            return;
+        }
+    };
+    if subscriptions.is_empty() {
+        return;
+    }
+
+    let message = PushMessage {
+        notification_id: notification.notification_id,
+        kind: notification.kind,
+        title: push_title(notification.kind).to_string(),
+        body: notification.message.clone(),
+        link: notification.link.clone(),
+    };
+    let payload = match serde_json::to_vec(&message) {
+        Ok(payload) => payload,
+        Err(e) => {
+            warn!("Failed to serialize push message: {}", e);
+            return;
+        }
+    };
+    debug!(
+        "Pushing {} notification to {} subscriptions of user_uuid: {}",
+        notification.kind,
+        subscriptions.len(),
+        notification.user_uuid
+    );
+    for subscription in &subscriptions {
+        if let Err(e) = send_push(subscription, &payload).await {
+            warn!(
+                "Failed to push to subscription {}: {}",
+                subscription.subscription_id, e
+            );
+        }
+    }
+}
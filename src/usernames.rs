@@ -0,0 +1,115 @@
+// src/usernames.rs
+//
+// Правила для имён пользователей: формат, зарезервированные имена, удержание
+// освобождённых имён (USERNAME_RELEASE_HOLD_DAYS) и частота смены
+// (USERNAME_CHANGE_INTERVAL_DAYS). Отображаемое имя не используется для входа.
+use crate::db::usernames::{is_username_held, is_username_reserved, is_username_taken};
+use crate::models::User;
+use chrono::{DateTime, Duration, Utc};
+use std::error::Error as StdError;
+use uuid::Uuid;
+
+pub const MIN_USERNAME_LENGTH: usize = 3;
+pub const MAX_USERNAME_LENGTH: usize = 16;
+pub const MAX_DISPLAY_NAME_LENGTH: usize = 32;
+
+const DEFAULT_CHANGE_INTERVAL_DAYS: i64 = 30;
+const DEFAULT_RELEASE_HOLD_DAYS: i64 = 90;
+
+fn env_days(name: &str, default: i64) -> i64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Как часто можно менять имя
+pub fn change_interval_days() -> i64 {
+    env_days(
+        "USERNAME_CHANGE_INTERVAL_DAYS",
+        DEFAULT_CHANGE_INTERVAL_DAYS,
+    )
+}
+
+/// Сколько дней освобождённое имя недоступно другим
+pub fn release_hold_days() -> i64 {
+    env_days("USERNAME_RELEASE_HOLD_DAYS", DEFAULT_RELEASE_HOLD_DAYS)
+}
+
+fn has_invisible_edges(name: &str) -> bool {
+    name.trim() != name || name.chars().any(char::is_control)
+}
+
+/// Проверяет формат имени. Возвращает текст ошибки
+pub fn check_username_format(username: &str) -> Option<String> {
+    if username.len() < MIN_USERNAME_LENGTH || username.len() > MAX_USERNAME_LENGTH {
+        return Some(format!(
+            "Username must be between {} and {} characters",
+            MIN_USERNAME_LENGTH, MAX_USERNAME_LENGTH
+        ));
+    }
+    if has_invisible_edges(username) {
+        return Some(
+            "Username must not start or end with spaces or contain control characters".to_string(),
+        );
+    }
+    None
+}
+
+/// Можно ли занять имя. Возвращает текст ошибки.
+/// user_uuid — кто занимает: своё прежнее имя можно вернуть до конца удержания
+pub async fn check_username_available(
+    username: &str,
+    user_uuid: Option<&Uuid>,
+) -> Result<Option<&'static str>, Box<dyn StdError + Send + Sync>> {
+    if is_username_taken(username, user_uuid).await? {
+        return Ok(Some("Username is already taken."));
+    }
+    if is_username_reserved(username).await? {
+        return Ok(Some("This username is reserved."));
+    }
+    let since = Utc::now() - Duration::days(release_hold_days());
+    if is_username_held(username, user_uuid, since).await? {
+        return Ok(Some(
+            "This username was released recently and is not available yet.",
+        ));
+    }
+    Ok(None)
+}
+
+/// Когда пользователь снова сможет сменить имя (None — уже может)
+pub fn next_username_change(user: &User) -> Option<DateTime<Utc>> {
+    let next = user.username_changed_at? + Duration::days(change_interval_days());
+    (next > Utc::now()).then_some(next)
+}
+
+/// Проверяет отображаемое имя (уже без пробелов по краям). Возвращает текст ошибки
+pub async fn check_display_name(
+    display_name: &str,
+) -> Result<Option<String>, Box<dyn StdError + Send + Sync>> {
+    if display_name.chars().count() > MAX_DISPLAY_NAME_LENGTH {
+        return Ok(Some(format!(
+            "Display name must be at most {} characters",
+            MAX_DISPLAY_NAME_LENGTH
+        )));
+    }
+    if has_invisible_edges(display_name) {
+        return Ok(Some(
+            "Display name must not contain control characters".to_string(),
+        ));
+    }
+    if is_username_reserved(display_name).await? {
+        return Ok(Some("This display name is reserved.".to_string()));
+    }
+    Ok(None)
+}
+
+/// Как пользователь подписан в чате: отображаемое имя и, рядом, имя для входа
+pub fn chat_name(username: &str, display_name: Option<&str>) -> String {
+    match display_name {
+        Some(display_name) if display_name != username => {
+            format!("{} (@{})", display_name, username)
+        }
+        _ => username.to_string(),
+    }
+}
@@ -12,10 +12,365 @@ fetch('https://api.ipify.org?format=json')
         console.error('Error fetching IP address:', error);
     });
 
-// MAC-адрес (заглушка)
-let macAddress = '00:00:00:00:00:00';
-
 let ws = null; // Глобальная переменная для WebSocket
+let currentUser = null; // user_uuid из события hello
+const QUICK_REACTIONS = ['👍', '❤️', '😂', '😮', '😢'];
+let replyTo = null; // { message_id, author } цитируемого сообщения
+let openThread = null; // message_id корня открытой ветки
+const typingUsers = new Map(); // user_uuid -> { author, thread_root, expires }
+const seenBy = new Map(); // `${conversation}:${user_uuid}` -> message_id
+let lastTypingSent = 0;
+let readTimer = null;
+
+function renderTyping() {
+    const typing = document.getElementById('typing');
+    if (!typing) return;
+    const now = Date.now();
+    const names = [];
+    typingUsers.forEach((state, userUuid) => {
+        if (state.expires <= now) {
+            typingUsers.delete(userUuid);
+        } else if (state.thread_root === openThread || state.thread_root === null) {
+            names.push(state.author);
+        }
+    });
+    typing.textContent = names.length ? `${names.join(', ')} typing...` : '';
+}
+setInterval(renderTyping, 1000);
+
+function notifyTyping() {
+    // Сервер всё равно пропускает не больше одного события за несколько секунд
+    if (!ws || Date.now() - lastTypingSent < 3000) return;
+    lastTypingSent = Date.now();
+    ws.send(JSON.stringify({ type: 'typing', thread_root: openThread }));
+}
+
+// Отмечаем прочитанным последнее сообщение, когда вкладка видна
+function scheduleRead(messageId) {
+    if (!ws || document.hidden) return;
+    clearTimeout(readTimer);
+    readTimer = setTimeout(() => ws.send(JSON.stringify({ type: 'read', message_id: messageId })), 500);
+}
+
+function renderSeen(conversation) {
+    const counts = new Map();
+    seenBy.forEach((messageId, key) => {
+        if (key.startsWith(`${conversation}:`) && !key.endsWith(`:${currentUser}`)) {
+            counts.set(messageId, (counts.get(messageId) || 0) + 1);
+        }
+    });
+    const listId = conversation === 'main' ? 'messages' : 'thread-messages';
+    document.querySelectorAll(`#${listId} li`).forEach(li => {
+        let seen = li.querySelector('.seen');
+        const count = counts.get(li.messageId);
+        if (!count) {
+            if (seen) seen.remove();
+            return;
+        }
+        if (!seen) {
+            seen = document.createElement('small');
+            seen.className = 'seen';
+            li.appendChild(seen);
+        }
+        seen.textContent = `Seen by ${count}`;
+    });
+}
+
+function applyRead(event) {
+    seenBy.set(`${event.conversation}:${event.user_uuid}`, event.message_id);
+    renderSeen(event.conversation);
+}
+
+function appendLine(text, listId = 'messages') {
+    const li = document.createElement('li');
+    li.textContent = text;
+    const messages = document.getElementById(listId);
+    if (messages) {
+        messages.appendChild(li);
+        messages.scrollTop = messages.scrollHeight;
+    }
+    return li;
+}
+
+function setReplyTarget(payload) {
+    replyTo = payload ? { message_id: payload.message_id, author: payload.author } : null;
+    const target = document.getElementById('reply-target');
+    if (!target) return;
+    target.hidden = !replyTo;
+    target.textContent = replyTo ? `Replying to ${replyTo.author} (click to cancel)` : '';
+}
+
+function threadSummaryText(thread) {
+    const replies = thread.reply_count === 1 ? '1 reply' : `${thread.reply_count} replies`;
+    const names = thread.participants.map(p => p.author).join(', ');
+    return `${replies} · last by ${thread.last_reply.author} · ${names}`;
+}
+
+function renderThreadSummary(li, thread) {
+    let summary = li.querySelector('.thread-summary');
+    if (!summary) {
+        summary = document.createElement('button');
+        summary.type = 'button';
+        summary.className = 'thread-summary';
+        summary.addEventListener('click', () => showThread(li.messageId));
+        li.appendChild(summary);
+    }
+    summary.textContent = threadSummaryText(thread);
+}
+
+async function showThread(messageId) {
+    const response = await fetch(`/api/messages/${messageId}/thread`, { credentials: 'same-origin' });
+    if (!response.ok) {
+        console.error('Failed to load thread:', response.status);
+        return;
+    }
+    const history = await response.json();
+    openThread = history.root.message_id;
+    const list = document.getElementById('thread-messages');
+    list.textContent = '';
+    appendMessage(history.root, 'thread-messages');
+    history.replies.forEach(reply => appendMessage(reply, 'thread-messages'));
+    document.getElementById('thread').hidden = false;
+    const last = history.replies[history.replies.length - 1];
+    if (last) scheduleRead(last.message_id);
+}
+
+function sendReaction(messageId, emoji, add) {
+    if (ws) {
+        ws.send(JSON.stringify({ type: add ? 'react' : 'unreact', message_id: messageId, emoji }));
+    }
+}
+
+// Кнопки реакций под сообщением: { emoji: { count, reacted } }
+function renderReactions(li) {
+    const box = li.querySelector('.reactions');
+    box.textContent = '';
+    const reactions = li.reactions;
+    const emojis = Object.keys(reactions).filter(emoji => reactions[emoji].count > 0);
+    QUICK_REACTIONS.forEach(emoji => { if (!emojis.includes(emoji)) emojis.push(emoji); });
+    emojis.forEach(emoji => {
+        const state = reactions[emoji] || { count: 0, reacted: false };
+        const button = document.createElement('button');
+        button.type = 'button';
+        button.className = state.reacted ? 'reaction reacted' : 'reaction';
+        button.textContent = state.count > 0 ? `${emoji} ${state.count}` : emoji;
+        button.addEventListener('click', () => sendReaction(li.messageId, emoji, !state.reacted));
+        box.appendChild(button);
+    });
+}
+
+// Текст сообщения с выделенными упоминаниями (границы упоминаний — в символах)
+function appendText(parent, payload) {
+    const chars = Array.from(payload.text);
+    let pos = 0;
+    (payload.mentions || []).forEach(mention => {
+        parent.append(chars.slice(pos, mention.start).join(''));
+        const span = document.createElement('span');
+        span.className = mention.user_uuid === currentUser ? 'mention mention-me' : 'mention';
+        span.textContent = chars.slice(mention.start, mention.end).join('');
+        span.title = mention.username;
+        parent.appendChild(span);
+        pos = mention.end;
+    });
+    parent.append(chars.slice(pos).join(''));
+}
+
+// Выделяет упоминания в уже отрисованном HTML сообщения (кроме кода и ссылок)
+function highlightMentions(root, payload) {
+    const mentions = payload.mentions || [];
+    if (!mentions.length) return;
+    const byName = new Map(mentions.map(m => [m.username.toLowerCase(), m]));
+    const walker = document.createTreeWalker(root, NodeFilter.SHOW_TEXT);
+    const nodes = [];
+    while (walker.nextNode()) {
+        if (!walker.currentNode.parentElement.closest('code, a')) nodes.push(walker.currentNode);
+    }
+    nodes.forEach(node => {
+        const parts = node.textContent.split(/(@[^\s@]+)/);
+        if (parts.length === 1) return;
+        const fragment = document.createDocumentFragment();
+        parts.forEach(part => {
+            const name = part.startsWith('@') ? part.slice(1).replace(/[!-\/:-@\[-`{-~]+$/, '') : null;
+            const mention = name && byName.get(name.toLowerCase());
+            if (!mention) {
+                fragment.append(part);
+                return;
+            }
+            const span = document.createElement('span');
+            span.className = mention.user_uuid === currentUser ? 'mention mention-me' : 'mention';
+            span.textContent = `@${name}`;
+            span.title = mention.username;
+            fragment.append(span, part.slice(name.length + 1));
+        });
+        node.replaceWith(fragment);
+    });
+}
+
+function appendMessage(payload, listId = 'messages') {
+    const li = appendLine(`${payload.author}: `, listId);
+    if (!li) return;
+    if (payload.html) {
+        // HTML приходит с сервера уже очищенным от всего, кроме разрешённых тегов
+        const body = document.createElement('div');
+        body.className = 'message-body';
+        body.innerHTML = payload.html;
+        highlightMentions(body, payload);
+        li.appendChild(body);
+    } else {
+        appendText(li, payload);
+    }
+    if (listId === 'messages') li.id = `message-${payload.message_id}`;
+    if (payload.reply_to) {
+        const quote = document.createElement('blockquote');
+        const text = payload.reply_to.text === null ? 'deleted message' : payload.reply_to.text;
+        quote.textContent = `${payload.reply_to.author}: ${text}`;
+        li.prepend(quote);
+    }
+    li.messageId = payload.message_id;
+    li.dataset.messageId = payload.message_id;
+    li.reactions = {};
+    (payload.reactions || []).forEach(r => { li.reactions[r.emoji] = { count: r.count, reacted: r.reacted }; });
+    const box = document.createElement('div');
+    box.className = 'reactions';
+    li.appendChild(box);
+    renderReactions(li);
+    const reply = document.createElement('button');
+    reply.type = 'button';
+    reply.className = 'reply';
+    reply.textContent = 'Reply';
+    reply.addEventListener('click', () => setReplyTarget(payload));
+    li.appendChild(reply);
+    if (payload.thread) renderThreadSummary(li, payload.thread);
+}
+
+function applyReaction(event) {
+    // Сообщение может быть и в ленте, и в открытой ветке
+    document.querySelectorAll(`li[data-message-id="${event.message_id}"]`).forEach(li => {
+        const state = li.reactions[event.emoji] || { count: 0, reacted: false };
+        state.count = event.count;
+        if (event.user_uuid === currentUser) state.reacted = event.added;
+        li.reactions[event.emoji] = state;
+        renderReactions(li);
+    });
+}
+
+function receiveMessage(payload) {
+    if (payload.user_uuid) typingUsers.delete(payload.user_uuid);
+    renderTyping();
+    if (payload.thread_root === null) {
+        appendMessage(payload);
+        scheduleRead(payload.message_id);
+    } else if (payload.thread_root === openThread) {
+        appendMessage(payload, 'thread-messages');
+        scheduleRead(payload.message_id);
+    }
+}
+
+function setUnreadNotifications(count) {
+    const counter = document.getElementById('notifications-count');
+    if (counter) counter.textContent = count;
+}
+
+// Уведомление в ленте; по щелчку отмечается прочитанным
+function appendNotification(notification) {
+    const li = appendLine(notification.link
+        ? `system: ${notification.message} ${notification.link}`
+        : `system: ${notification.message}`);
+    if (!li) return;
+    const counter = document.getElementById('notifications-count');
+    if (counter && !notification.read_at) counter.textContent = Number(counter.textContent) + 1;
+    li.addEventListener('click', () => {
+        fetch(`/api/notifications/${notification.notification_id}/read`, { method: 'PUT' })
+            .catch(error => console.error('Failed to mark notification read:', error));
+    }, { once: true });
+}
+
+function urlBase64ToUint8Array(value) {
+    const base64 = (value + '='.repeat((4 - value.length % 4) % 4)).replace(/-/g, '+').replace(/_/g, '/');
+    return Uint8Array.from(atob(base64), c => c.charCodeAt(0));
+}
+
+// Подписка на Web Push: уведомления приходят, даже когда вкладка закрыта
+async function enablePush() {
+    const registration = await navigator.serviceWorker.register('/static/push-sw.js');
+    const permission = await Notification.requestPermission();
+    if (permission !== 'granted') return;
+    const { public_key } = await fetch('/api/push/vapid-public-key').then(response => response.json());
+    const subscription = await registration.pushManager.subscribe({
+        userVisibleOnly: true,
+        applicationServerKey: urlBase64ToUint8Array(public_key)
+    });
+    const response = await fetch('/api/push/subscriptions', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(subscription.toJSON())
+    });
+    if (!response.ok) throw new Error((await response.json()).message);
+    document.getElementById('push-enable').hidden = true;
+}
+
+function handleChatEvent(event) {
+    switch (event.type) {
+        case 'hello':
+            currentUser = event.user_uuid;
+            fetch('/api/notifications/unread-count')
+                .then(response => response.json())
+                .then(data => setUnreadNotifications(data.unread_count))
+                .catch(error => console.error('Failed to load notifications:', error));
+            break;
+        case 'history':
+            event.messages.forEach(payload => appendMessage(payload));
+            if (event.messages.length) scheduleRead(event.messages[event.messages.length - 1].message_id);
+            break;
+        case 'message':
+            receiveMessage(event);
+            break;
+        case 'thread':
+            document.querySelectorAll(`#messages li[data-message-id="${event.message_id}"]`)
+                .forEach(li => renderThreadSummary(li, event.thread));
+            break;
+        case 'reaction':
+            applyReaction(event);
+            break;
+        case 'typing':
+            if (event.user_uuid !== currentUser) {
+                typingUsers.set(event.user_uuid, {
+                    author: event.author,
+                    thread_root: event.thread_root,
+                    expires: Date.parse(event.expires_at),
+                });
+                renderTyping();
+            }
+            break;
+        case 'read':
+            applyRead(event);
+            break;
+        case 'system':
+            appendLine(event.link ? `system: ${event.message} ${event.link}` : `system: ${event.message}`);
+            break;
+        case 'notification':
+            appendNotification(event);
+            break;
+        case 'notification_count':
+            setUnreadNotifications(event.unread_count);
+            break;
+        case 'slow_mode': {
+            // О ветке сообщаем, только если она открыта
+            if (event.thread_root !== null && event.thread_root !== openThread) break;
+            const listId = event.thread_root === null ? 'messages' : 'thread-messages';
+            appendLine(event.interval_seconds > 0
+                ? `system: slow mode: one message every ${event.interval_seconds} s`
+                : 'system: slow mode is off', listId);
+            break;
+        }
+        case 'error':
+            console.error('Chat error:', event.message);
+            // Превышение лимитов показываем пользователю, иначе сообщение будто пропало
+            if (event.retry_after) appendLine(`system: ${event.message}, try again in ${event.retry_after} s`);
+            else appendLine(`system: ${event.message}`);
+            break;
+    }
+}
 
 function connectWebSocket() {
     if (ws) {
@@ -29,7 +384,7 @@ function connectWebSocket() {
         return;
     }
 
-    ws = new WebSocket(`wss://cyb3ria.xyz/api/ws?session_id=${encodeURIComponent(sessionId)}`);
+    ws = new WebSocket(`wss://cyb3ria.xyz/api/ws?protocol=json&session_id=${encodeURIComponent(sessionId)}`);
 
     ws.onopen = () => {
         console.log('WebSocket connection established');
@@ -37,14 +392,11 @@ function connectWebSocket() {
     };
 
     ws.onmessage = event => {
-        const li = document.createElement('li');
-        li.textContent = event.data;
-        const messages = document.getElementById('messages');
-        if (messages) { // Проверяем, существует ли messages
-            messages.appendChild(li);
-             messages.scrollTop = messages.scrollHeight;
+        try {
+            handleChatEvent(JSON.parse(event.data));
+        } catch (error) {
+            console.error('Failed to handle chat event:', error);
         }
-
     };
 
     ws.onerror = error => {
@@ -68,13 +420,34 @@ document.addEventListener('DOMContentLoaded', () => {
 
      if (messages && form && input) { // Проверка на null
         connectWebSocket(); // Подключаем WebSocket только на странице чата
+        document.getElementById('reply-target').addEventListener('click', () => setReplyTarget(null));
+        input.addEventListener('input', notifyTyping);
+        document.getElementById('thread-close').addEventListener('click', () => {
+            openThread = null;
+            document.getElementById('thread').hidden = true;
+        });
+        // Число обновится событием notification_count
+        document.getElementById('notifications-read').addEventListener('click', () => {
+            fetch('/api/notifications/read', { method: 'PUT' })
+                .catch(error => console.error('Failed to mark notifications read:', error));
+        });
+        if ('serviceWorker' in navigator && 'PushManager' in window && Notification.permission !== 'denied') {
+            const pushButton = document.getElementById('push-enable');
+            pushButton.hidden = false;
+            pushButton.addEventListener('click', () => {
+                enablePush().catch(error => console.error('Failed to enable push notifications:', error));
+            });
+        }
         form.addEventListener('submit', event => {
           event.preventDefault();
           const message = {
+              type: 'message',
               message: input.value,
-              ip: ipAddress,
-              mac: macAddress
+              reply_to: replyTo ? replyTo.message_id : null,
+              thread_root: openThread,
+              ip: ipAddress
            };
+            setReplyTarget(null);
             if(ws){ // Проверяем, что ws определен
                 ws.send(JSON.stringify(message));
             }
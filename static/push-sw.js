@@ -0,0 +1,16 @@
+// Service worker для Web Push: показывает уведомление и открывает ссылку по щелчку.
+// Лежит в /static/, чтобы область действия покрывала страницу чата.
+self.addEventListener('push', event => {
+    const data = event.data ? event.data.json() : {};
+    event.waitUntil(self.registration.showNotification(data.title || 'cyb3ria', {
+        body: data.body,
+        tag: data.notification_id,
+        data: { link: data.link }
+    }));
+});
+
+self.addEventListener('notificationclick', event => {
+    event.notification.close();
+    const link = event.notification.data && event.notification.data.link;
+    event.waitUntil(clients.openWindow(link || '/static/chat.html'));
+});
@@ -0,0 +1,260 @@
+// tests/account.rs
+//
+// Выгрузка личных данных и удаление учётной записи.
+mod common;
+
+use common::*;
+use rust_server_cyb3ria_xyz::account::delete_due_accounts;
+use serde_json::{json, Value};
+use std::io::{Cursor, Read};
+use std::time::Duration;
+use uuid::Uuid;
+use warp::http::StatusCode;
+
+fn read_entry(archive: &mut zip::ZipArchive<Cursor<Vec<u8>>>, name: &str) -> Vec<u8> {
+    let mut content = Vec::new();
+    archive
+        .by_name(name)
+        .unwrap_or_else(|_| panic!("{} is missing", name))
+        .read_to_end(&mut content)
+        .unwrap();
+    content
+}
+
+fn read_json(archive: &mut zip::ZipArchive<Cursor<Vec<u8>>>, name: &str) -> Value {
+    serde_json::from_slice(&read_entry(archive, name)).unwrap()
+}
+
+#[tokio::test]
+async fn export_contains_manifest_data_and_files() {
+    if !setup().await {
+        return;
+    }
+    let (username, user_uuid, cookie) = signup().await;
+    let file_id = Uuid::new_v4();
+    let filename = format!("{}-notes.txt", file_id);
+    std::fs::write(
+        std::path::Path::new(&std::env::var("UPLOAD_DIR").unwrap()).join(file_id.to_string()),
+        b"my notes",
+    )
+    .unwrap();
+    db().await
+        .execute(
+            "INSERT INTO files (file_id, filename, user_uuid, size_bytes) VALUES ($1, $2, $3, 8)",
+            &[&file_id, &filename, &user_uuid],
+        )
+        .await
+        .unwrap();
+    db().await
+        .execute(
+            "INSERT INTO messages (message, user_uuid) VALUES ('hello from me', $1)",
+            &[&user_uuid],
+        )
+        .await
+        .unwrap();
+
+    let resp = request("POST", "/api/account/export", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::ACCEPTED, "{:?}", resp.body());
+    let export_id = body_json(&resp)["export_id"].as_str().unwrap().to_string();
+
+    let ready = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let resp = request("GET", "/api/account/export", &cookie, None).await;
+            let exports = body_json(&resp);
+            if exports[0]["status"] == "ready" {
+                return exports[0].clone();
+            }
+            assert_ne!(exports[0]["status"], "failed");
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("export was not ready in time");
+    assert_eq!(ready["export_id"], export_id.as_str());
+
+    let resp = request(
+        "GET",
+        &format!("/api/account/export/{}", export_id),
+        &cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "application/zip");
+    assert_eq!(
+        ready["size_bytes"].as_u64().unwrap() as usize,
+        resp.body().len()
+    );
+    let mut archive = zip::ZipArchive::new(Cursor::new(resp.body().to_vec())).unwrap();
+
+    let manifest = read_json(&mut archive, "manifest.json");
+    assert_eq!(manifest["username"], username.as_str());
+    let paths: Vec<&str> = manifest["contents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["path"].as_str().unwrap())
+        .collect();
+    for path in [
+        "account.json",
+        "profile.json",
+        "sessions.json",
+        "devices.json",
+        "messages.json",
+        "files.json",
+    ] {
+        assert!(paths.contains(&path), "{} not in manifest", path);
+    }
+
+    let account = read_json(&mut archive, "account.json");
+    assert_eq!(account["user_uuid"], user_uuid.to_string().as_str());
+    assert!(account.get("password_hash").is_none());
+    assert_eq!(
+        read_json(&mut archive, "messages.json")[0]["message"],
+        "hello from me"
+    );
+    assert_eq!(
+        read_json(&mut archive, "sessions.json")
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(
+        read_json(&mut archive, "devices.json")[0]["ip_history"][0]["ip_address"],
+        "127.0.0.1"
+    );
+    let files = read_json(&mut archive, "files.json");
+    let archive_path = files[0]["archive_path"].as_str().unwrap().to_string();
+    assert_eq!(archive_path, format!("files/{}/{}", file_id, filename));
+    assert_eq!(read_entry(&mut archive, &archive_path), b"my notes");
+
+    // Чужую выгрузку не скачать
+    let (_, _, stranger_cookie) = signup().await;
+    let resp = request(
+        "GET",
+        &format!("/api/account/export/{}", export_id),
+        &stranger_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn deletion_needs_password_and_can_be_cancelled() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, cookie) = signup().await;
+
+    let resp = request(
+        "POST",
+        "/api/account/deletion",
+        &cookie,
+        Some(json!({ "password": "wrong password" })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = request(
+        "POST",
+        "/api/account/deletion",
+        &cookie,
+        Some(json!({ "password": PASSWORD })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let delete_after = body_json(&resp)["delete_after"].clone();
+    assert!(delete_after.is_string());
+    let resp = request("GET", "/api/account/deletion", &cookie, None).await;
+    assert_eq!(body_json(&resp)["delete_after"], delete_after);
+
+    // До срока ничего не удаляется
+    delete_due_accounts().await.unwrap();
+    let resp = request("GET", "/api/files", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = request("DELETE", "/api/account/deletion", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = request("GET", "/api/account/deletion", &cookie, None).await;
+    assert!(body_json(&resp)["delete_after"].is_null());
+    let resp = request("DELETE", "/api/account/deletion", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = request("GET", "/api/audit", &cookie, None).await;
+    let audit = body_json(&resp);
+    assert_eq!(audit[0]["action"], "account_deletion_cancelled");
+    assert_eq!(audit[1]["action"], "account_deletion_requested");
+}
+
+#[tokio::test]
+async fn due_account_is_deleted_and_messages_anonymized() {
+    if !setup().await {
+        return;
+    }
+    let (username, user_uuid, cookie) = signup().await;
+    let file_id = Uuid::new_v4();
+    let filename = format!("{}-avatar.png", file_id);
+    let stored =
+        std::path::Path::new(&std::env::var("UPLOAD_DIR").unwrap()).join(file_id.to_string());
+    std::fs::write(&stored, b"png").unwrap();
+    let db = db().await;
+    db.execute(
+        "INSERT INTO files (file_id, filename, user_uuid, size_bytes) VALUES ($1, $2, $3, 3)",
+        &[&file_id, &filename, &user_uuid],
+    )
+    .await
+    .unwrap();
+    let message_id: i64 = db
+        .query_one(
+            "INSERT INTO messages (message, user_uuid) VALUES ('goodbye', $1) RETURNING message_id",
+            &[&user_uuid],
+        )
+        .await
+        .unwrap()
+        .get(0);
+
+    let resp = request(
+        "POST",
+        "/api/account/deletion",
+        &cookie,
+        Some(json!({ "password": PASSWORD })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    db.execute(
+        "UPDATE users SET delete_after = NOW() - INTERVAL '1 minute' WHERE user_uuid = $1",
+        &[&user_uuid],
+    )
+    .await
+    .unwrap();
+
+    assert!(delete_due_accounts().await.unwrap() >= 1);
+
+    let resp = request("GET", "/api/files", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = login(&username, PASSWORD).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert!(!stored.exists());
+    let row = db
+        .query_one(
+            "SELECT user_uuid, message FROM messages WHERE message_id = $1",
+            &[&message_id],
+        )
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, Option<Uuid>>(0), None);
+    assert_eq!(row.get::<_, String>(1), "goodbye");
+    for table in ["users", "sessions", "files", "devices", "profiles"] {
+        let count: i64 = db
+            .query_one(
+                &format!("SELECT COUNT(*) FROM {} WHERE user_uuid = $1", table),
+                &[&user_uuid],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(count, 0, "{} still has rows", table);
+    }
+}
@@ -0,0 +1,328 @@
+// tests/admin.rs
+//
+// Административное управление пользователями.
+mod common;
+
+use common::*;
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use uuid::Uuid;
+use warp::http::StatusCode;
+
+#[tokio::test]
+async fn admin_lists_and_inspects_users() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, admin_cookie) = signup_admin().await;
+    let (username, user_uuid, _) = signup().await;
+    db().await
+        .execute(
+            "INSERT INTO messages (message, user_uuid) VALUES ('one', $1), ('two', $1)",
+            &[&user_uuid],
+        )
+        .await
+        .unwrap();
+    db().await
+        .execute(
+            "INSERT INTO files (file_id, filename, user_uuid, size_bytes) VALUES ($1, 'a.txt', $2, 100), ($3, 'b.txt', $2, 50)",
+            &[&Uuid::new_v4(), &user_uuid, &Uuid::new_v4()],
+        )
+        .await
+        .unwrap();
+
+    let resp = request(
+        "GET",
+        &format!("/api/admin/users?search={}", username),
+        &admin_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let users = body_json(&resp);
+    let users = users.as_array().unwrap();
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0]["username"], username.as_str());
+    assert_eq!(users[0]["role"], "User");
+    assert!(users[0]["registration_date"].is_string());
+    assert!(users[0]["last_activity"].is_string());
+
+    let resp = request(
+        "GET",
+        &format!("/api/admin/users/{}", user_uuid),
+        &admin_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let details = body_json(&resp);
+    assert_eq!(details["username"], username.as_str());
+    assert_eq!(details["message_count"], 2);
+    assert_eq!(details["file_count"], 2);
+    assert_eq!(details["storage_bytes"], 150);
+    // Администратор входил с того же адреса, но устройство у пользователя своё
+    let devices = details["devices"].as_array().unwrap();
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0]["user_uuid"], user_uuid.to_string());
+    assert_eq!(devices[0]["ip_address"], "127.0.0.1");
+
+    let resp = request(
+        "GET",
+        &format!("/api/admin/users/{}", Uuid::new_v4()),
+        &admin_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn non_admins_cannot_manage_users() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, cookie) = signup().await;
+    let (_, other_uuid, _) = signup().await;
+    assert_eq!(
+        request("GET", "/api/admin/users", &cookie, None)
+            .await
+            .status(),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        request(
+            "DELETE",
+            &format!("/api/admin/users/{}", other_uuid),
+            &cookie,
+            None
+        )
+        .await
+        .status(),
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn suspension_revokes_sessions_and_blocks_login() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, admin_cookie) = signup_admin().await;
+    let (username, user_uuid, user_cookie) = signup().await;
+
+    let resp = request(
+        "POST",
+        &format!("/api/admin/users/{}/suspend", user_uuid),
+        &admin_cookie,
+        Some(json!({ "reason": "spam" })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert_eq!(
+        request("GET", "/api/files", &user_cookie, None)
+            .await
+            .status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        login(&username, PASSWORD).await.status(),
+        StatusCode::FORBIDDEN
+    );
+
+    let resp = request(
+        "POST",
+        &format!("/api/admin/users/{}/unsuspend", user_uuid),
+        &admin_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(login(&username, PASSWORD).await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn suspension_reason_is_optional() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, admin_cookie) = signup_admin().await;
+    let (username, user_uuid, _) = signup().await;
+    let path = format!("/api/admin/users/{}/suspend", user_uuid);
+
+    let resp = warp::test::request()
+        .method("POST")
+        .path(&path)
+        .header("cookie", &admin_cookie)
+        .header("content-type", "application/json")
+        .body("{not json")
+        .reply(&rust_server_cyb3ria_xyz::routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // Без тела — блокировка без причины
+    let resp = request("POST", &path, &admin_cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    assert_eq!(
+        login(&username, PASSWORD).await.status(),
+        StatusCode::FORBIDDEN
+    );
+    let resp = request(
+        "GET",
+        &format!("/api/admin/users/{}", user_uuid),
+        &admin_cookie,
+        None,
+    )
+    .await;
+    let details = body_json(&resp);
+    assert!(details["suspended_at"].is_string(), "{}", details);
+    assert!(details["suspended_reason"].is_null(), "{}", details);
+
+    // Тело без Content-Length (chunked) тоже читается, причина не теряется
+    let (_, other_uuid, _) = signup().await;
+    let (addr, server) = warp::serve(rust_server_cyb3ria_xyz::routes())
+        .bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    let body = json!({ "reason": "chunked spam" }).to_string();
+    let raw = format!(
+        "POST /api/admin/users/{}/suspend HTTP/1.1\r\nHost: {}\r\nCookie: {}\r\n\
+         Content-Type: application/json\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n\
+         {:x}\r\n{}\r\n0\r\n\r\n",
+        other_uuid,
+        addr,
+        admin_cookie,
+        body.len(),
+        body
+    );
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream.write_all(raw.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    let resp = request(
+        "GET",
+        &format!("/api/admin/users/{}", other_uuid),
+        &admin_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(body_json(&resp)["suspended_reason"], "chunked spam");
+}
+
+#[tokio::test]
+async fn admin_can_force_password_reset() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, admin_cookie) = signup_admin().await;
+    let (username, user_uuid, user_cookie) = signup().await;
+    let path = format!("/api/admin/users/{}/reset-password", user_uuid);
+
+    // Ссылку для сброса некуда отправить — пароль не меняется
+    let resp = request("POST", &path, &admin_cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    assert_eq!(login(&username, PASSWORD).await.status(), StatusCode::OK);
+
+    let email = format!("{}@example.com", username);
+    let resp = request(
+        "PUT",
+        "/api/account/email",
+        &user_cookie,
+        Some(json!({ "email": email, "password": PASSWORD })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = request("POST", &path, &admin_cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    // Администратор не получает пароля пользователя
+    assert_eq!(
+        body_json(&resp),
+        json!({ "message": "Password reset. A reset link was sent to the user's email." })
+    );
+
+    assert_eq!(
+        request("GET", "/api/files", &user_cookie, None)
+            .await
+            .status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        login(&username, PASSWORD).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Новый пароль задаёт сам пользователь по ссылке из письма
+    let mail = sent_mail(&email);
+    assert_eq!(mail.len(), 1);
+    let token = mail[0]
+        .lines()
+        .find_map(|line| line.strip_prefix("Token: "))
+        .unwrap();
+    let new_password = "chosen by me 42";
+    let resp = request(
+        "POST",
+        "/api/password/reset",
+        "",
+        Some(json!({
+            "token": token,
+            "new_password": new_password,
+            "repeat_password": new_password,
+        })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    assert_eq!(login(&username, new_password).await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn admin_can_delete_accounts() {
+    if !setup().await {
+        return;
+    }
+    let (_, admin_uuid, admin_cookie) = signup_admin().await;
+    let (username, user_uuid, _) = signup().await;
+    db().await
+        .execute(
+            "INSERT INTO messages (message, user_uuid) VALUES ('bye', $1)",
+            &[&user_uuid],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        request(
+            "DELETE",
+            &format!("/api/admin/users/{}", admin_uuid),
+            &admin_cookie,
+            None
+        )
+        .await
+        .status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    let resp = request(
+        "DELETE",
+        &format!("/api/admin/users/{}", user_uuid),
+        &admin_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        login(&username, PASSWORD).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    let orphaned: i64 = db()
+        .await
+        .query_one(
+            "SELECT COUNT(*) FROM messages WHERE message = 'bye' AND user_uuid IS NULL",
+            &[],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert!(orphaned >= 1);
+}
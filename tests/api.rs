@@ -0,0 +1,227 @@
+// tests/api.rs
+//
+// Сквозные тесты HTTP API. Требуют TEST_DATABASE_URL, например:
+// TEST_DATABASE_URL=postgres://postgres@127.0.0.1/postgres cargo test
+mod common;
+
+use chrono::{Duration, Utc};
+use common::*;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::json;
+use uuid::Uuid;
+use warp::http::StatusCode;
+
+#[tokio::test]
+async fn full_account_flow() {
+    if !setup().await {
+        return;
+    }
+    let (username, user_uuid, cookie) = signup().await;
+
+    // Профиль создаётся при регистрации и виден только вошедшим
+    let resp = warp::test::request()
+        .method("GET")
+        .path(&format!("/api/profile/{}", user_uuid))
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = warp::test::request()
+        .method("GET")
+        .path(&format!("/api/profile/{}", user_uuid))
+        .header("cookie", &cookie)
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let profile = body_json(&resp);
+    assert_eq!(profile["username"], username.as_str());
+    assert_eq!(profile["storage_access"], "Private");
+
+    let resp = warp::test::request()
+        .method("PUT")
+        .path("/api/profile")
+        .header("cookie", &cookie)
+        .json(&json!({
+            "bio": "hello",
+            "avatar": null,
+            "profile_banner": null,
+            "storage_access": "Public",
+            "allowed_viewers": null,
+        }))
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = warp::test::request()
+        .method("GET")
+        .path(&format!("/api/profile/{}", user_uuid))
+        .header("cookie", &cookie)
+        .reply(&routes())
+        .await;
+    let profile = body_json(&resp);
+    assert_eq!(profile["bio"], "hello");
+    assert_eq!(profile["storage_access"], "Public");
+
+    // Загрузка файла и список файлов
+    let filename = format!("{}.txt", Uuid::new_v4());
+    let boundary = "----cyb3riaboundary";
+    let body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{f}\"\r\nContent-Type: text/plain\r\n\r\nfile contents\r\n--{b}--\r\n",
+        b = boundary,
+        f = filename
+    );
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/upload")
+        .header("cookie", &cookie)
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(body)
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    // На диске файл лежит под file_id, а не под присланным именем
+    let file_id = body_json(&resp)["file_id"].as_str().unwrap().to_string();
+    let upload_dir = std::env::var("UPLOAD_DIR").unwrap();
+    let stored = std::path::Path::new(&upload_dir).join(&file_id);
+    assert_eq!(std::fs::read_to_string(stored).unwrap(), "file contents");
+    assert!(!std::path::Path::new(&upload_dir).join(&filename).exists());
+
+    let resp = warp::test::request()
+        .method("GET")
+        .path("/api/files")
+        .header("cookie", &cookie)
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let files = body_json(&resp);
+    let files = files.as_array().unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0]["filename"], filename.as_str());
+
+    // Выход делает cookie недействительной
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/logout")
+        .header("cookie", &cookie)
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = warp::test::request()
+        .method("GET")
+        .path("/api/files")
+        .header("cookie", &cookie)
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn register_rejects_mismatched_passwords() {
+    if !setup().await {
+        return;
+    }
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/register")
+        .remote_addr(remote_addr())
+        .json(&json!({
+            "username": unique_username(),
+            "password": "secret123",
+            "repeat_password": "secret456",
+            "invitation_code": "invite",
+            "ip_address": "127.0.0.1",
+            "mac_address": null,
+        }))
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(body_json(&resp)["message"], "Passwords do not match.");
+}
+
+#[tokio::test]
+async fn login_rejects_bad_password() {
+    if !setup().await {
+        return;
+    }
+    let (username, _, _) = signup().await;
+    let resp = login(&username, "wrongpass").await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert!(resp.headers().get("set-cookie").is_none());
+}
+
+#[tokio::test]
+async fn login_rejects_unknown_user() {
+    if !setup().await {
+        return;
+    }
+    let resp = login(&unique_username(), PASSWORD).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn login_sets_session_expiry() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, cookie) = signup().await;
+    let session_id = Uuid::parse_str(cookie.trim_start_matches("session_id=")).unwrap();
+    let row = db()
+        .await
+        .query_one(
+            "SELECT user_uuid, expires_at FROM sessions WHERE session_id = $1",
+            &[&session_id],
+        )
+        .await
+        .unwrap();
+    let owner: Uuid = row.get(0);
+    let expires_at: chrono::DateTime<Utc> = row.get(1);
+    assert_eq!(owner, user_uuid);
+    assert!(expires_at > Utc::now() + Duration::minutes(50));
+    assert!(expires_at <= Utc::now() + Duration::hours(1));
+}
+
+#[tokio::test]
+async fn expired_session_is_rejected() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, cookie) = signup().await;
+    let session_id = Uuid::parse_str(cookie.trim_start_matches("session_id=")).unwrap();
+    db().await
+        .execute(
+            "UPDATE sessions SET expires_at = $1 WHERE session_id = $2",
+            &[&(Utc::now() - Duration::minutes(1)), &session_id],
+        )
+        .await
+        .unwrap();
+
+    let resp = warp::test::request()
+        .method("GET")
+        .path("/api/files")
+        .header("cookie", &cookie)
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn requests_without_session_are_rejected() {
+    if !setup().await {
+        return;
+    }
+    for cookie in [
+        None,
+        Some("session_id=not-a-uuid".to_string()),
+        Some(format!("session_id={}", Uuid::new_v4())),
+    ] {
+        let mut request = warp::test::request().method("GET").path("/api/files");
+        if let Some(cookie) = &cookie {
+            request = request.header("cookie", cookie);
+        }
+        let resp = request.reply(&routes()).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED, "cookie: {:?}", cookie);
+    }
+}
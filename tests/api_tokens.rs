@@ -0,0 +1,232 @@
+// tests/api_tokens.rs
+//
+// Личные API-токены (Authorization: Bearer).
+mod common;
+
+use bytes::Bytes;
+use common::*;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::{json, Value};
+use std::time::Duration;
+use uuid::Uuid;
+use warp::http::{Response, StatusCode};
+
+async fn create_token(cookie: &str, scopes: Value) -> Value {
+    let resp = request(
+        "POST",
+        "/api/tokens",
+        cookie,
+        Some(json!({ "name": "bot", "scopes": scopes, "expires_in_days": 30 })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::CREATED, "{:?}", resp.body());
+    body_json(&resp)
+}
+
+async fn bearer_request(
+    method: &str,
+    path: &str,
+    token: &str,
+    body: Option<Value>,
+) -> Response<Bytes> {
+    let mut request = warp::test::request()
+        .method(method)
+        .path(path)
+        .remote_addr(remote_addr())
+        .header("authorization", format!("Bearer {}", token));
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+    request.reply(&routes()).await
+}
+
+#[tokio::test]
+async fn token_is_limited_to_its_scopes() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, cookie) = signup().await;
+    let created = create_token(&cookie, json!(["files:read"])).await;
+    let token = created["token"].as_str().unwrap();
+    assert!(token.starts_with("cyb3ria_"));
+
+    let resp = bearer_request("GET", "/api/files", token, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = bearer_request("PUT", "/api/profile", token, Some(json!({ "bio": "bot" }))).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let profile_path = format!("/api/profile/{}", user_uuid);
+    let resp = bearer_request("GET", &profile_path, token, None).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let reader = create_token(&cookie, json!(["profile:read"])).await;
+    let resp = bearer_request("GET", &profile_path, reader["token"].as_str().unwrap(), None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Управлять токенами можно только из сессии
+    let resp = bearer_request("GET", "/api/tokens", token, None).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = bearer_request("GET", "/api/files", "cyb3ria_wrong", None).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn tokens_are_listed_without_secret_and_can_be_revoked() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, cookie) = signup().await;
+    let created = create_token(&cookie, json!(["files:read", "chat:read"])).await;
+    let token = created["token"].as_str().unwrap();
+    let token_id = created["token_id"].as_str().unwrap();
+
+    let resp = request("GET", "/api/tokens", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let tokens = body_json(&resp);
+    assert_eq!(tokens.as_array().unwrap().len(), 1);
+    assert_eq!(tokens[0]["token_id"], token_id);
+    assert_eq!(tokens[0]["scopes"], json!(["files:read", "chat:read"]));
+    assert!(tokens[0].get("token").is_none());
+    assert!(!resp
+        .body()
+        .windows(token.len())
+        .any(|w| w == token.as_bytes()));
+
+    // Чужой токен отозвать нельзя
+    let (_, _, other_cookie) = signup().await;
+    let path = format!("/api/tokens/{}", token_id);
+    let resp = request("DELETE", &path, &other_cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = request("DELETE", &path, &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = bearer_request("GET", "/api/files", token, None).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn expired_and_suspended_tokens_are_rejected() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, cookie) = signup().await;
+    let expired = create_token(&cookie, json!(["files:read"])).await;
+    let active = create_token(&cookie, json!(["files:read"])).await;
+    let token_id = Uuid::parse_str(expired["token_id"].as_str().unwrap()).unwrap();
+
+    db().await
+        .execute(
+            "UPDATE api_tokens SET expires_at = NOW() - INTERVAL '1 minute' WHERE token_id = $1",
+            &[&token_id],
+        )
+        .await
+        .unwrap();
+    let resp = bearer_request(
+        "GET",
+        "/api/files",
+        expired["token"].as_str().unwrap(),
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let active = active["token"].as_str().unwrap();
+    assert_eq!(
+        bearer_request("GET", "/api/files", active, None)
+            .await
+            .status(),
+        StatusCode::OK
+    );
+    db().await
+        .execute(
+            "UPDATE users SET suspended_at = NOW() WHERE user_uuid = $1",
+            &[&user_uuid],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        bearer_request("GET", "/api/files", active, None)
+            .await
+            .status(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn invalid_token_requests_are_rejected() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, cookie) = signup().await;
+    for body in [
+        json!({ "name": "bot", "scopes": [] }),
+        json!({ "name": "", "scopes": ["files:read"] }),
+        json!({ "name": "bot", "scopes": ["files:read"], "expires_in_days": 0 }),
+        json!({ "name": "bot", "scopes": ["admin"] }),
+    ] {
+        let resp = request("POST", "/api/tokens", &cookie, Some(body.clone())).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn read_only_token_cannot_post_to_chat() {
+    if !setup().await {
+        return;
+    }
+    let (username, _, cookie) = signup().await;
+    let read_only = create_token(&cookie, json!(["chat:read"])).await;
+    let no_chat = create_token(&cookie, json!(["files:read"])).await;
+    let routes = routes();
+
+    let result = warp::test::ws()
+        .path("/api/ws")
+        .header(
+            "authorization",
+            format!("Bearer {}", no_chat["token"].as_str().unwrap()),
+        )
+        .handshake(routes.clone())
+        .await;
+    assert!(result.is_err());
+
+    let mut bot_ws = warp::test::ws()
+        .path("/api/ws")
+        .header(
+            "authorization",
+            format!("Bearer {}", read_only["token"].as_str().unwrap()),
+        )
+        .handshake(routes.clone())
+        .await
+        .expect("handshake");
+    let mut user_ws = warp::test::ws()
+        .path("/api/ws")
+        .header("cookie", &cookie)
+        .handshake(routes.clone())
+        .await
+        .expect("handshake");
+
+    let ignored = format!("ignored {}", Uuid::new_v4());
+    bot_ws
+        .send_text(json!({ "message": ignored, "ip": "", "mac": "" }).to_string())
+        .await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let marker = format!("marker {}", Uuid::new_v4());
+    user_ws
+        .send_text(json!({ "message": marker, "ip": "", "mac": "" }).to_string())
+        .await;
+
+    // Токен читает чат, но его сообщение не разослано
+    let expected = format!("{}: {}", username, marker);
+    let received = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let msg = bot_ws.recv().await.expect("WebSocket closed");
+            let text = msg.to_str().unwrap_or_default().to_string();
+            assert!(!text.contains(&ignored), "read-only message was broadcast");
+            if text == expected {
+                return;
+            }
+        }
+    })
+    .await;
+    assert!(received.is_ok());
+}
@@ -0,0 +1,240 @@
+// tests/attachments.rs
+//
+// Вложения файлов в сообщения чата: проверка прав, доступ для участников
+// разговора, описание вложений в сообщениях и истории, миниатюры.
+mod common;
+
+use common::*;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::{json, Value};
+use uuid::Uuid;
+use warp::http::StatusCode;
+use warp::test::WsClient;
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let image = image::RgbImage::from_pixel(width, height, image::Rgb([200, 30, 30]));
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image.write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+    bytes.into_inner()
+}
+
+/// Отправляет сообщение с вложениями; возвращает разосланное сообщение или ошибку
+async fn send(ws: &mut WsClient, text: &str, attachments: &[Uuid]) -> Value {
+    ws.send_text(
+        json!({ "type": "message", "message": text, "attachments": attachments }).to_string(),
+    )
+    .await;
+    recv_event(ws, |event| {
+        event["type"] == "error" || (event["type"] == "message" && event["text"] == text)
+    })
+    .await
+}
+
+#[tokio::test]
+async fn attachments_are_described_and_readable_by_members() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, alice_cookie) = signup().await;
+    let (_, _, bob_cookie) = signup().await;
+    let image_name = format!("{}.png", Uuid::new_v4());
+    let image_id = upload(&alice_cookie, &image_name, "image/png", &png(600, 300)).await;
+    let notes_name = format!("{} notes.html", Uuid::new_v4());
+    let notes_id = upload(&alice_cookie, &notes_name, "text/html", b"<b>notes</b>").await;
+
+    // До вложения файл видит только владелец
+    let path = format!("/api/files/{}", notes_id);
+    assert_eq!(request("GET", &path, &bob_cookie, None).await.status(), StatusCode::NOT_FOUND);
+
+    let app = routes();
+    let mut alice = connect(&app, &alice_cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut alice, |event| event["type"] == "history").await;
+    let text = format!("see attached {}", Uuid::new_v4());
+    let message = send(&mut alice, &text, &[notes_id, image_id, notes_id]).await;
+    assert_eq!(message["type"], "message", "{}", message);
+    let attachments = message["attachments"].as_array().unwrap();
+    assert_eq!(attachments.len(), 2);
+    assert_eq!(
+        attachments[0],
+        json!({
+            "file_id": notes_id,
+            "filename": notes_name,
+            "size_bytes": 12,
+            "mime_type": "text/html",
+            "url": format!("/api/files/{}", notes_id),
+            "thumbnail_url": null,
+        })
+    );
+    assert_eq!(attachments[1]["mime_type"], "image/png");
+    assert_eq!(
+        attachments[1]["thumbnail_url"],
+        format!("/api/files/{}/thumbnail", image_id).as_str()
+    );
+
+    // Вложенный файл читают все участники; не картинки только скачиваются
+    let resp = request("GET", &path, &bob_cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.body().as_ref(), b"<b>notes</b>");
+    assert_eq!(resp.headers()["content-type"], "text/html");
+    assert!(resp.headers()["content-disposition"]
+        .to_str()
+        .unwrap()
+        .starts_with("attachment; filename*=UTF-8''"));
+    assert_eq!(resp.headers()["x-content-type-options"], "nosniff");
+
+    let resp = request(
+        "GET",
+        &format!("/api/files/{}/thumbnail", image_id),
+        &bob_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "image/png");
+    let thumbnail = image::load_from_memory(resp.body()).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (256, 128));
+    let resp = request(
+        "GET",
+        &format!("/api/files/{}/thumbnail", notes_id),
+        &bob_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // История и ветки несут те же описания
+    let mut bob = connect(&app, &bob_cookie, "/api/ws?protocol=json").await;
+    let history = recv_event(&mut bob, |event| event["type"] == "history").await;
+    let stored = history["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|stored| stored["message_id"] == message["message_id"])
+        .unwrap();
+    assert_eq!(stored["attachments"], message["attachments"]);
+}
+
+#[tokio::test]
+async fn only_own_or_shared_files_can_be_attached() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, alice_cookie) = signup().await;
+    let (bob_name, _, bob_cookie) = signup().await;
+    let private_id = upload(&alice_cookie, "private.txt", "text/plain", b"secret").await;
+    let shared_id = upload(&alice_cookie, "shared.txt", "text/plain", b"shared").await;
+
+    let app = routes();
+    let mut bob = connect(&app, &bob_cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut bob, |event| event["type"] == "history").await;
+    for file_id in [private_id, Uuid::new_v4()] {
+        let reply = send(&mut bob, "look", &[file_id]).await;
+        assert_eq!(reply, json!({ "type": "error", "message": "Attachment not found" }));
+    }
+    let too_many: Vec<Uuid> = (0..11).map(|_| Uuid::new_v4()).collect();
+    let reply = send(&mut bob, "many", &too_many).await;
+    assert_eq!(reply["type"], "error");
+
+    // Файл, уже показанный в чате, можно переслать
+    let mut alice = connect(&app, &alice_cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut alice, |event| event["type"] == "history").await;
+    let shared = send(&mut alice, &format!("shared {}", Uuid::new_v4()), &[shared_id]).await;
+    let forwarded = send(&mut bob, &format!("fwd {}", Uuid::new_v4()), &[shared_id]).await;
+    assert_eq!(forwarded["attachments"], shared["attachments"]);
+
+    // Владелец узнаёт, что его файл переслали; о своих вложениях — нет
+    let notification = recv_event(&mut alice, |event| event["type"] == "notification").await;
+    assert_eq!(notification["kind"], "file_shared");
+    assert_eq!(
+        notification["message"],
+        format!("{} shared your file: shared.txt", bob_name).as_str()
+    );
+    assert_eq!(
+        notification["link"],
+        format!("/static/chat.html#message-{}", forwarded["message_id"]).as_str()
+    );
+    let resp = request("GET", "/api/notifications?kind=file_shared", &alice_cookie, None).await;
+    assert_eq!(body_json(&resp).as_array().unwrap().len(), 1);
+    let resp = request("GET", "/api/notifications?kind=file_shared", &bob_cookie, None).await;
+    assert_eq!(body_json(&resp), json!([]));
+
+    // Когда модератор удаляет сообщения, доступ пропадает
+    db().await
+        .execute(
+            "UPDATE messages SET deleted_at = NOW() WHERE message_id = ANY($1)",
+            &[&vec![
+                shared["message_id"].as_i64().unwrap(),
+                forwarded["message_id"].as_i64().unwrap(),
+            ]],
+        )
+        .await
+        .unwrap();
+    let path = format!("/api/files/{}", shared_id);
+    assert_eq!(request("GET", &path, &bob_cookie, None).await.status(), StatusCode::NOT_FOUND);
+    assert_eq!(request("GET", &path, &alice_cookie, None).await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn text_clients_see_attachment_links() {
+    if !setup().await {
+        return;
+    }
+    let (alice_name, _, alice_cookie) = signup().await;
+    let file_id = upload(&alice_cookie, "report.pdf", "application/octet-stream", b"%PDF").await;
+    let resp = request("GET", &format!("/api/files/{}", file_id), &alice_cookie, None).await;
+    assert_eq!(resp.headers()["content-type"], "application/pdf");
+
+    let app = routes();
+    let mut text = connect(&app, &alice_cookie, "/api/ws").await;
+    let mut alice = connect(&app, &alice_cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut alice, |event| event["type"] == "history").await;
+    let message = format!("report {}", Uuid::new_v4());
+    send(&mut alice, &message, &[file_id]).await;
+
+    let line = format!(
+        "{}: {} [report.pdf /api/files/{}]",
+        alice_name, message, file_id
+    );
+    tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        loop {
+            let msg = text.recv().await.expect("WebSocket closed");
+            if msg.to_str() == Ok(line.as_str()) {
+                break;
+            }
+        }
+    })
+    .await
+    .expect("message did not arrive");
+}
+
+#[tokio::test]
+async fn same_name_uploads_keep_their_own_content() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, alice_cookie) = signup().await;
+    let (_, _, bob_cookie) = signup().await;
+    let alice_id = upload(&alice_cookie, "report.pdf", "application/pdf", b"alice's report").await;
+    let bob_id = upload(&bob_cookie, "report.pdf", "application/pdf", b"bob's report").await;
+    assert_ne!(alice_id, bob_id);
+
+    for (cookie, file_id, content) in [
+        (&alice_cookie, alice_id, b"alice's report".as_slice()),
+        (&bob_cookie, bob_id, b"bob's report".as_slice()),
+    ] {
+        let resp = request("GET", &format!("/api/files/{}", file_id), cookie, None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body().as_ref(), content);
+        assert!(resp.headers()["content-disposition"]
+            .to_str()
+            .unwrap()
+            .ends_with("''report%2Epdf"));
+    }
+
+    // Имя с каталогами не выводит запись за пределы каталога загрузок
+    let upload_dir = std::path::PathBuf::from(std::env::var("UPLOAD_DIR").unwrap());
+    let escaped = format!("{}.txt", Uuid::new_v4());
+    let file_id = upload(&alice_cookie, &format!("../{}", escaped), "text/plain", b"x").await;
+    assert!(!upload_dir.parent().unwrap().join(&escaped).exists());
+    assert!(upload_dir.join(file_id.to_string()).exists());
+}
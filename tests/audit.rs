@@ -0,0 +1,180 @@
+// tests/audit.rs
+//
+// Журнал безопасности: запись событий, доступ к нему и срок хранения.
+mod common;
+
+use common::*;
+use rust_server_cyb3ria_xyz::audit::purge_expired_events;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::{json, Value};
+use uuid::Uuid;
+use warp::http::StatusCode;
+
+async fn audit_events(cookie: &str, path: &str) -> Vec<Value> {
+    let resp = request("GET", path, cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    body_json(&resp).as_array().unwrap().clone()
+}
+
+fn actions(events: &[Value]) -> Vec<&str> {
+    events
+        .iter()
+        .map(|event| event["action"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn account_events_are_recorded_for_the_owner() {
+    if !setup().await {
+        return;
+    }
+    let (username, user_uuid, _) = signup().await;
+
+    let resp = login(&username, "wrong-password").await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/login")
+        .remote_addr(remote_addr())
+        .header("user-agent", "audit-test/1.0")
+        .json(&json!({ "username": username, "password": PASSWORD }))
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let cookie = session_cookie(&resp);
+
+    let resp = request(
+        "PUT",
+        "/api/profile",
+        &cookie,
+        Some(json!({ "bio": "hi", "storage_access": "Public" })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    // Та же видимость — нового события нет
+    let resp = request(
+        "PUT",
+        "/api/profile",
+        &cookie,
+        Some(json!({ "bio": "hello", "storage_access": "Public" })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let events = audit_events(&cookie, "/api/audit").await;
+    assert_eq!(
+        actions(&events)[..3],
+        ["profile_visibility_changed", "login", "login_failed"]
+    );
+    assert_eq!(events[0]["target"], "Public");
+
+    let login = &events[1];
+    assert_eq!(login["actor_uuid"], user_uuid.to_string());
+    assert_eq!(login["ip_address"], "127.0.0.1");
+    assert_eq!(login["user_agent"], "audit-test/1.0");
+    assert!(login["device_id"].is_string());
+    // Неудачный вход выполнил неизвестно кто, но он касается этой учётной записи
+    let failed = &events[2];
+    assert!(failed["actor_uuid"].is_null());
+    assert_eq!(failed["target_uuid"], user_uuid.to_string());
+    assert_eq!(failed["target"], username.as_str());
+
+    // Постраничный вывод
+    let before = events[0]["event_id"].as_i64().unwrap();
+    let page = audit_events(&cookie, &format!("/api/audit?before={}&limit=1", before)).await;
+    assert_eq!(actions(&page), ["login"]);
+
+    let resp = request("POST", "/api/logout", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let (_, _, other_cookie) = signup().await;
+    let other_events = audit_events(&other_cookie, "/api/audit").await;
+    assert!(other_events
+        .iter()
+        .all(|event| event["target_uuid"] != user_uuid.to_string()));
+}
+
+#[tokio::test]
+async fn admins_read_everyones_log() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, cookie) = signup().await;
+    let resp = request("GET", "/api/admin/audit", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let (_, admin_uuid, admin_cookie) = signup_admin().await;
+    let resp = request(
+        "POST",
+        &format!("/api/admin/users/{}/suspend", user_uuid),
+        &admin_cookie,
+        Some(json!({ "reason": "spam" })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let events = audit_events(
+        &admin_cookie,
+        &format!("/api/admin/audit?user_uuid={}", user_uuid),
+    )
+    .await;
+    assert_eq!(
+        actions(&events)[..3],
+        ["sessions_revoked", "user_suspended", "login"]
+    );
+    assert_eq!(events[1]["actor_uuid"], admin_uuid.to_string());
+    assert_eq!(events[1]["target"], "spam");
+
+    let events = audit_events(&admin_cookie, "/api/admin/audit?action=user_suspended").await;
+    assert!(!events.is_empty());
+    assert!(actions(&events)
+        .iter()
+        .all(|action| *action == "user_suspended"));
+}
+
+#[tokio::test]
+async fn log_is_append_only_with_retention() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, _) = signup().await;
+    let db = db().await;
+
+    assert!(db
+        .execute(
+            "UPDATE audit_log SET action = 'logout' WHERE target_uuid = $1",
+            &[&user_uuid],
+        )
+        .await
+        .is_err());
+
+    // Событие старше срока хранения (365 дней по умолчанию) удаляется
+    let old_actor = Uuid::new_v4();
+    db.execute(
+        "INSERT INTO audit_log (actor_uuid, action, created_at) \
+         VALUES ($1, 'login', NOW() - INTERVAL '400 days')",
+        &[&old_actor],
+    )
+    .await
+    .unwrap();
+    assert!(purge_expired_events().await.unwrap() >= 1);
+
+    let remaining: i64 = db
+        .query_one(
+            "SELECT COUNT(*) FROM audit_log WHERE actor_uuid = $1",
+            &[&old_actor],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(remaining, 0);
+    let recent: i64 = db
+        .query_one(
+            "SELECT COUNT(*) FROM audit_log WHERE actor_uuid = $1",
+            &[&user_uuid],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert!(recent > 0);
+}
@@ -0,0 +1,74 @@
+// tests/chat.rs
+//
+// Тесты чата поверх WebSocket.
+mod common;
+
+use common::{setup, signup};
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::json;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Читает сообщения из сокета, пока не встретится ожидаемое
+async fn recv_until(client: &mut warp::test::WsClient, expected: &str) {
+    let found = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let msg = client.recv().await.expect("WebSocket closed");
+            if msg.to_str().is_ok_and(|text| text == expected) {
+                return;
+            }
+        }
+    })
+    .await;
+    assert!(found.is_ok(), "did not receive {:?}", expected);
+}
+
+#[tokio::test]
+async fn chat_broadcasts_and_keeps_history() {
+    if !setup().await {
+        return;
+    }
+    let (alice, _, alice_cookie) = signup().await;
+    let (_, _, bob_cookie) = signup().await;
+    let routes = routes();
+
+    let mut alice_ws = warp::test::ws()
+        .path("/api/ws")
+        .header("cookie", &alice_cookie)
+        .handshake(routes.clone())
+        .await
+        .expect("handshake");
+    let mut bob_ws = warp::test::ws()
+        .path("/api/ws")
+        .header("cookie", &bob_cookie)
+        .handshake(routes.clone())
+        .await
+        .expect("handshake");
+
+    let text = format!("hello {}", Uuid::new_v4());
+    alice_ws
+        .send_text(json!({ "message": text, "ip": "", "mac": "" }).to_string())
+        .await;
+
+    let expected = format!("{}: {}", alice, text);
+    recv_until(&mut bob_ws, &expected).await;
+    recv_until(&mut alice_ws, &expected).await;
+
+    // Новое подключение получает сообщение из истории
+    let mut late_ws = warp::test::ws()
+        .path("/api/ws")
+        .header("cookie", &bob_cookie)
+        .handshake(routes.clone())
+        .await
+        .expect("handshake");
+    recv_until(&mut late_ws, &expected).await;
+}
+
+#[tokio::test]
+async fn chat_requires_session() {
+    if !setup().await {
+        return;
+    }
+    let result = warp::test::ws().path("/api/ws").handshake(routes()).await;
+    assert!(result.is_err());
+}
@@ -0,0 +1,294 @@
+// tests/common/mod.rs
+//
+// Общая обвязка для интеграционных тестов: одноразовая база данных,
+// применение миграций и вспомогательные запросы к API.
+#![allow(dead_code)]
+
+use bytes::Bytes;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+use warp::http::{Response, StatusCode};
+use warp::test::WsClient;
+use warp::{Filter, Reply};
+
+static DATABASE: OnceCell<Option<String>> = OnceCell::const_new();
+
+pub const PASSWORD: &str = "secret123";
+
+pub fn remote_addr() -> SocketAddr {
+    "127.0.0.1:40000".parse().unwrap()
+}
+
+/// Создаёт одноразовую базу данных по TEST_DATABASE_URL и применяет миграции.
+/// Возвращает false, если TEST_DATABASE_URL не задан — тогда тест пропускается.
+pub async fn setup() -> bool {
+    let url = DATABASE
+        .get_or_init(|| async {
+            let admin_url = match std::env::var("TEST_DATABASE_URL") {
+                Ok(url) => url,
+                Err(_) => {
+                    eprintln!("TEST_DATABASE_URL is not set, skipping integration test");
+                    return None;
+                }
+            };
+            Some(create_database(&admin_url).await)
+        })
+        .await;
+    url.is_some()
+}
+
+async fn create_database(admin_url: &str) -> String {
+    let db_name = format!("cyb3ria_test_{}", Uuid::new_v4().simple());
+    let (admin, connection) = tokio_postgres::connect(admin_url, tokio_postgres::NoTls)
+        .await
+        .expect("Failed to connect to TEST_DATABASE_URL");
+    let handle = tokio::spawn(connection);
+    admin
+        .batch_execute(&format!("CREATE DATABASE {}", db_name))
+        .await
+        .expect("Failed to create test database");
+    drop(admin);
+    let _ = handle.await;
+
+    let database_url = with_database_name(admin_url, &db_name);
+    let (client, connection) = tokio_postgres::connect(&database_url, tokio_postgres::NoTls)
+        .await
+        .expect("Failed to connect to test database");
+    let handle = tokio::spawn(connection);
+
+    let mut migrations: Vec<_> =
+        std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations"))
+            .expect("Failed to read migrations directory")
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "sql"))
+            .collect();
+    migrations.sort();
+    for migration in migrations {
+        let sql = std::fs::read_to_string(&migration).unwrap();
+        client
+            .batch_execute(&sql)
+            .await
+            .unwrap_or_else(|e| panic!("Migration {:?} failed: {}", migration, e));
+    }
+    drop(client);
+    let _ = handle.await;
+
+    let upload_dir = std::env::temp_dir().join(&db_name);
+    std::fs::create_dir_all(&upload_dir).unwrap();
+
+    std::env::set_var("DATABASE_URL", &database_url);
+    std::env::set_var("UPLOAD_DIR", &upload_dir);
+    std::env::set_var("MAILER", "file");
+    std::env::set_var("MAIL_DIR", upload_dir.join("mail"));
+    // Локальная служба доставки Web Push работает по http
+    std::env::set_var("PUSH_ALLOW_HTTP", "1");
+    // Она слушает loopback, а push.example — имя без адресов для проверок подписки
+    std::env::set_var("PUSH_TRUSTED_HOSTS", "127.0.0.1,push.example");
+    database_url
+}
+
+fn with_database_name(url: &str, db_name: &str) -> String {
+    let (base, query) = match url.split_once('?') {
+        Some((base, query)) => (base, Some(query)),
+        None => (url, None),
+    };
+    let base = match base.rfind('/') {
+        Some(pos) if pos > "postgres://".len() => &base[..pos],
+        _ => base,
+    };
+    match query {
+        Some(query) => format!("{}/{}?{}", base, db_name, query),
+        None => format!("{}/{}", base, db_name),
+    }
+}
+
+/// Прямое подключение к тестовой базе (для подготовки данных)
+pub async fn db() -> tokio_postgres::Client {
+    rust_server_cyb3ria_xyz::db::connect_to_db().await.unwrap()
+}
+
+pub fn unique_username() -> String {
+    format!("u{}", &Uuid::new_v4().simple().to_string()[..12])
+}
+
+pub fn body_json(resp: &Response<Bytes>) -> Value {
+    serde_json::from_slice(resp.body()).unwrap_or(Value::Null)
+}
+
+pub async fn register(username: &str, password: &str) -> Response<Bytes> {
+    warp::test::request()
+        .method("POST")
+        .path("/api/register")
+        .remote_addr(remote_addr())
+        .json(&json!({
+            "username": username,
+            "password": password,
+            "repeat_password": password,
+            "invitation_code": "invite",
+            "ip_address": "127.0.0.1",
+        }))
+        .reply(&rust_server_cyb3ria_xyz::routes())
+        .await
+}
+
+pub async fn login(username: &str, password: &str) -> Response<Bytes> {
+    login_from(username, password, remote_addr()).await
+}
+
+pub async fn login_from(username: &str, password: &str, addr: SocketAddr) -> Response<Bytes> {
+    warp::test::request()
+        .method("POST")
+        .path("/api/login")
+        .remote_addr(addr)
+        .json(&json!({ "username": username, "password": password }))
+        .reply(&rust_server_cyb3ria_xyz::routes())
+        .await
+}
+
+/// Достаёт `session_id=...` из заголовка Set-Cookie
+pub fn session_cookie(resp: &Response<Bytes>) -> String {
+    set_cookie(resp, "session_id").expect("Set-Cookie header is missing")
+}
+
+/// Пара `name=value` из заголовков Set-Cookie ответа
+pub fn set_cookie(resp: &Response<Bytes>, name: &str) -> Option<String> {
+    resp.headers()
+        .get_all("set-cookie")
+        .iter()
+        .map(|header| header.to_str().unwrap().split(';').next().unwrap())
+        .find(|cookie| cookie.starts_with(&format!("{}=", name)))
+        .map(str::to_string)
+}
+
+/// Регистрирует нового пользователя и входит под ним.
+/// Возвращает (username, user_uuid, cookie).
+pub async fn signup() -> (String, Uuid, String) {
+    let username = unique_username();
+    let resp = register(&username, PASSWORD).await;
+    assert_eq!(resp.status(), StatusCode::FOUND, "{:?}", resp.body());
+    let resp = login(&username, PASSWORD).await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    let body = body_json(&resp);
+    let user_uuid = Uuid::parse_str(body["user_uuid"].as_str().unwrap()).unwrap();
+    (username, user_uuid, session_cookie(&resp))
+}
+
+/// Назначает роль напрямую в базе (первого администратора назначают вручную)
+pub async fn set_role(user_uuid: Uuid, role: &str) {
+    db().await
+        .execute(
+            "UPDATE users SET role = $1 WHERE user_uuid = $2",
+            &[&role, &user_uuid],
+        )
+        .await
+        .unwrap();
+}
+
+/// Регистрирует пользователя и делает его администратором
+pub async fn signup_admin() -> (String, Uuid, String) {
+    let (username, user_uuid, cookie) = signup().await;
+    set_role(user_uuid, "Admin").await;
+    (username, user_uuid, cookie)
+}
+
+/// Запрос с cookie сессии и необязательным JSON-телом
+pub async fn request(
+    method: &str,
+    path: &str,
+    cookie: &str,
+    body: Option<Value>,
+) -> Response<Bytes> {
+    let mut request = warp::test::request()
+        .method(method)
+        .path(path)
+        .remote_addr(remote_addr())
+        .header("cookie", cookie);
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+    request.reply(&rust_server_cyb3ria_xyz::routes()).await
+}
+
+/// Письма, отправленные на адрес (файловый почтальон из MAILER=file)
+pub fn sent_mail(to: &str) -> Vec<String> {
+    let dir = std::env::var("MAIL_DIR").unwrap();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+        .filter(|mail| mail.starts_with(&format!("To: {}\n", to)))
+        .collect()
+}
+
+/// Подключается к чату; клиенты одного app видят события друг друга
+pub async fn connect<F>(app: &F, cookie: &str, path: &str) -> WsClient
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply + Send,
+{
+    warp::test::ws()
+        .path(path)
+        .header("cookie", cookie)
+        .handshake(app.clone())
+        .await
+        .expect("handshake")
+}
+
+/// Ждёт JSON-событие, подходящее под условие
+pub async fn recv_event(ws: &mut WsClient, matches: impl Fn(&Value) -> bool) -> Value {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let msg = ws.recv().await.expect("WebSocket closed");
+            let Ok(text) = msg.to_str() else { continue };
+            let event: Value = serde_json::from_str(text).expect("not a JSON event");
+            if matches(&event) {
+                return event;
+            }
+        }
+    })
+    .await
+    .expect("event did not arrive in time")
+}
+
+/// Сообщение в чате напрямую через базу
+pub async fn insert_message(user_uuid: Uuid, text: &str) -> i64 {
+    db().await
+        .query_one(
+            "INSERT INTO messages (message, user_uuid) VALUES ($1, $2) RETURNING message_id",
+            &[&text, &user_uuid],
+        )
+        .await
+        .unwrap()
+        .get(0)
+}
+
+/// Загружает файл через /api/upload и возвращает его file_id
+pub async fn upload(cookie: &str, filename: &str, content_type: &str, contents: &[u8]) -> Uuid {
+    let boundary = "----cyb3riaboundary";
+    let mut body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{f}\"\r\nContent-Type: {t}\r\n\r\n",
+        b = boundary,
+        f = filename,
+        t = content_type
+    )
+    .into_bytes();
+    body.extend_from_slice(contents);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/upload")
+        .header("cookie", cookie)
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(body)
+        .reply(&rust_server_cyb3ria_xyz::routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    Uuid::parse_str(body_json(&resp)["file_id"].as_str().unwrap()).unwrap()
+}
@@ -0,0 +1,137 @@
+// tests/csrf.rs
+//
+// Проверка источника у изменяющих запросов и ограничения методов.
+mod common;
+
+use common::*;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::json;
+use warp::http::StatusCode;
+
+const HOST: &str = "cyb3ria.test";
+
+fn profile_update(cookie: &str) -> warp::test::RequestBuilder {
+    warp::test::request()
+        .method("PUT")
+        .path("/api/profile")
+        .remote_addr(remote_addr())
+        .header("host", HOST)
+        .header("cookie", cookie)
+        .json(&json!({ "bio": "csrf", "storage_access": "Private" }))
+}
+
+#[tokio::test]
+async fn cross_origin_requests_are_rejected() {
+    if !setup().await {
+        return;
+    }
+    let (username, _, cookie) = signup().await;
+
+    let resp = profile_update(&cookie)
+        .header("origin", "https://evil.example")
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // Без Origin проверяется Referer
+    let resp = profile_update(&cookie)
+        .header("referer", "https://evil.example/page")
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // Формы со страниц с Referrer-Policy: no-referrer присылают Origin: null
+    let resp = profile_update(&cookie)
+        .header("origin", "null")
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // Вход со стороннего сайта тоже отклоняется
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/login")
+        .remote_addr(remote_addr())
+        .header("origin", "https://evil.example")
+        .json(&json!({ "username": username, "password": PASSWORD }))
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // WebSocket-рукопожатие с чужого сайта
+    let resp = warp::test::request()
+        .method("GET")
+        .path("/api/ws")
+        .remote_addr(remote_addr())
+        .header("host", HOST)
+        .header("cookie", &cookie)
+        .header("origin", "https://evil.example")
+        .header("connection", "upgrade")
+        .header("upgrade", "websocket")
+        .header("sec-websocket-version", "13")
+        .header("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ==")
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn same_origin_and_token_requests_pass() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, cookie) = signup().await;
+
+    let resp = profile_update(&cookie)
+        .header("origin", format!("https://{}", HOST))
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+
+    let resp = profile_update(&cookie)
+        .header("referer", format!("https://{}/static/profile.html", HOST))
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Bearer-токен браузер сам не отправит, источник не важен
+    let resp = request(
+        "POST",
+        "/api/tokens",
+        &cookie,
+        Some(json!({ "name": "bot", "scopes": ["profile:write"] })),
+    )
+    .await;
+    let token = body_json(&resp)["token"].as_str().unwrap().to_string();
+    let resp = warp::test::request()
+        .method("PUT")
+        .path("/api/profile")
+        .remote_addr(remote_addr())
+        .header("origin", "https://evil.example")
+        .header("authorization", format!("Bearer {}", token))
+        .json(&json!({ "bio": "bot", "storage_access": "Private" }))
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn mutating_routes_require_their_method() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, cookie) = signup().await;
+
+    let resp = request("GET", "/api/logout", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    let resp = request("GET", "/api/upload", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    let resp = request("POST", "/api/files", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    let resp = request("GET", "/api/logout/extra", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // Сессия осталась действующей
+    let resp = request("GET", "/api/files", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
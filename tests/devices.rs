@@ -0,0 +1,162 @@
+// tests/devices.rs
+//
+// Определение устройств по cookie и User-Agent, история адресов и управление.
+mod common;
+
+use bytes::Bytes;
+use common::*;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use warp::http::{Response, StatusCode};
+
+const FIREFOX: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0";
+const FIREFOX_UPDATED: &str =
+    "Mozilla/5.0 (X11; Linux x86_64; rv:131.0) Gecko/20100101 Firefox/131.0";
+const CURL: &str = "curl/8.5.0";
+
+async fn login_with(
+    username: &str,
+    addr: SocketAddr,
+    user_agent: &str,
+    device_cookie: Option<&str>,
+) -> Response<Bytes> {
+    let mut builder = warp::test::request()
+        .method("POST")
+        .path("/api/login")
+        .remote_addr(addr)
+        .header("user-agent", user_agent)
+        .json(&json!({ "username": username, "password": PASSWORD }));
+    if let Some(cookie) = device_cookie {
+        builder = builder.header("cookie", cookie);
+    }
+    let resp = builder.reply(&routes()).await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    resp
+}
+
+async fn devices(cookie: &str) -> Vec<Value> {
+    let resp = request("GET", "/api/devices", cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    body_json(&resp).as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn browser_is_recognized_by_its_device_cookie() {
+    if !setup().await {
+        return;
+    }
+    let (username, _, _) = signup().await;
+
+    let resp = login_with(&username, remote_addr(), FIREFOX, None).await;
+    let device_token = set_cookie(&resp, "device_token").expect("device cookie is missing");
+    let first = devices(&session_cookie(&resp)).await;
+
+    // Другой адрес и обновлённый браузер — то же устройство, cookie не меняется
+    let resp = login_with(
+        &username,
+        "10.1.2.3:50000".parse().unwrap(),
+        FIREFOX_UPDATED,
+        Some(&device_token),
+    )
+    .await;
+    assert!(set_cookie(&resp, "device_token").is_none());
+    let list = devices(&session_cookie(&resp)).await;
+    assert_eq!(list.len(), first.len());
+    let device = list
+        .iter()
+        .find(|device| device["current"] == true)
+        .unwrap();
+    assert_eq!(device["ip_address"], "10.1.2.3");
+    assert_eq!(device["user_agent"], FIREFOX_UPDATED);
+    assert_eq!(device["ip_history"].as_array().unwrap().len(), 2);
+    assert!(device.get("fingerprint").is_none());
+
+    // Cookie, унесённая в другой клиент, не подходит: новое устройство и новый токен
+    let resp = login_with(&username, remote_addr(), CURL, Some(&device_token)).await;
+    let new_token = set_cookie(&resp, "device_token").expect("device cookie is missing");
+    assert_ne!(new_token, device_token);
+    assert_eq!(devices(&session_cookie(&resp)).await.len(), first.len() + 1);
+}
+
+#[tokio::test]
+async fn users_behind_one_address_get_their_own_devices() {
+    if !setup().await {
+        return;
+    }
+    let (alice, alice_uuid, _) = signup().await;
+    let (bob, bob_uuid, _) = signup().await;
+
+    let resp = login_with(&alice, remote_addr(), FIREFOX, None).await;
+    let device_token = set_cookie(&resp, "device_token").unwrap();
+    let alice_devices = devices(&session_cookie(&resp)).await;
+
+    // Тот же браузер: у Боба своё устройство, токен браузера остаётся прежним
+    let resp = login_with(&bob, remote_addr(), FIREFOX, Some(&device_token)).await;
+    assert!(set_cookie(&resp, "device_token").is_none());
+    let bob_devices = devices(&session_cookie(&resp)).await;
+
+    let bob_current = bob_devices
+        .iter()
+        .find(|device| device["current"] == true)
+        .unwrap();
+    assert_eq!(bob_current["user_uuid"], bob_uuid.to_string());
+    assert!(bob_devices
+        .iter()
+        .all(|device| device["user_uuid"] == bob_uuid.to_string()));
+    assert!(alice_devices
+        .iter()
+        .all(|device| device["user_uuid"] == alice_uuid.to_string()
+            && device["device_id"] != bob_current["device_id"]));
+}
+
+#[tokio::test]
+async fn devices_can_be_named_trusted_and_removed() {
+    if !setup().await {
+        return;
+    }
+    let (username, _, _) = signup().await;
+    let resp = login_with(&username, remote_addr(), FIREFOX, None).await;
+    let cookie = session_cookie(&resp);
+    let device = devices(&cookie)
+        .await
+        .into_iter()
+        .find(|device| device["current"] == true)
+        .unwrap();
+    let device_path = format!("/api/devices/{}", device["device_id"].as_str().unwrap());
+
+    let resp = request(
+        "PUT",
+        &device_path,
+        &cookie,
+        Some(json!({ "name": "  Laptop  ", "trusted": true })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let updated = devices(&cookie)
+        .await
+        .into_iter()
+        .find(|device| device["current"] == true)
+        .unwrap();
+    assert_eq!(updated["name"], "Laptop");
+    assert_eq!(updated["trusted"], true);
+
+    // Чужое устройство не найти
+    let (_, _, other_cookie) = signup().await;
+    let resp = request(
+        "PUT",
+        &device_path,
+        &other_cookie,
+        Some(json!({ "name": "mine", "trusted": true })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let resp = request("DELETE", &device_path, &other_cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // Удаление устройства завершает его сессии
+    let resp = request("DELETE", &device_path, &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = request("GET", "/api/devices", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
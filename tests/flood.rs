@@ -0,0 +1,332 @@
+// tests/flood.rs
+//
+// Защита чата от флуда: лимиты соединения и пользователя, длина сообщения,
+// повторы и медленный режим.
+mod common;
+
+use common::*;
+use rust_server_cyb3ria_xyz::flood::remaining_in_window;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use warp::http::StatusCode;
+use warp::test::WsClient;
+use warp::{Filter, Reply};
+
+/// Отправляет сообщение; возвращает его из рассылки или ошибку
+async fn send(ws: &mut WsClient, body: Value) -> Value {
+    let text = body["message"].as_str().unwrap().to_string();
+    let mut frame = body;
+    frame["type"] = json!("message");
+    ws.send_text(frame.to_string()).await;
+    recv_event(ws, |event| {
+        event["type"] == "error" || (event["type"] == "message" && event["text"] == text.as_str())
+    })
+    .await
+}
+
+/// Подключается к чату с JSON-протоколом и пропускает историю
+async fn joined<F>(app: &F, cookie: &str) -> WsClient
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply + Send,
+{
+    let mut ws = connect(app, cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut ws, |event| event["type"] == "history").await;
+    ws
+}
+
+/// Пустые сообщения отклоняются без обращения к базе, но жетоны расходуют
+async fn send_empty(ws: &mut WsClient, count: usize) -> Vec<Value> {
+    for _ in 0..count {
+        ws.send_text(json!({ "type": "message", "message": " " }).to_string())
+            .await;
+    }
+    let mut errors = Vec::new();
+    for _ in 0..count {
+        errors.push(recv_event(ws, |event| event["type"] == "error").await);
+    }
+    errors
+}
+
+fn too_fast(errors: &[Value]) -> usize {
+    errors
+        .iter()
+        .filter(|error| error["message"] == "You are sending messages too fast")
+        .count()
+}
+
+#[tokio::test]
+async fn connection_and_user_buckets_limit_bursts() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, cookie) = signup().await;
+    let app = routes();
+
+    let mut first = joined(&app, &cookie).await;
+    let errors = send_empty(&mut first, 12).await;
+    assert!(errors[..10]
+        .iter()
+        .all(|error| error == &json!({ "type": "error", "message": "Message is empty" })));
+    assert_eq!(too_fast(&errors), 2);
+    assert!(errors[11]["retry_after"].as_u64().unwrap() >= 1);
+
+    // Новые соединения получают свои вёдра, но общее ведро пользователя кончается
+    let mut second = joined(&app, &cookie).await;
+    let errors = send_empty(&mut second, 10).await;
+    assert_eq!(too_fast(&errors), 0, "{:?}", errors);
+    let mut third = joined(&app, &cookie).await;
+    let errors = send_empty(&mut third, 10).await;
+    assert!(too_fast(&errors) >= 5, "{:?}", errors);
+
+    // Лимиты одного пользователя не мешают другому
+    let (_, _, other_cookie) = signup().await;
+    let mut other = joined(&app, &other_cookie).await;
+    let text = format!("calm {}", Uuid::new_v4());
+    let message = send(&mut other, json!({ "message": text })).await;
+    assert_eq!(message["type"], "message", "{}", message);
+}
+
+#[tokio::test]
+async fn long_and_repeated_messages_are_refused() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, cookie) = signup().await;
+    let app = routes();
+    let mut ws = joined(&app, &cookie).await;
+
+    let reply = send(&mut ws, json!({ "message": "x".repeat(4001) })).await;
+    assert_eq!(
+        reply,
+        json!({ "type": "error", "message": "Message is longer than 4000 characters" })
+    );
+    let longest = format!("{}{}", Uuid::new_v4(), "я".repeat(3964));
+    assert_eq!(send(&mut ws, json!({ "message": longest })).await["type"], "message");
+
+    // Тот же текст подряд — повтор, даже с другим регистром и пробелами
+    let text = format!("Hello {}", Uuid::new_v4());
+    assert_eq!(send(&mut ws, json!({ "message": text })).await["type"], "message");
+    let repeated = format!("  {}  ", text.to_uppercase());
+    let reply = send(&mut ws, json!({ "message": repeated })).await;
+    assert_eq!(reply["message"], "Duplicate message");
+    assert!((1..=30).contains(&reply["retry_after"].as_u64().unwrap()));
+
+    // В ветке то же сообщение — уже не повтор
+    let root = insert_message(user_uuid, "root").await;
+    let reply = send(&mut ws, json!({ "message": text, "thread_root": root })).await;
+    assert_eq!(reply["type"], "message", "{}", reply);
+
+    // Клиенты со строковым протоколом тоже видят ошибку
+    let mut text_ws = connect(&app, &cookie, "/api/ws").await;
+    text_ws
+        .send_text(json!({ "message": "" }).to_string())
+        .await;
+    tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        loop {
+            let msg = text_ws.recv().await.expect("WebSocket closed");
+            if msg.to_str() == Ok("error: Message is empty") {
+                break;
+            }
+        }
+    })
+    .await
+    .expect("error did not arrive");
+}
+
+#[tokio::test]
+async fn moderators_enable_temporary_slow_mode() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, cookie) = signup().await;
+    let (_, moderator_uuid, moderator_cookie) = signup().await;
+    set_role(moderator_uuid, "Moderator").await;
+    let root = insert_message(user_uuid, "slow thread").await;
+    let reply = insert_message(user_uuid, "reply").await;
+    db().await
+        .execute(
+            "UPDATE messages SET thread_root = $1 WHERE message_id = $2",
+            &[&root, &reply],
+        )
+        .await
+        .unwrap();
+
+    let app = routes();
+    let mut ws = joined(&app, &cookie).await;
+    let put = |cookie: &str, body: Value| {
+        warp::test::request()
+            .method("PUT")
+            .path("/api/moderation/slow-mode")
+            .header("cookie", cookie)
+            .json(&body)
+            .reply(&app)
+    };
+
+    let resp = put(
+        &cookie,
+        json!({ "thread_root": root, "interval_seconds": 60, "duration_seconds": 600 }),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    for (interval, duration) in [(0, 600), (3601, 600), (60, 0), (60, 86401)] {
+        let resp = put(
+            &moderator_cookie,
+            json!({ "thread_root": root, "interval_seconds": interval, "duration_seconds": duration }),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // Ответ из ветки обозначает саму ветку
+    let resp = put(
+        &moderator_cookie,
+        json!({ "thread_root": reply, "interval_seconds": 60, "duration_seconds": 600 }),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    assert_eq!(body_json(&resp)["thread_root"], root);
+    let event = recv_event(&mut ws, |event| event["type"] == "slow_mode").await;
+    assert_eq!(event["thread_root"], root);
+    assert_eq!(event["interval_seconds"], 60);
+    assert!(event["expires_at"].is_string());
+
+    let first = format!("first {}", Uuid::new_v4());
+    let reply = send(&mut ws, json!({ "message": first, "thread_root": root })).await;
+    assert_eq!(reply["type"], "message", "{}", reply);
+    let second = format!("second {}", Uuid::new_v4());
+    let reply = send(&mut ws, json!({ "message": second, "thread_root": root })).await;
+    assert_eq!(reply["message"], "Slow mode is on");
+    assert!((1..=60).contains(&reply["retry_after"].as_u64().unwrap()));
+    // В общей ленте медленного режима нет
+    let reply = send(&mut ws, json!({ "message": second })).await;
+    assert_eq!(reply["type"], "message", "{}", reply);
+
+    // Модератор пишет без ограничений
+    let mut moderator = joined(&app, &moderator_cookie).await;
+    for _ in 0..2 {
+        let text = format!("mod {}", Uuid::new_v4());
+        let reply = send(&mut moderator, json!({ "message": text, "thread_root": root })).await;
+        assert_eq!(reply["type"], "message", "{}", reply);
+    }
+
+    // Новые подключения узнают о режиме сразу
+    let mut fresh = connect(&app, &cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut fresh, |event| {
+        event["type"] == "slow_mode" && event["thread_root"] == root
+    })
+    .await;
+    let resp = warp::test::request()
+        .path("/api/moderation/slow-mode")
+        .header("cookie", &moderator_cookie)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(body_json(&resp)
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|status| status["thread_root"] == root));
+
+    let path = format!("/api/moderation/slow-mode?thread_root={}", root);
+    let resp = warp::test::request()
+        .method("DELETE")
+        .path(&path)
+        .header("cookie", &moderator_cookie)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let event = recv_event(&mut ws, |event| event["type"] == "slow_mode").await;
+    assert_eq!(
+        event,
+        json!({ "type": "slow_mode", "thread_root": root, "interval_seconds": 0, "expires_at": null })
+    );
+    let reply = send(&mut ws, json!({ "message": second, "thread_root": root })).await;
+    assert_eq!(reply["type"], "message", "{}", reply);
+    let resp = warp::test::request()
+        .method("DELETE")
+        .path(&path)
+        .header("cookie", &moderator_cookie)
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // Медленный режим принадлежит своему экземпляру приложения
+    let resp = request("GET", "/api/moderation/slow-mode", &moderator_cookie, None).await;
+    assert_eq!(body_json(&resp), json!([]));
+}
+
+#[tokio::test]
+async fn parallel_connections_cannot_repeat_a_message() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, cookie) = signup().await;
+    let app = routes();
+    let mut first = joined(&app, &cookie).await;
+    let mut second = joined(&app, &cookie).await;
+
+    // Одно и то же сообщение из двух вкладок сразу: проходит только одно
+    let text = format!("twice {}", Uuid::new_v4());
+    let frame = json!({ "type": "message", "message": text }).to_string();
+    first.send_text(frame.clone()).await;
+    second.send_text(frame).await;
+    let mut replies = Vec::new();
+    for ws in [&mut first, &mut second] {
+        replies.push(
+            recv_event(ws, |event| {
+                event["type"] == "error"
+                    || (event["type"] == "message" && event["text"] == text.as_str())
+            })
+            .await,
+        );
+    }
+    let mut seen = Vec::new();
+    for ws in [&mut first, &mut second] {
+        while let Ok(Ok(msg)) =
+            tokio::time::timeout(Duration::from_millis(500), ws.recv()).await
+        {
+            if let Ok(text) = msg.to_str() {
+                seen.push(serde_json::from_str::<Value>(text).unwrap());
+            }
+        }
+    }
+    seen.extend(replies);
+    let errors: Vec<&Value> = seen.iter().filter(|event| event["type"] == "error").collect();
+    assert_eq!(errors.len(), 1, "{:?}", seen);
+    assert_eq!(errors[0]["message"], "Duplicate message");
+    let count: i64 = db()
+        .await
+        .query_one("SELECT COUNT(*) FROM messages WHERE message = $1", &[&text])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn retry_delay_does_not_underflow_at_window_boundary() {
+    let window = Duration::from_secs(30);
+    let started = Instant::now();
+    assert_eq!(
+        remaining_in_window(started, window, started),
+        Some(window)
+    );
+    assert_eq!(
+        remaining_in_window(started, window, started + Duration::from_millis(29_999)),
+        Some(Duration::from_millis(1))
+    );
+    // Ровно на границе и после неё окно закончилось, а не «отрицательно»
+    assert_eq!(remaining_in_window(started, window, started + window), None);
+    assert_eq!(
+        remaining_in_window(started, window, started + Duration::from_secs(31)),
+        None
+    );
+    // Часы, прочитанные раньше начала окна, тоже не ломают расчёт
+    assert_eq!(
+        remaining_in_window(started + Duration::from_secs(1), window, started),
+        Some(window)
+    );
+}
@@ -0,0 +1,127 @@
+// tests/lockout.rs
+//
+// Защита входа от перебора паролей.
+mod common;
+
+use chrono::Duration;
+use common::*;
+use rust_server_cyb3ria_xyz::handlers::auth::lockout::LockoutPolicy;
+use std::net::SocketAddr;
+use warp::http::StatusCode;
+
+fn configure() {
+    std::env::set_var("LOGIN_MAX_FAILURES", "5");
+    std::env::set_var("LOGIN_IP_MAX_FAILURES", "8");
+}
+
+#[test]
+fn lockout_duration_doubles_up_to_cap() {
+    let policy = LockoutPolicy {
+        max_account_failures: 5,
+        max_ip_failures: 20,
+        window: Duration::hours(1),
+        base_lockout: Duration::minutes(1),
+        max_lockout: Duration::minutes(10),
+    };
+    assert_eq!(policy.lockout_duration(4, 5), None);
+    assert_eq!(policy.lockout_duration(5, 5), Some(Duration::minutes(1)));
+    assert_eq!(policy.lockout_duration(6, 5), Some(Duration::minutes(2)));
+    assert_eq!(policy.lockout_duration(8, 5), Some(Duration::minutes(8)));
+    assert_eq!(policy.lockout_duration(9, 5), Some(Duration::minutes(10)));
+    assert_eq!(policy.lockout_duration(500, 5), Some(Duration::minutes(10)));
+}
+
+#[tokio::test]
+async fn unknown_user_and_bad_password_look_the_same() {
+    if !setup().await {
+        return;
+    }
+    configure();
+    let addr: SocketAddr = "10.0.0.1:1000".parse().unwrap();
+    let (username, _, _) = signup().await;
+
+    let bad_password = login_from(&username, "wrongpass", addr).await;
+    let unknown_user = login_from(&unique_username(), "wrongpass", addr).await;
+    assert_eq!(bad_password.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(unknown_user.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(bad_password.body(), unknown_user.body());
+}
+
+#[tokio::test]
+async fn account_is_locked_after_repeated_failures() {
+    if !setup().await {
+        return;
+    }
+    configure();
+    let addr: SocketAddr = "10.0.0.2:1000".parse().unwrap();
+    let (_, _, admin_cookie) = signup_admin().await;
+    let (username, _, _) = signup().await;
+
+    for _ in 0..5 {
+        let resp = login_from(&username, "wrongpass", addr).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    // Даже верный пароль не проходит, пока действует блокировка
+    let resp = login_from(&username, PASSWORD, addr).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: i64 = resp.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0 && retry_after <= 60);
+
+    let resp = request(
+        "GET",
+        "/api/admin/lockouts?active=true",
+        &admin_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let lockouts = body_json(&resp);
+    let lockout = lockouts
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|lockout| lockout["key"] == username.as_str())
+        .expect("lockout is listed for admins")
+        .clone();
+    assert_eq!(lockout["scope"], "account");
+    assert_eq!(lockout["failed_attempts"], 5);
+
+    let resp = request(
+        "DELETE",
+        &format!("/api/admin/lockouts/{}", lockout["lockout_id"]),
+        &admin_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        login_from(&username, PASSWORD, addr).await.status(),
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn ip_is_locked_after_failures_across_accounts() {
+    if !setup().await {
+        return;
+    }
+    configure();
+    let addr: SocketAddr = "10.0.0.3:1000".parse().unwrap();
+    for _ in 0..8 {
+        let resp = login_from(&unique_username(), "wrongpass", addr).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    let (username, _, _) = signup().await;
+    let resp = login_from(&username, PASSWORD, addr).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // С другого адреса вход работает
+    let resp = login_from(&username, PASSWORD, "10.0.0.4:1000".parse().unwrap()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
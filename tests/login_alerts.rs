@@ -0,0 +1,217 @@
+// tests/login_alerts.rs
+//
+// Предупреждения о входе с незнакомого устройства и ссылка «это был не я».
+mod common;
+
+use bytes::Bytes;
+use common::*;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::time::Duration;
+use warp::http::{Response, StatusCode};
+
+const LAPTOP: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0";
+const PHONE: &str = "Mozilla/5.0 (Linux; Android 14) AppleWebKit/537.36 Chrome/126.0 Mobile";
+
+async fn login_with(
+    username: &str,
+    addr: &str,
+    user_agent: &str,
+    device_cookie: Option<&str>,
+) -> Response<Bytes> {
+    let addr: SocketAddr = addr.parse().unwrap();
+    let mut builder = warp::test::request()
+        .method("POST")
+        .path("/api/login")
+        .remote_addr(addr)
+        .header("user-agent", user_agent)
+        .json(&json!({ "username": username, "password": PASSWORD }));
+    if let Some(cookie) = device_cookie {
+        builder = builder.header("cookie", cookie);
+    }
+    let resp = builder.reply(&routes()).await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    resp
+}
+
+async fn notifications(cookie: &str) -> Vec<Value> {
+    let resp = request("GET", "/api/notifications", cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    body_json(&resp).as_array().unwrap().clone()
+}
+
+/// Пользователь с почтой, уже входивший с ноутбука
+async fn user_with_laptop() -> (String, String, String, String) {
+    let (username, _, _) = signup().await;
+    let email = format!("{}@example.com", username);
+    let resp = login_with(&username, "198.51.100.7:40000", LAPTOP, None).await;
+    let laptop_cookie = session_cookie(&resp);
+    let device_token = set_cookie(&resp, "device_token").unwrap();
+    let resp = request(
+        "PUT",
+        "/api/account/email",
+        &laptop_cookie,
+        Some(json!({ "email": email, "password": PASSWORD })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    (username, email, laptop_cookie, device_token)
+}
+
+#[tokio::test]
+async fn unfamiliar_sign_in_is_reported_everywhere() {
+    if !setup().await {
+        return;
+    }
+    let (username, email, laptop_cookie, _) = user_with_laptop().await;
+    let before = notifications(&laptop_cookie).await.len();
+
+    let mut chat = warp::test::ws()
+        .path("/api/ws")
+        .header("cookie", &laptop_cookie)
+        .handshake(routes())
+        .await
+        .expect("handshake");
+
+    login_with(&username, "203.0.113.9:40000", PHONE, None).await;
+
+    let list = notifications(&laptop_cookie).await;
+    assert_eq!(list.len(), before + 1);
+    assert_eq!(list[0]["kind"], "new_login");
+    assert!(list[0]["message"].as_str().unwrap().contains("203.0.113.9"));
+    assert!(list[0]["link"]
+        .as_str()
+        .unwrap()
+        .starts_with("/static/not_me.html?alert="));
+
+    let mail = sent_mail(&email);
+    assert_eq!(mail.len(), 1);
+    assert!(mail[0].contains("Chrome/126.0"));
+    assert!(mail[0].contains("/static/not_me.html?token="));
+
+    let received = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let msg = chat.recv().await.expect("WebSocket closed");
+            if msg
+                .to_str()
+                .is_ok_and(|text| text.starts_with("system: New sign-in"))
+            {
+                return;
+            }
+        }
+    })
+    .await;
+    assert!(received.is_ok(), "no system message in chat");
+}
+
+#[tokio::test]
+async fn familiar_and_trusted_devices_stay_quiet() {
+    if !setup().await {
+        return;
+    }
+    let (username, email, laptop_cookie, device_token) = user_with_laptop().await;
+    let before = notifications(&laptop_cookie).await.len();
+
+    // То же устройство из той же сети /24
+    login_with(
+        &username,
+        "198.51.100.200:40000",
+        LAPTOP,
+        Some(&device_token),
+    )
+    .await;
+    assert_eq!(notifications(&laptop_cookie).await.len(), before);
+
+    // Знакомое устройство из новой сети — предупреждаем
+    login_with(&username, "192.0.2.10:40000", LAPTOP, Some(&device_token)).await;
+    assert_eq!(notifications(&laptop_cookie).await.len(), before + 1);
+
+    // Доверенное устройство может входить откуда угодно
+    let resp = request("GET", "/api/devices", &laptop_cookie, None).await;
+    let device = body_json(&resp)
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|device| device["current"] == true)
+        .unwrap()
+        .clone();
+    let resp = request(
+        "PUT",
+        &format!("/api/devices/{}", device["device_id"].as_str().unwrap()),
+        &laptop_cookie,
+        Some(json!({ "name": "Laptop", "trusted": true })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    login_with(&username, "233.252.0.1:40000", LAPTOP, Some(&device_token)).await;
+    assert_eq!(notifications(&laptop_cookie).await.len(), before + 1);
+    assert_eq!(sent_mail(&email).len(), 1);
+}
+
+#[tokio::test]
+async fn this_was_not_me_signs_the_device_out() {
+    if !setup().await {
+        return;
+    }
+    let (username, email, laptop_cookie, _) = user_with_laptop().await;
+
+    let resp = login_with(&username, "203.0.113.9:40000", PHONE, None).await;
+    let intruder_cookie = session_cookie(&resp);
+    let mail = &sent_mail(&email)[0];
+    let token = mail
+        .split("/static/not_me.html?token=")
+        .nth(1)
+        .unwrap()
+        .split_whitespace()
+        .next()
+        .unwrap()
+        .to_string();
+
+    // Чужое предупреждение по идентификатору не найти
+    let alert_link = notifications(&laptop_cookie).await[0]["link"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let alert_id = alert_link.split("alert=").nth(1).unwrap();
+    let (_, _, stranger_cookie) = signup().await;
+    let resp = request(
+        "POST",
+        &format!("/api/login-alerts/{}/revoke", alert_id),
+        &stranger_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = request(
+        "POST",
+        "/api/login-alerts/revoke",
+        "",
+        Some(json!({ "token": token })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+
+    let resp = request("GET", "/api/files", &intruder_cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = request("GET", "/api/files", &laptop_cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Повторное нажатие из уведомления ничего не ломает
+    let resp = request(
+        "POST",
+        &format!("/api/login-alerts/{}/revoke", alert_id),
+        &laptop_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        body_json(&resp)["message"],
+        "This sign-in has already been reported."
+    );
+
+    let resp = request("GET", "/api/audit", &laptop_cookie, None).await;
+    assert_eq!(body_json(&resp)[0]["action"], "suspicious_login_reported");
+}
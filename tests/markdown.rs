@@ -0,0 +1,149 @@
+// tests/markdown.rs
+//
+// Markdown в сообщениях чата: разрешённая разметка, очистка опасного HTML,
+// одинаковый HTML у новых сообщений и в истории.
+mod common;
+
+use common::*;
+use rust_server_cyb3ria_xyz::markdown::render_markdown;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::json;
+use uuid::Uuid;
+use warp::http::StatusCode;
+
+#[test]
+fn markdown_renders_allowed_subset() {
+    assert_eq!(
+        render_markdown("**bold** *italic* ~~gone~~ `x < y`"),
+        "<p><strong>bold</strong> <em>italic</em> <del>gone</del> <code>x &lt; y</code></p>"
+    );
+    assert_eq!(
+        render_markdown("- one\n- two\n\n3. three"),
+        "<ul>\n<li>one</li>\n<li>two</li>\n</ul>\n<ol start=\"3\">\n<li>three</li>\n</ol>"
+    );
+    assert_eq!(
+        render_markdown("```rust\nfn main() { \"<b>\" }\n```"),
+        "<pre><code>fn main() { \"&lt;b&gt;\" }\n</code></pre>"
+    );
+    assert_eq!(
+        render_markdown("[site](https://example.com) and <https://cyb3ria.xyz>"),
+        "<p><a href=\"https://example.com\" rel=\"noopener noreferrer nofollow\">site</a> and \
+         <a href=\"https://cyb3ria.xyz\" rel=\"noopener noreferrer nofollow\">https://cyb3ria.xyz</a></p>"
+    );
+    // Переносы строк в чате значимы; заголовки становятся абзацами
+    assert_eq!(render_markdown("# hi\nthere\nfriend"), "<p>hi</p>\n<p>there<br>\nfriend</p>");
+}
+
+#[test]
+fn markdown_strips_unsafe_markup() {
+    // Встроенный HTML показывается как текст
+    assert_eq!(
+        render_markdown("<script>alert(1)</script>"),
+        "&lt;script&gt;alert(1)&lt;/script&gt;"
+    );
+    assert_eq!(
+        render_markdown("hi <img src=x onerror=alert(1)>"),
+        "<p>hi &lt;img src=x onerror=alert(1)&gt;</p>"
+    );
+    // Опасные и относительные адреса убираются, текст ссылки остаётся
+    for link in [
+        "[x](javascript:alert(1))",
+        "[x](JaVaScRiPt:alert(1))",
+        "[x](data:text/html,<script>)",
+        "[x](/api/logout)",
+    ] {
+        assert_eq!(
+            render_markdown(link),
+            "<p><a rel=\"noopener noreferrer nofollow\">x</a></p>",
+            "{}",
+            link
+        );
+    }
+    // Картинки не загружаются — вместо них ссылка
+    assert_eq!(
+        render_markdown("![cat](https://example.com/cat.png \"title\")"),
+        "<p><a href=\"https://example.com/cat.png\" rel=\"noopener noreferrer nofollow\">cat</a></p>"
+    );
+}
+
+#[tokio::test]
+async fn live_and_history_messages_carry_the_same_html() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, cookie) = signup().await;
+    let app = routes();
+    let mut ws = connect(&app, &cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut ws, |event| event["type"] == "history").await;
+
+    let text = format!("**{}** <i onmouseover=alert(1)>", Uuid::new_v4());
+    ws.send_text(json!({ "type": "message", "message": text }).to_string())
+        .await;
+    let live = recv_event(&mut ws, |event| {
+        event["type"] == "message" && event["text"] == text.as_str()
+    })
+    .await;
+    let html = live["html"].as_str().unwrap();
+    assert!(html.starts_with("<p><strong>"), "{}", html);
+    assert!(html.ends_with("</strong> &lt;i onmouseover=alert(1)&gt;</p>"), "{}", html);
+
+    let stored: Option<String> = db()
+        .await
+        .query_one(
+            "SELECT message_html FROM messages WHERE message_id = $1",
+            &[&live["message_id"].as_i64().unwrap()],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(stored.as_deref(), Some(html));
+
+    // Сообщение, сохранённое без HTML, отрисовывается при чтении
+    let old_text = format!("old *{}*", Uuid::new_v4());
+    let old_id = insert_message(user_uuid, &old_text).await;
+
+    let mut fresh = connect(&app, &cookie, "/api/ws?protocol=json").await;
+    let history = recv_event(&mut fresh, |event| event["type"] == "history").await;
+    let messages = history["messages"].as_array().unwrap();
+    let find = |message_id: i64| {
+        messages
+            .iter()
+            .find(|message| message["message_id"] == message_id)
+            .unwrap()
+    };
+    assert_eq!(find(live["message_id"].as_i64().unwrap())["html"], html);
+    assert_eq!(
+        find(old_id)["html"],
+        render_markdown(&old_text).as_str()
+    );
+}
+
+#[tokio::test]
+async fn search_highlights_are_properly_escaped() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, cookie) = signup().await;
+    let word = format!("zq{}", Uuid::new_v4().simple());
+    insert_message(user_uuid, &format!("{} Tom & 'Jerry' <b>", word)).await;
+
+    let resp = request(
+        "GET",
+        &format!("/api/messages/search?q={}", word),
+        &cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let results = body_json(&resp);
+    let highlight = results[0]["highlight"].as_str().unwrap();
+    assert!(
+        highlight.contains(" Tom &amp; &#39;Jerry"),
+        "{}",
+        highlight
+    );
+    assert!(results[0]["message"]["html"]
+        .as_str()
+        .unwrap()
+        .ends_with(" Tom &amp; 'Jerry' &lt;b&gt;</p>"));
+}
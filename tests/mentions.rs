@@ -0,0 +1,195 @@
+// tests/mentions.rs
+//
+// Упоминания @username и уведомления упомянутым.
+mod common;
+
+use common::*;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::{json, Value};
+use uuid::Uuid;
+use warp::test::WsClient;
+
+/// Отправляет сообщение и ждёт, пока оно вернётся через рассылку
+async fn say(ws: &mut WsClient, text: &str) -> Value {
+    ws.send_text(json!({ "type": "message", "message": text }).to_string())
+        .await;
+    recv_event(ws, |event| {
+        event["type"] == "message" && event["text"] == text
+    })
+    .await
+}
+
+/// Уведомления пользователя о упоминаниях: (текст, доставлено ли)
+async fn mention_notifications(user_uuid: Uuid) -> Vec<(String, bool)> {
+    db().await
+        .query(
+            "SELECT message, delivered_at IS NOT NULL FROM notifications \
+             WHERE user_uuid = $1 AND kind = 'mention' ORDER BY created_at",
+            &[&user_uuid],
+        )
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect()
+}
+
+/// Уведомления сохраняются после рассылки сообщения — ждём их появления
+async fn wait_for_mentions(user_uuid: Uuid, count: usize) -> Vec<(String, bool)> {
+    for _ in 0..50 {
+        let notifications = mention_notifications(user_uuid).await;
+        if notifications.len() >= count {
+            return notifications;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("{} mention notifications did not arrive", count);
+}
+
+#[tokio::test]
+async fn mentions_are_highlighted_and_notify_online_users() {
+    if !setup().await {
+        return;
+    }
+    let (alice_name, alice_uuid, alice_cookie) = signup().await;
+    let (bob_name, bob_uuid, bob_cookie) = signup().await;
+    let app = routes();
+    let mut alice = connect(&app, &alice_cookie, "/api/ws?protocol=json").await;
+    let mut bob = connect(&app, &bob_cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut alice, |event| event["type"] == "history").await;
+    recv_event(&mut bob, |event| event["type"] == "history").await;
+
+    // Регистр не важен, запятая после имени отбрасывается; почта и
+    // неизвестные имена упоминаниями не считаются
+    let text = format!(
+        "hi @{}, mail{}@{}.org @nosuchuser{} @{}",
+        bob_name.to_uppercase(),
+        alice_name,
+        bob_name,
+        &Uuid::new_v4().simple().to_string()[..4],
+        alice_name
+    );
+    let message = say(&mut alice, &text).await;
+    let mentions = message["mentions"].as_array().unwrap();
+    assert_eq!(mentions.len(), 2, "{}", message);
+    assert_eq!(mentions[0]["user_uuid"], bob_uuid.to_string().as_str());
+    assert_eq!(mentions[0]["username"], bob_name.as_str());
+    assert_eq!(mentions[0]["start"], 3);
+    assert_eq!(mentions[0]["end"], 4 + bob_name.len());
+    assert_eq!(mentions[1]["user_uuid"], alice_uuid.to_string().as_str());
+
+    let notification = recv_event(&mut bob, |event| event["type"] == "notification").await;
+    assert_eq!(notification["kind"], "mention");
+    let prefix = format!("{} mentioned you: hi @", alice_name);
+    assert!(
+        notification["message"].as_str().unwrap().starts_with(&prefix),
+        "{}",
+        notification
+    );
+    assert_eq!(
+        notification["link"],
+        format!("/static/chat.html#message-{}", message["message_id"]).as_str()
+    );
+    assert_eq!(
+        mention_notifications(bob_uuid).await,
+        vec![(notification["message"].as_str().unwrap().to_string(), true)]
+    );
+    // Себя упомянуть можно, но уведомления нет
+    assert!(mention_notifications(alice_uuid).await.is_empty());
+}
+
+#[tokio::test]
+async fn offline_users_get_mentions_when_they_connect() {
+    if !setup().await {
+        return;
+    }
+    let (alice_name, _, alice_cookie) = signup().await;
+    let (bob_name, bob_uuid, bob_cookie) = signup().await;
+    let app = routes();
+    let mut alice = connect(&app, &alice_cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut alice, |event| event["type"] == "history").await;
+
+    let text = format!("@{} are you there? {}", bob_name, "x".repeat(100));
+    let message = say(&mut alice, &text).await;
+    let expected = format!(
+        "{} mentioned you: {}…",
+        alice_name,
+        text.chars().take(80).collect::<String>()
+    );
+    assert_eq!(
+        wait_for_mentions(bob_uuid, 1).await,
+        vec![(expected.clone(), false)]
+    );
+
+    // Прежний текстовый протокол получает уведомление строкой после истории
+    let mut bob = connect(&app, &bob_cookie, "/api/ws").await;
+    let line = format!(
+        "system: {} /static/chat.html#message-{}",
+        expected, message["message_id"]
+    );
+    tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        loop {
+            let msg = bob.recv().await.expect("WebSocket closed");
+            if msg.to_str() == Ok(line.as_str()) {
+                break;
+            }
+        }
+    })
+    .await
+    .expect("notification did not arrive");
+    assert_eq!(
+        mention_notifications(bob_uuid).await,
+        vec![(expected, true)]
+    );
+}
+
+#[tokio::test]
+async fn history_and_threads_include_mentions() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, alice_cookie) = signup().await;
+    let (bob_name, bob_uuid, _) = signup().await;
+    let app = routes();
+    let mut alice = connect(&app, &alice_cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut alice, |event| event["type"] == "history").await;
+
+    let root = say(&mut alice, &format!("ping @{}!", bob_name)).await;
+    let root_id = root["message_id"].as_i64().unwrap();
+    let reply_text = format!("(@{}) in thread", bob_name);
+    alice
+        .send_text(
+            json!({ "type": "message", "message": reply_text, "thread_root": root_id }).to_string(),
+        )
+        .await;
+    let reply = recv_event(&mut alice, |event| {
+        event["type"] == "message" && event["text"] == reply_text.as_str()
+    })
+    .await;
+    assert_eq!(reply["mentions"][0]["start"], 1);
+
+    let mut again = connect(&app, &alice_cookie, "/api/ws?protocol=json").await;
+    let history = recv_event(&mut again, |event| event["type"] == "history").await;
+    let root = history["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|message| message["message_id"] == root_id)
+        .unwrap();
+    assert_eq!(
+        root["mentions"][0]["user_uuid"],
+        bob_uuid.to_string().as_str()
+    );
+    assert_eq!(root["mentions"][0]["end"], 6 + bob_name.len());
+
+    let resp = request(
+        "GET",
+        &format!("/api/messages/{}/thread", root_id),
+        &alice_cookie,
+        None,
+    )
+    .await;
+    let thread = body_json(&resp);
+    assert_eq!(thread["replies"][0]["mentions"], reply["mentions"]);
+    assert_eq!(wait_for_mentions(bob_uuid, 2).await.len(), 2);
+}
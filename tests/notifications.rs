@@ -0,0 +1,213 @@
+// tests/notifications.rs
+//
+// Центр уведомлений: список, прочтение, очистка, настройки и доставка в чат.
+mod common;
+
+use common::*;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::{json, Value};
+use uuid::Uuid;
+use warp::http::StatusCode;
+
+/// Уведомление напрямую через базу; age — сколько минут назад оно пришло
+async fn insert_notification(user_uuid: Uuid, kind: &str, message: &str, age: i32) -> Uuid {
+    let notification_id = Uuid::new_v4();
+    db().await
+        .execute(
+            "INSERT INTO notifications (notification_id, user_uuid, kind, message, created_at, delivered_at) \
+             VALUES ($1, $2, $3, $4, NOW() - $5 * INTERVAL '1 minute', NOW())",
+            &[&notification_id, &user_uuid, &kind, &message, &f64::from(age)],
+        )
+        .await
+        .unwrap();
+    notification_id
+}
+
+async fn list(cookie: &str, query: &str) -> Vec<Value> {
+    let resp = request("GET", &format!("/api/notifications{}", query), cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    body_json(&resp).as_array().unwrap().clone()
+}
+
+fn messages(notifications: &[Value]) -> Vec<&str> {
+    notifications
+        .iter()
+        .map(|notification| notification["message"].as_str().unwrap())
+        .collect()
+}
+
+async fn unread_count(cookie: &str) -> i64 {
+    let resp = request("GET", "/api/notifications/unread-count", cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    body_json(&resp)["unread_count"].as_i64().unwrap()
+}
+
+#[tokio::test]
+async fn inbox_lists_filters_and_marks_read() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, cookie) = signup().await;
+    let (_, _, other_cookie) = signup().await;
+    let oldest = insert_notification(user_uuid, "mention", "oldest", 3).await;
+    insert_notification(user_uuid, "new_login", "login", 2).await;
+    let newest = insert_notification(user_uuid, "mention", "newest", 1).await;
+
+    assert_eq!(
+        messages(&list(&cookie, "").await),
+        vec!["newest", "login", "oldest"]
+    );
+    assert_eq!(
+        messages(&list(&cookie, "?kind=mention").await),
+        vec!["newest", "oldest"]
+    );
+    let first = list(&cookie, "?limit=1").await;
+    assert_eq!(messages(&first), vec!["newest"]);
+    let before = first[0]["created_at"].as_str().unwrap().replace('+', "%2B");
+    assert_eq!(
+        messages(&list(&cookie, &format!("?limit=1&before={}", before)).await),
+        vec!["login"]
+    );
+    assert_eq!(unread_count(&cookie).await, 3);
+
+    let path = format!("/api/notifications/{}/read", newest);
+    let resp = request("PUT", &path, &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(body_json(&resp)["read_at"].is_string());
+    assert_eq!(unread_count(&cookie).await, 2);
+    assert_eq!(
+        messages(&list(&cookie, "?unread=true").await),
+        vec!["login", "oldest"]
+    );
+
+    // Чужие уведомления не видны и не отмечаются
+    assert!(list(&other_cookie, "").await.is_empty());
+    let path = format!("/api/notifications/{}/read", oldest);
+    let resp = request("PUT", &path, &other_cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = request("PUT", "/api/notifications/read", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(body_json(&resp), json!({ "updated": 2, "unread_count": 0 }));
+    assert!(list(&cookie, "?unread=true").await.is_empty());
+}
+
+#[tokio::test]
+async fn notifications_can_be_deleted_and_cleared() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, cookie) = signup().await;
+    let (_, _, other_cookie) = signup().await;
+    let first = insert_notification(user_uuid, "mention", "first", 3).await;
+    let second = insert_notification(user_uuid, "mention", "second", 2).await;
+    insert_notification(user_uuid, "mention", "third", 1).await;
+
+    let path = format!("/api/notifications/{}", first);
+    let resp = request("DELETE", &path, &other_cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let resp = request("DELETE", &path, &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = request("DELETE", &path, &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // Сначала только прочитанные, потом все
+    let path = format!("/api/notifications/{}/read", second);
+    request("PUT", &path, &cookie, None).await;
+    let resp = request("DELETE", "/api/notifications?read=true", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(body_json(&resp), json!({ "updated": 1, "unread_count": 1 }));
+    assert_eq!(messages(&list(&cookie, "").await), vec!["third"]);
+
+    let resp = request("DELETE", "/api/notifications", &cookie, None).await;
+    assert_eq!(body_json(&resp), json!({ "updated": 1, "unread_count": 0 }));
+    assert!(list(&cookie, "").await.is_empty());
+}
+
+#[tokio::test]
+async fn preferences_control_delivery_over_the_chat_socket() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, alice_cookie) = signup().await;
+    let (bob_name, bob_uuid, bob_cookie) = signup().await;
+
+    let resp = request("GET", "/api/notifications/preferences", &bob_cookie, None).await;
+    assert_eq!(
+        body_json(&resp),
+        json!([
+            { "kind": "new_login", "in_app": true, "push": false },
+            { "kind": "mention", "in_app": true, "push": true },
+            { "kind": "file_shared", "in_app": true, "push": false }
+        ])
+    );
+    let resp = request(
+        "PUT",
+        "/api/notifications/preferences",
+        &bob_cookie,
+        Some(json!([{ "kind": "mention", "in_app": false }])),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        body_json(&resp)[1],
+        json!({ "kind": "mention", "in_app": false, "push": true })
+    );
+    let resp = request(
+        "PUT",
+        "/api/notifications/preferences",
+        &bob_cookie,
+        Some(json!([{ "kind": "no_such_kind", "in_app": false }])),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let app = routes();
+    let mut alice = connect(&app, &alice_cookie, "/api/ws?protocol=json").await;
+    let mut bob = connect(&app, &bob_cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut alice, |event| event["type"] == "history").await;
+    recv_event(&mut bob, |event| event["type"] == "history").await;
+
+    // Отключённый вид не сохраняется; после включения уведомление приходит сразу
+    let muted = format!("@{} muted {}", bob_name, Uuid::new_v4());
+    alice
+        .send_text(json!({ "type": "message", "message": muted }).to_string())
+        .await;
+    // Сокет обрабатывает сообщения по очереди: после следующего сообщения
+    // уведомление о предыдущем уже разослано
+    let after = format!("after {}", Uuid::new_v4());
+    alice
+        .send_text(json!({ "type": "message", "message": after }).to_string())
+        .await;
+    recv_event(&mut bob, |event| event["text"] == after.as_str()).await;
+    request(
+        "PUT",
+        "/api/notifications/preferences",
+        &bob_cookie,
+        Some(json!([{ "kind": "mention", "in_app": true }])),
+    )
+    .await;
+    let heard = format!("@{} heard {}", bob_name, Uuid::new_v4());
+    alice
+        .send_text(json!({ "type": "message", "message": heard }).to_string())
+        .await;
+    let notification = recv_event(&mut bob, |event| event["type"] == "notification").await;
+    assert!(
+        notification["message"].as_str().unwrap().ends_with(&heard),
+        "{}",
+        notification
+    );
+    assert_eq!(notification["user_uuid"], bob_uuid.to_string().as_str());
+    let inbox = list(&bob_cookie, "?kind=mention").await;
+    assert_eq!(inbox.len(), 1);
+    assert_eq!(inbox[0]["notification_id"], notification["notification_id"]);
+
+    // Другие вкладки узнают о прочтении
+    let path = format!(
+        "/api/notifications/{}/read",
+        notification["notification_id"].as_str().unwrap()
+    );
+    request("PUT", &path, &bob_cookie, None).await;
+    let count = recv_event(&mut bob, |event| event["type"] == "notification_count").await;
+    assert_eq!(count["unread_count"], 0);
+}
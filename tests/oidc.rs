@@ -0,0 +1,380 @@
+// tests/oidc.rs
+//
+// Провайдер OpenID Connect: код авторизации с PKCE, согласие, ID-токены.
+mod common;
+
+use bytes::Bytes;
+use common::*;
+use data_encoding::BASE64URL_NOPAD;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use rust_server_cyb3ria_xyz::oidc::IdTokenClaims;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use warp::http::{Response, StatusCode};
+
+const REDIRECT_URI: &str = "https://app.example/callback";
+const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+
+fn challenge(verifier: &str) -> String {
+    BASE64URL_NOPAD.encode(&Sha256::digest(verifier.as_bytes()))
+}
+
+/// Регистрирует приложение от имени администратора: (client_id, client_secret)
+async fn register_client() -> (String, String) {
+    let (_, _, admin_cookie) = signup_admin().await;
+    let resp = request(
+        "POST",
+        "/api/oauth/clients",
+        &admin_cookie,
+        Some(json!({ "name": "Example", "redirect_uris": [REDIRECT_URI] })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::CREATED, "{:?}", resp.body());
+    let body = body_json(&resp);
+    (
+        body["client_id"].as_str().unwrap().to_string(),
+        body["client_secret"].as_str().unwrap().to_string(),
+    )
+}
+
+fn authorize_query(client_id: &str, scope: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("response_type", "code".to_string()),
+        ("client_id", client_id.to_string()),
+        ("redirect_uri", REDIRECT_URI.to_string()),
+        ("scope", scope.to_string()),
+        ("state", "xyz".to_string()),
+        ("nonce", "n-0S6_WzA2Mj".to_string()),
+        ("code_challenge", challenge(VERIFIER)),
+        ("code_challenge_method", "S256".to_string()),
+    ]
+}
+
+async fn authorize(query: &[(&str, String)], cookie: Option<&str>) -> Response<Bytes> {
+    let mut request = warp::test::request()
+        .method("GET")
+        .path(&format!(
+            "/oauth/authorize?{}",
+            serde_urlencoded::to_string(query).unwrap()
+        ))
+        .remote_addr(remote_addr());
+    if let Some(cookie) = cookie {
+        request = request.header("cookie", cookie);
+    }
+    request.reply(&routes()).await
+}
+
+fn location(resp: &Response<Bytes>) -> String {
+    resp.headers()
+        .get("location")
+        .expect("Location header is missing")
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+/// Значение параметра из адреса перенаправления
+fn redirect_param(location: &str, name: &str) -> Option<String> {
+    let query = location.split_once('?')?.1;
+    serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+        .unwrap()
+        .into_iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value)
+}
+
+/// Согласие пользователя; возвращает адрес, куда вернуть браузер
+async fn consent(cookie: &str, query: &[(&str, String)], approve: bool) -> String {
+    let mut body: serde_json::Map<String, Value> = query
+        .iter()
+        .map(|(key, value)| (key.to_string(), json!(value)))
+        .collect();
+    body.insert("approve".to_string(), json!(approve));
+    let resp = request(
+        "POST",
+        "/api/oauth/consent",
+        cookie,
+        Some(Value::Object(body)),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    body_json(&resp)["redirect_to"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+async fn exchange_code(
+    client_id: &str,
+    client_secret: &str,
+    code: &str,
+    verifier: &str,
+) -> Response<Bytes> {
+    let form = serde_urlencoded::to_string([
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", REDIRECT_URI),
+        ("code_verifier", verifier),
+    ])
+    .unwrap();
+    let credentials =
+        data_encoding::BASE64.encode(format!("{}:{}", client_id, client_secret).as_bytes());
+    warp::test::request()
+        .method("POST")
+        .path("/oauth/token")
+        .remote_addr(remote_addr())
+        .header("content-type", "application/x-www-form-urlencoded")
+        .header("authorization", format!("Basic {}", credentials))
+        .body(form)
+        .reply(&routes())
+        .await
+}
+
+async fn userinfo(access_token: &str) -> Response<Bytes> {
+    warp::test::request()
+        .method("GET")
+        .path("/oauth/userinfo")
+        .remote_addr(remote_addr())
+        .header("authorization", format!("Bearer {}", access_token))
+        .reply(&routes())
+        .await
+}
+
+/// Проверяет подпись ID-токена по ключу из /oauth/jwks
+async fn verify_id_token(id_token: &str, client_id: &str) -> IdTokenClaims {
+    let resp = warp::test::request()
+        .method("GET")
+        .path("/oauth/jwks")
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let jwks = body_json(&resp);
+    let header = decode_header(id_token).unwrap();
+    let key = jwks["keys"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|key| key["kid"].as_str() == header.kid.as_deref())
+        .expect("Signing key is missing from JWKS");
+    let decoding_key =
+        DecodingKey::from_rsa_components(key["n"].as_str().unwrap(), key["e"].as_str().unwrap())
+            .unwrap();
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.set_audience(&[client_id]);
+    decode::<IdTokenClaims>(id_token, &decoding_key, &validation)
+        .unwrap()
+        .claims
+}
+
+#[tokio::test]
+async fn authorization_code_flow_issues_verifiable_id_token() {
+    if !setup().await {
+        return;
+    }
+    let (client_id, client_secret) = register_client().await;
+    let (username, user_uuid, cookie) = signup().await;
+    let query = authorize_query(&client_id, "openid profile");
+
+    // Без сессии — на страницу входа с возвратом обратно
+    let resp = authorize(&query, None).await;
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    assert!(location(&resp).starts_with("/static/login.html?next=%2Foauth%2Fauthorize"));
+
+    // Первый раз — страница согласия
+    let resp = authorize(&query, Some(&cookie)).await;
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    assert!(location(&resp).starts_with("/static/consent.html?"));
+
+    let redirect_to = consent(&cookie, &query, true).await;
+    assert!(redirect_to.starts_with(REDIRECT_URI));
+    assert_eq!(
+        redirect_param(&redirect_to, "state").as_deref(),
+        Some("xyz")
+    );
+    let code = redirect_param(&redirect_to, "code").unwrap();
+
+    let resp = exchange_code(&client_id, &client_secret, &code, VERIFIER).await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    assert_eq!(resp.headers()["cache-control"], "no-store");
+    let tokens = body_json(&resp);
+    assert_eq!(tokens["token_type"], "Bearer");
+    assert_eq!(tokens["scope"], "openid profile");
+
+    let claims = verify_id_token(tokens["id_token"].as_str().unwrap(), &client_id).await;
+    assert_eq!(claims.sub, user_uuid.to_string());
+    assert_eq!(claims.nonce.as_deref(), Some("n-0S6_WzA2Mj"));
+    assert_eq!(
+        claims.preferred_username.as_deref(),
+        Some(username.as_str())
+    );
+
+    let resp = userinfo(tokens["access_token"].as_str().unwrap()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let info = body_json(&resp);
+    assert_eq!(info["sub"], user_uuid.to_string());
+    assert_eq!(info["preferred_username"], username);
+    assert!(info.get("email").is_none());
+
+    // Согласие запомнено: следующий вход сразу возвращает код
+    let resp = authorize(&query, Some(&cookie)).await;
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    assert!(location(&resp).starts_with(&format!("{}?code=", REDIRECT_URI)));
+}
+
+#[tokio::test]
+async fn code_is_single_use_and_bound_to_verifier() {
+    if !setup().await {
+        return;
+    }
+    let (client_id, client_secret) = register_client().await;
+    let (_, _, cookie) = signup().await;
+    let query = authorize_query(&client_id, "openid");
+
+    // Неверный code_verifier
+    let redirect_to = consent(&cookie, &query, true).await;
+    let code = redirect_param(&redirect_to, "code").unwrap();
+    let wrong_verifier = "x".repeat(43);
+    let resp = exchange_code(&client_id, &client_secret, &code, &wrong_verifier).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(body_json(&resp)["error"], "invalid_grant");
+
+    // Неверный секрет приложения
+    let redirect_to = consent(&cookie, &query, true).await;
+    let code = redirect_param(&redirect_to, "code").unwrap();
+    let resp = exchange_code(&client_id, "wrong", &code, VERIFIER).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(body_json(&resp)["error"], "invalid_client");
+
+    // Повторный обмен кода отзывает уже выданный токен
+    let resp = exchange_code(&client_id, &client_secret, &code, VERIFIER).await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    let access_token = body_json(&resp)["access_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(userinfo(&access_token).await.status(), StatusCode::OK);
+
+    let resp = exchange_code(&client_id, &client_secret, &code, VERIFIER).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(body_json(&resp)["error"], "invalid_grant");
+    assert_eq!(
+        userinfo(&access_token).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn authorization_request_is_validated() {
+    if !setup().await {
+        return;
+    }
+    let (client_id, _) = register_client().await;
+    let (_, _, cookie) = signup().await;
+
+    // Незарегистрированный redirect_uri — ошибка показывается пользователю, не приложению
+    let mut query = authorize_query(&client_id, "openid");
+    query[2].1 = "https://evil.example/callback".to_string();
+    let resp = authorize(&query, Some(&cookie)).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // Без PKCE — ошибка возвращается приложению
+    let mut query = authorize_query(&client_id, "openid");
+    query.retain(|(key, _)| !key.starts_with("code_challenge"));
+    let resp = authorize(&query, Some(&cookie)).await;
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    let location = location(&resp);
+    assert!(location.starts_with(REDIRECT_URI));
+    assert_eq!(
+        redirect_param(&location, "error").as_deref(),
+        Some("invalid_request")
+    );
+
+    // Отказ пользователя
+    let query = authorize_query(&client_id, "openid");
+    let redirect_to = consent(&cookie, &query, false).await;
+    assert_eq!(
+        redirect_param(&redirect_to, "error").as_deref(),
+        Some("access_denied")
+    );
+    assert_eq!(
+        redirect_param(&redirect_to, "state").as_deref(),
+        Some("xyz")
+    );
+    assert!(redirect_param(&redirect_to, "code").is_none());
+}
+
+#[tokio::test]
+async fn only_admins_manage_clients() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, cookie) = signup().await;
+    let resp = request(
+        "POST",
+        "/api/oauth/clients",
+        &cookie,
+        Some(json!({ "name": "Mine", "redirect_uris": [REDIRECT_URI] })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let (_, _, admin_cookie) = signup_admin().await;
+    let resp = request(
+        "POST",
+        "/api/oauth/clients",
+        &admin_cookie,
+        Some(json!({ "name": "Plain", "redirect_uris": ["http://app.example/callback"] })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let (client_id, _) = register_client().await;
+    let resp = request("GET", "/api/oauth/clients", &admin_cookie, None).await;
+    let clients = body_json(&resp);
+    let client = clients
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|client| client["client_id"] == client_id.as_str())
+        .unwrap();
+    assert!(client.get("client_secret_hash").is_none());
+
+    let resp = request(
+        "DELETE",
+        &format!("/api/oauth/clients/{}", client_id),
+        &admin_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = request(
+        "DELETE",
+        &format!("/api/oauth/clients/{}", Uuid::new_v4().simple()),
+        &admin_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn discovery_document_points_to_endpoints() {
+    let resp = warp::test::request()
+        .method("GET")
+        .path("/.well-known/openid-configuration")
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let document = body_json(&resp);
+    let issuer = document["issuer"].as_str().unwrap();
+    assert_eq!(
+        document["jwks_uri"],
+        format!("{}/oauth/jwks", issuer).as_str()
+    );
+    assert_eq!(
+        document["code_challenge_methods_supported"],
+        json!(["S256"])
+    );
+}
@@ -0,0 +1,364 @@
+// tests/password.rs
+//
+// Смена пароля и восстановление по почте.
+mod common;
+
+use common::*;
+use rust_server_cyb3ria_xyz::password::{
+    check_password_strength, hash_password, needs_rehash, verify_password,
+};
+use serde_json::json;
+use std::net::SocketAddr;
+use std::time::Duration;
+use warp::http::StatusCode;
+
+const NEW_PASSWORD: &str = "newsecret1";
+
+fn reset_token(mail: &str) -> String {
+    mail.lines()
+        .find_map(|line| line.strip_prefix("Token: "))
+        .expect("Reset token is missing from the email")
+        .to_string()
+}
+
+/// Письма о сбросе отправляются в фоне — ждём, пока их будет count
+async fn wait_for_mail(to: &str, count: usize) -> Vec<String> {
+    for _ in 0..50 {
+        let mail = sent_mail(to);
+        if mail.len() >= count && mail.iter().all(|mail| mail.contains("\nToken: ")) {
+            return mail;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("password reset email to {} did not arrive", to);
+}
+
+async fn forgot_from(email: &str, addr: SocketAddr) -> StatusCode {
+    warp::test::request()
+        .method("POST")
+        .path("/api/password/forgot")
+        .remote_addr(addr)
+        .json(&json!({ "email": email }))
+        .reply(&rust_server_cyb3ria_xyz::routes())
+        .await
+        .status()
+}
+
+/// Заводит пользователя с адресом почты; возвращает имя, uuid и адрес
+async fn signup_with_email() -> (String, uuid::Uuid, String, String) {
+    let (username, user_uuid, cookie) = signup().await;
+    let email = format!("{}@example.com", username);
+    let resp = request(
+        "PUT",
+        "/api/account/email",
+        &cookie,
+        Some(json!({ "email": email, "password": PASSWORD })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    (username, user_uuid, cookie, email)
+}
+
+#[test]
+fn password_strength_rules() {
+    assert!(check_password_strength("secret123", "alice").is_none());
+    assert!(check_password_strength("correct horse battery staple", "alice").is_none());
+    assert!(check_password_strength("short1", "alice").is_some());
+    assert!(check_password_strength("onlyletters", "alice").is_some());
+    assert!(check_password_strength("Password123", "alice").is_some());
+    assert!(check_password_strength("alice2024!", "Alice").is_some());
+    assert!(check_password_strength("abababababababababab", "alice").is_some());
+    assert!(check_password_strength(&"x1".repeat(65), "alice").is_some());
+}
+
+#[test]
+fn argon2_hashes_verify_and_bcrypt_needs_rehash() {
+    let argon2_hash = hash_password(PASSWORD).unwrap();
+    assert!(argon2_hash.starts_with("$argon2id$"));
+    assert!(verify_password(PASSWORD, &argon2_hash).unwrap());
+    assert!(!verify_password("wrongpass", &argon2_hash).unwrap());
+    assert!(!needs_rehash(&argon2_hash));
+
+    let bcrypt_hash = bcrypt::hash(PASSWORD, 4).unwrap();
+    assert!(verify_password(PASSWORD, &bcrypt_hash).unwrap());
+    assert!(!verify_password("wrongpass", &bcrypt_hash).unwrap());
+    assert!(needs_rehash(&bcrypt_hash));
+}
+
+#[tokio::test]
+async fn login_rehashes_legacy_bcrypt_password() {
+    if !setup().await {
+        return;
+    }
+    let (username, user_uuid, _) = signup().await;
+    let client = db().await;
+    client
+        .execute(
+            "UPDATE users SET password_hash = $1 WHERE user_uuid = $2",
+            &[&bcrypt::hash(PASSWORD, 4).unwrap(), &user_uuid],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(login(&username, PASSWORD).await.status(), StatusCode::OK);
+
+    let row = client
+        .query_one(
+            "SELECT password_hash FROM users WHERE user_uuid = $1",
+            &[&user_uuid],
+        )
+        .await
+        .unwrap();
+    let password_hash: String = row.get(0);
+    assert!(password_hash.starts_with("$argon2id$"));
+    assert_eq!(login(&username, PASSWORD).await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn registration_rejects_weak_and_accepts_long_passwords() {
+    if !setup().await {
+        return;
+    }
+    let resp = register(&unique_username(), "password123").await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let username = unique_username();
+    let passphrase = "correct horse battery staple and then some";
+    let resp = register(&username, passphrase).await;
+    assert_eq!(resp.status(), StatusCode::FOUND, "{:?}", resp.body());
+    assert_eq!(login(&username, passphrase).await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn change_password_revokes_other_sessions() {
+    if !setup().await {
+        return;
+    }
+    let (username, _, cookie) = signup().await;
+    let other_cookie = session_cookie(&login(&username, PASSWORD).await);
+
+    let resp = request(
+        "POST",
+        "/api/password/change",
+        &cookie,
+        Some(json!({
+            "old_password": "wrongpass",
+            "new_password": NEW_PASSWORD,
+            "repeat_password": NEW_PASSWORD,
+        })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = request(
+        "POST",
+        "/api/password/change",
+        &cookie,
+        Some(json!({
+            "old_password": PASSWORD,
+            "new_password": NEW_PASSWORD,
+            "repeat_password": "different1",
+        })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = request(
+        "POST",
+        "/api/password/change",
+        &cookie,
+        Some(json!({
+            "old_password": PASSWORD,
+            "new_password": NEW_PASSWORD,
+            "repeat_password": NEW_PASSWORD,
+        })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+
+    // Текущая сессия остаётся, остальные отозваны
+    assert_eq!(
+        request("GET", "/api/files", &cookie, None).await.status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        request("GET", "/api/files", &other_cookie, None)
+            .await
+            .status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    assert_eq!(
+        login(&username, PASSWORD).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        login(&username, NEW_PASSWORD).await.status(),
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn reset_password_with_emailed_token() {
+    if !setup().await {
+        return;
+    }
+    let (username, _, cookie) = signup().await;
+    let email = format!("{}@example.com", username);
+
+    let resp = request(
+        "PUT",
+        "/api/account/email",
+        &cookie,
+        Some(json!({ "email": email, "password": PASSWORD })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+
+    // Ответ не выдаёт, есть ли такой адрес
+    let unknown = request(
+        "POST",
+        "/api/password/forgot",
+        "",
+        Some(json!({ "email": "nobody@example.com" })),
+    )
+    .await;
+    let resp = request(
+        "POST",
+        "/api/password/forgot",
+        "",
+        Some(json!({ "email": email.to_uppercase() })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(unknown.status(), StatusCode::OK);
+    assert_eq!(resp.body(), unknown.body());
+
+    let mail = wait_for_mail(&email, 1).await;
+    assert_eq!(mail.len(), 1);
+    assert!(mail[0].contains("/static/reset_password.html?token="));
+    let token = reset_token(&mail[0]);
+
+    let resp = request(
+        "POST",
+        "/api/password/reset",
+        "",
+        Some(json!({
+            "token": "not-a-token",
+            "new_password": NEW_PASSWORD,
+            "repeat_password": NEW_PASSWORD,
+        })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let reset = json!({
+        "token": token,
+        "new_password": NEW_PASSWORD,
+        "repeat_password": NEW_PASSWORD,
+    });
+    let resp = request("POST", "/api/password/reset", "", Some(reset.clone())).await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+
+    // Токен одноразовый, все сессии отозваны
+    let resp = request("POST", "/api/password/reset", "", Some(reset)).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        request("GET", "/api/files", &cookie, None).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        login(&username, NEW_PASSWORD).await.status(),
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn expired_reset_token_is_rejected() {
+    if !setup().await {
+        return;
+    }
+    let (username, user_uuid, cookie) = signup().await;
+    let email = format!("{}@example.com", username);
+    request(
+        "PUT",
+        "/api/account/email",
+        &cookie,
+        Some(json!({ "email": email, "password": PASSWORD })),
+    )
+    .await;
+    request(
+        "POST",
+        "/api/password/forgot",
+        "",
+        Some(json!({ "email": email })),
+    )
+    .await;
+    let token = reset_token(&wait_for_mail(&email, 1).await[0]);
+
+    db().await
+        .execute(
+            "UPDATE password_reset_tokens SET expires_at = NOW() - INTERVAL '1 minute' WHERE user_uuid = $1",
+            &[&user_uuid],
+        )
+        .await
+        .unwrap();
+
+    let resp = request(
+        "POST",
+        "/api/password/reset",
+        "",
+        Some(json!({
+            "token": token,
+            "new_password": NEW_PASSWORD,
+            "repeat_password": NEW_PASSWORD,
+        })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(login(&username, PASSWORD).await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn reset_emails_are_throttled_per_account_and_address() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, _, email) = signup_with_email().await;
+    let addr: SocketAddr = "10.0.7.1:40000".parse().unwrap();
+
+    // Пока свежий токен действует, повторный запрос письма не шлёт
+    assert_eq!(forgot_from(&email, addr).await, StatusCode::OK);
+    wait_for_mail(&email, 1).await;
+    assert_eq!(forgot_from(&email, addr).await, StatusCode::OK);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(sent_mail(&email).len(), 1);
+
+    db().await
+        .execute(
+            "UPDATE password_reset_tokens SET created_at = NOW() - INTERVAL '10 minutes' \
+             WHERE user_uuid = $1",
+            &[&user_uuid],
+        )
+        .await
+        .unwrap();
+    assert_eq!(forgot_from(&email, addr).await, StatusCode::OK);
+    wait_for_mail(&email, 2).await;
+
+    // С адреса, исчерпавшего часовой лимит, письма не уходят никому
+    let (_, _, _, other_email) = signup_with_email().await;
+    db().await
+        .execute(
+            "INSERT INTO password_reset_tokens (token_hash, user_uuid, expires_at, requested_ip) \
+             SELECT md5(random()::text), $1, NOW(), '10.0.7.1' FROM generate_series(1, 10)",
+            &[&user_uuid],
+        )
+        .await
+        .unwrap();
+    assert_eq!(forgot_from(&other_email, addr).await, StatusCode::OK);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(sent_mail(&other_email).is_empty());
+    let other_addr: SocketAddr = "10.0.7.2:40000".parse().unwrap();
+    assert_eq!(forgot_from(&other_email, other_addr).await, StatusCode::OK);
+    wait_for_mail(&other_email, 1).await;
+}
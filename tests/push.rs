@@ -0,0 +1,398 @@
+// tests/push.rs
+//
+// Web Push: шифрование по RFC 8291, подписки и доставка через локальную
+// службу доставки, которая расшифровывает сообщения ключами «браузера».
+mod common;
+
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes128Gcm, KeyInit, Nonce};
+use bytes::Bytes;
+use common::*;
+use data_encoding::BASE64URL_NOPAD;
+use hkdf::Hkdf;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::{PublicKey, SecretKey};
+use rust_server_cyb3ria_xyz::push::encrypt_payload_with;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+use warp::http::{HeaderMap, StatusCode};
+use warp::Filter;
+
+fn b64(value: &str) -> Vec<u8> {
+    BASE64URL_NOPAD.decode(value.as_bytes()).unwrap()
+}
+
+/// Ключи подписки «браузера»
+struct Browser {
+    secret: SecretKey,
+    auth: [u8; 16],
+}
+
+impl Browser {
+    fn new() -> Self {
+        Browser {
+            secret: SecretKey::random(&mut rand::thread_rng()),
+            auth: rand::random(),
+        }
+    }
+
+    fn keys(&self) -> Value {
+        json!({
+            "p256dh": BASE64URL_NOPAD.encode(self.secret.public_key().to_encoded_point(false).as_bytes()),
+            "auth": BASE64URL_NOPAD.encode(&self.auth),
+        })
+    }
+
+    /// Расшифровывает тело aes128gcm так, как это делает браузер
+    fn decrypt(&self, body: &[u8]) -> Value {
+        let salt = &body[..16];
+        assert_eq!(&body[16..20], &4096u32.to_be_bytes());
+        assert_eq!(body[20], 65);
+        let as_public = PublicKey::from_sec1_bytes(&body[21..86]).unwrap();
+        let shared = p256::ecdh::diffie_hellman(self.secret.to_nonzero_scalar(), as_public.as_affine());
+
+        let mut key_info = b"WebPush: info\0".to_vec();
+        key_info.extend_from_slice(self.secret.public_key().to_encoded_point(false).as_bytes());
+        key_info.extend_from_slice(&body[21..86]);
+        let mut ikm = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(&self.auth), shared.raw_secret_bytes())
+            .expand(&key_info, &mut ikm)
+            .unwrap();
+        let hkdf = Hkdf::<Sha256>::new(Some(salt), &ikm);
+        let mut cek = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        hkdf.expand(b"Content-Encoding: aes128gcm\0", &mut cek).unwrap();
+        hkdf.expand(b"Content-Encoding: nonce\0", &mut nonce).unwrap();
+
+        let mut plaintext = Aes128Gcm::new_from_slice(&cek)
+            .unwrap()
+            .decrypt(Nonce::from_slice(&nonce), &body[86..])
+            .unwrap();
+        assert_eq!(plaintext.pop(), Some(0x02));
+        serde_json::from_slice(&plaintext).unwrap()
+    }
+}
+
+/// Запрос, принятый службой доставки
+struct Delivery {
+    path: String,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+/// Локальная служба доставки: отвечает заданным статусом и пересылает запросы в канал.
+/// Возвращает адрес, по которому «браузер» подписывается
+async fn push_service(status: StatusCode) -> (String, mpsc::UnboundedReceiver<Delivery>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let service = warp::post()
+        .and(warp::path::full())
+        .and(warp::header::headers_cloned())
+        .and(warp::body::bytes())
+        .map(move |path: warp::path::FullPath, headers: HeaderMap, body: Bytes| {
+            let _ = tx.send(Delivery {
+                path: path.as_str().to_string(),
+                headers,
+                body,
+            });
+            warp::reply::with_status("", status)
+        });
+    let (addr, server) = warp::serve(service).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    (format!("http://{}", addr), rx)
+}
+
+async fn next_delivery(rx: &mut mpsc::UnboundedReceiver<Delivery>) -> Delivery {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("push did not arrive in time")
+        .unwrap()
+}
+
+async fn subscribe(cookie: &str, endpoint: &str, browser: &Browser) -> Value {
+    let resp = request(
+        "POST",
+        "/api/push/subscriptions",
+        cookie,
+        Some(json!({ "endpoint": endpoint, "expirationTime": null, "keys": browser.keys() })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::CREATED, "{:?}", resp.body());
+    body_json(&resp)
+}
+
+/// Упоминает пользователя из чата другого пользователя
+async fn mention(author_cookie: &str, username: &str) -> Value {
+    let app = routes();
+    let mut ws = connect(&app, author_cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut ws, |event| event["type"] == "history").await;
+    let text = format!("@{} ping {}", username, Uuid::new_v4());
+    ws.send_text(json!({ "type": "message", "message": text }).to_string())
+        .await;
+    recv_event(&mut ws, |event| {
+        event["type"] == "message" && event["text"] == text.as_str()
+    })
+    .await
+}
+
+#[test]
+fn encryption_matches_rfc8291_example() {
+    // RFC 8291, приложение A
+    let as_secret =
+        SecretKey::from_slice(&b64("yfWPiYE-n46HLnH0KqZOF1fJJU3MYrct3AELtAQ-oRw")).unwrap();
+    let ua_public = PublicKey::from_sec1_bytes(&b64(
+        "BCVxsr7N_eNgVRqvHtD0zTZsEc6-VV-JvLexhqUzORcxaOzi6-AYWXvTBHm4bjyPjs7Vd8pZGH6SRpkNtoIAiw4",
+    ))
+    .unwrap();
+    let auth = b64("BTBZMqHH6r4Tts7J_aSIgg");
+    let salt: [u8; 16] = b64("DGv6ra1nlYgDCS1FRnbzlw").try_into().unwrap();
+
+    let body = encrypt_payload_with(
+        b"When I grow up, I want to be a watermelon",
+        &ua_public,
+        &auth,
+        &as_secret,
+        &salt,
+    )
+    .unwrap();
+    assert_eq!(
+        BASE64URL_NOPAD.encode(&body),
+        "DGv6ra1nlYgDCS1FRnbzlwAAEABBBP4z9KsN6nGRTbVYI_c7VJSPQTBtkgcy27mlmlMoZIIgDll6e3vCYLocInmYWAmS6TlzAC8wEqKK6PBru3jl7A_yl95bQpu6cVPTpK4Mqgkf1CXztLVBSt2Ks3oZwbuwXPXLWyouBWLVWGNWQexSgSxsj_Qulcy4a-fN"
+    );
+}
+
+#[tokio::test]
+async fn offline_users_get_encrypted_mention_pushes() {
+    if !setup().await {
+        return;
+    }
+    let (alice_name, _, alice_cookie) = signup().await;
+    let (bob_name, _, bob_cookie) = signup().await;
+    let (base_url, mut deliveries) = push_service(StatusCode::CREATED).await;
+    let browser = Browser::new();
+    let endpoint = format!("{}/push/{}", base_url, Uuid::new_v4());
+    let subscription = subscribe(&bob_cookie, &endpoint, &browser).await;
+    assert_eq!(subscription["endpoint"], endpoint.as_str());
+    assert!(subscription.get("auth").is_none());
+
+    let message = mention(&alice_cookie, &bob_name).await;
+    let delivery = next_delivery(&mut deliveries).await;
+    assert_eq!(delivery.path, endpoint.trim_start_matches(&base_url));
+    assert_eq!(delivery.headers["content-encoding"], "aes128gcm");
+    assert!(delivery.headers.contains_key("ttl"));
+
+    // Подпись VAPID: открытый ключ совпадает с applicationServerKey, aud — служба доставки
+    let resp = warp::test::request()
+        .path("/api/push/vapid-public-key")
+        .reply(&routes())
+        .await;
+    let public_key = body_json(&resp)["public_key"].as_str().unwrap().to_string();
+    let authorization = delivery.headers["authorization"].to_str().unwrap();
+    let (token, key) = authorization
+        .strip_prefix("vapid t=")
+        .and_then(|rest| rest.split_once(", k="))
+        .expect("not a VAPID authorization");
+    assert_eq!(key, public_key);
+    let point = b64(key);
+    let decoding_key = jsonwebtoken::DecodingKey::from_ec_components(
+        &BASE64URL_NOPAD.encode(&point[1..33]),
+        &BASE64URL_NOPAD.encode(&point[33..65]),
+    )
+    .unwrap();
+    let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::ES256);
+    validation.set_audience(&[&base_url]);
+    jsonwebtoken::decode::<Value>(token, &decoding_key, &validation).expect("invalid VAPID JWT");
+
+    let push = browser.decrypt(&delivery.body);
+    assert_eq!(push["kind"], "mention");
+    assert!(
+        push["body"]
+            .as_str()
+            .unwrap()
+            .starts_with(&format!("{} mentioned you: @", alice_name)),
+        "{}",
+        push
+    );
+    assert_eq!(
+        push["link"],
+        format!("/static/chat.html#message-{}", message["message_id"]).as_str()
+    );
+
+    // Время доставки записывается после ответа службы, так что его ждём
+    for _ in 0..50 {
+        let resp = request("GET", "/api/push/subscriptions", &bob_cookie, None).await;
+        let subscriptions = body_json(&resp);
+        assert_eq!(subscriptions.as_array().unwrap().len(), 1);
+        if !subscriptions[0]["last_push_at"].is_null() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("last_push_at was not recorded");
+}
+
+#[tokio::test]
+async fn online_users_and_disabled_kinds_get_no_push() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, alice_cookie) = signup().await;
+    let (bob_name, _, bob_cookie) = signup().await;
+    let (base_url, mut deliveries) = push_service(StatusCode::CREATED).await;
+    let browser = Browser::new();
+    subscribe(&bob_cookie, &format!("{}/push/bob", base_url), &browser).await;
+
+    // Чат открыт — уведомление приходит туда, а не через push
+    let app = routes();
+    let mut bob = connect(&app, &bob_cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut bob, |event| event["type"] == "history").await;
+    mention(&alice_cookie, &bob_name).await;
+    recv_event(&mut bob, |event| event["type"] == "notification").await;
+    drop(bob);
+
+    let resp = request(
+        "PUT",
+        "/api/notifications/preferences",
+        &bob_cookie,
+        Some(json!([{ "kind": "mention", "push": false }])),
+    )
+    .await;
+    assert_eq!(
+        body_json(&resp)[1],
+        json!({ "kind": "mention", "in_app": true, "push": false })
+    );
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    mention(&alice_cookie, &bob_name).await;
+
+    // Только push: в центре уведомлений ничего не появляется
+    request(
+        "PUT",
+        "/api/notifications/preferences",
+        &bob_cookie,
+        Some(json!([{ "kind": "mention", "in_app": false, "push": true }])),
+    )
+    .await;
+    mention(&alice_cookie, &bob_name).await;
+    let push = browser.decrypt(&next_delivery(&mut deliveries).await.body);
+    assert_eq!(push["kind"], "mention");
+    assert!(deliveries.try_recv().is_err());
+
+    let resp = request("GET", "/api/notifications?kind=mention", &bob_cookie, None).await;
+    assert_eq!(body_json(&resp).as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn gone_subscriptions_are_removed() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, alice_cookie) = signup().await;
+    let (bob_name, _, bob_cookie) = signup().await;
+    let (base_url, mut deliveries) = push_service(StatusCode::GONE).await;
+    subscribe(&bob_cookie, &format!("{}/push/gone", base_url), &Browser::new()).await;
+
+    mention(&alice_cookie, &bob_name).await;
+    next_delivery(&mut deliveries).await;
+    for _ in 0..50 {
+        let resp = request("GET", "/api/push/subscriptions", &bob_cookie, None).await;
+        if body_json(&resp).as_array().unwrap().is_empty() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("expired subscription was not removed");
+}
+
+#[tokio::test]
+async fn internal_endpoints_are_checked_again_at_delivery() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, alice_cookie) = signup().await;
+    let (bob_name, _, bob_cookie) = signup().await;
+    let (base_url, mut deliveries) = push_service(StatusCode::CREATED).await;
+    let endpoint = format!("{}/push/{}", base_url, Uuid::new_v4());
+    subscribe(&bob_cookie, &endpoint, &Browser::new()).await;
+
+    // Подписка, записанная до проверки адресов, указывает на ту же службу по имени
+    db().await
+        .execute(
+            "UPDATE push_subscriptions SET endpoint = replace(endpoint, '127.0.0.1', 'localhost') \
+             WHERE endpoint = $1",
+            &[&endpoint],
+        )
+        .await
+        .unwrap();
+    mention(&alice_cookie, &bob_name).await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(deliveries.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn subscriptions_are_validated_and_owned() {
+    if !setup().await {
+        return;
+    }
+    let (_, _, alice_cookie) = signup().await;
+    let (_, _, bob_cookie) = signup().await;
+    let browser = Browser::new();
+
+    for (endpoint, keys) in [
+        ("ftp://push.example/1".to_string(), browser.keys()),
+        ("not a url".to_string(), browser.keys()),
+        // Адреса и имена внутренней сети
+        ("https://localhost/1".to_string(), browser.keys()),
+        ("http://localhost:8080/1".to_string(), browser.keys()),
+        ("https://127.0.0.2/1".to_string(), browser.keys()),
+        ("https://2130706434/1".to_string(), browser.keys()),
+        ("https://10.1.2.3/1".to_string(), browser.keys()),
+        ("https://172.16.0.1/1".to_string(), browser.keys()),
+        ("https://192.168.1.1/1".to_string(), browser.keys()),
+        ("http://169.254.169.254/latest/meta-data".to_string(), browser.keys()),
+        ("https://[::1]/1".to_string(), browser.keys()),
+        ("https://[fd00::1]/1".to_string(), browser.keys()),
+        ("https://[fe80::1]/1".to_string(), browser.keys()),
+        ("https://[::ffff:10.0.0.1]/1".to_string(), browser.keys()),
+        ("https://metadata.google.internal/1".to_string(), browser.keys()),
+        ("https://printer.local/1".to_string(), browser.keys()),
+        ("https://metadata/1".to_string(), browser.keys()),
+        (
+            "https://push.example/1".to_string(),
+            json!({ "p256dh": "AAAA", "auth": browser.keys()["auth"] }),
+        ),
+        (
+            "https://push.example/1".to_string(),
+            json!({ "p256dh": browser.keys()["p256dh"], "auth": "c2hvcnQ" }),
+        ),
+    ] {
+        let resp = request(
+            "POST",
+            "/api/push/subscriptions",
+            &alice_cookie,
+            Some(json!({ "endpoint": endpoint, "keys": keys })),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", endpoint);
+    }
+
+    // Тот же браузер после смены пользователя переходит к новому владельцу
+    let endpoint = format!("https://push.example/{}", Uuid::new_v4());
+    let first = subscribe(&alice_cookie, &endpoint, &browser).await;
+    let second = subscribe(&bob_cookie, &endpoint, &browser).await;
+    let resp = request("GET", "/api/push/subscriptions", &alice_cookie, None).await;
+    assert_eq!(body_json(&resp), json!([]));
+
+    let path = format!("/api/push/subscriptions/{}", first["subscription_id"].as_str().unwrap());
+    let resp = request("DELETE", &path, &bob_cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let path = format!("/api/push/subscriptions/{}", second["subscription_id"].as_str().unwrap());
+    let resp = request("DELETE", &path, &alice_cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let resp = request("DELETE", &path, &bob_cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = request("GET", "/api/push/subscriptions", &bob_cookie, None).await;
+    assert_eq!(body_json(&resp), json!([]));
+}
@@ -0,0 +1,188 @@
+// tests/reactions.rs
+//
+// Реакции на сообщения: через сокет чата и через REST.
+mod common;
+
+use common::*;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::{json, Value};
+use std::time::Duration;
+use uuid::Uuid;
+use warp::http::StatusCode;
+
+#[tokio::test]
+async fn socket_reactions_are_broadcast_and_counted_in_history() {
+    if !setup().await {
+        return;
+    }
+    let (_, author_uuid, author_cookie) = signup().await;
+    let (_, _, viewer_cookie) = signup().await;
+    let app = routes();
+    let mut author = connect(&app, &author_cookie, "/api/ws?protocol=json").await;
+    let mut viewer = connect(&app, &viewer_cookie, "/api/ws?protocol=json").await;
+
+    let hello = recv_event(&mut author, |event| event["type"] == "hello").await;
+    assert_eq!(hello["user_uuid"], author_uuid.to_string().as_str());
+    // Подписка на события появляется после отправки истории
+    recv_event(&mut author, |event| event["type"] == "history").await;
+    recv_event(&mut viewer, |event| event["type"] == "history").await;
+
+    let text = format!("react to me {}", Uuid::new_v4());
+    author
+        .send_text(json!({ "type": "message", "message": text }).to_string())
+        .await;
+    let message = recv_event(&mut viewer, |event| {
+        event["type"] == "message" && event["text"] == text.as_str()
+    })
+    .await;
+    let message_id = message["message_id"].as_i64().unwrap();
+    assert_eq!(message["user_uuid"], author_uuid.to_string().as_str());
+
+    viewer
+        .send_text(json!({ "type": "react", "message_id": message_id, "emoji": "🔥" }).to_string())
+        .await;
+    let reaction = recv_event(&mut author, |event| event["type"] == "reaction").await;
+    assert_eq!(reaction["message_id"], message_id);
+    assert_eq!(reaction["emoji"], "🔥");
+    assert_eq!(reaction["added"], true);
+    assert_eq!(reaction["count"], 1);
+
+    viewer
+        .send_text(json!({ "type": "react", "message_id": message_id, "emoji": "abc" }).to_string())
+        .await;
+    let error = recv_event(&mut viewer, |event| event["type"] == "error").await;
+    assert!(error["message"].as_str().unwrap().contains("emoji"));
+
+    // Новый клиент видит сводку в истории, reacted — только у поставившего
+    let mut again = connect(&app, &viewer_cookie, "/api/ws?protocol=json").await;
+    let history = recv_event(&mut again, |event| event["type"] == "history").await;
+    let entry = history["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["message_id"] == message_id)
+        .expect("message is missing from history")
+        .clone();
+    assert_eq!(
+        entry["reactions"],
+        json!([{ "emoji": "🔥", "count": 1, "reacted": true }])
+    );
+    let mut author_again = connect(&app, &author_cookie, "/api/ws?protocol=json").await;
+    let history = recv_event(&mut author_again, |event| event["type"] == "history").await;
+    let entry = history["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["message_id"] == message_id)
+        .unwrap()
+        .clone();
+    assert_eq!(entry["reactions"][0]["reacted"], false);
+}
+
+#[tokio::test]
+async fn rest_reactions_are_idempotent_and_validated() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, cookie) = signup().await;
+    let message_id = insert_message(user_uuid, "rest reactions").await;
+    let path = format!("/api/messages/{}/reactions", message_id);
+
+    for _ in 0..2 {
+        let resp = request("POST", &path, &cookie, Some(json!({ "emoji": "👍" }))).await;
+        assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+        assert_eq!(body_json(&resp)["count"], 1);
+    }
+    let resp = request("GET", &path, &cookie, None).await;
+    assert_eq!(
+        body_json(&resp),
+        json!([{ "emoji": "👍", "count": 1, "reacted": true }])
+    );
+
+    let resp = request("POST", &path, &cookie, Some(json!({ "emoji": "" }))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = request("POST", &path, &cookie, Some(json!({ "emoji": "lol" }))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = request(
+        "POST",
+        "/api/messages/999999999/reactions",
+        &cookie,
+        Some(json!({ "emoji": "👍" })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = request(
+        "DELETE",
+        &format!("{}?emoji=%F0%9F%91%8D", path),
+        &cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(body_json(&resp)["count"], 0);
+    let resp = request("GET", &path, &cookie, None).await;
+    assert_eq!(body_json(&resp), json!([]));
+
+    // На удалённое модератором сообщение реакцию не поставить
+    db().await
+        .execute(
+            "UPDATE messages SET deleted_at = NOW() WHERE message_id = $1",
+            &[&message_id],
+        )
+        .await
+        .unwrap();
+    let resp = request("POST", &path, &cookie, Some(json!({ "emoji": "👍" }))).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn text_clients_keep_the_old_format() {
+    if !setup().await {
+        return;
+    }
+    let (username, user_uuid, cookie) = signup().await;
+    let message_id = insert_message(user_uuid, "old client").await;
+    let app = routes();
+    let mut plain = connect(&app, &cookie, "/api/ws").await;
+    let mut modern = connect(&app, &cookie, "/api/ws?protocol=json").await;
+    // Подписка на события появляется после отправки истории
+    recv_event(&mut modern, |event| event["type"] == "history").await;
+
+    // Реакция через REST доходит до JSON-клиентов того же чата
+    let resp = warp::test::request()
+        .method("POST")
+        .path(&format!("/api/messages/{}/reactions", message_id))
+        .remote_addr(remote_addr())
+        .header("cookie", &cookie)
+        .json(&json!({ "emoji": "🎉" }))
+        .reply(&app)
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    let reaction = recv_event(&mut modern, |event| event["type"] == "reaction").await;
+    assert_eq!(reaction["message_id"], message_id);
+    assert_eq!(reaction["user_uuid"], user_uuid.to_string().as_str());
+
+    // Прежний формат запроса: без type, с ip; в ответ — только строки
+    let text = format!("plain {}", Uuid::new_v4());
+    plain
+        .send_text(json!({ "message": text, "ip": "127.0.0.1" }).to_string())
+        .await;
+    let expected = format!("{}: {}", username, text);
+    let received = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let msg = plain.recv().await.expect("WebSocket closed");
+            let Ok(line) = msg.to_str() else { continue };
+            assert!(
+                serde_json::from_str::<Value>(line).is_err(),
+                "text client got a JSON event: {}",
+                line
+            );
+            if line == expected {
+                return;
+            }
+        }
+    })
+    .await;
+    assert!(received.is_ok(), "did not receive {:?}", expected);
+}
@@ -0,0 +1,209 @@
+// tests/read_markers.rs
+//
+// Индикатор набора, отметки прочтения и счётчики непрочитанного.
+mod common;
+
+use common::*;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::{json, Value};
+use uuid::Uuid;
+use warp::http::StatusCode;
+
+fn unread_for<'a>(counts: &'a Value, conversation: &str) -> &'a Value {
+    counts
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|count| count["conversation"] == conversation)
+        .unwrap_or_else(|| panic!("{} is missing from {}", conversation, counts))
+}
+
+#[tokio::test]
+async fn typing_is_broadcast_throttled_and_expires() {
+    if !setup().await {
+        return;
+    }
+    let (typist_name, typist_uuid, typist_cookie) = signup().await;
+    let (_, _, watcher_cookie) = signup().await;
+    let app = routes();
+    let mut typist = connect(&app, &typist_cookie, "/api/ws?protocol=json").await;
+    let mut watcher = connect(&app, &watcher_cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut typist, |event| event["type"] == "history").await;
+    recv_event(&mut watcher, |event| event["type"] == "history").await;
+
+    for _ in 0..3 {
+        typist
+            .send_text(json!({ "type": "typing" }).to_string())
+            .await;
+    }
+    let text = format!("done typing {}", Uuid::new_v4());
+    typist
+        .send_text(json!({ "type": "message", "message": text }).to_string())
+        .await;
+
+    let typing = recv_event(&mut watcher, |event| event["type"] == "typing").await;
+    assert_eq!(typing["user_uuid"], typist_uuid.to_string().as_str());
+    assert_eq!(typing["author"], typist_name.as_str());
+    assert!(typing["thread_root"].is_null());
+    let expires_at: chrono::DateTime<chrono::Utc> =
+        typing["expires_at"].as_str().unwrap().parse().unwrap();
+    assert!(expires_at > chrono::Utc::now());
+
+    // Повторные события в пределах интервала не рассылаются
+    let next = recv_event(&mut watcher, |event| {
+        event["type"] == "typing" || event["type"] == "message"
+    })
+    .await;
+    assert_eq!(next["type"], "message");
+    assert_eq!(next["text"], text.as_str());
+
+    // Ничего не сохраняется
+    let count: i64 = db()
+        .await
+        .query_one(
+            "SELECT COUNT(*) FROM messages WHERE user_uuid = $1",
+            &[&typist_uuid],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(count, 1);
+}
+
+#[tokio::test]
+async fn read_receipts_are_broadcast_and_never_move_back() {
+    if !setup().await {
+        return;
+    }
+    let (_, author_uuid, author_cookie) = signup().await;
+    let (_, reader_uuid, reader_cookie) = signup().await;
+    let older = insert_message(author_uuid, "older").await;
+    let newer = insert_message(author_uuid, "newer").await;
+    let app = routes();
+    let mut author = connect(&app, &author_cookie, "/api/ws?protocol=json").await;
+    let mut reader = connect(&app, &reader_cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut author, |event| event["type"] == "history").await;
+    recv_event(&mut reader, |event| event["type"] == "history").await;
+
+    reader
+        .send_text(json!({ "type": "read", "message_id": newer }).to_string())
+        .await;
+    let receipt = recv_event(&mut author, |event| event["type"] == "read").await;
+    assert_eq!(receipt["user_uuid"], reader_uuid.to_string().as_str());
+    assert_eq!(receipt["conversation"], "main");
+    assert_eq!(receipt["message_id"], newer);
+
+    let resp = request(
+        "PUT",
+        "/api/chat/read",
+        &reader_cookie,
+        Some(json!({ "message_id": older })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(body_json(&resp)["last_read_message_id"], newer);
+
+    reader
+        .send_text(json!({ "type": "read", "message_id": 999999999 }).to_string())
+        .await;
+    let error = recv_event(&mut reader, |event| event["type"] == "error").await;
+    assert_eq!(error["message"], "Message not found");
+
+    let stored: i64 = db()
+        .await
+        .query_one(
+            "SELECT last_read_message_id FROM read_markers WHERE user_uuid = $1 AND conversation = 'main'",
+            &[&reader_uuid],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(stored, newer);
+}
+
+#[tokio::test]
+async fn unread_counts_cover_main_chat_and_joined_threads() {
+    if !setup().await {
+        return;
+    }
+    let (_, other_uuid, _) = signup().await;
+    let (_, user_uuid, cookie) = signup().await;
+    let seen = insert_message(other_uuid, "seen").await;
+    insert_message(other_uuid, "unseen one").await;
+    let last = insert_message(other_uuid, "unseen two").await;
+    insert_message(user_uuid, "my own message").await;
+
+    // Ветка, где пользователь отвечал, и чужая ветка, где не отвечал
+    let root = insert_message(other_uuid, "thread root").await;
+    let foreign_root = insert_message(other_uuid, "foreign root").await;
+    let db = db().await;
+    for (text, author, thread_root) in [
+        ("my reply", user_uuid, root),
+        ("their reply", other_uuid, root),
+        ("elsewhere", other_uuid, foreign_root),
+    ] {
+        db.execute(
+            "INSERT INTO messages (message, user_uuid, thread_root) VALUES ($1, $2, $3)",
+            &[&text, &author, &thread_root],
+        )
+        .await
+        .unwrap();
+    }
+
+    let resp = request(
+        "PUT",
+        "/api/chat/read",
+        &cookie,
+        Some(json!({ "message_id": seen })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(body_json(&resp)["conversation"], "main");
+
+    let resp = request("GET", "/api/chat/unread", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let counts = body_json(&resp);
+    let main = unread_for(&counts, "main");
+    assert_eq!(main["last_read_message_id"], seen);
+    // Два чужих после отметки и два корня веток; своё не считается.
+    // Общую ленту делят все тесты, поэтому сравниваем не точно
+    let main_unread = main["unread_count"].as_i64().unwrap();
+    assert!(main_unread >= 4, "{}", main);
+    let thread = unread_for(&counts, &format!("thread:{}", root));
+    assert_eq!(thread["thread_root"], root);
+    assert_eq!(thread["unread_count"], 1);
+    assert!(thread["last_read_message_id"].is_null());
+    assert!(counts
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|count| count["conversation"] != format!("thread:{}", foreign_root).as_str()));
+
+    let resp = request(
+        "PUT",
+        "/api/chat/read",
+        &cookie,
+        Some(json!({ "message_id": foreign_root })),
+    )
+    .await;
+    assert_eq!(body_json(&resp)["last_read_message_id"], foreign_root);
+    let resp = request("GET", "/api/chat/unread", &cookie, None).await;
+    let after = unread_for(&body_json(&resp), "main")["unread_count"]
+        .as_i64()
+        .unwrap();
+    assert!(
+        after < main_unread,
+        "{} after reading up to {}",
+        after,
+        last
+    );
+
+    let resp = request(
+        "PUT",
+        "/api/chat/read",
+        &cookie,
+        Some(json!({ "message_id": 999999999 })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
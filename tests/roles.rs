@@ -0,0 +1,125 @@
+// tests/roles.rs
+//
+// Роли и права: назначение ролей и модерация сообщений.
+mod common;
+
+use common::*;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::json;
+use uuid::Uuid;
+use warp::http::StatusCode;
+
+async fn put_role(cookie: &str, user_uuid: Uuid, role: &str) -> StatusCode {
+    warp::test::request()
+        .method("PUT")
+        .path(&format!("/api/admin/users/{}/role", user_uuid))
+        .header("cookie", cookie)
+        .json(&json!({ "role": role }))
+        .reply(&routes())
+        .await
+        .status()
+}
+
+async fn delete_message(cookie: &str, message_id: i64) -> StatusCode {
+    warp::test::request()
+        .method("DELETE")
+        .path(&format!("/api/moderation/messages/{}", message_id))
+        .header("cookie", cookie)
+        .reply(&routes())
+        .await
+        .status()
+}
+
+#[tokio::test]
+async fn only_admins_can_assign_roles() {
+    if !setup().await {
+        return;
+    }
+    let (_, admin_uuid, admin_cookie) = signup().await;
+    let (_, user_uuid, user_cookie) = signup().await;
+
+    assert_eq!(
+        put_role(&user_cookie, user_uuid, "Admin").await,
+        StatusCode::FORBIDDEN
+    );
+
+    set_role(admin_uuid, "Admin").await;
+    assert_eq!(
+        put_role(&admin_cookie, user_uuid, "Moderator").await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        put_role(&admin_cookie, admin_uuid, "User").await,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        put_role(&admin_cookie, Uuid::new_v4(), "User").await,
+        StatusCode::NOT_FOUND
+    );
+
+    let role: String = db()
+        .await
+        .query_one("SELECT role FROM users WHERE user_uuid = $1", &[&user_uuid])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(role, "Moderator");
+}
+
+#[tokio::test]
+async fn moderators_can_delete_messages() {
+    if !setup().await {
+        return;
+    }
+    let (_, author_uuid, author_cookie) = signup().await;
+    let (_, moderator_uuid, moderator_cookie) = signup().await;
+    set_role(moderator_uuid, "Moderator").await;
+
+    let message_id: i64 = db()
+        .await
+        .query_one(
+            "INSERT INTO messages (message, user_uuid) VALUES ('spam', $1) RETURNING message_id",
+            &[&author_uuid],
+        )
+        .await
+        .unwrap()
+        .get(0);
+
+    assert_eq!(
+        delete_message(&author_cookie, message_id).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        delete_message(&moderator_cookie, message_id).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        delete_message(&moderator_cookie, message_id).await,
+        StatusCode::NOT_FOUND
+    );
+
+    let row = db()
+        .await
+        .query_one(
+            "SELECT deleted_at IS NOT NULL, deleted_by FROM messages WHERE message_id = $1",
+            &[&message_id],
+        )
+        .await
+        .unwrap();
+    assert!(row.get::<_, bool>(0));
+    assert_eq!(row.get::<_, Option<Uuid>>(1), Some(moderator_uuid));
+}
+
+#[tokio::test]
+async fn admin_routes_require_session() {
+    if !setup().await {
+        return;
+    }
+    let resp = warp::test::request()
+        .method("PUT")
+        .path(&format!("/api/admin/users/{}/role", Uuid::new_v4()))
+        .json(&json!({ "role": "Admin" }))
+        .reply(&routes())
+        .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
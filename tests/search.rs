@@ -0,0 +1,184 @@
+// tests/search.rs
+//
+// Полнотекстовый поиск по истории чата.
+mod common;
+
+use common::*;
+use serde_json::Value;
+use uuid::Uuid;
+use warp::http::StatusCode;
+
+/// Слово, которое встречается только в сообщениях этого теста
+fn unique_word() -> String {
+    format!("zq{}", Uuid::new_v4().simple())
+}
+
+async fn search(cookie: &str, query: &str) -> Vec<Value> {
+    let resp = request(
+        "GET",
+        &format!("/api/messages/search?{}", query),
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    body_json(&resp).as_array().unwrap().clone()
+}
+
+fn ids(results: &[Value]) -> Vec<i64> {
+    results
+        .iter()
+        .map(|result| result["message"]["message_id"].as_i64().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn search_ranks_and_highlights_matches() {
+    if !setup().await {
+        return;
+    }
+    let (username, user_uuid, cookie) = signup().await;
+    let word = unique_word();
+    let once = insert_message(user_uuid, &format!("<b>{}</b> once", word)).await;
+    let twice = insert_message(user_uuid, &format!("{} and {} again", word, word)).await;
+    insert_message(user_uuid, "nothing to see here").await;
+
+    let results = search(&cookie, &format!("q={}", word)).await;
+    assert_eq!(ids(&results), vec![twice, once]);
+    assert!(results[0]["rank"].as_f64().unwrap() > results[1]["rank"].as_f64().unwrap());
+    assert_eq!(results[0]["message"]["author"], username.as_str());
+
+    // Совпадение выделено, а разметка из сообщения экранирована
+    let highlight = results[1]["highlight"].as_str().unwrap();
+    assert!(
+        highlight.contains(&format!("<mark>{}</mark>", word)),
+        "{}",
+        highlight
+    );
+    assert!(!highlight.contains("<b>"), "{}", highlight);
+
+    // Синтаксис websearch: исключение слова
+    let results = search(&cookie, &format!("q={}+-again", word)).await;
+    assert_eq!(ids(&results), vec![once]);
+
+    let results = search(&cookie, &format!("q={}&limit=1&offset=1", word)).await;
+    assert_eq!(ids(&results), vec![once]);
+}
+
+#[tokio::test]
+async fn search_filters_by_author_conversation_and_date() {
+    if !setup().await {
+        return;
+    }
+    let (alice_name, alice_uuid, cookie) = signup().await;
+    let (_, bob_uuid, _) = signup().await;
+    let word = unique_word();
+    let root = insert_message(alice_uuid, &format!("{} root", word)).await;
+    let from_bob = insert_message(bob_uuid, &format!("{} from bob", word)).await;
+    let db = db().await;
+    let reply: i64 = db
+        .query_one(
+            "INSERT INTO messages (message, user_uuid, thread_root) VALUES ($1, $2, $3) \
+             RETURNING message_id",
+            &[&format!("{} in thread", word), &alice_uuid, &root],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    let old: i64 = db
+        .query_one(
+            "INSERT INTO messages (message, user_uuid, created_at) \
+             VALUES ($1, $2, '2020-01-01T00:00:00Z') RETURNING message_id",
+            &[&format!("{} long ago", word), &alice_uuid],
+        )
+        .await
+        .unwrap()
+        .get(0);
+
+    let mut found = ids(&search(&cookie, &format!("q={}&author={}", word, bob_uuid)).await);
+    assert_eq!(found, vec![from_bob]);
+    found = ids(&search(
+        &cookie,
+        &format!("q={}&author={}", word, alice_name.to_uppercase()),
+    )
+    .await);
+    found.sort();
+    assert_eq!(found, vec![root, reply, old]);
+
+    let found = ids(&search(&cookie, &format!("q={}&conversation=thread:{}", word, root)).await);
+    assert_eq!(found, vec![reply]);
+    let mut found = ids(&search(&cookie, &format!("q={}&conversation=main", word)).await);
+    found.sort();
+    assert_eq!(found, vec![root, from_bob, old]);
+
+    let mut found = ids(&search(&cookie, &format!("q={}&from=2021-01-01T00:00:00Z", word)).await);
+    found.sort();
+    assert_eq!(found, vec![root, from_bob, reply]);
+    let found = ids(&search(&cookie, &format!("q={}&to=2021-01-01T00:00:00Z", word)).await);
+    assert_eq!(found, vec![old]);
+}
+
+#[tokio::test]
+async fn search_filters_by_attachments() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, cookie) = signup().await;
+    let word = unique_word();
+    let plain = insert_message(user_uuid, &format!("{} plain", word)).await;
+    let with_file = insert_message(user_uuid, &format!("{} with file", word)).await;
+    let file_id = upload(&cookie, "notes.txt", "text/plain", b"notes").await;
+    db().await
+        .execute(
+            "INSERT INTO message_attachments (message_id, file_id, position, attached_by) \
+             VALUES ($1, $2, 0, $3)",
+            &[&with_file, &file_id, &user_uuid],
+        )
+        .await
+        .unwrap();
+
+    let found = ids(&search(&cookie, &format!("q={}&has_attachment=true", word)).await);
+    assert_eq!(found, vec![with_file]);
+    let found = ids(&search(&cookie, &format!("q={}&has_attachment=false", word)).await);
+    assert_eq!(found, vec![plain]);
+    let mut found = ids(&search(&cookie, &format!("q={}", word)).await);
+    found.sort();
+    assert_eq!(found, vec![plain, with_file]);
+}
+
+#[tokio::test]
+async fn search_skips_deleted_messages_and_rejects_bad_queries() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, cookie) = signup().await;
+    let word = unique_word();
+    let kept = insert_message(user_uuid, &word).await;
+    let deleted = insert_message(user_uuid, &word).await;
+    db().await
+        .execute(
+            "UPDATE messages SET deleted_at = NOW() WHERE message_id = $1",
+            &[&deleted],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        ids(&search(&cookie, &format!("q={}", word)).await),
+        vec![kept]
+    );
+
+    for query in ["q=", "q=+++", "q=hello&conversation=room:1"] {
+        let resp = request(
+            "GET",
+            &format!("/api/messages/search?{}", query),
+            &cookie,
+            None,
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", query);
+    }
+
+    let resp = request("GET", &format!("/api/messages/search?q={}", word), "", None).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,237 @@
+// tests/threads.rs
+//
+// Цитаты и ветки ответов в чате.
+mod common;
+
+use common::*;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::{json, Value};
+use uuid::Uuid;
+use warp::http::StatusCode;
+use warp::test::WsClient;
+
+/// Отправляет сообщение и ждёт, пока оно вернётся через рассылку
+async fn say(ws: &mut WsClient, body: Value) -> Value {
+    let text = body["message"].as_str().unwrap().to_string();
+    let mut frame = body;
+    frame["type"] = json!("message");
+    ws.send_text(frame.to_string()).await;
+    recv_event(ws, |event| {
+        event["type"] == "message" && event["text"] == text.as_str()
+    })
+    .await
+}
+
+fn find_message(history: &Value, message_id: i64) -> Option<Value> {
+    history["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["message_id"] == message_id)
+        .cloned()
+}
+
+#[tokio::test]
+async fn replies_quote_their_parent() {
+    if !setup().await {
+        return;
+    }
+    let (username, user_uuid, cookie) = signup().await;
+    let app = routes();
+    let mut ws = connect(&app, &cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut ws, |event| event["type"] == "history").await;
+
+    let parent_text = format!("parent {}", Uuid::new_v4());
+    let parent = say(&mut ws, json!({ "message": parent_text })).await;
+    let parent_id = parent["message_id"].as_i64().unwrap();
+    assert!(parent["reply_to"].is_null());
+
+    let reply_text = format!("reply {}", Uuid::new_v4());
+    let reply = say(
+        &mut ws,
+        json!({ "message": reply_text, "reply_to": parent_id }),
+    )
+    .await;
+    assert_eq!(reply["reply_to"]["message_id"], parent_id);
+    assert_eq!(reply["reply_to"]["author"], username.as_str());
+    assert_eq!(reply["reply_to"]["text"], parent_text.as_str());
+    assert_eq!(
+        reply["reply_to"]["user_uuid"],
+        user_uuid.to_string().as_str()
+    );
+    // Цитата остаётся в общей ленте
+    assert!(reply["thread_root"].is_null());
+
+    ws.send_text(
+        json!({ "type": "message", "message": "orphan", "reply_to": 999999999 }).to_string(),
+    )
+    .await;
+    let error = recv_event(&mut ws, |event| event["type"] == "error").await;
+    assert_eq!(error["message"], "Quoted message not found");
+
+    // Удалённое модератором сообщение цитируется без текста
+    db().await
+        .execute(
+            "UPDATE messages SET deleted_at = NOW() WHERE message_id = $1",
+            &[&parent_id],
+        )
+        .await
+        .unwrap();
+    let mut again = connect(&app, &cookie, "/api/ws?protocol=json").await;
+    let history = recv_event(&mut again, |event| event["type"] == "history").await;
+    let reply = find_message(&history, reply["message_id"].as_i64().unwrap()).unwrap();
+    assert_eq!(reply["reply_to"]["message_id"], parent_id);
+    assert!(reply["reply_to"]["text"].is_null());
+    assert!(find_message(&history, parent_id).is_none());
+}
+
+#[tokio::test]
+async fn thread_replies_are_summarized_in_the_main_stream() {
+    if !setup().await {
+        return;
+    }
+    let (_, alice_uuid, alice_cookie) = signup().await;
+    let (_, bob_uuid, bob_cookie) = signup().await;
+    let app = routes();
+    let mut alice = connect(&app, &alice_cookie, "/api/ws?protocol=json").await;
+    let mut bob = connect(&app, &bob_cookie, "/api/ws?protocol=json").await;
+    recv_event(&mut alice, |event| event["type"] == "history").await;
+    recv_event(&mut bob, |event| event["type"] == "history").await;
+
+    let root = say(
+        &mut alice,
+        json!({ "message": format!("root {}", Uuid::new_v4()) }),
+    )
+    .await;
+    let root_id = root["message_id"].as_i64().unwrap();
+
+    let first = say(
+        &mut bob,
+        json!({ "message": format!("first {}", Uuid::new_v4()), "thread_root": root_id }),
+    )
+    .await;
+    assert_eq!(first["thread_root"], root_id);
+    let summary = recv_event(&mut alice, |event| {
+        event["type"] == "thread" && event["thread"]["reply_count"] == 1
+    })
+    .await;
+    assert_eq!(summary["message_id"], root_id);
+
+    // Ответ на сообщение из ветки попадает в ту же ветку
+    let second_text = format!("second {}", Uuid::new_v4());
+    let second = say(
+        &mut alice,
+        json!({ "message": second_text, "reply_to": first["message_id"] }),
+    )
+    .await;
+    assert_eq!(second["thread_root"], root_id);
+    let summary = recv_event(&mut bob, |event| {
+        event["type"] == "thread" && event["thread"]["reply_count"] == 2
+    })
+    .await;
+    let thread = &summary["thread"];
+    assert_eq!(thread["last_reply"]["text"], second_text.as_str());
+    let participants: Vec<&str> = thread["participants"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|participant| participant["user_uuid"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        participants,
+        vec![bob_uuid.to_string(), alice_uuid.to_string()]
+    );
+
+    // В истории общей ленты — корень со сводкой, без ответов ветки
+    let mut again = connect(&app, &alice_cookie, "/api/ws?protocol=json").await;
+    let history = recv_event(&mut again, |event| event["type"] == "history").await;
+    let root = find_message(&history, root_id).expect("root is missing from history");
+    assert_eq!(root["thread"]["reply_count"], 2);
+    assert_eq!(
+        root["thread"]["last_reply"]["message_id"],
+        second["message_id"]
+    );
+    assert!(find_message(&history, first["message_id"].as_i64().unwrap()).is_none());
+}
+
+#[tokio::test]
+async fn thread_history_endpoint_returns_root_and_replies() {
+    if !setup().await {
+        return;
+    }
+    let (_, user_uuid, cookie) = signup().await;
+    let root_id = insert_message(user_uuid, "thread root").await;
+    let mut reply_ids = Vec::new();
+    for i in 0..3 {
+        let reply_id: i64 = db()
+            .await
+            .query_one(
+                "INSERT INTO messages (message, user_uuid, thread_root, created_at) \
+                 VALUES ($1, $2, $3, NOW() + $4 * INTERVAL '1 second') RETURNING message_id",
+                &[&format!("reply {}", i), &user_uuid, &root_id, &(i as f64)],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        reply_ids.push(reply_id);
+    }
+    let resp = request(
+        "POST",
+        &format!("/api/messages/{}/reactions", reply_ids[0]),
+        &cookie,
+        Some(json!({ "emoji": "👀" })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = request(
+        "GET",
+        &format!("/api/messages/{}/thread", root_id),
+        &cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    let thread = body_json(&resp);
+    assert_eq!(thread["root"]["message_id"], root_id);
+    assert_eq!(thread["root"]["thread"]["reply_count"], 3);
+    let replies: Vec<i64> = thread["replies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|reply| reply["message_id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(replies, reply_ids);
+    assert_eq!(thread["replies"][0]["reactions"][0]["emoji"], "👀");
+
+    // По ответу открывается его ветка; limit оставляет последние ответы
+    let resp = request(
+        "GET",
+        &format!("/api/messages/{}/thread?limit=2", reply_ids[0]),
+        &cookie,
+        None,
+    )
+    .await;
+    let thread = body_json(&resp);
+    assert_eq!(thread["root"]["message_id"], root_id);
+    assert_eq!(thread["replies"][0]["message_id"], reply_ids[1]);
+    assert_eq!(thread["replies"].as_array().unwrap().len(), 2);
+
+    let resp = request("GET", "/api/messages/999999999/thread", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    db().await
+        .execute(
+            "UPDATE messages SET deleted_at = NOW() WHERE message_id = $1",
+            &[&root_id],
+        )
+        .await
+        .unwrap();
+    let resp = request(
+        "GET",
+        &format!("/api/messages/{}/thread", reply_ids[2]),
+        &cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
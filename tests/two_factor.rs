@@ -0,0 +1,227 @@
+// tests/two_factor.rs
+//
+// Двухфакторная аутентификация (TOTP).
+mod common;
+
+use chrono::Utc;
+use common::*;
+use rust_server_cyb3ria_xyz::routes;
+use rust_server_cyb3ria_xyz::totp;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use warp::http::StatusCode;
+
+#[test]
+fn totp_matches_rfc6238_vectors() {
+    let secret = b"12345678901234567890";
+    for (time, code) in [
+        (59, "287082"),
+        (1111111109, "081804"),
+        (1111111111, "050471"),
+        (1234567890, "005924"),
+        (2000000000, "279037"),
+    ] {
+        assert_eq!(totp::code_at_step(secret, totp::time_step(time)), code);
+        assert!(totp::verify_code(secret, code, time, 0).is_some());
+    }
+    assert!(totp::verify_code(secret, "287082", 59 + 90, 1).is_none());
+    assert!(totp::verify_code(secret, "28708", 59, 1).is_none());
+}
+
+#[test]
+fn otpauth_uri_contains_secret_and_issuer() {
+    let uri = totp::otpauth_uri("cyb3ria", "alice bob", b"12345678901234567890");
+    assert!(uri.starts_with("otpauth://totp/cyb3ria:alice%20bob?"));
+    assert!(uri.contains("secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"));
+    assert!(uri.contains("issuer=cyb3ria"));
+}
+
+fn current_step() -> i64 {
+    totp::time_step(Utc::now().timestamp())
+}
+
+fn code_for(secret: &str, step: i64) -> String {
+    totp::code_at_step(&totp::decode_secret(secret).unwrap(), step)
+}
+
+async fn login_second_step(challenge_id: &Value, code: &str) -> warp::http::Response<bytes::Bytes> {
+    login_second_step_from(challenge_id, code, remote_addr()).await
+}
+
+async fn login_second_step_from(
+    challenge_id: &Value,
+    code: &str,
+    addr: SocketAddr,
+) -> warp::http::Response<bytes::Bytes> {
+    warp::test::request()
+        .method("POST")
+        .path("/api/login/2fa")
+        .remote_addr(addr)
+        .json(&json!({ "challenge_id": challenge_id, "code": code }))
+        .reply(&routes())
+        .await
+}
+
+/// Включает 2FA и возвращает (секрет, использованный шаг, коды восстановления)
+async fn enable_two_factor(cookie: &str) -> (String, i64, Vec<String>) {
+    let resp = request("POST", "/api/2fa/enroll", cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let secret = body_json(&resp)["secret"].as_str().unwrap().to_string();
+
+    let resp = request(
+        "POST",
+        "/api/2fa/confirm",
+        cookie,
+        Some(json!({ "code": "000000" })),
+    )
+    .await;
+    let step = current_step();
+    if code_for(&secret, step) != "000000" {
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    let resp = request(
+        "POST",
+        "/api/2fa/confirm",
+        cookie,
+        Some(json!({ "code": code_for(&secret, step) })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    let codes = body_json(&resp)["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|code| code.as_str().unwrap().to_string())
+        .collect();
+    (secret, step, codes)
+}
+
+#[tokio::test]
+async fn login_requires_second_factor_once_enabled() {
+    if !setup().await {
+        return;
+    }
+    let (username, _, cookie) = signup().await;
+    let (secret, step, recovery_codes) = enable_two_factor(&cookie).await;
+    assert_eq!(recovery_codes.len(), 10);
+
+    let resp = request("GET", "/api/2fa", &cookie, None).await;
+    assert_eq!(body_json(&resp)["enabled"], true);
+    assert_eq!(body_json(&resp)["recovery_codes_left"], 10);
+
+    // Пароль принят, но сессии ещё нет
+    let resp = login(&username, PASSWORD).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().get("set-cookie").is_none());
+    let body = body_json(&resp);
+    assert_eq!(body["two_factor_required"], true);
+    let challenge_id = body["challenge_id"].clone();
+
+    // Код, уже использованный при подтверждении, повторно не принимается
+    let resp = login_second_step(&challenge_id, &code_for(&secret, step)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = login_second_step(&challenge_id, &code_for(&secret, step + 1)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let session = session_cookie(&resp);
+    assert_eq!(
+        request("GET", "/api/files", &session, None).await.status(),
+        StatusCode::OK
+    );
+
+    // Попытка входа одноразовая
+    let resp = login_second_step(&challenge_id, &code_for(&secret, step + 1)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // Код восстановления срабатывает один раз
+    let challenge_id = body_json(&login(&username, PASSWORD).await)["challenge_id"].clone();
+    let resp = login_second_step(&challenge_id, &recovery_codes[0]).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let challenge_id = body_json(&login(&username, PASSWORD).await)["challenge_id"].clone();
+    let resp = login_second_step(&challenge_id, &recovery_codes[0]).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn challenge_expires_after_too_many_wrong_codes() {
+    if !setup().await {
+        return;
+    }
+    let (username, _, cookie) = signup().await;
+    let (_, _, recovery_codes) = enable_two_factor(&cookie).await;
+
+    let challenge_id = body_json(&login(&username, PASSWORD).await)["challenge_id"].clone();
+    for _ in 0..5 {
+        let resp = login_second_step(&challenge_id, "bad-code").await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED, "{:?}", resp.body());
+    }
+    let resp = login_second_step(&challenge_id, &recovery_codes[0]).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn wrong_codes_lock_the_account_across_challenges() {
+    if !setup().await {
+        return;
+    }
+    let addr: SocketAddr = "10.0.3.1:1000".parse().unwrap();
+    let (username, _, cookie) = signup().await;
+    let (secret, step, _) = enable_two_factor(&cookie).await;
+
+    let start_login = || async {
+        let resp = login_from(&username, PASSWORD, addr).await;
+        assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+        body_json(&resp)["challenge_id"].clone()
+    };
+
+    // Верный пароль больше не сбрасывает счётчик: каждая новая попытка входа
+    // с неверным кодом приближает блокировку
+    for _ in 0..4 {
+        let resp = login_second_step_from(&start_login().await, "bad-code", addr).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+    let earlier_challenge = start_login().await;
+    let resp = login_second_step_from(&start_login().await, "bad-code", addr).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = login_from(&username, PASSWORD, addr).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp.headers().contains_key("retry-after"));
+
+    // Попытка, начатая до блокировки, тоже не принимает коды, даже верные
+    let resp = login_second_step_from(&earlier_challenge, &code_for(&secret, step + 1), addr).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp.headers().get("set-cookie").is_none());
+}
+
+#[tokio::test]
+async fn disabling_requires_password_and_code() {
+    if !setup().await {
+        return;
+    }
+    let (username, _, cookie) = signup().await;
+    let (_, _, recovery_codes) = enable_two_factor(&cookie).await;
+
+    let resp = request(
+        "POST",
+        "/api/2fa/disable",
+        &cookie,
+        Some(json!({ "password": "wrongpass", "code": recovery_codes[0] })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = request(
+        "POST",
+        "/api/2fa/disable",
+        &cookie,
+        Some(json!({ "password": PASSWORD, "code": recovery_codes[1] })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = login(&username, PASSWORD).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().get("set-cookie").is_some());
+}
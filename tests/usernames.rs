@@ -0,0 +1,228 @@
+// tests/usernames.rs
+//
+// Смена имени, история имён, зарезервированные имена и отображаемые имена.
+mod common;
+
+use common::*;
+use rust_server_cyb3ria_xyz::routes;
+use serde_json::json;
+use std::time::Duration;
+use uuid::Uuid;
+use warp::http::StatusCode;
+
+async fn change_username(cookie: &str, username: &str) -> warp::http::Response<bytes::Bytes> {
+    request(
+        "PUT",
+        "/api/account/username",
+        cookie,
+        Some(json!({ "username": username, "password": PASSWORD })),
+    )
+    .await
+}
+
+/// Снимает ограничение частоты смены имени
+async fn allow_next_change(user_uuid: Uuid) {
+    db().await
+        .execute(
+            "UPDATE users SET username_changed_at = NOW() - INTERVAL '1 year' WHERE user_uuid = $1",
+            &[&user_uuid],
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn username_change_is_limited_and_recorded() {
+    if !setup().await {
+        return;
+    }
+    let (old_name, user_uuid, cookie) = signup().await;
+    let new_name = unique_username();
+
+    let resp = request(
+        "PUT",
+        "/api/account/username",
+        &cookie,
+        Some(json!({ "username": new_name, "password": "wrong password" })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = change_username(&cookie, "x").await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = change_username(&cookie, &new_name).await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+    assert_eq!(body_json(&resp)["username"], new_name.as_str());
+
+    // Сессия остаётся, входить нужно под новым именем
+    let resp = request("GET", &format!("/api/profile/{}", user_uuid), &cookie, None).await;
+    assert_eq!(body_json(&resp)["username"], new_name.as_str());
+    assert_eq!(login(&new_name, PASSWORD).await.status(), StatusCode::OK);
+    assert_eq!(
+        login(&old_name, PASSWORD).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    let resp = change_username(&cookie, &unique_username()).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let resp = request("GET", "/api/account/username", &cookie, None).await;
+    let info = body_json(&resp);
+    assert!(info["next_change_at"].is_string());
+    assert_eq!(info["history"][0]["old_username"], old_name.as_str());
+    assert_eq!(info["history"][0]["new_username"], new_name.as_str());
+
+    let resp = request("GET", "/api/audit", &cookie, None).await;
+    let audit = body_json(&resp);
+    let change = audit
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|event| event["action"] == "username_changed")
+        .expect("no username_changed event");
+    assert_eq!(
+        change["target"],
+        format!("{} -> {}", old_name, new_name).as_str()
+    );
+}
+
+#[tokio::test]
+async fn released_and_reserved_names_are_protected() {
+    if !setup().await {
+        return;
+    }
+    let (old_name, user_uuid, cookie) = signup().await;
+    let resp = change_username(&cookie, &unique_username()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Освобождённое имя не достаётся другим, в том числе в другом регистре
+    let resp = register(&old_name.to_uppercase(), PASSWORD).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let (_, other_uuid, other_cookie) = signup().await;
+    let resp = change_username(&other_cookie, &old_name).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    assert!(body_json(&resp)["message"]
+        .as_str()
+        .unwrap()
+        .contains("released recently"));
+
+    // Прежний владелец может его вернуть
+    allow_next_change(user_uuid).await;
+    let resp = change_username(&cookie, &old_name).await;
+    assert_eq!(resp.status(), StatusCode::OK, "{:?}", resp.body());
+
+    // Зарезервированные имена
+    assert_eq!(
+        register("Admin", PASSWORD).await.status(),
+        StatusCode::CONFLICT
+    );
+    let reserved = unique_username();
+    let (_, _, admin_cookie) = signup_admin().await;
+    let resp = request(
+        "POST",
+        "/api/admin/reserved-usernames",
+        &admin_cookie,
+        Some(json!({ "username": reserved.to_uppercase(), "reason": "Brand" })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let resp = request("GET", "/api/admin/reserved-usernames", &admin_cookie, None).await;
+    assert!(body_json(&resp)
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|name| name["username"] == reserved.as_str() && name["reason"] == "Brand"));
+    allow_next_change(other_uuid).await;
+    let resp = change_username(&other_cookie, &reserved).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let resp = request(
+        "PUT",
+        "/api/account/display-name",
+        &other_cookie,
+        Some(json!({ "display_name": reserved })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = request(
+        "DELETE",
+        &format!("/api/admin/reserved-usernames?username={}", reserved),
+        &admin_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = change_username(&other_cookie, &reserved).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = request("GET", "/api/admin/reserved-usernames", &cookie, None).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn display_name_shows_in_chat_and_admin_can_rename() {
+    if !setup().await {
+        return;
+    }
+    let (username, user_uuid, cookie) = signup().await;
+    let resp = request(
+        "PUT",
+        "/api/account/display-name",
+        &cookie,
+        Some(json!({ "display_name": "  Night Owl  " })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = request("GET", &format!("/api/profile/{}", user_uuid), &cookie, None).await;
+    assert_eq!(body_json(&resp)["display_name"], "Night Owl");
+
+    let text = format!("hoot {}", Uuid::new_v4());
+    db().await
+        .execute(
+            "INSERT INTO messages (message, user_uuid) VALUES ($1, $2)",
+            &[&text, &user_uuid],
+        )
+        .await
+        .unwrap();
+
+    // Принудительная смена имени администратором сразу видна в истории чата
+    let (_, _, admin_cookie) = signup_admin().await;
+    let forced = unique_username();
+    let resp = request(
+        "PUT",
+        &format!("/api/admin/users/{}/username", user_uuid),
+        &admin_cookie,
+        Some(json!({ "username": forced })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = request(
+        "GET",
+        &format!("/api/admin/users/{}/username-history", user_uuid),
+        &admin_cookie,
+        None,
+    )
+    .await;
+    let history = body_json(&resp);
+    assert_eq!(history[0]["old_username"], username.as_str());
+    assert_eq!(history[0]["new_username"], forced.as_str());
+    assert!(history[0]["changed_by"].is_string());
+
+    let mut ws = warp::test::ws()
+        .path("/api/ws")
+        .header("cookie", &cookie)
+        .handshake(routes())
+        .await
+        .expect("handshake");
+    let expected = format!("Night Owl (@{}): {}", forced, text);
+    let found = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let msg = ws.recv().await.expect("WebSocket closed");
+            if msg.to_str().is_ok_and(|msg| msg == expected) {
+                return;
+            }
+        }
+    })
+    .await;
+    assert!(found.is_ok(), "did not receive {:?}", expected);
+}